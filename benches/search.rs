@@ -0,0 +1,15 @@
+//! Reports the search benchmarks of every bundled environment.
+//!
+//! Run with `cargo bench`, optionally followed by `-- <iterations>` to change the number of
+//! iterations searched in every environment.
+
+use mcts::bench::bench_environments;
+
+
+fn main() {
+    // Cargo passes its own flags, such as `--bench`, which are not iteration counts.
+    let iterations = std::env::args().skip(1).find_map(|arg| arg.parse::<u64>().ok()).unwrap_or(5000);
+    for result in bench_environments(iterations) {
+        println!("{}", result);
+    }
+}
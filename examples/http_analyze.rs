@@ -0,0 +1,185 @@
+//! HTTP analysis endpoint, streaming the principal variation of a chess search as server-sent events.
+//!
+//! Run with `cargo run --release --example http_analyze [address]`, listening on `127.0.0.1:8080`
+//! by default, and analyze a position with
+//! `curl -N -d 'movetime=2000 <fen>' http://127.0.0.1:8080/analyze`.
+//!
+//! `POST /analyze` takes a body in the format of `mcts::service::SearchRequest::parse` without
+//! the environment, `[iterations=<n> | movetime=<ms>] [seed=<n>] <fen>`. The search runs on its
+//! own thread and sends its progress over a channel, which the connection forwards as `pv`
+//! events of JSON, followed by a `bestmove` event once the search ends. A client disconnecting
+//! stops the search. The server only uses the standard library, reading HTTP/1.1 requests
+//! directly, and the same loop fits the handler of an async framework such as axum.
+
+// Explicit returns are the style of the codebase.
+#![allow(clippy::needless_return)]
+
+#[cfg(not(target_arch = "wasm32"))]
+#[global_allocator]
+static GLOBAL: mimalloc::MiMalloc = mimalloc::MiMalloc;
+
+use chess::ChessMove;
+use mcts::chess_env::{ChessRolloutPolicy, ChessState};
+use mcts::hooks::{SearchControl, SearchHooks};
+use mcts::mcts::{MCTSTree, NodeId};
+use mcts::progress::{ProgressReporter, SearchProgress};
+use mcts::service::{SearchLimit, SearchRequest, TIMED_SEARCH_CAPACITY};
+use mcts::time_manager::{TimeControl, TimeManager};
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc;
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+
+/// Address listened on without an address argument.
+const DEFAULT_ADDRESS: &str = "127.0.0.1:8080";
+
+/// Time between two `pv` events.
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(250);
+
+
+/// Hooks of an analysis, reporting progress and stopping at the time limit or when the client leaves.
+struct AnalysisHooks {
+    reporter: ProgressReporter<ChessMove>,
+    time: Option<TimeManager>,
+    stop: Arc<AtomicBool>,
+}
+
+impl SearchHooks<ChessMove, ChessState> for AnalysisHooks {
+    fn on_iteration(&mut self, tree: &MCTSTree<ChessMove, ChessState>, iteration: u64) -> SearchControl {
+        self.reporter.on_iteration(tree, iteration);
+        let out_of_time = self.time.as_mut().is_some_and(|time| time.should_stop(tree));
+        return if out_of_time || self.stop.load(Ordering::Relaxed) { SearchControl::Stop } else { SearchControl::Continue };
+    }
+
+    fn on_expansion(&mut self, tree: &MCTSTree<ChessMove, ChessState>, node: NodeId) {
+        SearchHooks::<ChessMove, ChessState>::on_expansion(&mut self.reporter, tree, node);
+    }
+}
+
+
+/// Encodes a progress message as a JSON object.
+fn progress_json(progress: &SearchProgress<ChessMove>) -> String {
+    let pv: Vec<String> = progress.pv.iter().map(|action| format!("\"{}\"", action)).collect();
+    return format!(
+        "{{\"best\":{},\"visits\":{},\"iterations\":{},\"depth\":{},\"elapsed_ms\":{},\"pv\":[{}]}}",
+        progress.best.map_or("null".to_string(), |best| format!("\"{}\"", best)),
+        progress.visits, progress.iterations, progress.depth, progress.elapsed.as_millis(), pv.join(",")
+    );
+}
+
+
+/// Writes a complete HTTP response with a plain text body.
+fn respond(stream: &mut TcpStream, status: &str, body: &str) -> std::io::Result<()> {
+    return write!(
+        stream,
+        "HTTP/1.1 {}\r\nContent-Type: text/plain\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status, body.len(), body
+    );
+}
+
+
+/// Sends the headers of an event stream, then a `pv` event for every progress message until the search ends.
+///
+/// # Returns
+/// The best action of the last progress message.
+fn stream_progress(stream: &mut TcpStream, receiver: mpsc::Receiver<SearchProgress<ChessMove>>) -> std::io::Result<Option<ChessMove>> {
+    write!(stream, "HTTP/1.1 200 OK\r\nContent-Type: text/event-stream\r\nCache-Control: no-cache\r\nConnection: close\r\n\r\n")?;
+    let mut best = None;
+    for progress in receiver.iter() {
+        write!(stream, "event: pv\ndata: {}\n\n", progress_json(&progress))?;
+        stream.flush()?;
+        best = progress.best;
+    }
+    return Ok(best);
+}
+
+
+/// Searches the request of `body`, streaming its progress to `stream` as server-sent events.
+fn analyze(stream: &mut TcpStream, body: &str) -> std::io::Result<()> {
+    let request = match SearchRequest::parse(&format!("chess {}", body)) {
+        Ok(request) => request,
+        Err(error) => return respond(stream, "400 Bad Request", &error.to_string()),
+    };
+    let capacity = match request.limit {
+        SearchLimit::Iterations(iterations) => iterations as usize + 1,
+        SearchLimit::Time(_) => TIMED_SEARCH_CAPACITY,
+    };
+    let mut tree = match MCTSTree::<ChessMove, ChessState>::try_with_capacity(capacity, Some(request.seed), &request.position, 30) {
+        Ok(tree) => tree,
+        Err(error) => return respond(stream, "400 Bad Request", &format!("invalid position: {}", error)),
+    };
+    tree.searcher.rollout_policy = Box::new(ChessRolloutPolicy::default());
+    tree.budget.max_nodes = Some(capacity);
+
+    let (sender, receiver) = mpsc::channel();
+    let stop = Arc::new(AtomicBool::new(false));
+    let (iterations, time) = match request.limit {
+        SearchLimit::Iterations(iterations) => (iterations, None),
+        SearchLimit::Time(time) => (u64::MAX, Some(TimeManager::new(TimeControl::fixed(time)))),
+    };
+    let mut hooks = AnalysisHooks { reporter: ProgressReporter::new(sender, PROGRESS_INTERVAL), time, stop: stop.clone() };
+    let search = thread::spawn(move || {
+        let searched = tree.search_with_hooks(iterations, &mut hooks);
+        hooks.reporter.report(&tree, searched);
+    });
+
+    let streamed = stream_progress(stream, receiver);
+    stop.store(true, Ordering::Relaxed);
+    search.join().expect("search thread panicked");
+
+    let best = streamed?.map_or("none".to_string(), |best| best.to_string());
+    write!(stream, "event: bestmove\ndata: {}\n\n", best)?;
+    return stream.flush();
+}
+
+
+/// Reads one HTTP request from `stream` and answers it.
+fn serve(mut stream: TcpStream) -> std::io::Result<()> {
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line)?;
+    let mut content_length = 0;
+    loop {
+        let mut header = String::new();
+        if reader.read_line(&mut header)? == 0 || header.trim().is_empty() {
+            break;
+        }
+        if let Some((name, value)) = header.split_once(':') {
+            if name.trim().eq_ignore_ascii_case("content-length") {
+                content_length = value.trim().parse().unwrap_or(0);
+            }
+        }
+    }
+    let mut body = vec![0; content_length];
+    reader.read_exact(&mut body)?;
+
+    let mut parts = request_line.split_whitespace();
+    match (parts.next(), parts.next()) {
+        (Some("POST"), Some("/analyze")) => return analyze(&mut stream, String::from_utf8_lossy(&body).trim()),
+        (Some(_), Some("/analyze")) => return respond(&mut stream, "405 Method Not Allowed", "use POST"),
+        _ => return respond(&mut stream, "404 Not Found", "not found"),
+    }
+}
+
+fn main() {
+    let address = std::env::args().nth(1).unwrap_or_else(|| DEFAULT_ADDRESS.to_string());
+    let listener = TcpListener::bind(&address).unwrap_or_else(|error| panic!("can not listen on {}: {}", address, error));
+    println!("Listening on http://{}/analyze", address);
+
+    for stream in listener.incoming() {
+        match stream {
+            Ok(stream) => {
+                thread::spawn(move || {
+                    if let Err(error) = serve(stream) {
+                        eprintln!("connection failed: {}", error);
+                    }
+                });
+            },
+            Err(error) => eprintln!("connection failed: {}", error),
+        }
+    }
+}
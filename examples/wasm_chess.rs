@@ -0,0 +1,85 @@
+//! Chess engine compiled to WebAssembly, so the chess example can play client-side in a web page.
+//!
+//! Built with `cargo build --release --example wasm_chess --target wasm32-unknown-unknown`, and
+//! played by `wasm_chess.html`. The module exports a C interface without any bindings crate:
+//! the page writes a FEN into memory from `alloc`, asks `best_move` for the engine's reply and
+//! reads it back as a NUL terminated UCI move, released with `free_string`. The time of the
+//! search is read from the `now_ms` function the page provides, see `mcts::clock`.
+
+// Explicit returns are the style of the codebase.
+#![allow(clippy::needless_return)]
+
+use chess::ChessMove;
+use mcts::chess_env::{ChessRolloutPolicy, ChessState};
+use mcts::mcts::MCTSTree;
+use mcts::time_manager::TimeControl;
+use std::ffi::CString;
+use std::os::raw::c_char;
+use std::time::Duration;
+
+
+/// Number of nodes reserved for a search, enough for a few seconds in a browser.
+const ARENA_CAPACITY: usize = 200000;
+
+
+/// Allocates `len` bytes for the page to write a FEN into.
+#[no_mangle]
+pub extern "C" fn alloc(len: usize) -> *mut u8 {
+    let mut buffer = Vec::<u8>::with_capacity(len);
+    let pointer = buffer.as_mut_ptr();
+    std::mem::forget(buffer);
+    return pointer;
+}
+
+/// Frees `len` bytes allocated by `alloc`.
+///
+/// # Safety
+/// `pointer` was returned by `alloc(len)` and is not used after.
+#[no_mangle]
+pub unsafe extern "C" fn dealloc(pointer: *mut u8, len: usize) {
+    drop(Vec::from_raw_parts(pointer, 0, len));
+}
+
+/// Searches the position of the FEN at `fen` for `millis` milliseconds.
+///
+/// # Arguments
+/// * `fen` : UTF-8 FEN of the position, `fen_len` bytes long.
+/// * `millis` : Time of the search.
+/// * `seed` : Seed of the random generator of the tree, such that searches differ between games.
+///
+/// # Returns
+/// The engine's move as a NUL terminated UCI string, to be freed with `free_string`. Null if
+/// the FEN is invalid or the position has no legal moves.
+///
+/// # Safety
+/// `fen` points to `fen_len` readable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn best_move(fen: *const u8, fen_len: usize, millis: u32, seed: u32) -> *mut c_char {
+    let Ok(fen) = std::str::from_utf8(std::slice::from_raw_parts(fen, fen_len)) else {
+        return std::ptr::null_mut();
+    };
+    // A zero seed leaves the xorshift generator without state, so seeds are offset by one.
+    let Ok(mut tree) = MCTSTree::<ChessMove, ChessState>::try_with_capacity(ARENA_CAPACITY, Some(seed as u64 + 1), fen, 30) else {
+        return std::ptr::null_mut();
+    };
+    tree.searcher.rollout_policy = Box::new(ChessRolloutPolicy::default());
+    tree.searcher.early_termination = true;
+    tree.budget.max_nodes = Some(ARENA_CAPACITY);
+    tree.go(TimeControl::fixed(Duration::from_millis(millis as u64)));
+
+    return match tree.current_best() {
+        Some(best) => CString::new(best.action.to_string()).expect("move has no NUL").into_raw(),
+        None => std::ptr::null_mut(),
+    };
+}
+
+/// Frees a move returned by `best_move`.
+///
+/// # Safety
+/// `pointer` was returned by `best_move` and is not used after.
+#[no_mangle]
+pub unsafe extern "C" fn free_string(pointer: *mut c_char) {
+    if !pointer.is_null() {
+        drop(CString::from_raw(pointer));
+    }
+}
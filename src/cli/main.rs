@@ -0,0 +1,76 @@
+//! Command line search tool, running the search described by a TOML configuration file.
+//!
+//! Run as `mcts-cli <config.toml>`, see `mcts::config` for the keys of the configuration.
+//! The best action and root statistics are printed as text or JSON, and the tree is exported
+//! to a JSON file when the configuration names one.
+
+// Explicit returns are the style of the codebase.
+#![allow(clippy::needless_return)]
+
+#[cfg(not(target_arch = "wasm32"))]
+#[global_allocator]
+static GLOBAL: mimalloc::MiMalloc = mimalloc::MiMalloc;
+
+use mcts::config::{OutputFormat, SearchConfig};
+use mcts::service::{search, SearchResponse};
+use std::process::ExitCode;
+
+
+/// Prints the best action, the root moves by visits and the totals of the search.
+fn print_text(response: &SearchResponse) {
+    println!("best action: {}", response.best_action);
+    println!();
+    let width = response.moves.iter().map(|root_move| root_move.action.len()).max().unwrap_or(0).max(6);
+    println!("{:<width$}  {:>8}  {:>6}", "action", "visits", "value", width = width);
+    for root_move in &response.moves {
+        println!("{:<width$}  {:>8}  {:>6.3}", root_move.action, root_move.visits, root_move.value, width = width);
+    }
+    println!();
+    println!(
+        "{} iterations, {} nodes in {} ms",
+        response.iterations, response.nodes, response.elapsed.as_millis()
+    );
+}
+
+fn main() -> ExitCode {
+    let Some(path) = std::env::args().nth(1) else {
+        eprintln!("usage: mcts-cli <config.toml>");
+        return ExitCode::FAILURE;
+    };
+    let config = match std::fs::read_to_string(&path) {
+        Ok(text) => SearchConfig::parse(&text),
+        Err(error) => {
+            eprintln!("can not read {}: {}", path, error);
+            return ExitCode::FAILURE;
+        },
+    };
+    let config = match config {
+        Ok(config) => config,
+        Err(error) => {
+            eprintln!("invalid configuration {}: {}", path, error);
+            return ExitCode::FAILURE;
+        },
+    };
+
+    let mut response = match search(&config.request) {
+        Ok(response) => response,
+        Err(error) => {
+            eprintln!("search failed: {}", error);
+            return ExitCode::FAILURE;
+        },
+    };
+
+    // The tree goes to its own file rather than into the printed result.
+    if let (Some(tree_path), Some(tree)) = (&config.tree_path, response.tree.take()) {
+        if let Err(error) = std::fs::write(tree_path, tree) {
+            eprintln!("can not write {}: {}", tree_path.display(), error);
+            return ExitCode::FAILURE;
+        }
+    }
+
+    match config.format {
+        OutputFormat::Text => print_text(&response),
+        OutputFormat::Json => println!("{}", response.to_json()),
+    }
+    return ExitCode::SUCCESS;
+}
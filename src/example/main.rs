@@ -1,9 +1,14 @@
+// Explicit returns are the style of the codebase.
+#![allow(clippy::needless_return)]
+
+#[cfg(not(target_arch = "wasm32"))]
 #[global_allocator]
 static GLOBAL: mimalloc::MiMalloc = mimalloc::MiMalloc;
 
 use chess::{Board, ChessMove, BoardStatus};
-use mcts::mcts::MCTSTree;
+use mcts::mcts::{MCTSTree, TreeConfig};
 use mcts::chess_env::ChessState;
+use mcts::pgn::PgnGame;
 use std::io;
 use std::io::{stdin, Write};
 use std::str::FromStr;
@@ -23,6 +28,7 @@ fn piece_to_char(piece: chess::Piece) -> char {
 
 
 /// Print chess position to stdout.
+#[allow(clippy::unnecessary_unwrap, clippy::println_empty_string)]
 fn print_board(board: Board) {
     for i in (0..8).rev() {
         for j in 0..8 {
@@ -60,9 +66,25 @@ fn print_board(board: Board) {
 
 /// Provides a basic match against the MCTS engine in chess.
 /// User always goes first.
+///
+/// A PGN file can be passed as the first argument to continue the game it records.
+/// The game is printed as PGN once it ends.
 pub fn main() {
     let runs = 50000;
-    let mut game_state = Board::default();
+    let mut pgn_game = match std::env::args().nth(1) {
+        Some(path) => {
+            let pgn = std::fs::read_to_string(&path).expect("could not read pgn file");
+            PgnGame::from_pgn(&pgn).expect("invalid pgn")
+        },
+        None => PgnGame::new(),
+    };
+    pgn_game.set_header("White", "user");
+    pgn_game.set_header("Black", "mcts");
+    let mut game_state = pgn_game.game_state().board;
+
+    // The tree is allocated once, and reset to the position of every engine move.
+    let config = TreeConfig { arena_capacity: 100000, seed: None, average_child_count: 30 };
+    let mut tree = MCTSTree::<ChessMove, ChessState>::from_state(pgn_game.game_state(), config);
     
     while game_state.status() == BoardStatus::Ongoing {
         // Get user move in SAN.
@@ -72,29 +94,38 @@ pub fn main() {
         let _ = stdin().read_line(&mut move_text);
 
         // Make move and update gamestate.
-        game_state = game_state.make_move_new(ChessMove::from_san(&game_state, &move_text).unwrap());
+        let user_move = ChessMove::from_san(&game_state, move_text.trim()).unwrap();
+        game_state = game_state.make_move_new(user_move);
+        pgn_game.push(user_move);
 
         // Print board after user move.
         print_board(game_state);
 
 
-        // Construct MCTS tree from game state fen and find optimal path.
-        let mut tree = MCTSTree::<ChessMove, ChessState>::with_capacity(100000, None, game_state.to_string(), 30);
+        // Root the tree at the game state and find optimal path.
+        tree.reset(pgn_game.game_state());
         for _i in 0..runs {
-            let select = tree.select(0, None);
+            let select = tree.select(tree.root(), None);
             let expand = tree.expand(select);
             let simulate = tree.simulate(expand);
             tree.backpropagate(expand, simulate);
         }
-        let path = tree.trace_path(tree.select(0, Some(0.0)));
+
+        // Show the candidate moves the engine weighed before making its choice.
+        print!("{}", tree.root_move_table(Some(8), 6));
+        let best_leaf = tree.select(tree.root(), Some(0.0));
+        let path = tree.trace_path(best_leaf);
 
         // Best move is first move of optimal path.
-        let action = &tree.arena[path[0]];
+        let action = *tree.action(path[0]).expect("stale node").expect("no action");
 
         // Make optimal move.
-        game_state = game_state.make_move_new(action.game_state.last_move.unwrap());
+        game_state = game_state.make_move_new(action);
+        pgn_game.push(action);
 
         // Print board after MCTS move.
         print_board(game_state);
     }
+
+    print!("{}", pgn_game.to_pgn());
 }
@@ -0,0 +1,170 @@
+//! Anytime queries of the best root action, readable from other threads while a search runs,
+//! so that a "move now" request or a nearly flagged clock can be answered immediately.
+
+use crate::arena::{NodeId, NodeIndex};
+use crate::game_state_trait::GameState;
+use crate::hooks::{SearchControl, SearchHooks};
+use crate::mcts::MCTSTree;
+use std::sync::{Arc, Mutex};
+
+
+/// The best root action of a search at some point in time, with its statistics.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RootBest<Action> {
+    /// Action of the most visited child of the root.
+    pub action: Action,
+
+    /// Simulations of the child.
+    pub visits: u64,
+
+    /// Mean score of the child, for the player choosing the action.
+    pub value: f64,
+
+    /// Simulations of the root.
+    pub root_visits: u64,
+}
+
+
+/// Current best action.
+impl<Action, GameStateObj, Data> MCTSTree<Action, GameStateObj, Data>
+where
+    Action: Clone,
+    GameStateObj: GameState<Action> + Clone
+{
+    /// The most visited child of the root, and its statistics.
+    ///
+    /// Only the children of the root are read, so this can be called between any two iterations.
+    /// Ties go to the first expanded child.
+    ///
+    /// # Returns
+    /// None if no child of the root has been visited.
+    pub fn current_best(&self) -> Option<RootBest<Action>> {
+        let mut best: Option<NodeIndex> = None;
+        for child in self.arena.expanded(0) {
+            let sims = self.arena.stats[child as usize].sims;
+            if sims > 0 && best.is_none_or(|best| sims > self.arena.stats[best as usize].sims) {
+                best = Some(child);
+            }
+        }
+        return best.map(|best| {
+            let stats = &self.arena.stats[best as usize];
+            return RootBest {
+                action: self.arena.actions[best as usize].clone().expect("no action"),
+                visits: stats.sims,
+                value: stats.score / stats.sims as f64,
+                root_visits: self.arena.stats[0].sims,
+            };
+        });
+    }
+}
+
+
+/// A snapshot of the best root action shared between a search and its readers.
+///
+/// Passed as the hooks of `MCTSTree::search_with_hooks`, it publishes `current_best` whenever
+/// the best action changes and every `interval` iterations, and clones of it read the latest
+/// snapshot from any thread. Snapshots are replaced as a whole, so readers never see the
+/// action of one snapshot with the statistics of another.
+#[derive(Debug)]
+pub struct SharedBest<Action> {
+    /// Latest published snapshot, None before the first one.
+    snapshot: Arc<Mutex<Option<RootBest<Action>>>>,
+
+    /// Number of iterations between refreshes of the statistics of an unchanged best action.
+    pub interval: u64,
+}
+
+impl<Action> Clone for SharedBest<Action> {
+    fn clone(&self) -> Self {
+        return SharedBest { snapshot: self.snapshot.clone(), interval: self.interval };
+    }
+}
+
+
+impl<Action: Clone> SharedBest<Action> {
+    /// Creates a shared snapshot without any published best action.
+    ///
+    /// # Panics
+    /// If `interval` is 0.
+    pub fn new(interval: u64) -> Self {
+        assert!(interval > 0, "interval must be positive");
+        return SharedBest { snapshot: Arc::new(Mutex::new(None)), interval };
+    }
+
+    /// The latest published snapshot, None if the search has not visited any child of the root yet.
+    pub fn get(&self) -> Option<RootBest<Action>> {
+        return self.snapshot.lock().expect("poisoned snapshot").clone();
+    }
+
+    /// Publishes the current best action of `tree`.
+    pub fn publish<GameStateObj, Data>(&self, tree: &MCTSTree<Action, GameStateObj, Data>)
+    where
+        GameStateObj: GameState<Action> + Clone
+    {
+        let best = tree.current_best();
+        *self.snapshot.lock().expect("poisoned snapshot") = best;
+    }
+}
+
+impl<Action, GameStateObj, Data> SearchHooks<Action, GameStateObj, Data> for SharedBest<Action>
+where
+    Action: Clone,
+    GameStateObj: GameState<Action> + Clone
+{
+    fn on_iteration(&mut self, tree: &MCTSTree<Action, GameStateObj, Data>, iteration: u64) -> SearchControl {
+        if iteration.is_multiple_of(self.interval) {
+            self.publish(tree);
+        }
+        return SearchControl::Continue;
+    }
+
+    fn on_new_best_move(&mut self, tree: &MCTSTree<Action, GameStateObj, Data>, _best: NodeId) {
+        self.publish(tree);
+    }
+}
+
+
+/// Defines unit tests for anytime queries.
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tictactoe_env::TicTacToeState;
+
+    /// Test if the current best action is the most visited child of the root.
+    #[test]
+    fn test_current_best() {
+        let mut tree = MCTSTree::<u8, TicTacToeState>::with_capacity(3000, Some(1), ".........".to_string(), 9);
+        assert!(tree.current_best().is_none());
+        tree.search(1000);
+
+        let best = tree.current_best().unwrap();
+        let policy = tree.root_policy();
+        let max_visits = policy.iter().map(|(_, visits, _)| *visits).max().unwrap();
+        let first_best = policy.iter().find(|(_, visits, _)| *visits == max_visits).unwrap();
+        assert_eq!((best.action, best.visits, best.root_visits), (first_best.0, max_visits, 1000));
+        assert!(best.value >= 0.0 && best.value <= 1.0);
+    }
+
+    /// Test if a search on another thread publishes snapshots which can be read while it runs.
+    #[test]
+    fn test_shared_best() {
+        let shared = SharedBest::<u8>::new(100);
+        assert!(shared.get().is_none());
+
+        let mut hooks = shared.clone();
+        let search = std::thread::spawn(move || {
+            let mut tree = MCTSTree::<u8, TicTacToeState>::with_capacity(6000, Some(1), ".........".to_string(), 9);
+            tree.search_with_hooks(5000, &mut hooks);
+            return tree.current_best();
+        });
+        while !search.is_finished() {
+            if let Some(best) = shared.get() {
+                assert!(best.visits <= best.root_visits);
+            }
+        }
+        let best = search.join().unwrap();
+
+        // The last iteration is a multiple of the interval, so the final snapshot is published.
+        assert_eq!(shared.get(), best);
+    }
+}
@@ -0,0 +1,590 @@
+use crate::game_state_trait::GameState;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::fmt;
+use core::mem;
+
+/// Index of a node within the tree's memory arena.
+///
+/// Defaults to `usize`. The `compact` feature switches to `u32`, which halves the size
+/// of parent and child references for trees with less than 4 billion nodes.
+#[cfg(not(feature = "compact"))]
+pub type NodeIndex = usize;
+
+/// Index of a node within the tree's memory arena.
+///
+/// Defaults to `usize`. The `compact` feature switches to `u32`, which halves the size
+/// of parent and child references for trees with less than 4 billion nodes.
+#[cfg(feature = "compact")]
+pub type NodeIndex = u32;
+
+
+/// Generation checked handle to a node.
+///
+/// Raw indices are reused once nodes are freed, so an index held across a free may silently
+/// refer to an unrelated node. A handle also records the arena generation its node was
+/// created in, letting the arena detect handles to freed nodes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct NodeId {
+    /// Index of the node within the arena.
+    pub index: NodeIndex,
+
+    /// Arena generation the node was created in.
+    pub generation: u32,
+}
+
+
+/// Error produced when resolving a `NodeId` whose node has been freed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StaleNodeError(pub NodeId);
+
+impl fmt::Display for StaleNodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        return write!(f, "node {} of generation {} has been freed", self.0.index, self.0.generation);
+    }
+}
+
+impl core::error::Error for StaleNodeError {}
+
+
+/// Represents a node in the mcts game tree.
+/// It holds game tree information as well as mcts statistics.
+///
+/// Trees datastructures in general hold their parent as well as a list of thier children.
+///
+/// Game trees associate each node with a gamestate and each line as an action.
+///
+/// Mcts progressively builds the tree, starting with children being represented as an action before being explored
+/// to having a full node representation once explored.
+///
+/// Results of rollouts and child rollouts are kept within the tree for ucb calculation.
+///
+/// In order to avoid self referential structure sizing, all references to nodes are
+/// opaque pointers, with the actual nodes being allocated within a memory arena.
+///
+/// The arena does not store nodes in this form. `MCTSNode` is the owned representation
+/// used to insert a node, which the arena splits into its parallel arrays.
+///
+/// `Data` is a payload integrations may attach to nodes, such as an evaluation cache or
+/// domain annotations, and defaults to none.
+#[derive(Debug)]
+pub struct MCTSNode<Action, GameStateObj, Data = ()>
+where
+    GameStateObj: GameState<Action> + Clone
+{
+    // Game state asocciated with the node in the tree.
+    pub game_state: GameStateObj,
+
+    /// Action applied to the parent's game state to reach this node. None if root.
+    pub action: Option<Action>,
+
+    /// Parent of current node. None if root, as root has no parent.
+    pub parent: Option<NodeIndex>,
+
+    /// Tree indexes for already expanded children.
+    pub expanded: Vec<NodeIndex>,
+
+    /// Legal moves corresponding to unexpanded child nodes.
+    pub unexpanded: Vec<Action>,
+
+    /// Sum of all simulation wins of the sub-graph with the current node as its root.
+    pub wins: u64,
+
+    /// Sum of all simulation draws of the sub-graph with the current node as its root.
+    /// technically not needed for MCTS, but allows for differentiating draws and losses.
+    pub draws: u64,
+
+    /// Sum of all simulations of the sub-graph with the current node as its root.
+    pub sims: u64,
+
+    /// Sum of all simulation rewards of the sub-graph with the current node as its root.
+    pub score: f64,
+
+    /// User data attached to the node, None until set.
+    pub data: Option<Data>,
+}
+
+
+/// Simulation statistics of a node.
+///
+/// These are the only values read for every child during selection and written
+/// for every node during backpropagation, so they are kept densely packed.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct NodeStats {
+    /// Sum of all simulation wins of the sub-graph with the current node as its root.
+    pub wins: u64,
+
+    /// Sum of all simulation draws of the sub-graph with the current node as its root.
+    /// technically not needed for MCTS, but allows for differentiating draws and losses.
+    pub draws: u64,
+
+    /// Sum of all simulations of the sub-graph with the current node as its root.
+    pub sims: u64,
+
+    /// Sum of all simulation rewards of the sub-graph with the current node as its root,
+    /// from the perspective of the player who moved into the node.
+    ///
+    /// For two player games a win is worth 1, a draw `Searcher::draw_score` and a loss 0,
+    /// so this matches `wins` plus the credit of `draws` unless games report `GameResult::Score`.
+    pub score: f64,
+}
+
+impl NodeStats {
+    /// Adds the statistics of `other`, such as those of the same node in another tree.
+    ///
+    /// # Panics
+    /// In debug builds, if a counter overflows. Release builds saturate at `u64::MAX` instead.
+    pub fn add(&mut self, other: &NodeStats) {
+        self.wins = add_count(self.wins, other.wins);
+        self.draws = add_count(self.draws, other.draws);
+        self.sims = add_count(self.sims, other.sims);
+        self.score += other.score;
+    }
+}
+
+
+/// Adds `amount` to the counter `count`, checked in debug builds and saturating in release builds,
+/// so that long analyses and merged trees never wrap around silently.
+pub(crate) fn add_count(count: u64, amount: u64) -> u64 {
+    debug_assert!(count.checked_add(amount).is_some(), "visit counter overflow");
+    return count.saturating_add(amount);
+}
+
+
+/// Entry in the child list of a node.
+#[derive(Debug, Clone)]
+pub enum ChildSlot<Action> {
+    /// Child which has been expanded into a node of the tree.
+    Expanded(NodeIndex),
+
+    /// Legal action whose child has not been expanded yet.
+    Unexpanded(Action),
+}
+
+
+/// Region of the child slab holding the children of a single node.
+///
+/// The first `expanded` slots of the region hold expanded children,
+/// the remaining slots up to `len` hold unexpanded actions.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct ChildSegment {
+    /// Position of the first child within the slab. Kept as `usize` under `compact`, as the slab
+    /// may outgrow the node count.
+    pub start: usize,
+
+    /// Number of children of the node.
+    pub len: u32,
+
+    /// Number of children which have been expanded.
+    pub expanded: u32,
+
+    /// Number of slots reserved for the node. Slots past `len` are placeholders.
+    pub capacity: u32,
+
+    /// True once every legal action of the node is held in the segment.
+    /// False while actions are yet to be generated.
+    pub complete: bool,
+}
+
+
+/// Links between a node and the rest of the tree.
+#[derive(Debug, Clone, Copy)]
+pub struct NodeTopology {
+    /// Parent of current node. None if root, as root has no parent.
+    pub parent: Option<NodeIndex>,
+
+    /// Cached `GameState::side_to_move` of the node's game state, so backpropagation
+    /// does not need to touch the game state arena.
+    pub side_to_move: bool,
+
+    /// Cached `GameState::is_chance_node` of the node's game state.
+    pub is_chance: bool,
+
+    /// Probability of reaching the node from its parent. The outcome probability for the
+    /// children of chance nodes, the prior of the action for children expanded from 
+    /// `GameState::action_priors`, and 1 otherwise.
+    pub probability: f32,
+
+    /// Location of the node's children within the child slab.
+    pub children: ChildSegment,
+}
+
+
+/// Memory arena for mcts nodes, laid out as a struct of arrays.
+///
+/// A node is the set of entries sharing the same index in each array. Keeping the
+/// statistics, topology and game states apart means selection and backpropagation
+/// only pull statistics and parent links through the cache, rather than every
+/// node's (potentially large) game state.
+///
+/// The children of every node are stored contiguously within a single shared slab,
+/// so nodes do not hold any allocations of their own.
+///
+/// Game states are stored sparsely. A node without a stored state is re-derived by
+/// replaying the actions leading to it from its nearest ancestor that has one.
+pub struct MCTSArena<Action, GameStateObj, Data = ()> {
+    /// Simulation statistics of every node.
+    pub stats: Vec<NodeStats>,
+
+    /// Parent and child links of every node.
+    pub topology: Vec<NodeTopology>,
+
+    /// Child lists of every node, referenced through `NodeTopology::children`.
+    pub children: Vec<ChildSlot<Action>>,
+
+    /// Action applied to the parent's game state to reach every node. None for the root.
+    pub actions: Vec<Option<Action>>,
+
+    /// Index into `states` of every node's game state. None if the state was not stored.
+    pub state_slots: Vec<Option<NodeIndex>>,
+
+    /// Stored game states, referenced through `state_slots`.
+    pub states: Vec<GameStateObj>,
+
+    /// User data attached to every node, see `MCTSNode::data`.
+    pub data: Vec<Option<Data>>,
+
+    /// Generation every node was created in.
+    pub generations: Vec<u32>,
+
+    /// Current generation of the arena, advanced every time nodes are freed.
+    pub generation: u32,
+}
+
+
+impl<Action, GameStateObj, Data> MCTSArena<Action, GameStateObj, Data>
+where
+    GameStateObj: GameState<Action> + Clone
+{
+    /// Creates an empty arena with space reserved for `capacity` nodes in each array,
+    /// and `child_capacity` children within the child slab.
+    pub fn with_capacity(capacity: usize, child_capacity: usize) -> Self {
+        return Self {
+            stats: Vec::with_capacity(capacity),
+            topology: Vec::with_capacity(capacity),
+            children: Vec::with_capacity(child_capacity),
+            actions: Vec::with_capacity(capacity),
+            state_slots: Vec::with_capacity(capacity),
+            states: Vec::with_capacity(capacity),
+            data: Vec::with_capacity(capacity),
+            generations: Vec::with_capacity(capacity),
+            generation: 0,
+        };
+    }
+
+    /// Splits `node` across the arena arrays.
+    ///
+    /// # Arguments
+    /// * `node` : The node to insert.
+    ///
+    /// * `store_state` : Whether to keep the node's game state. If false the state is dropped
+    /// and has to be re-derived from an ancestor when required.
+    ///
+    /// # Returns
+    /// The index of the newly inserted node.
+    pub fn push(&mut self, node: MCTSNode<Action, GameStateObj, Data>, store_state: bool) -> NodeIndex {
+        let topology = NodeTopology {
+            parent: node.parent,
+            side_to_move: node.game_state.side_to_move(),
+            is_chance: node.game_state.is_chance_node(),
+            probability: 1.0,
+            children: self.allocate_children(node.expanded, node.unexpanded),
+        };
+        let stats = NodeStats { wins: node.wins, draws: node.draws, sims: node.sims, score: node.score };
+        let game_state = if store_state { Some(node.game_state) } else { None };
+        return self.push_parts(stats, topology, node.action, game_state, node.data);
+    }
+
+    /// Inserts a node from its already split parts.
+    ///
+    /// Allows nodes without a game state to be moved between arenas.
+    /// The child segment of `topology` must already be allocated within this arena.
+    ///
+    /// # Returns
+    /// The index of the newly inserted node.
+    ///
+    /// # Panics
+    /// If the index of the node does not fit in `NodeIndex`, which only happens under `compact`.
+    pub fn push_parts(
+        &mut self,
+        stats: NodeStats,
+        topology: NodeTopology,
+        action: Option<Action>,
+        game_state: Option<GameStateObj>,
+        data: Option<Data>)
+    -> NodeIndex {
+        // States are only stored for some nodes, so their slots fit whenever the node index does.
+        let node = NodeIndex::try_from(self.stats.len()).expect("arena exceeds NodeIndex range");
+        self.stats.push(stats);
+        self.topology.push(topology);
+        self.actions.push(action);
+        self.data.push(data);
+        self.generations.push(self.generation);
+        if let Some(game_state) = game_state {
+            self.state_slots.push(Some(self.states.len() as NodeIndex));
+            self.states.push(game_state);
+        }
+        else {
+            self.state_slots.push(None);
+        }
+        return node;
+    }
+
+    /// Appends a contiguous child list to the end of the child slab.
+    ///
+    /// # Returns
+    /// The segment of the slab holding the children.
+    pub fn allocate_children<E, U>(&mut self, expanded: E, unexpanded: U) -> ChildSegment
+    where
+        E: IntoIterator<Item = NodeIndex>,
+        U: IntoIterator<Item = Action>
+    {
+        let start = self.children.len();
+        self.children.extend(expanded.into_iter().map(ChildSlot::Expanded));
+        let expanded_count = self.children.len() - start;
+        self.children.extend(unexpanded.into_iter().map(ChildSlot::Unexpanded));
+        let len = (self.children.len() - start) as u32;
+        return ChildSegment {
+            start,
+            len,
+            expanded: expanded_count as u32,
+            capacity: len,
+            complete: true,
+        };
+    }
+
+    /// Allocates an empty child list for a node whose actions have not been generated yet.
+    pub fn allocate_lazy_children(&mut self) -> ChildSegment {
+        return ChildSegment { start: self.children.len(), complete: false, ..Default::default() };
+    }
+
+    /// Appends an expanded child to the child list of a lazily generated `node`.
+    ///
+    /// When the segment is full, its children are moved to the end of the slab with doubled
+    /// capacity. The slab space of the previous segment is not reused.
+    ///
+    /// # Invariants
+    /// Assumes every child of `node` is expanded, which holds for lazily generated nodes.
+    pub fn push_expanded_child(&mut self, node: NodeIndex, child: NodeIndex) {
+        let mut segment = self.topology[node as usize].children;
+        if segment.len == segment.capacity {
+            let start = self.children.len();
+            for i in 0..segment.len as usize {
+                let existing = match self.children[segment.start + i] {
+                    ChildSlot::Expanded(existing) => existing,
+                    ChildSlot::Unexpanded(_) => panic!("unexpanded child within lazy segment"),
+                };
+                self.children.push(ChildSlot::Expanded(existing));
+            }
+            segment.capacity = (segment.capacity * 2).max(4);
+            self.children.extend((segment.len..segment.capacity).map(|_| ChildSlot::Expanded(0)));
+            segment.start = start;
+        }
+        self.children[segment.start + segment.len as usize] = ChildSlot::Expanded(child);
+        segment.len += 1;
+        segment.expanded += 1;
+        self.topology[node as usize].children = segment;
+    }
+
+    /// Replaces the children of `node` with a newly allocated child list.
+    ///
+    /// The slab space of the previous child list is not reused.
+    pub fn set_children(&mut self, node: NodeIndex, expanded: Vec<NodeIndex>, unexpanded: Vec<Action>) {
+        self.topology[node as usize].children = self.allocate_children(expanded, unexpanded);
+    }
+
+    /// Iterates over the indexes of the expanded children of `node`.
+    pub fn expanded(&self, node: NodeIndex) -> impl Iterator<Item = NodeIndex> + '_ {
+        let segment = self.topology[node as usize].children;
+        let start = segment.start;
+        return self.children[start..start + segment.expanded as usize].iter().map(|slot| match slot {
+            ChildSlot::Expanded(child) => *child,
+            ChildSlot::Unexpanded(_) => panic!("unexpanded child within expanded region"),
+        });
+    }
+
+    /// Iterates over the actions of the unexpanded children of `node`.
+    pub fn unexpanded(&self, node: NodeIndex) -> impl Iterator<Item = &Action> + '_ {
+        let segment = self.topology[node as usize].children;
+        let start = segment.start;
+        return self.children[start + segment.expanded as usize..start + segment.len as usize].iter().map(|slot| match slot {
+            ChildSlot::Unexpanded(action) => action,
+            ChildSlot::Expanded(_) => panic!("expanded child within unexpanded region"),
+        });
+    }
+
+    /// Number of expanded children of `node`.
+    pub fn expanded_count(&self, node: NodeIndex) -> usize {
+        return self.topology[node as usize].children.expanded as usize;
+    }
+
+    /// True if `node` may have children left to expand, either unexpanded
+    /// actions or actions which have not been generated yet.
+    pub fn has_untried_actions(&self, node: NodeIndex) -> bool {
+        return self.unexpanded_count(node) > 0 || !self.topology[node as usize].children.complete;
+    }
+
+    /// Number of unexpanded children of `node`.
+    pub fn unexpanded_count(&self, node: NodeIndex) -> usize {
+        let segment = self.topology[node as usize].children;
+        return (segment.len - segment.expanded) as usize;
+    }
+
+    /// Returns the action of the `index`-th unexpanded child of `node`.
+    pub fn unexpanded_action(&self, node: NodeIndex, index: usize) -> &Action {
+        return self.unexpanded(node).nth(index).expect("unexpanded index out of range");
+    }
+
+    /// Marks the `index`-th unexpanded child of `node` as expanded into `child`.
+    ///
+    /// The remaining unexpanded actions keep their relative order.
+    ///
+    /// # Returns
+    /// The action of the expanded child, which is no longer held by the slab.
+    pub fn expand_child(&mut self, node: NodeIndex, index: usize, child: NodeIndex) -> Action {
+        let segment = &mut self.topology[node as usize].children;
+        let first_unexpanded = segment.start + segment.expanded as usize;
+        segment.expanded += 1;
+
+        // Move the chosen action to the front of the unexpanded region, then expand it in place.
+        self.children[first_unexpanded..=first_unexpanded + index].rotate_right(1);
+        match core::mem::replace(&mut self.children[first_unexpanded], ChildSlot::Expanded(child)) {
+            ChildSlot::Unexpanded(action) => return action,
+            ChildSlot::Expanded(_) => panic!("expanded child within unexpanded region"),
+        }
+    }
+
+    /// Returns the game state stored for `node`, or None if it has to be re-derived.
+    pub fn stored_state(&self, node: NodeIndex) -> Option<&GameStateObj> {
+        return self.state_slots[node as usize].map(|slot| &self.states[slot as usize]);
+    }
+
+    /// Replaces the arena with a compacted copy holding only the subtree of `root`.
+    ///
+    /// Nodes are copied breadth first, so `root` is placed at index 0 and parents always
+    /// precede their children. The copy is a new generation of the arena, so every
+    /// existing handle becomes stale.
+    ///
+    /// # Arguments
+    /// * `root` : The node whose subtree survives.
+    ///
+    /// * `root_game_state` : Game state of `root`. The root has no parent to replay from,
+    /// so it always stores its state.
+    ///
+    /// # Returns
+    /// A mapping from old node indices to the new ones. None for freed nodes.
+    pub fn retain_subtree(&mut self, root: NodeIndex, root_game_state: GameStateObj) -> Vec<Option<NodeIndex>> {
+        // Order the surviving nodes breadth first.
+        let mut order: Vec<NodeIndex> = vec![root];
+        let mut remap: Vec<Option<NodeIndex>> = vec![None; self.len()];
+        let mut i = 0;
+        while i < order.len() {
+            remap[order[i] as usize] = Some(i as NodeIndex);
+            let children: Vec<NodeIndex> = self.expanded(order[i]).collect();
+            order.extend(children);
+            i += 1;
+        }
+
+        // The new arena only reserves the surviving nodes, releasing the memory of the others.
+        let child_count = order.iter().map(|node| self.topology[*node as usize].children.len as usize).sum();
+        let mut old = core::mem::replace(self, MCTSArena::with_capacity(order.len(), child_count));
+        self.generation = old.generation + 1;
+        let mut old_states: Vec<Option<GameStateObj>> = old.states.into_iter().map(Some).collect();
+        let mut old_children: Vec<Option<ChildSlot<Action>>> = old.children.into_iter().map(Some).collect();
+        let mut root_game_state = Some(root_game_state);
+
+        // Move every surviving node into the new arena, remapping its links.
+        // The root's parent lies outside of the subtree, so it is remapped to None.
+        for old_node in order {
+            let old_topology = old.topology[old_node as usize];
+            let start = old_topology.children.start;
+            let (expanded, unexpanded) = old_children[start..start + old_topology.children.len as usize]
+                .split_at_mut(old_topology.children.expanded as usize);
+            let mut children = self.allocate_children(
+                expanded.iter().map(|slot| match slot {
+                    Some(ChildSlot::Expanded(child)) => remap[*child as usize].expect("unreachable child"),
+                    _ => panic!("unexpanded child within expanded region"),
+                }),
+                unexpanded.iter_mut().map(|slot| match slot.take() {
+                    Some(ChildSlot::Unexpanded(action)) => action,
+                    _ => panic!("expanded child within unexpanded region"),
+                }),
+            );
+            children.complete = old_topology.children.complete;
+            let topology = NodeTopology {
+                parent: old_topology.parent.and_then(|parent| remap[parent as usize]),
+                children,
+                ..old_topology
+            };
+            let (action, game_state) = if old_node == root {
+                (None, root_game_state.take())
+            }
+            else {
+                (
+                    old.actions[old_node as usize].take(),
+                    old.state_slots[old_node as usize].and_then(|slot| old_states[slot as usize].take())
+                )
+            };
+            let data = old.data[old_node as usize].take();
+            self.push_parts(old.stats[old_node as usize], topology, action, game_state, data);
+        }
+
+        return remap;
+    }
+
+    /// Frees every node in the arena, keeping the allocated capacity.
+    ///
+    /// Advances the arena generation, so handles to the freed nodes no longer resolve.
+    pub fn clear(&mut self) {
+        self.stats.clear();
+        self.topology.clear();
+        self.children.clear();
+        self.actions.clear();
+        self.state_slots.clear();
+        self.states.clear();
+        self.data.clear();
+        self.generations.clear();
+        self.generation += 1;
+    }
+
+    /// Creates a generation checked handle to the node at `index`.
+    pub fn handle(&self, index: NodeIndex) -> NodeId {
+        return NodeId { index, generation: self.generations[index as usize] };
+    }
+
+    /// Resolves a handle back into an arena index.
+    ///
+    /// # Returns
+    /// The index of the node, or a `StaleNodeError` if the node has been freed.
+    pub fn resolve(&self, id: NodeId) -> Result<NodeIndex, StaleNodeError> {
+        match self.generations.get(id.index as usize) {
+            Some(generation) if *generation == id.generation => return Ok(id.index),
+            _ => return Err(StaleNodeError(id)),
+        }
+    }
+
+    /// Bytes reserved by the arena arrays, including their unused capacity.
+    ///
+    /// Heap memory owned by actions, game states and user data themselves, such as the move
+    /// history of a game state, is not counted.
+    pub fn memory_usage(&self) -> usize {
+        return self.stats.capacity() * mem::size_of::<NodeStats>()
+            + self.topology.capacity() * mem::size_of::<NodeTopology>()
+            + self.children.capacity() * mem::size_of::<ChildSlot<Action>>()
+            + self.actions.capacity() * mem::size_of::<Option<Action>>()
+            + self.state_slots.capacity() * mem::size_of::<Option<NodeIndex>>()
+            + self.states.capacity() * mem::size_of::<GameStateObj>()
+            + self.data.capacity() * mem::size_of::<Option<Data>>()
+            + self.generations.capacity() * mem::size_of::<u32>();
+    }
+
+    /// Number of nodes held within the arena.
+    pub fn len(&self) -> usize {
+        return self.stats.len();
+    }
+
+    /// True if the arena holds no nodes.
+    pub fn is_empty(&self) -> bool {
+        return self.stats.is_empty();
+    }
+}
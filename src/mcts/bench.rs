@@ -0,0 +1,182 @@
+//! Benchmarks of the search across the bundled environments, with fixed seeds so that
+//! runs are comparable between builds. The `search` bench target reports them with `cargo bench`.
+
+#[cfg(feature = "chess")]
+use crate::chess_env::{ChessRolloutPolicy, ChessState};
+use crate::connect4_env::Connect4State;
+use crate::game2048_env::Game2048State;
+use crate::game_state_trait::GameState;
+use crate::go_env::{GoRolloutPolicy, GoState};
+use crate::hex_env::HexState;
+use crate::mcts::{MCTSTree, PlayerMode};
+use crate::nim_env::NimState;
+use crate::tictactoe_env::TicTacToeState;
+use std::fmt;
+use crate::clock::Instant;
+use std::time::Duration;
+
+
+/// Seed of the random generator of every benchmarked tree.
+pub const BENCH_SEED: u64 = 1;
+
+/// Starting position of the chess benchmark.
+#[cfg(feature = "chess")]
+const CHESS_FEN: &str = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+
+
+/// Measurements of a benchmark.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BenchResult {
+    /// Name of the benchmark, the environment searched.
+    pub name: &'static str,
+
+    /// Number of iterations searched.
+    pub iterations: u64,
+
+    /// Number of nodes allocated by the search.
+    pub nodes: usize,
+
+    /// Time spent searching.
+    pub search_time: Duration,
+
+    /// Iterations searched per second.
+    pub iterations_per_second: f64,
+
+    /// Nodes allocated per second of search.
+    pub nodes_per_second: f64,
+
+    /// Rollouts played per second from the root, outside of the search.
+    pub rollouts_per_second: f64,
+
+    /// Moves played per second by the rollouts from the root.
+    pub rollout_moves_per_second: f64,
+}
+
+impl fmt::Display for BenchResult {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        return write!(
+            f,
+            "{:<10} {:>8} iterations {:>10.0} it/s {:>10.0} nodes/s {:>10.0} rollouts/s {:>12.0} moves/s",
+            self.name,
+            self.iterations,
+            self.iterations_per_second,
+            self.nodes_per_second,
+            self.rollouts_per_second,
+            self.rollout_moves_per_second
+        );
+    }
+}
+
+
+/// Rate of `count` events in `elapsed` time, 0 if no time elapsed.
+fn per_second(count: f64, elapsed: Duration) -> f64 {
+    let seconds = elapsed.as_secs_f64();
+    return if seconds == 0.0 { 0.0 } else { count / seconds };
+}
+
+/// Benchmarks `tree`, first playing `rollouts` rollouts from its root, then searching `iterations` iterations.
+///
+/// Rollouts are simulated without being backpropagated, so they do not change the statistics
+/// of the tree before it is searched.
+pub fn bench_tree<Action, GameStateObj>(
+    name: &'static str,
+    tree: &mut MCTSTree<Action, GameStateObj>,
+    iterations: u64,
+    rollouts: u64) -> BenchResult
+where
+    GameStateObj: GameState<Action> + Clone
+{
+    let rollout_moves: u64 = tree.counters.rollout_lengths.iter().enumerate().map(|(moves, count)| moves as u64 * count).sum();
+    let start = Instant::now();
+    for _i in 0..rollouts {
+        tree.simulate_at(0);
+    }
+    let rollout_time = start.elapsed();
+    let played: u64 = tree.counters.rollout_lengths.iter().enumerate().map(|(moves, count)| moves as u64 * count).sum();
+
+    let nodes = tree.arena.len();
+    let search_time = tree.counters.search_time;
+    tree.search(iterations);
+    let nodes = tree.arena.len() - nodes;
+    let search_time = tree.counters.search_time - search_time;
+
+    return BenchResult {
+        name,
+        iterations,
+        nodes,
+        search_time,
+        iterations_per_second: per_second(iterations as f64, search_time),
+        nodes_per_second: per_second(nodes as f64, search_time),
+        rollouts_per_second: per_second(rollouts as f64, rollout_time),
+        rollout_moves_per_second: per_second((played - rollout_moves) as f64, rollout_time),
+    };
+}
+
+/// Benchmarks every bundled environment from its starting position, seeded with `BENCH_SEED`.
+///
+/// # Arguments
+/// * `iterations` : Number of iterations searched, and of rollouts played, in every environment.
+///
+/// # Returns
+/// The result of every environment, in the order Tic-Tac-Toe, Connect Four, Hex, Go, Nim, 2048 and chess,
+/// which is only benchmarked with the `chess` feature.
+pub fn bench_environments(iterations: u64) -> Vec<BenchResult> {
+    let capacity = iterations as usize + 1;
+    let mut results = Vec::new();
+
+    let mut tree = MCTSTree::<u8, TicTacToeState>::with_capacity(capacity, Some(BENCH_SEED), ".........".to_string(), 9);
+    results.push(bench_tree("tictactoe", &mut tree, iterations, iterations));
+
+    let mut tree = MCTSTree::<u8, Connect4State>::with_capacity(capacity, Some(BENCH_SEED), Connect4State::new().to_string(), 7);
+    results.push(bench_tree("connect4", &mut tree, iterations, iterations));
+
+    let mut tree = MCTSTree::<u16, HexState>::with_capacity(capacity, Some(BENCH_SEED), HexState::new(7).to_string(), 49);
+    results.push(bench_tree("hex", &mut tree, iterations, iterations));
+
+    let mut tree = MCTSTree::<_, GoState>::with_capacity(capacity, Some(BENCH_SEED), GoState::default().to_string(), 82);
+    tree.searcher.rollout_policy = Box::new(GoRolloutPolicy);
+    results.push(bench_tree("go", &mut tree, iterations, iterations));
+
+    let mut tree = MCTSTree::<(usize, u32), NimState>::with_capacity(capacity, Some(BENCH_SEED), "3 4 5".to_string(), 12);
+    results.push(bench_tree("nim", &mut tree, iterations, iterations));
+
+    let mut tree = MCTSTree::<_, Game2048State>::with_capacity(capacity, Some(BENCH_SEED), Game2048State::new().to_string(), 4);
+    tree.searcher.player_mode = PlayerMode::SinglePlayer;
+    results.push(bench_tree("2048", &mut tree, iterations, iterations));
+
+    #[cfg(feature = "chess")]
+    {
+        let mut tree = MCTSTree::<_, ChessState>::with_capacity(capacity, Some(BENCH_SEED), CHESS_FEN.to_string(), 30);
+        tree.searcher.rollout_policy = Box::new(ChessRolloutPolicy::default());
+        results.push(bench_tree("chess", &mut tree, iterations, iterations));
+    }
+
+    return results;
+}
+
+
+/// Defines unit tests for the benchmarks.
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Test if every environment is benchmarked with the requested number of iterations.
+    #[test]
+    fn test_bench_environments() {
+        let results = bench_environments(20);
+        let names: Vec<&str> = results.iter().map(|result| result.name).collect();
+        let mut expected = vec!["tictactoe", "connect4", "hex", "go", "nim", "2048"];
+        if cfg!(feature = "chess") {
+            expected.push("chess");
+        }
+        assert_eq!(names, expected);
+        for result in &results {
+            assert_eq!(result.iterations, 20);
+            // Chance nodes of 2048 allocate every outcome at once.
+            assert!(result.nodes > 0);
+            assert!(result.iterations_per_second > 0.0);
+            assert!(result.rollouts_per_second > 0.0);
+            assert!(result.to_string().starts_with(result.name));
+        }
+    }
+}
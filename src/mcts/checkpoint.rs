@@ -0,0 +1,258 @@
+//! Periodic checkpoints of long searches, which can be resumed after a crash.
+
+use crate::game_state_trait::GameState;
+use crate::hooks::{SearchControl, SearchHooks};
+use crate::mcts::MCTSTree;
+use crate::serialize::{Decode, Encode};
+use std::fs;
+use std::io;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use crate::clock::Instant;
+use std::time::Duration;
+
+
+/// Writes `bytes` to `path` through a temporary file next to it, which is synced to disk and
+/// then renamed over it, so a crash while writing never leaves a truncated file behind.
+pub(crate) fn write_atomically(path: &Path, bytes: &[u8]) -> io::Result<()> {
+    let mut temporary = path.to_path_buf().into_os_string();
+    temporary.push(".tmp");
+    let mut file = fs::File::create(&temporary)?;
+    file.write_all(bytes)?;
+    file.sync_all()?;
+    fs::rename(&temporary, path)?;
+
+    // The rename itself is only durable once the directory holding the file is synced.
+    #[cfg(unix)]
+    {
+        let directory = path.parent().filter(|parent| !parent.as_os_str().is_empty()).unwrap_or(Path::new("."));
+        fs::File::open(directory)?.sync_all()?;
+    }
+    return Ok(());
+}
+
+
+/// Writes snapshots of a tree to a file while it is searched.
+///
+/// Every checkpoint replaces the previous one, so the file always holds the latest snapshot.
+/// Checkpoints are written to a temporary file next to it, which is synced to disk and then
+/// renamed over it, so a crash while writing never leaves a truncated checkpoint behind.
+///
+/// The checkpointer is a `SearchHooks`, so it checkpoints a search passed to
+/// `MCTSTree::search_with_hooks` as it runs.
+///
+/// A checkpoint holds the number of iterations searched, followed by the tree encoded
+/// by `MCTSTree::to_compact_bytes`.
+pub struct Checkpointer {
+    /// File the checkpoints are written to.
+    pub path: PathBuf,
+
+    /// Number of iterations between checkpoints. None to not checkpoint by iterations.
+    pub every_iterations: Option<u64>,
+
+    /// Time between checkpoints. None to not checkpoint by time.
+    pub every_duration: Option<Duration>,
+
+    /// Iteration count of the last checkpoint.
+    pub last_iterations: u64,
+
+    /// Time of the last checkpoint, or of the creation of the checkpointer.
+    pub last_time: Instant,
+
+    /// Iterations searched with the checkpointer as the hooks of the search, continuing from the
+    /// iterations of the checkpoint loaded by `resume`.
+    pub iterations: u64,
+
+    /// Error of the last checkpoint written by the hooks, which stops the search. None if every
+    /// checkpoint was written.
+    pub error: Option<io::Error>,
+}
+
+
+impl Checkpointer {
+    /// Creates a checkpointer writing to `path`, once either interval has passed since the last checkpoint.
+    ///
+    /// # Arguments
+    /// * `path` : File the checkpoints are written to.
+    ///
+    /// * `every_iterations` : Number of iterations between checkpoints, or None.
+    ///
+    /// * `every_duration` : Time between checkpoints, or None.
+    pub fn new(path: impl Into<PathBuf>, every_iterations: Option<u64>, every_duration: Option<Duration>) -> Self {
+        return Checkpointer {
+            path: path.into(),
+            every_iterations,
+            every_duration,
+            last_iterations: 0,
+            last_time: Instant::now(),
+            iterations: 0,
+            error: None,
+        };
+    }
+
+    /// Determines whether a checkpoint is due after `iterations` iterations of search.
+    pub fn is_due(&self, iterations: u64) -> bool {
+        let by_iterations = self.every_iterations.is_some_and(|every| iterations >= self.last_iterations + every);
+        let by_time = self.every_duration.is_some_and(|every| self.last_time.elapsed() >= every);
+        return by_iterations || by_time;
+    }
+
+    /// Writes a checkpoint of `tree` if one is due, see `is_due`.
+    ///
+    /// Meant to be called after every iteration of search, which the hooks of the checkpointer do.
+    ///
+    /// # Returns
+    /// True if a checkpoint was written, or the error writing it.
+    pub fn maybe_checkpoint<Action, GameStateObj, Data>(
+        &mut self,
+        tree: &MCTSTree<Action, GameStateObj, Data>,
+        iterations: u64)
+    -> io::Result<bool>
+    where
+        Action: Encode + Decode + PartialEq,
+        GameStateObj: GameState<Action> + Clone + Encode + Decode
+    {
+        if !self.is_due(iterations) {
+            return Ok(false);
+        }
+        self.checkpoint(tree, iterations)?;
+        return Ok(true);
+    }
+
+    /// Writes a checkpoint of `tree` after `iterations` iterations of search.
+    ///
+    /// The state of the random generator and the search settings are saved with the tree, see
+    /// `MCTSTree::to_compact_bytes`. A search resumed from the checkpoint therefore continues
+    /// exactly as this one does, once the rollout policy and evaluator, which can not be saved,
+    /// are set again. User data of the nodes is not saved, and resumed nodes have none.
+    pub fn checkpoint<Action, GameStateObj, Data>(
+        &mut self,
+        tree: &MCTSTree<Action, GameStateObj, Data>,
+        iterations: u64)
+    -> io::Result<()>
+    where
+        Action: Encode + Decode + PartialEq,
+        GameStateObj: GameState<Action> + Clone + Encode + Decode
+    {
+        let mut bytes = Vec::new();
+        iterations.encode(&mut bytes);
+        bytes.extend(tree.to_compact_bytes());
+        write_atomically(&self.path, &bytes)?;
+
+        self.last_iterations = iterations;
+        self.last_time = Instant::now();
+        return Ok(());
+    }
+
+    /// Loads the latest checkpoint written to the file of the checkpointer, so the search
+    /// can be resumed. The next checkpoint is due relative to the loaded one.
+    ///
+    /// # Returns
+    /// The tree and the number of iterations it was searched for, None if no checkpoint
+    /// exists, or an error if the checkpoint can not be read.
+    #[allow(clippy::type_complexity)]
+    pub fn resume<Action, GameStateObj, Data>(&mut self) -> io::Result<Option<(MCTSTree<Action, GameStateObj, Data>, u64)>>
+    where
+        Action: Encode + Decode + PartialEq,
+        GameStateObj: GameState<Action> + Clone + Encode + Decode
+    {
+        let bytes = match fs::read(&self.path) {
+            Ok(bytes) => bytes,
+            Err(error) if error.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(error) => return Err(error),
+        };
+        let mut input = &bytes[..];
+        let iterations = u64::decode(&mut input)?;
+        let tree = MCTSTree::from_bytes(input)?;
+        self.last_iterations = iterations;
+        self.last_time = Instant::now();
+        self.iterations = iterations;
+        return Ok(Some((tree, iterations)));
+    }
+}
+
+/// Checkpoints the searched tree whenever a checkpoint is due, counting the iterations searched.
+impl<Action, GameStateObj, Data> SearchHooks<Action, GameStateObj, Data> for Checkpointer
+where
+    Action: Encode + Decode + PartialEq,
+    GameStateObj: GameState<Action> + Clone + Encode + Decode
+{
+    fn on_iteration(&mut self, tree: &MCTSTree<Action, GameStateObj, Data>, _iteration: u64) -> SearchControl {
+        self.iterations += 1;
+        if let Err(error) = self.maybe_checkpoint(tree, self.iterations) {
+            self.error = Some(error);
+            return SearchControl::Stop;
+        }
+        return SearchControl::Continue;
+    }
+
+    fn tracks_best_move(&self) -> bool {
+        return false;
+    }
+}
+
+
+/// Defines unit tests for checkpoints.
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tictactoe_env::TicTacToeState;
+
+    /// Test if a search resumed from a checkpoint continues exactly as the original search, with its settings.
+    #[test]
+    fn test_resume() {
+        let path = std::env::temp_dir().join(format!("mcts_checkpoint_{}", std::process::id()));
+        let crashed_path = std::env::temp_dir().join(format!("mcts_checkpoint_crashed_{}", std::process::id()));
+        let mut checkpointer = Checkpointer::new(&path, Some(100), None);
+        assert!(checkpointer.resume::<u8, TicTacToeState, u32>().unwrap().is_none());
+
+        let mut tree = MCTSTree::<u8, TicTacToeState, u32>::with_capacity(1000, Some(3), ".........".to_string(), 9);
+        tree.searcher.draw_score = 0.25;
+        tree.searcher.simulations_per_expansion = 2;
+        tree.node_data_mut(tree.root()).unwrap().replace(7);
+        assert_eq!(tree.search_with_hooks(250, &mut checkpointer), 250);
+        assert_eq!(checkpointer.last_iterations, 200);
+
+        // A search crashing now resumes from the checkpoint of iteration 200.
+        fs::copy(&path, &crashed_path).unwrap();
+        let mut crashed_checkpointer = Checkpointer::new(&crashed_path, Some(100), None);
+        let (mut resumed, iterations) = crashed_checkpointer.resume::<u8, TicTacToeState, u32>().unwrap().unwrap();
+        assert_eq!(iterations, 200);
+        assert_eq!(resumed.node_data(resumed.root()), Ok(None));
+        assert_eq!(crashed_checkpointer.last_iterations, 200);
+
+        tree.search_with_hooks(250, &mut checkpointer);
+        resumed.search_with_hooks(300, &mut crashed_checkpointer);
+        assert_eq!((checkpointer.iterations, crashed_checkpointer.iterations), (500, 500));
+        assert_eq!(crashed_checkpointer.last_iterations, 500);
+        assert!(checkpointer.error.is_none());
+        assert_eq!(resumed.arena.len(), tree.arena.len());
+        for node in 0..tree.arena.len() {
+            assert_eq!(resumed.arena.stats[node], tree.arena.stats[node]);
+        }
+        fs::remove_file(&path).unwrap();
+        fs::remove_file(&crashed_path).unwrap();
+    }
+
+    /// Test if a checkpoint which can not be written stops the search with its error.
+    #[test]
+    fn test_checkpoint_error() {
+        let path = std::env::temp_dir().join(format!("mcts_checkpoint_missing_{}", std::process::id())).join("checkpoint");
+        let mut checkpointer = Checkpointer::new(&path, Some(5), None);
+        let mut tree = MCTSTree::<u8, TicTacToeState>::with_capacity(100, Some(3), ".........".to_string(), 9);
+        assert_eq!(tree.search_with_hooks(50, &mut checkpointer), 5);
+        assert_eq!(checkpointer.error.map(|error| error.kind()), Some(io::ErrorKind::NotFound));
+    }
+
+    /// Test if checkpoints are due by time as well as by iterations.
+    #[test]
+    fn test_is_due() {
+        let mut checkpointer = Checkpointer::new("unused", None, Some(Duration::ZERO));
+        assert!(checkpointer.is_due(0));
+        checkpointer.every_duration = Some(Duration::from_secs(3600));
+        assert!(!checkpointer.is_due(1_000_000));
+        checkpointer.every_iterations = Some(10);
+        assert!(checkpointer.is_due(10));
+        assert!(!checkpointer.is_due(9));
+    }
+}
@@ -1,36 +1,95 @@
 use crate::game_state_trait::{GameResult, GameState};
-use chess::{Board, ChessMove, Color, MoveGen, Piece};
+use crate::rollout::{sample_weighted, RolloutPolicy};
+use crate::tablebase::{self, Wdl};
+use crate::serialize::{Decode, DecodeError, Encode};
+use chess::{BitBoard, Board, BoardBuilder, BoardStatus, ChessMove, Color, File, MoveGen, Piece, Square, ALL_PIECES, ALL_SQUARES};
+use crate::rng::{Rng, SearchRng};
+use std::fmt;
 use std::str::FromStr;
 
 
 /// Holds the state of the chessboard.
 ///
 /// Wraps the chess module's `Board` with the addition of:
-/// Tracking 50 move rule.
+/// Tracking 50 move rule, as the number of half-moves since the last capture or pawn move.
 /// Tracking the last move.
+/// Tracking the castling rooks of Chess960 games, which the board can not castle with.
 #[derive(Debug, Clone)]
 pub struct ChessState {
     pub board: Board,
     pub fifty_move_counter: u16,
-    pub last_move: Option<ChessMove>
+    pub last_move: Option<ChessMove>,
+    pub chess960: Option<Chess960Castling>,
+}
+
+
+/// Castling rooks of a Chess960 game.
+///
+/// The board only castles from the standard setup, so Chess960 games keep boards without
+/// castling rights and track the rooks which can still castle here instead. Castling moves
+/// are encoded as the king capturing its own rook, as in UCI Chess960 mode.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Chess960Castling {
+    /// Files of the rooks which can still castle, indexed by color, then by 0 for the
+    /// king side (towards the h file) and 1 for the queen side.
+    pub rook_files: [[Option<File>; 2]; 2],
 }
 
 
 /// Allows the MCTS engine to build ChessState trees.
 impl GameState<ChessMove> for ChessState {
+    type ParseError = chess::Error;
+
     fn from_str(starting_fen: String) -> Self {
-        return ChessState {
-            board: Board::from_str(&starting_fen).unwrap(), 
-            fifty_move_counter: 0, 
-            last_move: None, 
+        return Self::try_from_str(&starting_fen).expect("invalid fen");
+    }
+
+    fn try_from_str(starting_fen: &str) -> Result<Self, chess::Error> {
+        let invalid_fen = || chess::Error::InvalidFen { fen: starting_fen.to_string() };
+        let mut fields: Vec<&str> = starting_fen.split_whitespace().collect();
+
+        // The halfmove clock is the fifth field of the fen. The board ignores it, 
+        // so it is parsed separately, defaulting to 0 if it is missing.
+        let halfmove_clock = match fields.get(4) {
+            Some(field) => field.parse::<u16>().map_err(|_| invalid_fen())?,
+            None => 0,
         };
+
+        // The board only understands castling from the standard setup, so castling rights are
+        // parsed separately, accepting both Shredder (`HAha`) and X-FEN (`KQkq`) notation.
+        let castling_field = fields.get(2).copied().unwrap_or("-");
+        if fields.len() > 2 {
+            fields[2] = "-";
+        }
+        let board = Board::from_str(&fields.join(" "))?;
+        let castling = Chess960Castling::parse(&board, castling_field).ok_or_else(invalid_fen)?;
+        if !castling.is_standard(&board) {
+            return Ok(ChessState { board, fifty_move_counter: halfmove_clock, last_move: None, chess960: Some(castling) });
+        }
+
+        // Standard castling rights are handed to the board, which castles with them itself.
+        let standard_field = castling.to_fen_field(false);
+        fields[2] = &standard_field;
+        return Ok(ChessState {
+            board: Board::from_str(&fields.join(" "))?, 
+            fifty_move_counter: halfmove_clock, 
+            last_move: None, 
+            chess960: None,
+        });
     }
     
     fn apply_action(&self, action: &ChessMove) -> Self {
+        let mut new_state = self.clone();
+        new_state.apply_action_mut(action);
+        return new_state;
+    }
+
+    fn apply_action_mut(&mut self, action: &ChessMove) {
         let mut new_fifty_move_counter = self.fifty_move_counter + 1;
 
         // Any capture or pawn move should reset the 50 move counter.        
-        let is_capture = self.board.piece_on(action.get_dest()).is_some();
+        let is_castle = self.is_chess960_castle(action);
+        let is_capture = !is_castle && self.board.piece_on(action.get_dest()).is_some();
         
         let src_piece = self.board.piece_on(action.get_source());
         let pawn_moved = src_piece.is_some() && src_piece.unwrap() == Piece::Pawn;
@@ -38,26 +97,50 @@ impl GameState<ChessMove> for ChessState {
         if is_capture || pawn_moved {
             new_fifty_move_counter = 0;
         }
-       
-        return ChessState {
-            board: self.board.make_move_new(*action), 
-            fifty_move_counter: new_fifty_move_counter, 
-            last_move: Some(*action), 
-        };
+
+        if let Some(castling) = &mut self.chess960 {
+            castling.update(&self.board, action);
+        }
+        self.board = make_move(&self.board, *action);
+        self.fifty_move_counter = new_fifty_move_counter;
+        self.last_move = Some(*action);
     }
 
     fn status_with_moves_left(&self) -> bool {
         // If there are still legal moves left, the game can still end up as a draw
-        // due to the 50 move rule. The 3 fold repition rule is not considered.
-        if self.fifty_move_counter >= 50 {
+        // due to the 50 move rule, after 100 half-moves. The 3 fold repition rule is not considered.
+        if self.fifty_move_counter >= 100 {
+            return false;
+        }
+
+        // Neither side can deliver checkmate, so the game is drawn.
+        if self.insufficient_material() {
+            return false;
+        }
+
+        // Endgames covered by a registered tablebase end with their exact result.
+        if tablebase::probe(&self.board).is_some() {
             return false;
         }
         return true;
     }
 
     fn result(&self) -> GameResult {
-        // There are no legal moves left, so if there are no checks the game is a draw.
-        if self.board.checkers() == &chess::EMPTY || self.fifty_move_counter >= 50 {
+        // Tablebase results are given for the side to move.
+        if let Some(wdl) = tablebase::probe(&self.board) {
+            let white_to_move = self.board.side_to_move() == Color::White;
+            match wdl {
+                Wdl::Win if white_to_move => return GameResult::FirstPlayerWin,
+                Wdl::Win => return GameResult::SecondPlayerWin,
+                Wdl::Loss if white_to_move => return GameResult::SecondPlayerWin,
+                Wdl::Loss => return GameResult::FirstPlayerWin,
+                _ => return GameResult::Draw,
+            }
+        }
+
+        // Checkmate stands even when the mating move reaches the fifty move limit,
+        // any other end of the game is a draw.
+        if self.board.status() != BoardStatus::Checkmate {
             return GameResult::Draw;
         } 
         if self.board.side_to_move() == Color::Black {
@@ -69,7 +152,38 @@ impl GameState<ChessMove> for ChessState {
     }
 
     fn generate_legal_actions(&self) -> Vec<ChessMove> {
-        return MoveGen::new_legal(&self.board).collect();
+        let mut actions: Vec<ChessMove> = MoveGen::new_legal(&self.board).collect();
+        self.chess960_castles_into(&mut actions);
+        return actions;
+    }
+
+    fn legal_actions_into(&self, actions: &mut Vec<ChessMove>) {
+        actions.clear();
+        actions.extend(MoveGen::new_legal(&self.board));
+        self.chess960_castles_into(actions);
+    }
+
+    fn state_hash(&self) -> Option<u64> {
+        // The board keeps a Zobrist hash of the pieces, side to move, castling rights and 
+        // en passant square, which is updated incrementally by every move.
+        // Chess960 castling rights are not part of the board, so they are mixed in separately.
+        let castling = self.chess960.map_or(0, |castling| {
+            let files = castling.rook_files.iter().flatten();
+            return files.fold(0u64, |rights, file| rights * 9 + file.map_or(0, |file| file.to_index() as u64 + 1));
+        });
+        return Some(self.board.get_hash() ^ castling.wrapping_mul(0x9E37_79B9_7F4A_7C15));
+    }
+
+    fn action_code(&self, action: &ChessMove) -> Option<u64> {
+        // Moves are coded by their source and destination squares, then their promotion.
+        let squares = action.get_source().to_index() as u64 * 64 + action.get_dest().to_index() as u64;
+        return Some(squares + 4096 * action.get_promotion().map_or(0, |piece| piece.to_index() as u64 + 1));
+    }
+
+    fn evaluate(&self) -> f32 {
+        // Material balance is mapped to an expected score with a logistic curve, 
+        // where a one pawn advantage is worth roughly a 56% share of the win.
+        return 1.0 / (1.0 + f32::exp(-(self.material_balance() as f32) / 4.0));
     }
 
     fn side_to_move(&self) -> bool {
@@ -78,6 +192,442 @@ impl GameState<ChessMove> for ChessState {
 }
 
 
+/// Encodes the state as its fen, see `ChessState::to_fen`.
+impl fmt::Display for ChessState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        return write!(f, "{}", self.to_fen());
+    }
+}
+
+
+impl ChessState {
+    /// Creates the Chess960 starting position with the given Scharnagl number.
+    ///
+    /// # Arguments
+    /// * `index` : Number of the starting position, from 0 to 959. 518 is the standard starting position.
+    ///
+    /// # Panics
+    /// If `index` is 960 or more.
+    pub fn chess960(index: u32) -> ChessState {
+        assert!(index < 960, "chess960 starting positions are numbered from 0 to 959");
+        let mut back_rank: [Option<char>; 8] = [None; 8];
+        let mut n = index as usize;
+
+        // Bishops go on opposite colored squares, first the light squared one on b, d, f or h.
+        back_rank[2 * (n % 4) + 1] = Some('B');
+        n /= 4;
+        back_rank[2 * (n % 4)] = Some('B');
+        n /= 4;
+
+        // The queen and knights go on the remaining empty squares, counted from the a file.
+        let place = |back_rank: &mut [Option<char>; 8], empty_index: usize, piece: char| {
+            let file = (0..8).filter(|file| back_rank[*file].is_none()).nth(empty_index).unwrap();
+            back_rank[file] = Some(piece);
+        };
+        place(&mut back_rank, n % 6, 'Q');
+        n /= 6;
+        let knights = [(0, 1), (0, 2), (0, 3), (0, 4), (1, 2), (1, 3), (1, 4), (2, 3), (2, 4), (3, 4)][n];
+        place(&mut back_rank, knights.1, 'N');
+        place(&mut back_rank, knights.0, 'N');
+
+        // The king goes between the two rooks.
+        for piece in ['R', 'K', 'R'] {
+            place(&mut back_rank, 0, piece);
+        }
+
+        let white: String = back_rank.iter().map(|piece| piece.unwrap()).collect();
+        let rooks: Vec<char> = (0..8).filter(|file| back_rank[*file] == Some('R')).map(|file| (b'a' + file as u8) as char).collect();
+        let castling = format!("{}{}{}{}", rooks[1].to_ascii_uppercase(), rooks[0].to_ascii_uppercase(), rooks[1], rooks[0]);
+        let fen = format!("{}/pppppppp/8/8/8/8/PPPPPPPP/{} w {} - 0 1", white.to_ascii_lowercase(), white, castling);
+        return ChessState::try_from_str(&fen).expect("invalid chess960 starting position");
+    }
+
+    /// Creates a uniformly random Chess960 starting position.
+    pub fn random_chess960(random_generator: &mut SearchRng) -> ChessState {
+        return ChessState::chess960(random_generator.gen_range(0, 960));
+    }
+
+    /// Encodes the state as a fen, keeping the halfmove clock. Chess960 castling rights
+    /// are written in Shredder notation, such as `HAha`.
+    pub fn to_fen(&self) -> String {
+        let board_fen = self.board.to_string();
+        let mut fields: Vec<String> = board_fen.split_whitespace().take(4).map(String::from).collect();
+        if let Some(castling) = &self.chess960 {
+            fields[2] = castling.to_fen_field(true);
+        }
+        return format!("{} {} 1", fields.join(" "), self.fifty_move_counter);
+    }
+
+    /// Determines whether `action` is a Chess960 castling move, encoded as the king capturing its own rook.
+    pub fn is_chess960_castle(&self, action: &ChessMove) -> bool {
+        return self.chess960.is_some() && is_own_capture(&self.board, *action);
+    }
+
+    /// Appends the legal Chess960 castling moves of the side to move to `actions`.
+    fn chess960_castles_into(&self, actions: &mut Vec<ChessMove>) {
+        let castling = match &self.chess960 {
+            Some(castling) => castling,
+            None => return,
+        };
+        let board = &self.board;
+        if board.checkers() != &chess::EMPTY {
+            return;
+        }
+        let color = board.side_to_move();
+        let back_rank = color.to_my_backrank();
+        let king = board.king_square(color);
+
+        for (side, rook_file) in castling.rook_files[color.to_index()].iter().enumerate() {
+            let rook = match rook_file {
+                Some(file) => Square::make_square(back_rank, *file),
+                None => continue,
+            };
+            let (king_to, rook_to) = castle_destinations(color, side);
+
+            // Every square either piece crosses must be empty, apart from the king and rook themselves.
+            let others = *board.combined() ^ BitBoard::from_square(king) ^ BitBoard::from_square(rook);
+            let files = [king, rook, king_to, rook_to].map(|square| square.get_file().to_index());
+            let (low, high) = (*files.iter().min().unwrap(), *files.iter().max().unwrap());
+            let path_clear = (low..=high).all(|file| (others & BitBoard::set(back_rank, File::from_index(file))) == chess::EMPTY);
+
+            // The king may not pass through or land on an attacked square.
+            let (low, high) = (files[0].min(files[2]), files[0].max(files[2]));
+            let king_safe = (low..=high).all(|file| {
+                return !is_attacked_through(board, Square::make_square(back_rank, File::from_index(file)), !color, others);
+            });
+            if path_clear && king_safe {
+                actions.push(ChessMove::new(king, rook, None));
+            }
+        }
+    }
+
+    /// Counts the material of white minus the material of black, using the
+    /// classical pawn = 1, knight = bishop = 3, rook = 5, queen = 9 piece values.
+    pub fn material_balance(&self) -> i32 {
+        let mut balance = 0;
+        for (piece, value) in [(Piece::Pawn, 1), (Piece::Knight, 3), (Piece::Bishop, 3), (Piece::Rook, 5), (Piece::Queen, 9)] {
+            let pieces = self.board.pieces(piece);
+            let white = (pieces & self.board.color_combined(Color::White)).popcnt() as i32;
+            let black = (pieces & self.board.color_combined(Color::Black)).popcnt() as i32;
+            balance += value * (white - black);
+        }
+        return balance;
+    }
+
+    /// Determines whether the position is quiet, where the side to move is not in check
+    /// and has no captures or checks available, so the material balance is unlikely to change
+    /// on the next move.
+    pub fn is_quiet(&self) -> bool {
+        if self.board.checkers() != &chess::EMPTY {
+            return false;
+        }
+        // En passant is the only capture not landing on an occupied square. The board stores
+        // the square of the pawn which can be captured, rather than the square moved to.
+        let en_passant_target = self.board.en_passant().and_then(|square| square.forward(self.board.side_to_move()));
+        for action in self.generate_legal_actions() {
+            let is_capture = self.board.color_on(action.get_dest()) == Some(!self.board.side_to_move()) || Some(action.get_dest()) == en_passant_target;
+            if is_capture || make_move(&self.board, action).checkers() != &chess::EMPTY {
+                return false;
+            }
+        }
+        return true;
+    }
+
+    /// Determines whether neither side has the material left to deliver checkmate.
+    ///
+    /// Covers king against king, a single minor piece against a lone king, 
+    /// and bishops against bishops when every bishop stands on the same square color.
+    pub fn insufficient_material(&self) -> bool {
+        let heavy_or_pawns = self.board.pieces(Piece::Pawn) | self.board.pieces(Piece::Rook) | self.board.pieces(Piece::Queen);
+        if heavy_or_pawns != chess::EMPTY {
+            return false;
+        }
+
+        let knights = *self.board.pieces(Piece::Knight);
+        let bishops = *self.board.pieces(Piece::Bishop);
+        if (knights | bishops).popcnt() <= 1 {
+            return true;
+        }
+
+        // Bishops confined to a single square color can never cover the king's escape squares.
+        let dark_squares = chess::BitBoard::new(0x55AA_55AA_55AA_55AA);
+        let single_color = (bishops & dark_squares) == chess::EMPTY || (bishops & !dark_squares) == chess::EMPTY;
+        return knights == chess::EMPTY && single_color;
+    }
+}
+
+
+
+impl Chess960Castling {
+    /// Parses the castling field of a fen for `board`.
+    ///
+    /// Shredder letters give the file of the castling rook, while `K` and `Q` give the outermost
+    /// rook on that side of the king. Upper case letters are rights of white.
+    ///
+    /// # Returns
+    /// The castling rooks, or None if a right has no matching rook or the king is off its back rank.
+    pub fn parse(board: &Board, field: &str) -> Option<Chess960Castling> {
+        let mut castling = Chess960Castling::default();
+        for letter in field.chars().filter(|letter| *letter != '-') {
+            let color = if letter.is_ascii_uppercase() { Color::White } else { Color::Black };
+            let back_rank = color.to_my_backrank();
+            let king = board.king_square(color);
+            if king.get_rank() != back_rank {
+                return None;
+            }
+            let is_rook = |file: &File| {
+                let square = Square::make_square(back_rank, *file);
+                return board.piece_on(square) == Some(Piece::Rook) && board.color_on(square) == Some(color);
+            };
+
+            let king_file = king.get_file().to_index();
+            let rook_file = match letter.to_ascii_lowercase() {
+                'k' => (king_file + 1..8).rev().map(File::from_index).find(is_rook)?,
+                'q' => (0..king_file).map(File::from_index).find(is_rook)?,
+                file @ 'a'..='h' => Some(File::from_index((file as u8 - b'a') as usize)).filter(is_rook)?,
+                _ => return None,
+            };
+            let side = if rook_file.to_index() > king_file { 0 } else { 1 };
+            castling.rook_files[color.to_index()][side] = Some(rook_file);
+        }
+        return Some(castling);
+    }
+
+    /// Determines whether every castling right belongs to a king on the e file and a rook
+    /// in the corner, which the board can castle with itself.
+    pub fn is_standard(&self, board: &Board) -> bool {
+        for color in [Color::White, Color::Black] {
+            for (side, corner) in [File::H, File::A].iter().enumerate() {
+                if let Some(file) = self.rook_files[color.to_index()][side] {
+                    if board.king_square(color).get_file() != File::E || file != *corner {
+                        return false;
+                    }
+                }
+            }
+        }
+        return true;
+    }
+
+    /// Encodes the castling rights as the castling field of a fen, 
+    /// in Shredder notation if `shredder` is set and otherwise as `KQkq`.
+    pub fn to_fen_field(&self, shredder: bool) -> String {
+        let mut field = String::new();
+        for color in [Color::White, Color::Black] {
+            for (side, letter) in ['k', 'q'].iter().enumerate() {
+                if let Some(file) = self.rook_files[color.to_index()][side] {
+                    let letter = if shredder { (b'a' + file.to_index() as u8) as char } else { *letter };
+                    field.push(if color == Color::White { letter.to_ascii_uppercase() } else { letter });
+                }
+            }
+        }
+        if field.is_empty() {
+            field.push('-');
+        }
+        return field;
+    }
+
+    /// Removes the castling rights lost by playing `action` on `board`. Moving the king loses
+    /// both rights of its side, while moving or capturing a castling rook loses its right.
+    pub fn update(&mut self, board: &Board, action: &ChessMove) {
+        if board.piece_on(action.get_source()) == Some(Piece::King) {
+            self.rook_files[board.side_to_move().to_index()] = [None; 2];
+        }
+        for color in [Color::White, Color::Black] {
+            for rook_file in self.rook_files[color.to_index()].iter_mut() {
+                let rook = rook_file.map(|file| Square::make_square(color.to_my_backrank(), file));
+                if rook == Some(action.get_source()) || rook == Some(action.get_dest()) {
+                    *rook_file = None;
+                }
+            }
+        }
+    }
+}
+
+
+/// Squares the king and rook of `color` end on after castling, 
+/// on the king side for `side` 0 and on the queen side for `side` 1.
+fn castle_destinations(color: Color, side: usize) -> (Square, Square) {
+    let (king_file, rook_file) = if side == 0 { (File::G, File::F) } else { (File::C, File::D) };
+    return (Square::make_square(color.to_my_backrank(), king_file), Square::make_square(color.to_my_backrank(), rook_file));
+}
+
+
+/// Determines whether `action` moves a piece onto a piece of its own color,
+/// which only Chess960 castling moves do.
+fn is_own_capture(board: &Board, action: ChessMove) -> bool {
+    return board.color_on(action.get_dest()) == Some(board.side_to_move());
+}
+
+
+/// Plays `action` on `board`, including Chess960 castling moves encoded as the king capturing its own rook.
+///
+/// # Invariants
+/// `action` is assumed to be legal on `board`.
+pub fn make_move(board: &Board, action: ChessMove) -> Board {
+    if !is_own_capture(board, action) {
+        return board.make_move_new(action);
+    }
+
+    let color = board.side_to_move();
+    let (king, rook) = (action.get_source(), action.get_dest());
+    let side = if rook.get_file().to_index() > king.get_file().to_index() { 0 } else { 1 };
+    let (king_to, rook_to) = castle_destinations(color, side);
+    let mut builder = BoardBuilder::from(board);
+    builder
+        .clear_square(king)
+        .clear_square(rook)
+        .piece(king_to, Piece::King, color)
+        .piece(rook_to, Piece::Rook, color)
+        .side_to_move(!color)
+        .en_passant(None);
+    return Board::try_from(&builder).expect("castling produced an invalid board");
+}
+
+
+/// Classical value of a piece in pawns. Kings are given no value as they are never captured.
+pub fn piece_value(piece: Piece) -> u32 {
+    match piece {
+        Piece::Pawn => return 1,
+        Piece::Knight => return 3,
+        Piece::Bishop => return 3,
+        Piece::Rook => return 5,
+        Piece::Queen => return 9,
+        Piece::King => return 0,
+    }
+}
+
+
+/// Determines whether `square` is attacked by any piece of `attacker` on `board`.
+pub fn is_attacked(board: &Board, square: Square, attacker: Color) -> bool {
+    return is_attacked_through(board, square, attacker, *board.combined());
+}
+
+
+/// Determines whether `square` is attacked by any piece of `attacker` on `board`,
+/// with sliding pieces blocked only by the pieces in `blockers`.
+fn is_attacked_through(board: &Board, square: Square, attacker: Color, blockers: BitBoard) -> bool {
+    let attackers = board.color_combined(attacker);
+    let pawns = board.pieces(Piece::Pawn) & attackers;
+    let knights = board.pieces(Piece::Knight) & attackers;
+    let diagonal = (board.pieces(Piece::Bishop) | board.pieces(Piece::Queen)) & attackers;
+    let straight = (board.pieces(Piece::Rook) | board.pieces(Piece::Queen)) & attackers;
+    let kings = board.pieces(Piece::King) & attackers;
+
+    // Attacks are symmetric, so the square attacks every piece that attacks it.
+    return (chess::get_pawn_attacks(square, !attacker, pawns) != chess::EMPTY)
+        || (chess::get_knight_moves(square) & knights) != chess::EMPTY
+        || (chess::get_bishop_moves(square, blockers) & diagonal) != chess::EMPTY
+        || (chess::get_rook_moves(square, blockers) & straight) != chess::EMPTY
+        || (chess::get_king_moves(square) & kings) != chess::EMPTY;
+}
+
+
+/// Heavy rollout policy for chess, using material knowledge to play more realistic moves.
+///
+/// Captures are preferred in proportion to the value of the captured piece, queen moves
+/// onto attacked squares are avoided, and checks are played with a fixed probability.
+/// Every move keeps a non-zero probability, so rollouts still explore every line.
+#[derive(Debug, Clone, Copy)]
+pub struct ChessRolloutPolicy {
+    /// Probability of playing a random checking move, when one is available.
+    pub check_probability: f32,
+
+    /// Extra weight of a capture per pawn of value of the captured piece, relative to a quiet move.
+    pub capture_weight: f32,
+
+    /// Weight of a queen move to an attacked square that does not capture a queen, relative to a quiet move.
+    pub hanging_queen_weight: f32,
+
+    /// Number of moves after which rollouts end as soon as the position is quiet, 
+    /// scored by `ChessState::evaluate`. None plays rollouts out. Defaults to None.
+    pub quiescence_cutoff: Option<u32>,
+}
+
+impl Default for ChessRolloutPolicy {
+    fn default() -> Self {
+        return ChessRolloutPolicy { 
+            check_probability: 0.2, 
+            capture_weight: 2.0, 
+            hanging_queen_weight: 0.05, 
+            quiescence_cutoff: None 
+        };
+    }
+}
+
+impl RolloutPolicy<ChessMove, ChessState> for ChessRolloutPolicy {
+    fn choose(&mut self, game_state: &ChessState, actions: &[ChessMove], random_generator: &mut SearchRng) -> usize {
+        let board = &game_state.board;
+
+        if random_generator.gen::<f32>() < self.check_probability {
+            let checks: Vec<usize> = (0..actions.len())
+                .filter(|i| make_move(board, actions[*i]).checkers() != &chess::EMPTY)
+                .collect();
+            if checks.len() > 0 {
+                return checks[random_generator.gen_range(0, checks.len())];
+            }
+        }
+
+        let weights: Vec<f32> = actions.iter().map(|action| {
+            let victim = board.piece_on(action.get_dest()).filter(|_| !game_state.is_chess960_castle(action));
+            let mut weight = 1.0 + self.capture_weight * victim.map_or(0, piece_value) as f32;
+            if board.piece_on(action.get_source()) == Some(Piece::Queen) && victim != Some(Piece::Queen) {
+                let after = board.make_move_new(*action);
+                if is_attacked(&after, action.get_dest(), after.side_to_move()) {
+                    weight *= self.hanging_queen_weight;
+                }
+            }
+            return weight;
+        }).collect();
+        return sample_weighted(&weights, random_generator);
+    }
+
+    fn cutoff(&mut self, game_state: &ChessState, depth: u32) -> Option<GameResult> {
+        if self.quiescence_cutoff.is_some_and(|cutoff| depth >= cutoff) && game_state.is_quiet() {
+            return Some(GameResult::Score(game_state.evaluate()));
+        }
+        return None;
+    }
+}
+
+
+impl Encode for ChessMove {
+    fn encode(&self, out: &mut Vec<u8>) {
+        out.push(self.get_source().to_int());
+        out.push(self.get_dest().to_int());
+        self.get_promotion().map(|piece| piece.to_index() as u8).encode(out);
+    }
+}
+
+impl Decode for ChessMove {
+    fn decode(input: &mut &[u8]) -> Result<Self, DecodeError> {
+        let square = |index: u8| ALL_SQUARES.get(index as usize).copied().ok_or(DecodeError(format!("invalid square {}", index)));
+        let source = square(u8::decode(input)?)?;
+        let dest = square(u8::decode(input)?)?;
+        let promotion = match Option::<u8>::decode(input)? {
+            Some(index) => Some(*ALL_PIECES.get(index as usize).ok_or(DecodeError(format!("invalid piece {}", index)))?),
+            None => None,
+        };
+        return Ok(ChessMove::new(source, dest, promotion));
+    }
+}
+
+/// Encodes the state as its fen, followed by the last move.
+impl Encode for ChessState {
+    fn encode(&self, out: &mut Vec<u8>) {
+        self.to_fen().encode(out);
+        self.last_move.encode(out);
+    }
+}
+
+impl Decode for ChessState {
+    fn decode(input: &mut &[u8]) -> Result<Self, DecodeError> {
+        let fen = String::decode(input)?;
+        let mut state = ChessState::try_from_str(&fen).map_err(|error| DecodeError(error.to_string()))?;
+        state.last_move = Option::<ChessMove>::decode(input)?;
+        return Ok(state);
+    }
+}
+
 
 /// Defines unit tests for the GameState implementation of
 /// ChessState.
@@ -102,7 +652,7 @@ mod tests {
         assert!(test == ground_truth);
     }
     
-    /// A series of tests to check if the logic behind making moves is correct.
+    // A series of tests to check if the logic behind making moves is correct.
 
     /// Tests e4 and nf3 opening moves.
     #[test]
@@ -196,7 +746,7 @@ mod tests {
         assert!(test.board == ground_truth);
     }
 
-    /// Tests to check that legal move lists are correct and complete.
+    // Tests to check that legal move lists are correct and complete.
 
     /// Tests legal move generation during an abitrarily middle game position.
     #[test]
@@ -287,6 +837,212 @@ mod tests {
         assert!(after_mate.generate_legal_actions().len() == 0);
     }
 
+    /// Test if the halfmove clock is read from the fen.
+    #[test]
+    fn test_halfmove_clock_from_fen() {
+        let game_start = ChessState::from_str("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1".to_string());
+        assert!(game_start.fifty_move_counter == 0);
+
+        let endgame = ChessState::from_str("8/8/4k3/8/8/3K4/R7/8 w - - 37 80".to_string());
+        assert!(endgame.fifty_move_counter == 37);
+
+        // Fens without move counters default to a clock of 0.
+        let no_counters = ChessState::from_str("8/8/4k3/8/8/3K4/R7/8 w - -".to_string());
+        assert!(no_counters.fifty_move_counter == 0);
+    }
+
+    /// Test if the fifty move rule ends the game after 100 half-moves.
+    #[test]
+    fn test_fifty_move_rule() {
+        let near_limit = ChessState::from_str("8/8/4k3/8/8/3K4/R7/8 w - - 98 80".to_string());
+        assert!(near_limit.status_with_moves_left());
+
+        // One half-move below the limit the game continues.
+        let below_limit = near_limit.apply_action(&ChessMove::new(Square::A2, Square::A1, None));
+        assert!(below_limit.fifty_move_counter == 99);
+        assert!(below_limit.status_with_moves_left());
+
+        let at_limit = below_limit.apply_action(&ChessMove::new(Square::E6, Square::E5, None));
+        assert!(at_limit.fifty_move_counter == 100);
+        assert!(!at_limit.status_with_moves_left());
+        assert!(at_limit.result() == GameResult::Draw);
+
+        // A mate on the hundredth half-move wins rather than draws.
+        let mating = ChessState::from_str("6k1/5ppp/8/8/8/8/8/R5K1 w - - 99 80".to_string())
+            .apply_action(&ChessMove::new(Square::A1, Square::A8, None));
+        assert!(mating.fifty_move_counter == 100);
+        assert!(!mating.status_with_moves_left());
+        assert!(mating.result() == GameResult::FirstPlayerWin);
+
+        // A pawn move or capture resets the clock.
+        let pawn_move = ChessState::from_str("8/8/4k3/8/8/3K4/4P3/8 w - - 99 80".to_string())
+            .apply_action(&ChessMove::new(Square::E2, Square::E4, None));
+        assert!(pawn_move.fifty_move_counter == 0);
+    }
+
+    /// Test if positions without mating material are drawn.
+    #[test]
+    fn test_insufficient_material() {
+        let insufficient = [
+            "8/8/4k3/8/8/3K4/8/8 w - - 0 1",
+            "8/8/4k3/8/8/3K4/5B2/8 w - - 0 1",
+            "8/8/4k3/8/8/3K4/5N2/8 b - - 0 1",
+            "8/2b5/4k3/8/8/3K4/5B2/8 w - - 0 1",
+        ];
+        for fen in insufficient {
+            let game_state = ChessState::from_str(fen.to_string());
+            assert!(game_state.insufficient_material());
+            assert!(!game_state.status_with_moves_left());
+            assert!(game_state.result() == GameResult::Draw);
+        }
+
+        let sufficient = [
+            "8/8/4k3/8/8/3K4/R7/8 w - - 0 1",
+            "8/8/4k3/8/8/3K4/4P3/8 w - - 0 1",
+            "8/8/4k3/8/8/3K4/4NN2/8 w - - 0 1",
+            "8/3b4/4k3/8/8/3K4/5B2/8 w - - 0 1",
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+        ];
+        for fen in sufficient {
+            let game_state = ChessState::from_str(fen.to_string());
+            assert!(!game_state.insufficient_material());
+            assert!(game_state.status_with_moves_left());
+        }
+    }
+
+    /// Prober which only knows a single position.
+    struct SinglePositionProber {
+        board: Board,
+        wdl: Wdl,
+    }
+
+    impl tablebase::TablebaseProber for SinglePositionProber {
+        fn max_pieces(&self) -> u32 {
+            return 3;
+        }
+
+        fn probe_wdl(&self, board: &Board) -> Option<Wdl> {
+            return if *board == self.board { Some(self.wdl) } else { None };
+        }
+    }
+
+    /// Test if positions covered by the tablebase end with the probed result.
+    #[test]
+    fn test_tablebase_probe() {
+        let covered = ChessState::from_str("8/8/4k3/8/8/3K4/8/Q7 w - - 0 1".to_string());
+        let prober = SinglePositionProber { board: covered.board, wdl: Wdl::Win };
+        assert!(tablebase::set_tablebase(Box::new(prober)).is_ok());
+
+        assert!(!covered.status_with_moves_left());
+        assert!(covered.result() == GameResult::FirstPlayerWin);
+
+        // Positions the tables do not cover are played out.
+        let uncovered = ChessState::from_str("8/8/4k3/8/8/3K4/8/Q7 b - - 0 1".to_string());
+        assert!(uncovered.status_with_moves_left());
+    }
+
+    /// Test if square attacks are detected for every piece type.
+    #[test]
+    fn test_is_attacked() {
+        let board = Board::from_str("4k3/8/8/3p4/8/5N2/8/R3K3 w Q - 0 1").unwrap();
+        assert!(is_attacked(&board, Square::E4, Color::Black));
+        assert!(is_attacked(&board, Square::E5, Color::White));
+        assert!(is_attacked(&board, Square::A8, Color::White));
+        assert!(is_attacked(&board, Square::D7, Color::Black));
+        assert!(!is_attacked(&board, Square::H8, Color::White));
+    }
+
+    /// Test if the chess rollout policy prefers winning material and keeps its queen.
+    #[test]
+    fn test_chess_rollout_policy() {
+        let seed = 1;
+        let mut random_generator: SearchRng = crate::rng::SeedableRng::from_seed(seed);
+        let mut policy = ChessRolloutPolicy { check_probability: 0.0, ..Default::default() };
+
+        // The rook on a1 can take an undefended queen on a8.
+        let free_queen = ChessState::from_str("q3k3/8/8/8/8/8/8/R3K3 w - - 0 1".to_string());
+        let actions = free_queen.generate_legal_actions();
+        let capture = actions.iter().position(|action| action.get_dest() == Square::A8).unwrap();
+        let mut captures = 0;
+        for _i in 0..1000 {
+            if policy.choose(&free_queen, &actions, &mut random_generator) == capture {
+                captures += 1;
+            }
+        }
+        assert!(captures > 500);
+
+        // The queen on d1 should rarely step next to the pawns attacking d4 and e4.
+        let pawns = ChessState::from_str("4k3/8/8/2pp4/8/8/8/3QK3 w - - 0 1".to_string());
+        let actions = pawns.generate_legal_actions();
+        let mut hanging = 0;
+        for _i in 0..1000 {
+            let action = actions[policy.choose(&pawns, &actions, &mut random_generator)];
+            if action.get_source() == Square::D1 && is_attacked(&pawns.board.make_move_new(action), action.get_dest(), Color::Black) {
+                hanging += 1;
+            }
+        }
+        assert!(hanging < 50);
+
+        // Checks are always played when the check probability is 1.
+        policy.check_probability = 1.0;
+        let actions = pawns.generate_legal_actions();
+        for _i in 0..100 {
+            let action = actions[policy.choose(&pawns, &actions, &mut random_generator)];
+            assert!(pawns.board.make_move_new(action).checkers() != &chess::EMPTY);
+        }
+    }
+
+    /// Test if positions with captures or checks available are not quiet.
+    #[test]
+    fn test_is_quiet() {
+        let quiet = ChessState::from_str("4k3/8/8/8/8/8/4P3/4K3 w - - 0 1".to_string());
+        assert!(quiet.is_quiet());
+        let capture = ChessState::from_str("4k3/8/8/8/8/3p4/4P3/4K3 w - - 0 1".to_string());
+        assert!(!capture.is_quiet());
+        let check = ChessState::from_str("4k3/8/8/8/8/8/8/R3K3 w - - 0 1".to_string());
+        assert!(!check.is_quiet());
+        let en_passant = ChessState::from_str("4k3/8/8/3pP3/8/8/8/4K3 w - d6 0 2".to_string());
+        assert!(!en_passant.is_quiet());
+    }
+
+    /// Test if rollouts end at quiet positions once the cutoff is reached.
+    #[test]
+    fn test_quiescence_cutoff() {
+        let mut policy = ChessRolloutPolicy { quiescence_cutoff: Some(4), ..Default::default() };
+        let quiet = ChessState::from_str("4k3/8/8/8/8/8/4P3/4K3 w - - 0 1".to_string());
+        assert!(policy.cutoff(&quiet, 3).is_none());
+        assert!(policy.cutoff(&quiet, 4) == Some(GameResult::Score(quiet.evaluate())));
+        let capture = ChessState::from_str("4k3/8/8/8/8/3p4/4P3/4K3 w - - 0 1".to_string());
+        assert!(policy.cutoff(&capture, 10).is_none());
+    }
+
+    /// Test if invalid fens are reported rather than panicking.
+    #[test]
+    fn test_try_from_str() {
+        let start = ChessState::try_from_str("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1");
+        assert!(start.is_ok());
+        assert!(ChessState::try_from_str("not a fen").is_err());
+
+        // Trees report the error of their starting position.
+        let tree = crate::mcts::MCTSTree::<ChessMove, ChessState>::try_with_capacity(10, None, "not a fen", 30);
+        assert!(tree.is_err());
+    }
+
+    /// Test if legal actions generated into a buffer match the generated vector.
+    #[test]
+    fn test_legal_actions_into() {
+        let middlegame = ChessState::from_str(
+            "rn2kbnr/ppp3pp/3q1p2/4p3/4P1b1/3P1P2/PPP3PP/RNBQK2R b KQkq - 0 7".to_string()
+        );
+        let mut actions = Vec::new();
+        middlegame.legal_actions_into(&mut actions);
+        assert!(actions == middlegame.generate_legal_actions());
+
+        // The buffer is replaced rather than appended to.
+        middlegame.legal_actions_into(&mut actions);
+        assert!(actions.len() == middlegame.generate_legal_actions().len());
+    }
+
     /// Test whether current player indicator is correct.
     #[test]
     fn test_side_to_move() {
@@ -305,4 +1061,123 @@ mod tests {
         );
         assert!(checkmate_white_to_move.side_to_move());
     }
+
+    /// Test if the static evaluation follows the material balance.
+    #[test]
+    fn test_evaluate() {
+        let start = ChessState::from_str("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1".to_string());
+        assert_eq!(start.material_balance(), 0);
+        assert_eq!(start.evaluate(), 0.5);
+
+        // Black is missing the queen.
+        let queen_up = ChessState::from_str("rnb1kbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1".to_string());
+        assert_eq!(queen_up.material_balance(), 9);
+        assert!(queen_up.evaluate() > 0.9);
+    }
+
+    /// Test if transpositions share a hash, while different positions do not.
+    #[test]
+    fn test_state_hash() {
+        let start = ChessState::from_str("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1".to_string());
+        let knights_first = start
+            .apply_action(&ChessMove::new(Square::G1, Square::F3, None))
+            .apply_action(&ChessMove::new(Square::G8, Square::F6, None))
+            .apply_action(&ChessMove::new(Square::B1, Square::C3, None));
+        let queenside_first = start
+            .apply_action(&ChessMove::new(Square::B1, Square::C3, None))
+            .apply_action(&ChessMove::new(Square::G8, Square::F6, None))
+            .apply_action(&ChessMove::new(Square::G1, Square::F3, None));
+        assert!(knights_first.state_hash().is_some());
+        assert_eq!(knights_first.state_hash(), queenside_first.state_hash());
+        assert!(knights_first.state_hash() != start.state_hash());
+
+        // The side to move is part of the hash.
+        let black_to_move = ChessState::from_str("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR b KQkq - 0 1".to_string());
+        assert!(black_to_move.state_hash() != start.state_hash());
+    }
+
+    /// Test if Chess960 castling rights are parsed from Shredder and X-FEN notation.
+    #[test]
+    fn test_chess960_from_str() {
+        let shredder = ChessState::from_str("1r2k1r1/1p4p1/8/8/8/8/1P4P1/1R2K1R1 w GBgb - 0 1".to_string());
+        let x_fen = ChessState::from_str("1r2k1r1/1p4p1/8/8/8/8/1P4P1/1R2K1R1 w KQkq - 0 1".to_string());
+        let expected = Chess960Castling { rook_files: [[Some(File::G), Some(File::B)], [Some(File::G), Some(File::B)]] };
+        assert_eq!(shredder.chess960, Some(expected));
+        assert_eq!(x_fen.chess960, Some(expected));
+        assert_eq!(shredder.to_fen(), "1r2k1r1/1p4p1/8/8/8/8/1P4P1/1R2K1R1 w GBgb - 0 1");
+
+        // Standard setups are castled by the board, whichever notation they use.
+        let standard = ChessState::from_str("r3k2r/8/8/8/8/8/8/R3K2R w HAha - 0 1".to_string());
+        assert!(standard.chess960.is_none());
+        assert!(standard.generate_legal_actions().contains(&ChessMove::new(Square::E1, Square::G1, None)));
+
+        // Rights without a matching rook are invalid.
+        assert!(ChessState::try_from_str("4k3/8/8/8/8/8/8/1R2K3 w C - 0 1").is_err());
+    }
+
+    /// Test if Chess960 castling moves are generated and played as the king capturing its own rook.
+    #[test]
+    fn test_chess960_castling() {
+        let game_state = ChessState::from_str("1r2k1r1/1p4p1/8/8/8/8/1P4P1/1R2K1R1 w GBgb - 0 1".to_string());
+        let kingside = ChessMove::new(Square::E1, Square::G1, None);
+        let queenside = ChessMove::new(Square::E1, Square::B1, None);
+        let actions = game_state.generate_legal_actions();
+        assert!(actions.contains(&kingside));
+        assert!(actions.contains(&queenside));
+        assert!(game_state.is_chess960_castle(&kingside));
+
+        let mut buffer = Vec::new();
+        game_state.legal_actions_into(&mut buffer);
+        assert!(buffer == actions);
+
+        let castled = game_state.apply_action(&kingside);
+        assert_eq!(castled.board.piece_on(Square::G1), Some(Piece::King));
+        assert_eq!(castled.board.piece_on(Square::F1), Some(Piece::Rook));
+        assert_eq!(castled.board.piece_on(Square::E1), None);
+        assert_eq!(castled.board.side_to_move(), Color::Black);
+        assert_eq!(castled.fifty_move_counter, 1);
+        assert_eq!(castled.chess960.unwrap().rook_files[0], [None, None]);
+        assert_eq!(castled.to_fen(), "1r2k1r1/1p4p1/8/8/8/8/1P4P1/1R3RK1 b gb - 1 1");
+
+        let castled = game_state.apply_action(&queenside);
+        assert_eq!(castled.board.piece_on(Square::C1), Some(Piece::King));
+        assert_eq!(castled.board.piece_on(Square::D1), Some(Piece::Rook));
+
+        // Moving a rook loses its right only.
+        let rook_moved = game_state.apply_action(&ChessMove::new(Square::B1, Square::A1, None));
+        assert_eq!(rook_moved.chess960.unwrap().rook_files[0], [Some(File::G), None]);
+        assert!(rook_moved.state_hash() != ChessState::from_str("1r2k1r1/1p4p1/8/8/8/8/1P4P1/R3K1R1 b gb - 1 1".to_string()).state_hash());
+
+        // The king may not cross attacked squares, nor castle out of check.
+        let attacked = ChessState::from_str("1r2kr2/1p4p1/8/8/8/8/1P4P1/1R2K1R1 w GB - 0 1".to_string());
+        assert!(!attacked.generate_legal_actions().contains(&kingside));
+        assert!(attacked.generate_legal_actions().contains(&queenside));
+        let in_check = ChessState::from_str("1r2k1r1/1p4p1/8/8/4r3/8/1P4P1/1R2K1R1 w GB - 0 1".to_string());
+        assert!(!in_check.generate_legal_actions().iter().any(|action| in_check.is_chess960_castle(action)));
+    }
+
+    /// Test if Chess960 starting positions follow the Scharnagl numbering and setup rules.
+    #[test]
+    fn test_chess960_start() {
+        let standard = ChessState::chess960(518);
+        assert!(standard.board == Board::default());
+        assert!(standard.chess960.is_none());
+        assert_eq!(ChessState::chess960(0).to_fen(), "bbqnnrkr/pppppppp/8/8/8/8/PPPPPPPP/BBQNNRKR w HFhf - 0 1");
+
+        let seed = 1;
+        let mut random_generator: SearchRng = crate::rng::SeedableRng::from_seed(seed);
+        for _i in 0..100 {
+            let game_state = ChessState::random_chess960(&mut random_generator);
+            let bishops = *game_state.board.pieces(Piece::Bishop) & game_state.board.color_combined(Color::White);
+            let dark_squares = chess::BitBoard::new(0x55AA_55AA_55AA_55AA);
+            assert_eq!((bishops & dark_squares).popcnt(), 1);
+
+            let king = game_state.board.king_square(Color::White).get_file().to_index();
+            let rooks: Vec<usize> = (*game_state.board.pieces(Piece::Rook) & game_state.board.color_combined(Color::White))
+                .map(|square| square.get_file().to_index())
+                .collect();
+            assert!(rooks[0] < king && king < rooks[1]);
+            assert!(game_state.generate_legal_actions().len() > 0);
+        }
+    }
 }
@@ -0,0 +1,97 @@
+//! Iteration over the children of a node with their statistics, so consumers can inspect the
+//! alternatives at a node without reading the arena directly.
+
+use crate::arena::{NodeId, NodeIndex};
+use crate::game_state_trait::GameState;
+use crate::mcts::{MCTSTree, Value};
+
+
+/// Statistics of a child, as listed by `MCTSTree::children`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ChildStats {
+    /// Simulations of the child.
+    pub visits: u64,
+
+    /// Simulations won by the player who moved into the child.
+    pub wins: u64,
+
+    /// Simulations drawn.
+    pub draws: u64,
+
+    /// Mean reward of the player who moved into the child, 0 if it is unvisited.
+    pub mean: f64,
+
+    /// Value of the child during selection, see `MCTSTree::node_value`.
+    pub value: Value,
+
+    /// Prior probability of the child, or the probability of the outcome below a chance node.
+    pub prior: f32,
+}
+
+
+/// Children of a node.
+impl<Action, GameStateObj, Data> MCTSTree<Action, GameStateObj, Data>
+where
+    GameStateObj: GameState<Action> + Clone
+{
+    /// The expanded children of `node` in order of expansion, with their actions and statistics.
+    ///
+    /// Actions which have not been expanded have no node and no statistics, and are left out.
+    ///
+    /// # Returns
+    /// An iterator of a handle to every child, the action leading to it and its statistics.
+    ///
+    /// # Panics
+    /// If `node` is stale.
+    pub fn children(&self, node: NodeId) -> impl Iterator<Item = (NodeId, &Action, ChildStats)> + '_ {
+        return self.children_at(self.index_of(node));
+    }
+
+    /// `children` by arena index.
+    pub(crate) fn children_at(&self, node: NodeIndex) -> impl Iterator<Item = (NodeId, &Action, ChildStats)> + '_ {
+        return self.arena.expanded(node).map(move |child| {
+            let stats = &self.arena.stats[child as usize];
+            let child_stats = ChildStats {
+                visits: stats.sims,
+                wins: stats.wins,
+                draws: stats.draws,
+                mean: if stats.sims == 0 { 0.0 } else { stats.score / stats.sims as f64 },
+                value: self.node_value_at(child),
+                prior: self.arena.topology[child as usize].probability,
+            };
+            return (self.arena.handle(child), self.arena.actions[child as usize].as_ref().expect("no action"), child_stats);
+        });
+    }
+}
+
+
+/// Unit tests for the iteration over children.
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tictactoe_env::TicTacToeState;
+    use alloc::string::ToString;
+    use alloc::vec::Vec;
+
+    /// Test if the children of the root are listed with the statistics of their nodes.
+    #[test]
+    fn test_children() {
+        let mut tree = MCTSTree::<u8, TicTacToeState>::with_capacity(1000, Some(1), "XX.OO....".to_string(), 9);
+        assert_eq!(tree.children(tree.root()).count(), 0);
+        tree.search(300);
+
+        let children: Vec<(NodeId, &u8, ChildStats)> = tree.children(tree.root()).collect();
+        assert_eq!(children.len(), 5);
+        assert_eq!(children.iter().map(|(_, _, stats)| stats.visits).sum::<u64>(), 300);
+        for (id, action, stats) in children {
+            let child = tree.arena.resolve(id).unwrap();
+            assert_eq!(tree.arena.actions[child as usize].as_ref(), Some(action));
+            assert_eq!(stats.visits, tree.arena.stats[child as usize].sims);
+            assert_eq!(stats.mean, tree.arena.stats[child as usize].score / stats.visits as f64);
+            assert_eq!(stats.value, tree.node_value_at(child));
+        }
+
+        let (_, best, _) = tree.children(tree.root()).max_by_key(|(_, _, stats)| stats.visits).unwrap();
+        assert_eq!(*best, 2);
+    }
+}
@@ -0,0 +1,113 @@
+//! Monotonic clock of the engine, timing searches, phases and time controls.
+//!
+//! `std::time::Instant` panics on `wasm32-unknown-unknown`, which has no clock of its own,
+//! so on WebAssembly the time is read from the host instead: the module imports a function
+//! `now_ms` from the `mcts` import module, returning milliseconds of a monotonic clock, such
+//! as `performance.now()` in a browser. Without the `std` feature there is no clock at all,
+//! and every `Instant` is the same, so measured search and phase times are 0. Elsewhere,
+//! `Instant` is the standard one.
+
+#[cfg(all(feature = "std", not(target_arch = "wasm32")))]
+pub use std::time::Instant;
+
+#[cfg(all(feature = "std", target_arch = "wasm32"))]
+pub use self::host::Instant;
+
+#[cfg(not(feature = "std"))]
+pub use self::stopped::Instant;
+
+
+#[cfg(all(feature = "std", target_arch = "wasm32"))]
+mod host {
+    use std::ops::{Add, Sub};
+    use std::time::Duration;
+
+    #[link(wasm_import_module = "mcts")]
+    extern "C" {
+        /// Milliseconds of a monotonic clock of the host.
+        fn now_ms() -> f64;
+    }
+
+
+    /// A point in time of the clock of the host, in milliseconds since an arbitrary origin.
+    #[derive(Debug, Clone, Copy, PartialEq, PartialOrd)]
+    pub struct Instant(f64);
+
+    impl Instant {
+        /// The current time of the host.
+        pub fn now() -> Self {
+            // SAFETY: `now_ms` takes no arguments and only reads the clock of the host.
+            return Instant(unsafe { now_ms() });
+        }
+
+        /// Time from `earlier` to `self`, 0 if `earlier` is later.
+        pub fn duration_since(&self, earlier: Instant) -> Duration {
+            return Duration::from_secs_f64((self.0 - earlier.0).max(0.0) / 1000.0);
+        }
+
+        /// Time since `self`.
+        pub fn elapsed(&self) -> Duration {
+            return Instant::now().duration_since(*self);
+        }
+    }
+
+    impl Sub for Instant {
+        type Output = Duration;
+
+        fn sub(self, earlier: Instant) -> Duration {
+            return self.duration_since(earlier);
+        }
+    }
+
+    impl Add<Duration> for Instant {
+        type Output = Instant;
+
+        fn add(self, duration: Duration) -> Instant {
+            return Instant(self.0 + duration.as_secs_f64() * 1000.0);
+        }
+    }
+}
+
+
+#[cfg(not(feature = "std"))]
+mod stopped {
+    use core::ops::{Add, Sub};
+    use core::time::Duration;
+
+    /// A point in time of a clock which never advances.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+    pub struct Instant;
+
+    impl Instant {
+        /// The only point in time.
+        pub fn now() -> Self {
+            return Instant;
+        }
+
+        /// Always 0.
+        pub fn duration_since(&self, _earlier: Instant) -> Duration {
+            return Duration::ZERO;
+        }
+
+        /// Always 0.
+        pub fn elapsed(&self) -> Duration {
+            return Duration::ZERO;
+        }
+    }
+
+    impl Sub for Instant {
+        type Output = Duration;
+
+        fn sub(self, _earlier: Instant) -> Duration {
+            return Duration::ZERO;
+        }
+    }
+
+    impl Add<Duration> for Instant {
+        type Output = Instant;
+
+        fn add(self, _duration: Duration) -> Instant {
+            return Instant;
+        }
+    }
+}
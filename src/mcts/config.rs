@@ -0,0 +1,305 @@
+//! Search configurations read from TOML files, so that searches can be run without writing code.
+//!
+//! A configuration holds the keys:
+//! * `environment` and `position` : The environment by name and the position to search, as in `SearchRequest`.
+//! * `seed` : Seed of the random generator of the tree, 1 by default.
+//! * `budget.iterations` or `budget.movetime_ms` : Limit of the search, 10000 iterations by default.
+//! * `search.exploration` : Constant exploration factor, the default schedule of the tree when not set.
+//! * `output.format` : `"text"` by default, or `"json"`, see `OutputFormat`.
+//! * `output.tree` and `output.tree_depth` : File the tree is exported to as JSON, and the depth of the export.
+//!
+//! Keys of a table are written under its header, such as `iterations = 20000` under `[budget]`.
+//!
+//! Only the subset of TOML used by configurations is parsed: tables, and keys holding basic
+//! strings, integers, floats and booleans. Unknown keys are errors, so misspelled options
+//! are not silently ignored.
+
+use crate::service::{Environment, SearchLimit, SearchRequest, ServiceError};
+use std::collections::HashMap;
+use std::fmt;
+use std::path::PathBuf;
+use std::time::Duration;
+
+
+/// Depth of the exported tree when `output.tree_depth` is not set.
+const DEFAULT_TREE_DEPTH: u32 = 3;
+
+
+/// Value of a key of a configuration file.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ConfigValue {
+    String(String),
+    Integer(i64),
+    Float(f64),
+    Boolean(bool),
+}
+
+
+/// Format of the result of a search.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutputFormat {
+    /// The best action, then a table of the root moves and the totals of the search.
+    Text,
+
+    /// The JSON object of `SearchResponse::to_json`.
+    Json,
+}
+
+
+/// A search and the output of its result.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SearchConfig {
+    pub request: SearchRequest,
+
+    pub format: OutputFormat,
+
+    /// File the exported tree is written to, None to not export it.
+    pub tree_path: Option<PathBuf>,
+}
+
+
+/// Reasons a configuration is invalid.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ConfigError {
+    /// A line is not valid TOML, with its number from 1.
+    Syntax { line: usize, message: String },
+
+    UnknownKey(String),
+
+    /// A key holds a value of the wrong type or out of range.
+    InvalidValue(String),
+
+    MissingKey(String),
+
+    /// The environment is not bundled.
+    Service(ServiceError),
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigError::Syntax { line, message } => return write!(f, "line {}: {}", line, message),
+            ConfigError::UnknownKey(key) => return write!(f, "unknown key: {}", key),
+            ConfigError::InvalidValue(key) => return write!(f, "invalid value of {}", key),
+            ConfigError::MissingKey(key) => return write!(f, "missing key: {}", key),
+            ConfigError::Service(error) => return write!(f, "{}", error),
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+
+/// Parses the keys of a TOML document, qualified by their table as `table.key`.
+pub fn parse_toml(text: &str) -> Result<HashMap<String, ConfigValue>, ConfigError> {
+    let mut values = HashMap::new();
+    let mut table = String::new();
+    for (i, raw_line) in text.lines().enumerate() {
+        let syntax = |message: &str| ConfigError::Syntax { line: i + 1, message: message.to_string() };
+        let line = strip_comment(raw_line).trim();
+        if line.is_empty() {
+            continue;
+        }
+
+        if let Some(header) = line.strip_prefix('[') {
+            let name = header.strip_suffix(']').ok_or_else(|| syntax("unclosed table header"))?.trim();
+            if name.is_empty() || !name.split('.').all(is_bare_key) {
+                return Err(syntax("invalid table name"));
+            }
+            table = format!("{}.", name);
+            continue;
+        }
+
+        let (key, value) = line.split_once('=').ok_or_else(|| syntax("expected key = value"))?;
+        let key = key.trim();
+        if !key.split('.').all(is_bare_key) {
+            return Err(syntax("invalid key"));
+        }
+        let value = parse_value(value.trim()).ok_or_else(|| syntax("invalid value"))?;
+        if values.insert(format!("{}{}", table, key), value).is_some() {
+            return Err(syntax("duplicate key"));
+        }
+    }
+    return Ok(values);
+}
+
+/// Removes a comment from `line`, ignoring `#` inside strings.
+fn strip_comment(line: &str) -> &str {
+    let mut in_string = false;
+    let mut escaped = false;
+    for (i, character) in line.char_indices() {
+        if escaped {
+            escaped = false;
+            continue;
+        }
+        match character {
+            '\\' if in_string => escaped = true,
+            '"' => in_string = !in_string,
+            '#' if !in_string => return &line[..i],
+            _ => {},
+        }
+    }
+    return line;
+}
+
+fn is_bare_key(key: &str) -> bool {
+    return !key.is_empty() && key.chars().all(|character| character.is_ascii_alphanumeric() || character == '_' || character == '-');
+}
+
+/// Parses a basic string, integer, float or boolean.
+fn parse_value(value: &str) -> Option<ConfigValue> {
+    if let Some(quoted) = value.strip_prefix('"') {
+        let mut string = String::new();
+        let mut characters = quoted.chars();
+        loop {
+            match characters.next()? {
+                '"' => break,
+                '\\' => string.push(match characters.next()? {
+                    'n' => '\n',
+                    't' => '\t',
+                    '"' => '"',
+                    '\\' => '\\',
+                    _ => return None,
+                }),
+                character => string.push(character),
+            }
+        }
+        return if characters.as_str().is_empty() { Some(ConfigValue::String(string)) } else { None };
+    }
+    match value {
+        "true" => return Some(ConfigValue::Boolean(true)),
+        "false" => return Some(ConfigValue::Boolean(false)),
+        _ => {},
+    }
+    let number = value.replace('_', "");
+    if let Ok(integer) = number.parse::<i64>() {
+        return Some(ConfigValue::Integer(integer));
+    }
+    return number.parse::<f64>().ok().filter(|float| float.is_finite()).map(ConfigValue::Float);
+}
+
+
+impl SearchConfig {
+    /// Parses a configuration from the text of a TOML file.
+    pub fn parse(text: &str) -> Result<Self, ConfigError> {
+        let mut values = parse_toml(text)?;
+        let mut take = |key: &str| values.remove(key);
+
+        let environment = match take("environment") {
+            Some(ConfigValue::String(name)) => name.parse::<Environment>().map_err(ConfigError::Service)?,
+            Some(_) => return Err(ConfigError::InvalidValue("environment".to_string())),
+            None => return Err(ConfigError::MissingKey("environment".to_string())),
+        };
+        let position = match take("position") {
+            Some(ConfigValue::String(position)) => position,
+            Some(_) => return Err(ConfigError::InvalidValue("position".to_string())),
+            None => return Err(ConfigError::MissingKey("position".to_string())),
+        };
+        let mut request = SearchRequest::new(environment, &position);
+
+        if let Some(seed) = take("seed") {
+            request.seed = integer_at_least("seed", seed, 0)?;
+        }
+        match (take("budget.iterations"), take("budget.movetime_ms")) {
+            (Some(_), Some(_)) => return Err(ConfigError::InvalidValue("budget".to_string())),
+            (Some(iterations), None) => request.limit = SearchLimit::Iterations(integer_at_least("budget.iterations", iterations, 1)?),
+            (None, Some(time)) => request.limit = SearchLimit::Time(Duration::from_millis(integer_at_least("budget.movetime_ms", time, 1)?)),
+            (None, None) => {},
+        }
+        if let Some(exploration) = take("search.exploration") {
+            request.exploration = match exploration {
+                ConfigValue::Float(factor) if factor >= 0.0 => Some(factor as f32),
+                ConfigValue::Integer(factor) if factor >= 0 => Some(factor as f32),
+                _ => return Err(ConfigError::InvalidValue("search.exploration".to_string())),
+            };
+        }
+
+        let format = match take("output.format") {
+            None => OutputFormat::Text,
+            Some(ConfigValue::String(format)) if format == "text" => OutputFormat::Text,
+            Some(ConfigValue::String(format)) if format == "json" => OutputFormat::Json,
+            Some(_) => return Err(ConfigError::InvalidValue("output.format".to_string())),
+        };
+        let tree_path = match take("output.tree") {
+            None => None,
+            Some(ConfigValue::String(path)) => Some(PathBuf::from(path)),
+            Some(_) => return Err(ConfigError::InvalidValue("output.tree".to_string())),
+        };
+        let tree_depth = match take("output.tree_depth") {
+            None => DEFAULT_TREE_DEPTH,
+            Some(depth) => integer_at_least("output.tree_depth", depth, 0)?.min(u32::MAX as u64) as u32,
+        };
+        if tree_path.is_some() {
+            request.export_depth = Some(tree_depth);
+        }
+
+        if let Some(key) = values.into_keys().min() {
+            return Err(ConfigError::UnknownKey(key));
+        }
+        return Ok(SearchConfig { request, format, tree_path });
+    }
+}
+
+/// Reads `value` of `key` as an integer of at least `min`.
+fn integer_at_least(key: &str, value: ConfigValue, min: i64) -> Result<u64, ConfigError> {
+    match value {
+        ConfigValue::Integer(integer) if integer >= min => return Ok(integer as u64),
+        _ => return Err(ConfigError::InvalidValue(key.to_string())),
+    }
+}
+
+
+/// Defines unit tests for search configurations.
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Test if the TOML subset is parsed into qualified keys.
+    #[test]
+    fn test_parse_toml() {
+        let values = parse_toml("name = \"a # b\\n\" # comment\n\n[table]\ncount = 1_000\nrate = -0.5\nflag = true\n").unwrap();
+        assert_eq!(values["name"], ConfigValue::String("a # b\n".to_string()));
+        assert_eq!(values["table.count"], ConfigValue::Integer(1000));
+        assert_eq!(values["table.rate"], ConfigValue::Float(-0.5));
+        assert_eq!(values["table.flag"], ConfigValue::Boolean(true));
+
+        assert_eq!(parse_toml("a = 1\nb 2"), Err(ConfigError::Syntax { line: 2, message: "expected key = value".to_string() }));
+        assert!(parse_toml("[table").is_err());
+        assert!(parse_toml("a = \"open").is_err());
+        assert!(parse_toml("a = 1\na = 2").is_err());
+    }
+
+    /// Test if configurations are read into requests and output options.
+    #[test]
+    fn test_parse_config() {
+        let config = SearchConfig::parse(
+            "environment = \"tictactoe\"\nposition = \"X...O....\"\nseed = 7\n\n[budget]\nmovetime_ms = 250\n\n\
+             [search]\nexploration = 1\n\n[output]\nformat = \"json\"\ntree = \"tree.json\"\n"
+        ).unwrap();
+        assert_eq!(config.request, SearchRequest {
+            limit: SearchLimit::Time(Duration::from_millis(250)),
+            seed: 7,
+            exploration: Some(1.0),
+            export_depth: Some(DEFAULT_TREE_DEPTH),
+            ..SearchRequest::new(Environment::TicTacToe, "X...O....")
+        });
+        assert_eq!(config.format, OutputFormat::Json);
+        assert_eq!(config.tree_path, Some(PathBuf::from("tree.json")));
+
+        let config = SearchConfig::parse("environment = \"nim\"\nposition = \"3 4 5\"").unwrap();
+        assert_eq!(config.request, SearchRequest::new(Environment::Nim, "3 4 5"));
+        assert_eq!((config.format, config.tree_path), (OutputFormat::Text, None));
+    }
+
+    /// Test if invalid configurations are reported with the offending key.
+    #[test]
+    fn test_parse_config_errors() {
+        let parse = |text: &str| SearchConfig::parse(text).unwrap_err();
+        assert_eq!(parse("position = \"3\""), ConfigError::MissingKey("environment".to_string()));
+        assert_eq!(parse("environment = \"checkers\""), ConfigError::Service(ServiceError::UnknownEnvironment("checkers".to_string())));
+        assert_eq!(parse("environment = \"nim\"\nposition = \"3\"\nbudget.iterations = 0"), ConfigError::InvalidValue("budget.iterations".to_string()));
+        assert_eq!(parse("environment = \"nim\"\nposition = \"3\"\n[budget]\niterations = 5\nmovetime_ms = 5"), ConfigError::InvalidValue("budget".to_string()));
+        assert_eq!(parse("environment = \"nim\"\nposition = \"3\"\n[output]\nformat = \"xml\""), ConfigError::InvalidValue("output.format".to_string()));
+        assert_eq!(parse("environment = \"nim\"\nposition = \"3\"\n[search]\nexplore = 1.0"), ConfigError::UnknownKey("search.explore".to_string()));
+    }
+}
@@ -0,0 +1,456 @@
+use crate::game_state_trait::{GameResult, GameState};
+use crate::serialize::{decode_from_str, Decode, DecodeError, Encode};
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::fmt;
+
+
+/// Number of columns of the board.
+pub const WIDTH: usize = 7;
+
+/// Number of rows of the board.
+pub const HEIGHT: usize = 6;
+
+/// Bits used per column, with one sentinel bit above the top row so that
+/// shifted lines never wrap from one column into the next.
+const COLUMN_BITS: usize = HEIGHT + 1;
+
+/// Bottom cell of every column.
+const BOTTOM_ROW: u64 = 0x408_1020_4081;
+
+
+/// Error produced when a string is not a valid Connect Four position.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Connect4ParseError(pub String);
+
+impl fmt::Display for Connect4ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        return write!(f, "invalid connect four position: {}", self.0);
+    }
+}
+
+impl core::error::Error for Connect4ParseError {}
+
+
+/// Holds the state of a Connect Four board as a pair of bitboards.
+///
+/// Bit `column * 7 + row` is the cell of `column` and `row`, counted from the bottom left corner.
+/// Actions are the column to drop a disc into, from 0 to 6. The first player moves first.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Connect4State {
+    /// Discs of the side to move.
+    pub current: u64,
+
+    /// Discs of both sides.
+    pub mask: u64,
+
+    /// Number of discs played.
+    pub moves: u8,
+}
+
+
+impl Connect4State {
+    /// Creates the empty starting board.
+    pub fn new() -> Self {
+        return Connect4State { current: 0, mask: 0, moves: 0 };
+    }
+
+    /// Determines whether a disc can still be dropped into `column`.
+    pub fn can_play(&self, column: u8) -> bool {
+        return self.mask & top_cell(column) == 0;
+    }
+
+    /// Determines whether the side to move completes four in a row by playing `column`.
+    ///
+    /// # Invariants
+    /// `column` is assumed to not be full.
+    pub fn is_winning_move(&self, column: u8) -> bool {
+        let dropped = (self.mask + bottom_cell(column)) & column_cells(column);
+        return has_four(self.current | dropped);
+    }
+
+    /// Determines whether the player who moved last has completed four in a row.
+    pub fn last_move_won(&self) -> bool {
+        return has_four(self.current ^ self.mask);
+    }
+
+    /// Discs of the first player.
+    pub fn first_player_discs(&self) -> u64 {
+        return if self.moves.is_multiple_of(2) { self.current } else { self.current ^ self.mask };
+    }
+
+    /// Removes the top disc of `column`, reverting the last move when it was played in `column`.
+    pub fn undo_action(&mut self, column: &u8) {
+        let discs = self.mask & column_cells(*column);
+        let top_disc = 1 << (63 - discs.leading_zeros());
+        self.mask ^= top_disc;
+        self.current ^= self.mask;
+        self.moves -= 1;
+    }
+}
+
+impl Default for Connect4State {
+    fn default() -> Self {
+        return Connect4State::new();
+    }
+}
+
+
+/// Bit of the bottom cell of `column`.
+fn bottom_cell(column: u8) -> u64 {
+    return 1 << (column as usize * COLUMN_BITS);
+}
+
+/// Bit of the top cell of `column`.
+fn top_cell(column: u8) -> u64 {
+    return 1 << (HEIGHT - 1 + column as usize * COLUMN_BITS);
+}
+
+/// Bits of every playable cell of `column`.
+fn column_cells(column: u8) -> u64 {
+    return ((1 << HEIGHT) - 1) << (column as usize * COLUMN_BITS);
+}
+
+/// Determines whether `discs` contain four in a row, vertically, horizontally or diagonally.
+fn has_four(discs: u64) -> bool {
+    // Shifting by 1 moves a cell up, by 7 across and by 6 or 8 diagonally.
+    for shift in [1, COLUMN_BITS, COLUMN_BITS - 1, COLUMN_BITS + 1] {
+        let pairs = discs & (discs >> shift);
+        if pairs & (pairs >> (2 * shift)) != 0 {
+            return true;
+        }
+    }
+    return false;
+}
+
+
+/// Draws the board as 6 rows of 7 cells from the top, with `X` for discs of the first player,
+/// `O` for the second player and `.` for empty cells, which `GameState::from_str` parses back.
+impl fmt::Display for Connect4State {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let first_player = self.first_player_discs();
+        for row in (0..HEIGHT).rev() {
+            for column in 0..WIDTH {
+                let cell = 1 << (column * COLUMN_BITS + row);
+                let mark = if self.mask & cell == 0 { '.' } else if first_player & cell != 0 { 'X' } else { 'O' };
+                write!(f, "{}", mark)?;
+            }
+            if row > 0 {
+                writeln!(f)?;
+            }
+        }
+        return Ok(());
+    }
+}
+
+
+/// Allows the MCTS engine to build Connect4State trees.
+impl GameState<u8> for Connect4State {
+    type ParseError = Connect4ParseError;
+
+    fn from_str(game_state: String) -> Self {
+        return Self::try_from_str(&game_state).expect("invalid connect four position");
+    }
+
+    /// Parses either a sequence of the columns played, numbered from 1 as in `4453`,
+    /// or the 42 cells of the board as drawn by `Display`, ignoring whitespace.
+    fn try_from_str(game_state: &str) -> Result<Self, Connect4ParseError> {
+        let invalid = || Connect4ParseError(game_state.to_string());
+        let marks: Vec<char> = game_state.chars().filter(|mark| !mark.is_whitespace()).collect();
+        let mut state = Connect4State::new();
+
+        if marks.iter().all(|mark| mark.is_ascii_digit()) {
+            for mark in marks {
+                let column = mark.to_digit(10).filter(|column| (1..=WIDTH as u32).contains(column)).ok_or_else(invalid)? as u8 - 1;
+                if !state.can_play(column) || state.last_move_won() {
+                    return Err(invalid());
+                }
+                state.apply_action_mut(&column);
+            }
+            return Ok(state);
+        }
+
+        if marks.len() != WIDTH * HEIGHT {
+            return Err(invalid());
+        }
+        let mut first_player = 0;
+        for (i, mark) in marks.iter().enumerate() {
+            let cell: u64 = 1 << ((i % WIDTH) * COLUMN_BITS + HEIGHT - 1 - i / WIDTH);
+            match mark.to_ascii_uppercase() {
+                'X' => first_player |= cell,
+                'O' => {},
+                '.' => continue,
+                _ => return Err(invalid()),
+            }
+            state.mask |= cell;
+        }
+
+        // Discs can not float above empty cells, and the first player has at most one disc more.
+        let filled_from_bottom = (0..WIDTH as u8).all(|column| {
+            let discs = state.mask & column_cells(column);
+            return (discs + bottom_cell(column)) & discs == 0;
+        });
+        let first_count = first_player.count_ones();
+        let second_count = state.mask.count_ones() - first_count;
+        if !filled_from_bottom || (first_count != second_count && first_count != second_count + 1) {
+            return Err(invalid());
+        }
+        state.moves = state.mask.count_ones() as u8;
+        state.current = if state.moves.is_multiple_of(2) { first_player } else { first_player ^ state.mask };
+        return Ok(state);
+    }
+
+    fn apply_action(&self, action: &u8) -> Self {
+        let mut new_state = *self;
+        new_state.apply_action_mut(action);
+        return new_state;
+    }
+
+    fn apply_action_mut(&mut self, action: &u8) {
+        // The discs of the side to move become those of the opponent, then the
+        // lowest empty cell of the column is filled by carrying into it.
+        self.current ^= self.mask;
+        self.mask |= self.mask + bottom_cell(*action);
+        self.moves += 1;
+    }
+
+    fn status_with_moves_left(&self) -> bool {
+        return !self.last_move_won();
+    }
+
+    fn result(&self) -> GameResult {
+        if !self.last_move_won() {
+            return GameResult::Draw;
+        }
+        if self.moves % 2 == 1 {
+            return GameResult::FirstPlayerWin;
+        }
+        return GameResult::SecondPlayerWin;
+    }
+
+    fn generate_legal_actions(&self) -> Vec<u8> {
+        let mut actions = Vec::with_capacity(WIDTH);
+        self.legal_actions_into(&mut actions);
+        return actions;
+    }
+
+    fn legal_actions_into(&self, actions: &mut Vec<u8>) {
+        actions.clear();
+        // Four in a row ends the game, even with empty cells left.
+        if self.last_move_won() {
+            return;
+        }
+        actions.extend((0..WIDTH as u8).filter(|column| self.can_play(*column)));
+    }
+
+    fn state_hash(&self) -> Option<u64> {
+        // Adding the bottom row marks the first empty cell of every column, which together
+        // with the discs of the side to move encodes the position uniquely.
+        return Some(self.current + self.mask + BOTTOM_ROW);
+    }
+
+    fn action_code(&self, action: &u8) -> Option<u64> {
+        // The same column is coded apart for either side.
+        return Some(*action as u64 * 2 + self.side_to_move() as u64);
+    }
+
+    fn rollout_move_cap(&self) -> Option<u32> {
+        // Games end once the board is full.
+        return None;
+    }
+
+    fn side_to_move(&self) -> bool {
+        return self.moves.is_multiple_of(2);
+    }
+}
+
+
+/// Encodes the state through its string encoding, which holds every part of it.
+impl Encode for Connect4State {
+    fn encode(&self, out: &mut Vec<u8>) {
+        self.to_string().encode(out);
+    }
+}
+
+impl Decode for Connect4State {
+    fn decode(input: &mut &[u8]) -> Result<Self, DecodeError> {
+        return decode_from_str(input);
+    }
+}
+
+
+/// Encodes the board as two 6x7 planes from the bottom row, the discs of the side to move and
+/// of its opponent. Actions are the index of their column.
+#[cfg(feature = "neural")]
+impl crate::neural::TensorEncoding<u8> for Connect4State {
+    const INPUT_SHAPE: &'static [usize] = &[2, HEIGHT, WIDTH];
+    const POLICY_SIZE: usize = WIDTH;
+
+    fn encode(&self, input: &mut [f32]) {
+        let opponent = self.current ^ self.mask;
+        for row in 0..HEIGHT {
+            for column in 0..WIDTH {
+                let cell = 1 << (column * COLUMN_BITS + row);
+                if self.current & cell != 0 {
+                    input[row * WIDTH + column] = 1.0;
+                } else if opponent & cell != 0 {
+                    input[(HEIGHT + row) * WIDTH + column] = 1.0;
+                }
+            }
+        }
+    }
+
+    fn action_index(&self, action: &u8) -> usize {
+        return *action as usize;
+    }
+}
+
+
+/// Defines unit tests for the GameState implementation of Connect4State.
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mcts::MCTSTree;
+    use std::collections::HashSet;
+
+    /// Solves `state` with negamax and alpha-beta pruning.
+    ///
+    /// # Returns
+    /// 1 if the side to move wins with perfect play, 0 for a draw and -1 for a loss.
+    fn solve(state: &Connect4State, mut alpha: i32, beta: i32) -> i32 {
+        let actions = state.generate_legal_actions();
+        if actions.len() == 0 {
+            return if state.last_move_won() { -1 } else { 0 };
+        }
+        for action in actions {
+            alpha = alpha.max(-solve(&state.apply_action(&action), -beta, -alpha));
+            if alpha >= beta {
+                break;
+            }
+        }
+        return alpha;
+    }
+
+    /// Chooses the move of the engine after `iterations` iterations of search from `state`.
+    fn engine_move(state: &Connect4State, iterations: u32, seed: u64) -> u8 {
+        let mut tree = MCTSTree::<u8, Connect4State>::with_capacity(iterations as usize + 1, Some(seed), state.to_string(), 7);
+        tree.search(iterations as u64);
+        let best = tree.get_max_uct_child_at(0, Some(0.0)).expect("no legal moves");
+        return tree.arena.actions[best as usize].expect("no action");
+    }
+
+    /// Test if the number of distinct positions after each ply matches the known counts.
+    #[test]
+    fn test_position_counts() {
+        let mut positions = HashSet::from([Connect4State::new()]);
+        for expected in [7, 49, 238, 1120, 4263, 16422] {
+            positions = positions.iter()
+                .flat_map(|state| state.generate_legal_actions().into_iter().map(|action| state.apply_action(&action)))
+                .collect();
+            assert_eq!(positions.len(), expected);
+
+            // State hashes are unique per position.
+            let hashes: HashSet<u64> = positions.iter().map(|state| state.state_hash().unwrap()).collect();
+            assert_eq!(hashes.len(), expected);
+        }
+    }
+
+    /// Test if move sequences and boards are parsed, and invalid ones are rejected.
+    #[test]
+    fn test_try_from_str() {
+        let state = Connect4State::try_from_str("4453").unwrap();
+        assert_eq!(state.moves, 4);
+        assert!(state.side_to_move());
+        let board = state.to_string();
+        assert_eq!(board, ".......\n.......\n.......\n.......\n...O...\n..OXX..");
+        assert_eq!(Connect4State::from_str(board), state);
+
+        assert!(Connect4State::try_from_str("").is_ok());
+        assert!(Connect4State::try_from_str("8").is_err());
+        assert!(Connect4State::try_from_str("1111111").is_err());
+        assert!(Connect4State::try_from_str("12121211").is_err());
+
+        // Floating discs and impossible disc counts.
+        assert!(Connect4State::try_from_str(&format!("{}X......", ".".repeat(35))).is_ok());
+        assert!(Connect4State::try_from_str(&format!("X{}", ".".repeat(41))).is_err());
+        assert!(Connect4State::try_from_str(&format!("{}O......", ".".repeat(35))).is_err());
+    }
+
+    /// Test if four in a row is detected in every direction, without lines wrapping between columns.
+    #[test]
+    fn test_has_four() {
+        let cell = |column: usize, row: usize| -> u64 { 1 << (column * COLUMN_BITS + row) };
+        assert!(has_four(cell(2, 0) | cell(2, 1) | cell(2, 2) | cell(2, 3)));
+        assert!(has_four(cell(3, 5) | cell(4, 5) | cell(5, 5) | cell(6, 5)));
+        assert!(has_four(cell(0, 0) | cell(1, 1) | cell(2, 2) | cell(3, 3)));
+        assert!(has_four(cell(6, 2) | cell(5, 3) | cell(4, 4) | cell(3, 5)));
+        assert!(!has_four(cell(0, 3) | cell(0, 4) | cell(0, 5) | cell(1, 0)));
+        assert!(!has_four(cell(0, 0) | cell(1, 1) | cell(2, 2) | cell(4, 4)));
+    }
+
+    /// Test if four in a row ends the game with the right winner, and full boards are drawn.
+    #[test]
+    fn test_result() {
+        let vertical = Connect4State::from_str("1212121".to_string());
+        assert!(vertical.generate_legal_actions().is_empty());
+        assert!(!vertical.status_with_moves_left());
+        assert_eq!(vertical.result(), GameResult::FirstPlayerWin);
+
+        let horizontal = Connect4State::from_str("1122334".to_string());
+        assert_eq!(horizontal.result(), GameResult::FirstPlayerWin);
+        let second_player = Connect4State::from_str("21212131".to_string());
+        assert_eq!(second_player.result(), GameResult::SecondPlayerWin);
+
+        // Columns are filled in pairs so that neither side ever lines up four.
+        let full = Connect4State::from_str("121212343434565656777777212121434343656565".to_string());
+        assert!(full.generate_legal_actions().is_empty());
+        assert_eq!(full.result(), GameResult::Draw);
+    }
+
+    /// Test if undoing moves restores the previous position.
+    #[test]
+    fn test_undo_action() {
+        let mut state = Connect4State::from_str("44536".to_string());
+        let before = state;
+        for column in [0, 3, 3, 6] {
+            state.apply_action_mut(&column);
+        }
+        for column in [6, 3, 3, 0] {
+            state.undo_action(&column);
+        }
+        assert_eq!(state, before);
+    }
+
+    /// Test if the engine takes wins, blocks losses and agrees with perfect play in late positions.
+    #[test]
+    fn test_engine_moves() {
+        // The first player wins by completing the bottom row.
+        let win = Connect4State::from_str("112233".to_string());
+        assert_eq!(engine_move(&win, 2000, 1), 3);
+
+        // The second player has to block the bottom row.
+        let block = Connect4State::from_str("11223".to_string());
+        assert_eq!(engine_move(&block, 2000, 1), 3);
+
+        // Late positions reached by random play, with few enough empty cells to be solved.
+        let seed = 1;
+        let mut random_generator: crate::rng::SearchRng = crate::rng::SeedableRng::from_seed(seed);
+        let mut checked = 0;
+        while checked < 3 {
+            let mut late = Connect4State::new();
+            while late.moves < 30 && late.generate_legal_actions().len() > 0 {
+                let actions = late.generate_legal_actions();
+                late.apply_action_mut(&actions[crate::rng::Rng::gen_range(&mut random_generator, 0, actions.len())]);
+            }
+            if late.generate_legal_actions().len() == 0 {
+                continue;
+            }
+
+            // The engine keeps a won or drawn position.
+            let value = solve(&late, -1, 1);
+            let action = engine_move(&late, 20000, checked);
+            assert!(-solve(&late.apply_action(&action), -1, 1) >= value.min(0), "engine blundered in {}", late);
+            checked += 1;
+        }
+    }
+}
@@ -0,0 +1,148 @@
+//! Comparison of the visit distributions of two trees, such as searches before and after a code
+//! change or with two seeds, for tracking down behavioral regressions in selection or backpropagation.
+
+use crate::arena::NodeIndex;
+use crate::game_state_trait::GameState;
+use crate::mcts::MCTSTree;
+use crate::float::Float;
+use alloc::collections::VecDeque;
+use alloc::vec::Vec;
+
+
+/// A node whose children were visited differently by two trees.
+#[derive(Debug, Clone, PartialEq)]
+pub struct VisitDivergence<Action> {
+    /// Actions leading from the roots to the node.
+    pub path: Vec<Action>,
+
+    /// Total variation distance between the visit distributions over the children of the node,
+    /// between 0 for equal distributions and 1 for distributions without common children.
+    pub distance: f64,
+
+    /// Every action expanded by either tree, with the simulations of its child in either tree.
+    pub visits: Vec<(Action, u64, u64)>,
+}
+
+
+/// Comparison of trees.
+impl<Action, GameStateObj, Data> MCTSTree<Action, GameStateObj, Data>
+where
+    Action: Clone + PartialEq,
+    GameStateObj: GameState<Action> + Clone
+{
+    /// Compares the visit distributions of the tree and `other`, reporting the nodes where they diverge.
+    ///
+    /// Starting from the roots, nodes reached by the same actions in both trees are compared by
+    /// the share of simulations each of their children received. Nodes are compared as long as
+    /// they have enough simulations in both trees for their distributions to be meaningful.
+    ///
+    /// # Arguments
+    /// * `other` : A tree searched from the same root state.
+    ///
+    /// * `threshold` : Nodes are reported if the total variation distance between their
+    /// distributions is above the threshold.
+    ///
+    /// * `min_sims` : Nodes with fewer simulations in either tree are not compared.
+    ///
+    /// # Returns
+    /// The diverging nodes in breadth first order, so the shallowest divergences come first.
+    pub fn diff(&self, other: &Self, threshold: f64, min_sims: u64) -> Vec<VisitDivergence<Action>> {
+        let mut divergences = Vec::new();
+        let mut queue: VecDeque<(NodeIndex, NodeIndex, Vec<Action>)> = VecDeque::from([(0, 0, Vec::new())]);
+        while let Some((node, other_node, path)) = queue.pop_front() {
+            let sims = self.arena.stats[node as usize].sims;
+            let other_sims = other.arena.stats[other_node as usize].sims;
+            if sims == 0 || other_sims == 0 || sims < min_sims || other_sims < min_sims {
+                continue;
+            }
+
+            // Pair up the children of both nodes by their actions.
+            let mut children: Vec<(Action, Option<NodeIndex>, Option<NodeIndex>)> = self.arena.expanded(node)
+                .map(|child| (self.arena.actions[child as usize].clone().expect("no action"), Some(child), None))
+                .collect();
+            for other_child in other.arena.expanded(other_node) {
+                let action = other.arena.actions[other_child as usize].as_ref().expect("no action");
+                match children.iter_mut().find(|(child_action, _, _)| child_action == action) {
+                    Some(paired) => paired.2 = Some(other_child),
+                    None => children.push((action.clone(), None, Some(other_child))),
+                }
+            }
+
+            let visits: Vec<(Action, u64, u64)> = children.iter().map(|(action, child, other_child)| {
+                let child_sims = child.map_or(0, |child| self.arena.stats[child as usize].sims);
+                let other_child_sims = other_child.map_or(0, |other_child| other.arena.stats[other_child as usize].sims);
+                return (action.clone(), child_sims, other_child_sims);
+            }).collect();
+            let total: u64 = visits.iter().map(|(_, child_sims, _)| child_sims).sum();
+            let other_total: u64 = visits.iter().map(|(_, _, other_child_sims)| other_child_sims).sum();
+            if total > 0 && other_total > 0 {
+                let distance = visits.iter()
+                    .map(|(_, child_sims, other_child_sims)| Float::abs(*child_sims as f64 / total as f64 - *other_child_sims as f64 / other_total as f64))
+                    .sum::<f64>() / 2.0;
+                if distance > threshold {
+                    divergences.push(VisitDivergence { path: path.clone(), distance, visits });
+                }
+            }
+
+            for (action, child, other_child) in children {
+                if let (Some(child), Some(other_child)) = (child, other_child) {
+                    let mut child_path = path.clone();
+                    child_path.push(action);
+                    queue.push_back((child, other_child, child_path));
+                }
+            }
+        }
+        return divergences;
+    }
+}
+
+
+/// Defines unit tests for comparing trees.
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game_state_trait::GameResult;
+    use crate::tictactoe_env::TicTacToeState;
+
+    /// Test if identical searches do not diverge, and searches with another seed do at some threshold.
+    #[test]
+    fn test_diff() {
+        let mut tree = MCTSTree::<u8, TicTacToeState>::with_capacity(3000, Some(1), "X...O....".to_string(), 9);
+        let mut same = MCTSTree::<u8, TicTacToeState>::with_capacity(3000, Some(1), "X...O....".to_string(), 9);
+        let mut other = MCTSTree::<u8, TicTacToeState>::with_capacity(3000, Some(2), "X...O....".to_string(), 9);
+        tree.search(1000);
+        same.search(1000);
+        other.search(1000);
+        assert!(tree.diff(&same, 0.0, 1).is_empty());
+
+        let divergences = tree.diff(&other, 0.0, 10);
+        assert!(!divergences.is_empty());
+        assert!(divergences.windows(2).all(|pair| pair[0].path.len() <= pair[1].path.len()));
+        assert!(divergences.iter().all(|divergence| divergence.distance > 0.0 && divergence.distance <= 1.0));
+        assert!(tree.diff(&other, 1.0, 10).is_empty());
+
+        // The root lists the visits of every child in both trees.
+        let root = divergences.iter().find(|divergence| divergence.path.is_empty()).unwrap();
+        assert_eq!(root.visits.len(), 7);
+        let visits: u64 = root.visits.iter().map(|(_, sims, _)| sims).sum();
+        assert_eq!(visits, 1000);
+    }
+
+    /// Test if children expanded by only one tree count as fully diverging visits.
+    #[test]
+    fn test_diff_disjoint() {
+        let mut tree = MCTSTree::<u8, TicTacToeState>::with_capacity(10, Some(1), "X...O....".to_string(), 9);
+        let mut other = MCTSTree::<u8, TicTacToeState>::with_capacity(10, Some(5), "X...O....".to_string(), 9);
+        let child = tree.expand_at(0);
+        tree.backpropagate_at(child, GameResult::FirstPlayerWin);
+        let mut other_child = other.expand_at(0);
+        while other.arena.actions[other_child as usize] == tree.arena.actions[child as usize] {
+            other_child = other.expand_at(0);
+        }
+        other.backpropagate_at(other_child, GameResult::FirstPlayerWin);
+
+        let divergences = tree.diff(&other, 0.5, 1);
+        assert_eq!(divergences.len(), 1);
+        assert_eq!(divergences[0].distance, 1.0);
+    }
+}
@@ -0,0 +1,111 @@
+//! Leaf evaluation, replacing random rollouts and hand written priors with the policy and value
+//! of a model, as in AlphaZero.
+//!
+//! A tree with an `evaluator` takes the priors of expanded leaves and the value of simulated
+//! nodes from it instead of `GameState::action_priors` and rollouts. States are evaluated in
+//! batches, so that models running on accelerators can amortize the cost of their inference.
+
+use crate::game_state_trait::GameState;
+use alloc::vec::Vec;
+
+
+/// Policy and value of a state.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Evaluation<Action> {
+    /// Pairs of legal actions and their prior probabilities, as given by `GameState::action_priors`.
+    /// Empty to expand the actions of the state one at a time.
+    pub priors: Vec<(Action, f32)>,
+
+    /// Value of the state on the scale of `GameState::evaluate`. For two player games, the
+    /// expected share of the win of the first player between 0 and 1, for single player games
+    /// an estimate of the final score.
+    pub value: f32,
+}
+
+
+/// Evaluates the leaves of a search.
+pub trait LeafEvaluator<Action, GameStateObj> {
+    /// Evaluates a batch of non-terminal states.
+    ///
+    /// # Returns
+    /// The evaluation of every state, in the order of `states`.
+    fn evaluate_batch(&mut self, states: &[&GameStateObj]) -> Vec<Evaluation<Action>>;
+
+    /// Evaluates a single non-terminal state, as a batch of one state.
+    fn evaluate(&mut self, state: &GameStateObj) -> Evaluation<Action> {
+        return self.evaluate_batch(&[state]).pop().expect("no evaluation");
+    }
+}
+
+
+/// Evaluator reading the priors and static evaluation of the game state itself,
+/// `GameState::action_priors` and `GameState::evaluate`.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct StaticEvaluator;
+
+impl<Action, GameStateObj> LeafEvaluator<Action, GameStateObj> for StaticEvaluator
+where
+    GameStateObj: GameState<Action>
+{
+    fn evaluate_batch(&mut self, states: &[&GameStateObj]) -> Vec<Evaluation<Action>> {
+        return states.iter()
+            .map(|state| Evaluation { priors: state.action_priors(), value: state.evaluate() })
+            .collect();
+    }
+}
+
+
+/// Unit tests for leaf evaluation.
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mcts::{MCTSTree, SelectionPolicy};
+    use crate::tictactoe_env::TicTacToeState;
+    use alloc::boxed::Box;
+
+    /// Evaluator preferring the center, then the corners, and scoring every state as even.
+    struct CenterEvaluator;
+
+    impl LeafEvaluator<u8, TicTacToeState> for CenterEvaluator {
+        fn evaluate_batch(&mut self, states: &[&TicTacToeState]) -> Vec<Evaluation<u8>> {
+            return states.iter().map(|state| {
+                let weights: Vec<(u8, f32)> = state.generate_legal_actions().into_iter()
+                    .map(|cell| (cell, if cell == 4 { 4.0 } else if cell % 2 == 0 { 2.0 } else { 1.0 }))
+                    .collect();
+                let total: f32 = weights.iter().map(|(_, weight)| weight).sum();
+                return Evaluation {
+                    priors: weights.into_iter().map(|(cell, weight)| (cell, weight / total)).collect(),
+                    value: 0.5,
+                };
+            }).collect();
+        }
+    }
+
+    /// Test if the static evaluator reads the game state.
+    #[test]
+    fn test_static_evaluator() {
+        let state = TicTacToeState::new();
+        let evaluation = LeafEvaluator::<u8, TicTacToeState>::evaluate(&mut StaticEvaluator, &state);
+        assert_eq!(evaluation, Evaluation { priors: Vec::new(), value: 0.5 });
+    }
+
+    /// Test if trees expand leaves with the priors of the evaluator, and simulate with its value.
+    #[test]
+    fn test_search_with_evaluator() {
+        let mut tree = MCTSTree::<u8, TicTacToeState>::with_capacity(10000, Some(1), ".........".into(), 9);
+        tree.searcher.selection_policy = SelectionPolicy::Puct;
+        tree.searcher.evaluator = Some(Box::new(CenterEvaluator));
+        tree.search(1);
+
+        // The first iteration expands every action of the root in order of prior.
+        assert_eq!(tree.arena.expanded_count(0), 9);
+        let first = tree.arena.expanded(0).next().unwrap();
+        assert_eq!(tree.arena.actions[first as usize], Some(4));
+        assert!((tree.arena.topology[first as usize].probability - 4.0 / 16.0).abs() < 1e-6);
+
+        // Values of the evaluator replace rollouts.
+        tree.search(99);
+        assert!(tree.counters.rollout_lengths.iter().skip(1).all(|count| *count == 0));
+        assert_eq!(tree.arena.stats[0].sims, 100);
+    }
+}
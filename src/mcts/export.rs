@@ -0,0 +1,178 @@
+//! Structured exports of trees for external tools, such as visualization dashboards.
+
+use crate::arena::NodeIndex;
+use crate::game_state_trait::GameState;
+use crate::mcts::MCTSTree;
+use alloc::string::{String, ToString};
+use alloc::vec;
+use core::fmt;
+use core::fmt::Write;
+
+
+/// Writes `text` to `out` as a quoted JSON string.
+pub(crate) fn write_json_string(out: &mut String, text: &str) {
+    out.push('"');
+    for character in text.chars() {
+        match character {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            character if (character as u32) < 0x20 => write!(out, "\\u{:04x}", character as u32).expect("write to string"),
+            character => out.push(character),
+        }
+    }
+    out.push('"');
+}
+
+
+/// Exports of the tree.
+impl<Action, GameStateObj, Data> MCTSTree<Action, GameStateObj, Data>
+where
+    GameStateObj: GameState<Action> + Clone
+{
+    /// Exports the tree as JSON, labelling actions with their `Display` encoding.
+    ///
+    /// See `export_json_with`.
+    pub fn export_json(&self, depth_limit: Option<u32>, min_sims: u64) -> String
+    where
+        Action: fmt::Display
+    {
+        return self.export_json_with(depth_limit, min_sims, |action| action.to_string());
+    }
+
+    /// Exports the tree as JSON, for consumption by external tools.
+    ///
+    /// Every node is an object with its `action`, null for the root, its `sims`, `wins` and
+    /// `draws`, its mean `value`, null for unvisited nodes, a `pv` flag set for nodes on the
+    /// principal variation, and its `children`. The principal variation follows the most
+    /// visited child from the root.
+    ///
+    /// # Arguments
+    /// * `depth_limit` : Depth below the root past which children are left out. None for no limit.
+    ///
+    /// * `min_sims` : Children with fewer simulations are left out, along with their subtrees.
+    ///
+    /// * `label` : Encodes an action as the string written to the `action` field.
+    ///
+    /// # Returns
+    /// The JSON document, with the root as its top level object.
+    pub fn export_json_with<F>(&self, depth_limit: Option<u32>, min_sims: u64, label: F) -> String
+    where
+        F: Fn(&Action) -> String
+    {
+        // Mark the most visited child of every node on the principal variation.
+        let mut pv = vec![false; self.arena.len()];
+        let mut node: NodeIndex = 0;
+        pv[0] = true;
+        while let Some(child) = self.arena.expanded(node).max_by_key(|child| self.arena.stats[*child as usize].sims) {
+            pv[child as usize] = true;
+            node = child;
+        }
+
+        let mut out = String::new();
+        self.write_json_node(&mut out, 0, 0, depth_limit, min_sims, &label, &pv);
+        return out;
+    }
+
+    /// Writes the JSON object of `node` and its exported subtree to `out`.
+    #[allow(clippy::too_many_arguments)]
+    fn write_json_node<F>(
+        &self,
+        out: &mut String,
+        node: NodeIndex,
+        depth: u32,
+        depth_limit: Option<u32>,
+        min_sims: u64,
+        label: &F,
+        pv: &[bool])
+    where
+        F: Fn(&Action) -> String
+    {
+        let stats = &self.arena.stats[node as usize];
+        out.push_str("{\"action\":");
+        match &self.arena.actions[node as usize] {
+            Some(action) => write_json_string(out, &label(action)),
+            None => out.push_str("null"),
+        }
+        write!(out, ",\"sims\":{},\"wins\":{},\"draws\":{},\"value\":", stats.sims, stats.wins, stats.draws).expect("write to string");
+        if stats.sims == 0 {
+            out.push_str("null");
+        }
+        else {
+            write!(out, "{}", stats.score / stats.sims as f64).expect("write to string");
+        }
+        write!(out, ",\"pv\":{},\"children\":[", pv[node as usize]).expect("write to string");
+
+        if depth_limit.is_none_or(|limit| depth < limit) {
+            let mut first = true;
+            for child in self.arena.expanded(node) {
+                if self.arena.stats[child as usize].sims < min_sims {
+                    continue;
+                }
+                if !first {
+                    out.push(',');
+                }
+                first = false;
+                self.write_json_node(out, child, depth + 1, depth_limit, min_sims, label, pv);
+            }
+        }
+        out.push_str("]}");
+    }
+}
+
+
+/// Defines unit tests for the exports of trees.
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::nim_env::NimState;
+    use crate::tictactoe_env::TicTacToeState;
+
+    /// Test if strings are escaped.
+    #[test]
+    fn test_write_json_string() {
+        let mut out = String::new();
+        write_json_string(&mut out, "a\"b\\c\n\u{1}");
+        assert_eq!(out, "\"a\\\"b\\\\c\\n\\u0001\"");
+    }
+
+    /// Test if the export follows the depth and visit filters, and flags the principal variation.
+    #[test]
+    fn test_export_json() {
+        let mut tree = MCTSTree::<u8, TicTacToeState>::with_capacity(1000, Some(1), "XX..O....".to_string(), 9);
+        tree.expand_at(0);
+        let json = tree.export_json(None, 0);
+        assert!(json.starts_with("{\"action\":null,\"sims\":0,\"wins\":0,\"draws\":0,\"value\":null,\"pv\":true,\"children\":[{\"action\":\""));
+
+        tree.search(500);
+        let json = tree.export_json(Some(1), 0);
+        assert_eq!(json.matches("\"action\":").count(), 1 + tree.arena.expanded_count(0));
+        assert_eq!(json.matches("\"pv\":true").count(), 2);
+
+        // The most visited child of the root is the only child on the principal variation.
+        let best = tree.arena.expanded(0).max_by_key(|child| tree.arena.stats[*child as usize].sims).unwrap();
+        let stats = tree.arena.stats[best as usize];
+        let expected = format!(
+            "{{\"action\":\"{}\",\"sims\":{},\"wins\":{},\"draws\":{},\"value\":{},\"pv\":true,\"children\":[]}}",
+            tree.arena.actions[best as usize].unwrap(), stats.sims, stats.wins, stats.draws, stats.score / stats.sims as f64
+        );
+        assert!(json.contains(&expected), "{}", json);
+
+        // Every child of the root has fewer simulations than the root.
+        let filtered = tree.export_json(None, tree.arena.stats[0].sims);
+        assert!(filtered.ends_with("\"children\":[]}"));
+    }
+
+    /// Test if actions without a `Display` encoding are labelled by the given function.
+    #[test]
+    fn test_export_json_with() {
+        let mut tree = MCTSTree::<(usize, u32), NimState>::with_capacity(10, Some(1), "1 2".to_string(), 3);
+        let child = tree.expand_at(0);
+        tree.backpropagate_at(child, crate::game_state_trait::GameResult::FirstPlayerWin);
+        let json = tree.export_json_with(None, 1, |(pile, count)| format!("{}x{}", pile, count));
+        assert!(json.contains(",\"pv\":true,\"children\":[{\"action\":\""));
+        assert!(json.contains("\"sims\":1,\"wins\":1,\"draws\":0,\"value\":1,\"pv\":true"));
+    }
+}
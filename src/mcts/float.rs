@@ -0,0 +1,274 @@
+//! Floating point functions of the engine, without the standard library.
+//!
+//! `core` has no square roots, logarithms, powers or even absolute values, which are provided
+//! by the platform's math library through `std`. With the `std` feature, the functions of
+//! `Float` are those of the standard library. Without it, they are computed in software to
+//! within a few units in the last place, which is plenty for exploration terms and statistics.
+
+
+/// Floating point functions missing from `core`, called as `Float::sqrt(x)` to pick these over
+/// the inherent methods of the standard library in every configuration.
+pub trait Float: Copy {
+    /// Square root, NaN for negative numbers.
+    fn sqrt(self) -> Self;
+
+    /// Natural logarithm, NaN for negative numbers and negative infinity for 0.
+    fn ln(self) -> Self;
+
+    /// `self` raised to the power `exponent`.
+    fn powf(self, exponent: Self) -> Self;
+
+    /// Fractional part, with the sign of `self`.
+    fn fract(self) -> Self;
+
+    /// Absolute value.
+    fn abs(self) -> Self;
+}
+
+
+#[cfg(feature = "std")]
+impl Float for f64 {
+    fn sqrt(self) -> f64 {
+        return f64::sqrt(self);
+    }
+
+    fn ln(self) -> f64 {
+        return f64::ln(self);
+    }
+
+    fn powf(self, exponent: f64) -> f64 {
+        return f64::powf(self, exponent);
+    }
+
+    fn fract(self) -> f64 {
+        return f64::fract(self);
+    }
+
+    fn abs(self) -> f64 {
+        return f64::abs(self);
+    }
+}
+
+#[cfg(feature = "std")]
+impl Float for f32 {
+    fn sqrt(self) -> f32 {
+        return f32::sqrt(self);
+    }
+
+    fn ln(self) -> f32 {
+        return f32::ln(self);
+    }
+
+    fn powf(self, exponent: f32) -> f32 {
+        return f32::powf(self, exponent);
+    }
+
+    fn fract(self) -> f32 {
+        return f32::fract(self);
+    }
+
+    fn abs(self) -> f32 {
+        return f32::abs(self);
+    }
+}
+
+#[cfg(not(feature = "std"))]
+impl Float for f64 {
+    fn sqrt(self) -> f64 {
+        return soft::sqrt(self);
+    }
+
+    fn ln(self) -> f64 {
+        return soft::ln(self);
+    }
+
+    fn powf(self, exponent: f64) -> f64 {
+        return soft::powf(self, exponent);
+    }
+
+    fn fract(self) -> f64 {
+        return soft::fract(self);
+    }
+
+    fn abs(self) -> f64 {
+        return soft::abs(self);
+    }
+}
+
+#[cfg(not(feature = "std"))]
+impl Float for f32 {
+    fn sqrt(self) -> f32 {
+        return soft::sqrt(self as f64) as f32;
+    }
+
+    fn ln(self) -> f32 {
+        return soft::ln(self as f64) as f32;
+    }
+
+    fn powf(self, exponent: f32) -> f32 {
+        return soft::powf(self as f64, exponent as f64) as f32;
+    }
+
+    fn fract(self) -> f32 {
+        return soft::fract(self as f64) as f32;
+    }
+
+    fn abs(self) -> f32 {
+        return f32::from_bits(self.to_bits() & !(1 << 31));
+    }
+}
+
+
+/// Software implementations in double precision.
+#[cfg(any(not(feature = "std"), test))]
+mod soft {
+    use core::f64::consts::LN_2;
+
+    /// Newton's method from an estimate halving the exponent.
+    pub fn sqrt(x: f64) -> f64 {
+        if x.is_nan() || x < 0.0 {
+            return f64::NAN;
+        }
+        if x == 0.0 || x.is_infinite() {
+            return x;
+        }
+        if x < f64::MIN_POSITIVE {
+            // Subnormals are scaled into the normal range first.
+            return sqrt(x * (1u64 << 54) as f64) / (1u64 << 27) as f64;
+        }
+        let mut estimate = f64::from_bits((x.to_bits() >> 1) + (1023 << 51));
+        for _i in 0..6 {
+            estimate = 0.5 * (estimate + x / estimate);
+        }
+        return estimate;
+    }
+
+    /// Splits `x` into `m * 2^e` with `m` in `[sqrt(1/2), sqrt(2))`, and sums the series of
+    /// `ln(m) = 2 atanh((m - 1) / (m + 1))`.
+    pub fn ln(x: f64) -> f64 {
+        if x.is_nan() || x < 0.0 {
+            return f64::NAN;
+        }
+        if x == 0.0 {
+            return f64::NEG_INFINITY;
+        }
+        if x.is_infinite() {
+            return x;
+        }
+
+        // Subnormals are scaled into the normal range first.
+        let (x, mut exponent) = if x < f64::MIN_POSITIVE { (x * (1u64 << 54) as f64, -54) } else { (x, 0) };
+        let bits = x.to_bits();
+        exponent += ((bits >> 52) & 0x7FF) as i64 - 1023;
+        let mut mantissa = f64::from_bits((bits & 0xFFFFFFFFFFFFF) | (1023 << 52));
+        if mantissa > core::f64::consts::SQRT_2 {
+            mantissa *= 0.5;
+            exponent += 1;
+        }
+
+        let s = (mantissa - 1.0) / (mantissa + 1.0);
+        let s2 = s * s;
+        let mut term = s;
+        let mut sum = 0.0;
+        let mut denominator = 1.0;
+        while abs(term) > f64::EPSILON * 1e-3 {
+            sum += term / denominator;
+            term *= s2;
+            denominator += 2.0;
+        }
+        return exponent as f64 * LN_2 + 2.0 * sum;
+    }
+
+    /// Splits `x` into `k ln(2) + r` with `|r| <= ln(2) / 2`, and sums the series of `e^r`.
+    pub fn exp(x: f64) -> f64 {
+        if x.is_nan() {
+            return x;
+        }
+        if x > 709.8 {
+            return f64::INFINITY;
+        }
+        if x < -745.2 {
+            return 0.0;
+        }
+        let k = (x / LN_2 + if x < 0.0 { -0.5 } else { 0.5 }) as i64;
+        let r = x - k as f64 * LN_2;
+        let mut term = 1.0;
+        let mut sum = 1.0;
+        let mut n = 1.0;
+        while abs(term) > f64::EPSILON * 1e-3 {
+            term *= r / n;
+            sum += term;
+            n += 1.0;
+        }
+
+        // 2^k is applied in two steps, as it may not be representable on its own near the limits.
+        let half = k / 2;
+        let power = |k: i64| f64::from_bits(((k + 1023) as u64) << 52);
+        return sum * power(half) * power(k - half);
+    }
+
+    /// `e^(exponent ln(x))` for positive `x`, with the usual special cases of zeros and negative bases.
+    pub fn powf(x: f64, exponent: f64) -> f64 {
+        if exponent == 0.0 {
+            return 1.0;
+        }
+        if x == 0.0 {
+            return if exponent > 0.0 { 0.0 } else { f64::INFINITY };
+        }
+        if x < 0.0 {
+            // Negative bases only have real powers for integer exponents.
+            if fract(exponent) != 0.0 {
+                return f64::NAN;
+            }
+            let odd = fract(exponent / 2.0) != 0.0;
+            let magnitude = exp(exponent * ln(-x));
+            return if odd { -magnitude } else { magnitude };
+        }
+        return exp(exponent * ln(x));
+    }
+
+    pub fn abs(x: f64) -> f64 {
+        return f64::from_bits(x.to_bits() & !(1 << 63));
+    }
+
+    pub fn fract(x: f64) -> f64 {
+        if x.is_nan() || x.is_infinite() {
+            return f64::NAN;
+        }
+
+        // Doubles of at least 2^52 are integers.
+        if abs(x) >= (1u64 << 52) as f64 {
+            return 0.0;
+        }
+        return x - (x as i64) as f64;
+    }
+}
+
+
+/// Defines unit tests for floating point functions.
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Test if the software implementations agree with the standard library.
+    #[test]
+    fn test_soft() {
+        let relative_error = |a: f64, b: f64| if a == b { 0.0 } else { ((a - b) / b).abs() };
+        for x in [1e-310, 1e-9, 0.1, 0.5, 1.0, 1.5, 2.0, 3.0, 10.0, 12345.678, 1e30] {
+            assert!(relative_error(soft::sqrt(x), x.sqrt()) < 1e-15, "sqrt({})", x);
+            assert!((soft::ln(x) - x.ln()).abs() < 1e-13, "ln({})", x);
+            for exponent in [-2.5, -1.0, 0.5, 1.0, 3.0] {
+                assert!(relative_error(soft::powf(x, exponent), x.powf(exponent)) < 1e-12, "{} ^ {}", x, exponent);
+            }
+        }
+        for x in [-2.75, -1.0, 0.0, 0.25, 7.5, 1e20] {
+            assert_eq!(soft::fract(x), x.fract());
+        }
+        assert!(soft::sqrt(-1.0).is_nan() && soft::ln(-1.0).is_nan());
+        assert_eq!(soft::ln(0.0), f64::NEG_INFINITY);
+        assert!((soft::powf(-2.0, 3.0) + 8.0).abs() < 1e-12);
+        assert_eq!(soft::powf(0.0, 2.0), 0.0);
+        assert_eq!(soft::abs(-0.5), 0.5);
+        assert_eq!(<f32 as Float>::sqrt(4.0), 2.0);
+    }
+}
@@ -0,0 +1,438 @@
+use crate::game_state_trait::{GameResult, GameState};
+use crate::serialize::{decode_from_str, Decode, DecodeError, Encode};
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::fmt;
+
+
+/// Probability that a spawned tile is a 2 rather than a 4.
+pub const TWO_PROBABILITY: f32 = 0.9;
+
+
+/// Error produced when a string is not a valid 2048 position.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Game2048ParseError(pub String);
+
+impl fmt::Display for Game2048ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        return write!(f, "invalid 2048 position: {}", self.0);
+    }
+}
+
+impl core::error::Error for Game2048ParseError {}
+
+
+/// Direction the tiles of the board are slid in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Direction {
+    Up,
+    Right,
+    Down,
+    Left,
+}
+
+impl Direction {
+    /// Every direction, in the order legal actions are generated.
+    pub const ALL: [Direction; 4] = [Direction::Up, Direction::Right, Direction::Down, Direction::Left];
+
+    /// Cells of every line of the board, ordered from the edge the tiles slide towards.
+    fn lines(&self) -> [[usize; 4]; 4] {
+        let mut lines = [[0; 4]; 4];
+        for (line, cells) in lines.iter_mut().enumerate() {
+            for (position, cell) in cells.iter_mut().enumerate() {
+                *cell = match self {
+                    Direction::Up => position * 4 + line,
+                    Direction::Down => (3 - position) * 4 + line,
+                    Direction::Left => line * 4 + position,
+                    Direction::Right => line * 4 + 3 - position,
+                };
+            }
+        }
+        return lines;
+    }
+}
+
+
+/// An event of a game of 2048, either a slide chosen by the player or a random tile spawn.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Game2048Move {
+    Slide(Direction),
+
+    /// Places a tile on an empty cell, where `exponent` is 1 for a 2 and 2 for a 4.
+    Spawn { cell: u8, exponent: u8 },
+}
+
+
+/// Holds the state of a game of 2048, a single player game with random tile spawns.
+///
+/// Every slide which moves at least one tile is followed by a chance event spawning a tile
+/// on a random empty cell, which is a 2 with probability `TWO_PROBABILITY` and otherwise a 4.
+/// The game ends once no slide moves a tile, and the result is the score, the sum of every
+/// tile created by a merge. Spawn events are generated by `chance_outcomes`, so searches should
+/// use `PlayerMode::SinglePlayer`.
+///
+/// Scores are raw, so the exploration factor of selection should be scaled to
+/// the typical score of a game.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Game2048State {
+    /// Exponents of the tiles of every cell, row by row from the top left corner, with 0 for empty cells.
+    pub cells: [u8; 16],
+
+    /// Sum of every tile created by a merge so far.
+    pub score: u32,
+
+    /// Number of tile spawns due before the next slide, which makes the state a chance node.
+    pub spawns: u8,
+}
+
+
+impl Game2048State {
+    /// Creates an empty board, which spawns two tiles before the first slide.
+    pub fn new() -> Self {
+        return Game2048State { cells: [0; 16], score: 0, spawns: 2 };
+    }
+
+    /// Value of the largest tile on the board.
+    pub fn max_tile(&self) -> u32 {
+        let exponent = *self.cells.iter().max().expect("empty board");
+        return if exponent == 0 { 0 } else { 1 << exponent };
+    }
+
+    /// Slides every tile in `direction`, merging equal neighbours once per slide.
+    ///
+    /// # Returns
+    /// The board after the slide and the score gained by its merges.
+    pub fn slide(&self, direction: Direction) -> ([u8; 16], u32) {
+        let mut cells = [0; 16];
+        let mut gained = 0;
+        for line in direction.lines() {
+            let mut target = 0;
+            let mut mergeable = false;
+            for cell in line {
+                let exponent = self.cells[cell];
+                if exponent == 0 {
+                    continue;
+                }
+                // Merge into the last placed tile, unless it has already merged.
+                if mergeable && cells[line[target - 1]] == exponent {
+                    cells[line[target - 1]] += 1;
+                    gained += 1 << (exponent + 1);
+                    mergeable = false;
+                }
+                else {
+                    cells[line[target]] = exponent;
+                    target += 1;
+                    mergeable = true;
+                }
+            }
+        }
+        return (cells, gained);
+    }
+}
+
+impl Default for Game2048State {
+    fn default() -> Self {
+        return Game2048State::new();
+    }
+}
+
+
+/// Encodes the board as four rows of tile values with `.` for empty cells, followed by the score
+/// and the number of pending spawns, such as `score 24 spawns 1`. `GameState::from_str` parses it back.
+impl fmt::Display for Game2048State {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for row in self.cells.chunks(4) {
+            let tiles: Vec<String> = row.iter()
+                .map(|exponent| if *exponent == 0 { ".".to_string() } else { (1u32 << exponent).to_string() })
+                .collect();
+            writeln!(f, "{}", tiles.join(" "))?;
+        }
+        write!(f, "score {}", self.score)?;
+        if self.spawns > 0 {
+            write!(f, " spawns {}", self.spawns)?;
+        }
+        return Ok(());
+    }
+}
+
+
+/// Allows the MCTS engine to build Game2048State trees.
+impl GameState<Game2048Move> for Game2048State {
+    type ParseError = Game2048ParseError;
+
+    fn from_str(game_state: String) -> Self {
+        return Self::try_from_str(&game_state).expect("invalid 2048 position");
+    }
+
+    fn try_from_str(game_state: &str) -> Result<Self, Game2048ParseError> {
+        // An empty string is the start of a new game.
+        let tokens: Vec<&str> = game_state.split_whitespace().collect();
+        if tokens.is_empty() {
+            return Ok(Game2048State::new());
+        }
+
+        let error = || Game2048ParseError(game_state.to_string());
+        if tokens.len() < 16 {
+            return Err(error());
+        }
+        let mut state = Game2048State { cells: [0; 16], score: 0, spawns: 0 };
+        for (cell, token) in tokens[..16].iter().enumerate() {
+            if *token == "." || *token == "0" {
+                continue;
+            }
+            let value = token.parse::<u32>().map_err(|_| error())?;
+            if value < 2 || !value.is_power_of_two() {
+                return Err(error());
+            }
+            state.cells[cell] = value.trailing_zeros() as u8;
+        }
+
+        // The board is followed by optional score and spawns fields.
+        let fields = &tokens[16..];
+        if !fields.len().is_multiple_of(2) {
+            return Err(error());
+        }
+        for field in fields.chunks(2) {
+            match field[0] {
+                "score" => state.score = field[1].parse().map_err(|_| error())?,
+                "spawns" => state.spawns = field[1].parse().map_err(|_| error())?,
+                _ => return Err(error()),
+            }
+        }
+        if state.spawns as usize > state.cells.iter().filter(|exponent| **exponent == 0).count() {
+            return Err(error());
+        }
+        return Ok(state);
+    }
+
+    fn apply_action(&self, action: &Game2048Move) -> Self {
+        let mut new_state = self.clone();
+        new_state.apply_action_mut(action);
+        return new_state;
+    }
+
+    fn apply_action_mut(&mut self, action: &Game2048Move) {
+        match action {
+            Game2048Move::Slide(direction) => {
+                let (cells, gained) = self.slide(*direction);
+                self.cells = cells;
+                self.score += gained;
+                self.spawns = 1;
+            }
+            Game2048Move::Spawn { cell, exponent } => {
+                self.cells[*cell as usize] = *exponent;
+                self.spawns -= 1;
+            }
+        }
+    }
+
+    fn status_with_moves_left(&self) -> bool {
+        return true;
+    }
+
+    fn result(&self) -> GameResult {
+        return GameResult::Score(self.score as f32);
+    }
+
+    fn generate_legal_actions(&self) -> Vec<Game2048Move> {
+        let mut actions = Vec::with_capacity(4);
+        self.legal_actions_into(&mut actions);
+        return actions;
+    }
+
+    fn legal_actions_into(&self, actions: &mut Vec<Game2048Move>) {
+        actions.clear();
+        // Slides which leave the board unchanged are not moves.
+        for direction in Direction::ALL {
+            if self.slide(direction).0 != self.cells {
+                actions.push(Game2048Move::Slide(direction));
+            }
+        }
+    }
+
+    fn is_chance_node(&self) -> bool {
+        return self.spawns > 0;
+    }
+
+    fn chance_outcomes(&self) -> Vec<(Game2048Move, f32)> {
+        let empty: Vec<u8> = (0..16).filter(|cell| self.cells[*cell as usize] == 0).collect();
+        let mut outcomes = Vec::with_capacity(empty.len() * 2);
+        for cell in empty.iter() {
+            let probability = 1.0 / empty.len() as f32;
+            outcomes.push((Game2048Move::Spawn { cell: *cell, exponent: 1 }, probability * TWO_PROBABILITY));
+            outcomes.push((Game2048Move::Spawn { cell: *cell, exponent: 2 }, probability * (1.0 - TWO_PROBABILITY)));
+        }
+        return outcomes;
+    }
+
+    fn state_hash(&self) -> Option<u64> {
+        // Exponents fit in 4 bits for every tile below 65536.
+        let board = self.cells.iter().fold(0u64, |hash, exponent| (hash << 4) | (*exponent as u64 & 0xF));
+        return Some(board ^ ((self.spawns as u64) << 62));
+    }
+
+    fn action_code(&self, action: &Game2048Move) -> Option<u64> {
+        // Slides take the codes 0 to 3, and spawns follow them by cell and tile.
+        match action {
+            Game2048Move::Slide(direction) => return Some(*direction as u64),
+            Game2048Move::Spawn { cell, exponent } => return Some(4 + *cell as u64 * 2 + (*exponent as u64 - 1)),
+        }
+    }
+
+    fn evaluate(&self) -> f32 {
+        return self.score as f32;
+    }
+
+    fn rollout_move_cap(&self) -> Option<u32> {
+        // Every slide spawns a tile, so games end once the board locks up.
+        return None;
+    }
+
+    fn side_to_move(&self) -> bool {
+        return true;
+    }
+}
+
+
+impl Encode for Game2048Move {
+    fn encode(&self, out: &mut Vec<u8>) {
+        match self {
+            Game2048Move::Slide(direction) => {
+                out.push(0);
+                out.push(*direction as u8);
+            }
+            Game2048Move::Spawn { cell, exponent } => {
+                out.push(1);
+                out.push(*cell);
+                out.push(*exponent);
+            }
+        }
+    }
+}
+
+impl Decode for Game2048Move {
+    fn decode(input: &mut &[u8]) -> Result<Self, DecodeError> {
+        if !bool::decode(input)? {
+            let direction = u8::decode(input)?;
+            return Direction::ALL.get(direction as usize)
+                .map(|direction| Game2048Move::Slide(*direction))
+                .ok_or(DecodeError(format!("invalid direction {}", direction)));
+        }
+        let cell = u8::decode(input)?;
+        let exponent = u8::decode(input)?;
+        if cell >= 16 {
+            return Err(DecodeError(format!("invalid cell {}", cell)));
+        }
+        return Ok(Game2048Move::Spawn { cell, exponent });
+    }
+}
+
+/// Encodes the state through its string encoding, which holds every part of it.
+impl Encode for Game2048State {
+    fn encode(&self, out: &mut Vec<u8>) {
+        self.to_string().encode(out);
+    }
+}
+
+impl Decode for Game2048State {
+    fn decode(input: &mut &[u8]) -> Result<Self, DecodeError> {
+        return decode_from_str(input);
+    }
+}
+
+
+/// Defines unit tests for the GameState implementation of Game2048State,
+/// checking the sliding rules, tile spawns and single player search.
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mcts::{ExplorationSchedule, MCTSTree, PlayerMode};
+
+    /// Parses a board of 16 tile values without a score or pending spawns.
+    fn board(tiles: &str) -> Game2048State {
+        return Game2048State::from_str(tiles.to_string());
+    }
+
+    /// Test if tiles slide to the edge and equal tiles merge once per slide.
+    #[test]
+    fn test_slide() {
+        let state = board("2 2 2 2  4 4 8 .  2 . . 2  8 4 4 .");
+        let (cells, gained) = state.slide(Direction::Left);
+        let left = Game2048State { cells, score: gained, spawns: 0 };
+        assert_eq!(left, board("4 4 . .  8 8 . .  4 . . .  8 8 . . score 28"));
+
+        let (cells, _) = state.slide(Direction::Right);
+        assert_eq!(Game2048State { cells, score: 0, spawns: 0 }, board(". . 4 4  . . 8 8  . . . 4  . . 8 8"));
+
+        let (cells, gained) = state.slide(Direction::Down);
+        assert_eq!(Game2048State { cells, score: gained, spawns: 0 }, board("2 . . .  4 . 2 .  2 2 8 .  8 8 4 4 score 12"));
+    }
+
+    /// Test if slides spawn a tile on a random empty cell, and slides which change nothing are not legal.
+    #[test]
+    fn test_spawns() {
+        let state = board("2 . . .  . . . .  . . . .  . . . .");
+        assert_eq!(state.generate_legal_actions(), vec![Game2048Move::Slide(Direction::Right), Game2048Move::Slide(Direction::Down)]);
+
+        let slid = state.apply_action(&Game2048Move::Slide(Direction::Right));
+        assert!(slid.is_chance_node());
+        let outcomes = slid.chance_outcomes();
+        assert_eq!(outcomes.len(), 30);
+        let total: f32 = outcomes.iter().map(|outcome| outcome.1).sum();
+        assert!((total - 1.0).abs() < 1e-5);
+
+        let spawned = slid.apply_action(&outcomes[1].0);
+        assert!(!spawned.is_chance_node());
+        assert_eq!(spawned.cells.iter().filter(|exponent| **exponent != 0).count(), 2);
+
+        // New games spawn two tiles before the first slide.
+        let mut new_game = Game2048State::new();
+        for _i in 0..2 {
+            assert!(new_game.is_chance_node());
+            new_game.apply_action_mut(&new_game.chance_outcomes()[0].0);
+        }
+        assert!(!new_game.is_chance_node());
+    }
+
+    /// Test if positions round trip through their string encoding, and invalid ones are rejected.
+    #[test]
+    fn test_try_from_str() {
+        let state = Game2048State::from_str("".to_string());
+        assert_eq!(state, Game2048State::new());
+        assert_eq!(Game2048State::from_str(state.to_string()), state);
+
+        let state = board("2 4 8 16  . . . .  . . . .  . . . 2048 score 36");
+        assert_eq!(state.max_tile(), 2048);
+        assert_eq!(state.score, 36);
+        assert_eq!(Game2048State::from_str(state.to_string()), state);
+
+        assert!(Game2048State::try_from_str("2 4 8").is_err());
+        assert!(Game2048State::try_from_str("3 . . .  . . . .  . . . .  . . . .").is_err());
+        assert!(Game2048State::try_from_str("2 . . .  . . . .  . . . .  . . . . score").is_err());
+    }
+
+    /// Test if a board where no slide moves a tile ends the game with its score.
+    #[test]
+    fn test_result() {
+        let state = board("2 4 2 4  4 2 4 2  2 4 2 4  4 2 4 2 score 100");
+        assert!(state.generate_legal_actions().is_empty());
+        assert_eq!(state.result(), GameResult::Score(100.0));
+    }
+
+    /// Test if single player search through chance nodes prefers the slide merging the largest tiles.
+    #[test]
+    fn test_search() {
+        let state = board("2 4 2 4  4 2 4 2  2 4 2 4  512 512 . 4");
+        let mut tree = MCTSTree::<Game2048Move, Game2048State>::with_capacity(5001, Some(1), state.to_string(), 4);
+        tree.searcher.player_mode = PlayerMode::SinglePlayer;
+        tree.searcher.rollout_depth_limit = Some(1);
+        tree.searcher.exploration_schedule = ExplorationSchedule::Constant(100.0);
+        tree.search(5000);
+
+        let best = tree.get_max_uct_child_at(0, Some(0.0)).unwrap();
+        let action = tree.arena.actions[best as usize].expect("no action");
+        assert!(action == Game2048Move::Slide(Direction::Left) || action == Game2048Move::Slide(Direction::Right));
+        assert!(tree.arena.topology[best as usize].is_chance);
+    }
+}
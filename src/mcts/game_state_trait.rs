@@ -1,13 +1,50 @@
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+
+
 /// Represents the possible outcomes of a two player, turn-based game.
 ///
 /// This is used to logically abstract results and their data representation.
 ///
 /// PartialEq is used for comparison, while Debug is used for printing results.
-#[derive(PartialEq, Debug)]
+/// Clone and Copy allow results to be passed by value freely.
+#[derive(PartialEq, Debug, Clone, Copy)]
 pub enum GameResult {
     FirstPlayerWin,
     SecondPlayerWin,
     Draw,
+    
+    /// A numeric outcome. For single player games, the raw score that was reached. 
+    /// For two player games, the first player's share of the win between 0 and 1,
+    /// with the second player receiving the remainder.
+    Score(f32),
+
+    /// A finished game together with its margin of victory from the first player's perspective, 
+    /// such as a disc difference or point lead. Positive margins are first player wins, 
+    /// negative margins second player wins and a margin of 0 is a draw.
+    Margin(f32),
+}
+
+
+impl GameResult {
+    /// Determines the winner of the result.
+    ///
+    /// # Returns
+    /// True if the first player has won, false if the second player has won,
+    /// or None for draws and scores.
+    pub fn winner(&self) -> Option<bool> {
+        match self {
+            GameResult::FirstPlayerWin => return Some(true),
+            GameResult::SecondPlayerWin => return Some(false),
+            GameResult::Margin(margin) if *margin != 0.0 => return Some(*margin > 0.0),
+            _ => return None,
+        }
+    }
+
+    /// True if the result is a draw, including a margin of 0.
+    pub fn is_draw(&self) -> bool {
+        return *self == GameResult::Draw || *self == GameResult::Margin(0.0);
+    }
 }
 
 
@@ -16,12 +53,42 @@ pub enum GameResult {
 ///
 /// Action is a generic data type representing a single legal move in the game.
 pub trait GameState<Action> {
+
+    /// Error produced when a string representation can not be parsed into a gamestate.
+    type ParseError;
     
     /// Parses a gamestate from a string representation.
+    ///
+    /// # Panics
+    /// Implementations may panic on invalid representations, use `try_from_str` to handle them.
     fn from_str(game_state: String) -> Self;
+
+    /// Parses a gamestate from a string representation, without panicking on invalid ones.
+    ///
+    /// Defaults to `from_str`, for game states whose parsing can not fail.
+    ///
+    /// # Returns
+    /// The parsed gamestate, or the error describing why the representation is invalid.
+    fn try_from_str(game_state: &str) -> Result<Self, Self::ParseError> 
+    where
+        Self: Sized
+    {
+        return Ok(Self::from_str(game_state.to_string()));
+    }
     
     /// Generates a gamestate copy with `action` applied to it.
     fn apply_action(&self, action: &Action) -> Self;
+
+    /// Applies `action` to the gamestate in place.
+    ///
+    /// Used by rollouts and state replay to avoid a copy of the state every ply.
+    /// Defaults to replacing the state with the result of `apply_action`.
+    fn apply_action_mut(&mut self, action: &Action) 
+    where
+        Self: Sized
+    {
+        *self = self.apply_action(action);
+    }
     
     /// Determines whether the game has ended, given the fact that there
     /// are still legal moves left.
@@ -45,11 +112,120 @@ pub trait GameState<Action> {
     /// Generates possible legal actions from the current position.
     fn generate_legal_actions(&self) -> Vec<Action>;
 
+    /// Writes the possible legal actions from the current position into `actions`,
+    /// replacing its contents.
+    ///
+    /// Lets rollouts reuse a single buffer rather than allocating a vector every ply.
+    /// Defaults to copying the result of `generate_legal_actions`, implementations
+    /// should override it to generate directly into the buffer.
+    fn legal_actions_into(&self, actions: &mut Vec<Action>) {
+        actions.clear();
+        actions.extend(self.generate_legal_actions());
+    }
+
+    /// Whether the engine should generate the legal actions of a node one at a time through
+    /// `legal_action_at`, instead of generating all of them when the node is created.
+    ///
+    /// Lazily generated actions are expanded in generation order rather than at random,
+    /// so implementations should order their actions from most to least promising.
+    const LAZY_ACTIONS: bool = false;
+
+    /// Generates the legal action at position `index` of a fixed ordering of the legal 
+    /// actions from the current position.
+    ///
+    /// Only used when `LAZY_ACTIONS` is true. The default implementation generates
+    /// every legal action, so implementations with large branching factors should 
+    /// override it with one that can generate a single action cheaply.
+    ///
+    /// # Returns
+    /// The action at `index`, or None if there are `index` or fewer legal actions.
+    fn legal_action_at(&self, index: usize) -> Option<Action> {
+        return self.generate_legal_actions().into_iter().nth(index);
+    }
+
+    /// Determines whether the next event of the game is random rather than chosen by a player,
+    /// such as a dice roll or a tile spawn.
+    ///
+    /// Chance states advance through `chance_outcomes` instead of `generate_legal_actions`.
+    fn is_chance_node(&self) -> bool {
+        return false;
+    }
+
+    /// Generates the possible outcomes of the chance event from the current position.
+    ///
+    /// # Invariants
+    /// Only called on chance states.
+    ///
+    /// # Returns
+    /// Every outcome as an action paired with its probability. Probabilities should sum to 1.
+    fn chance_outcomes(&self) -> Vec<(Action, f32)> {
+        return Vec::new();
+    }
+
+    /// Prior probabilities of the legal actions of the state, such as the policy output of a model.
+    ///
+    /// When priors are given, every action is expanded at once in descending order of prior,
+    /// with the priors used by `SelectionPolicy::Puct` selection. Actions missing from the 
+    /// priors are not searched.
+    ///
+    /// # Returns
+    /// Pairs of legal actions and their prior probabilities. Defaults to empty, 
+    /// expanding actions one at a time in a random order.
+    fn action_priors(&self) -> Vec<(Action, f32)> {
+        return Vec::new();
+    }
+
+    /// 64 bit hash identifying the state, for transposition tables, caches and 
+    /// the deduplication of training data.
+    ///
+    /// Equal states must have equal hashes. The hash should be cheap to compute,
+    /// ideally updated incrementally in `apply_action`, such as a Zobrist hash.
+    ///
+    /// # Returns
+    /// The hash of the state, or None if the game state does not support hashing. Defaults to None.
+    fn state_hash(&self) -> Option<u64> {
+        return None;
+    }
+
+    /// Code identifying `action` in the current state, which adaptive rollout policies such as
+    /// `NestedPolicyAdaptation` learn a weight for.
+    ///
+    /// Actions sharing a code share their weight, so codes should tell apart the actions worth
+    /// playing differently, such as by including the side to move or the moved piece.
+    ///
+    /// # Returns
+    /// The code of the action, or None if the game state does not support action codes. Defaults to None.
+    fn action_code(&self, _action: &Action) -> Option<u64> {
+        return None;
+    }
+
+    /// Statically evaluates a non-terminal state, replacing the rest of a rollout 
+    /// once it reaches the rollout depth limit.
+    ///
+    /// # Returns
+    /// For two player games, the expected share of the win of the first player, between 0 and 1.
+    /// For single player games, an estimate of the final score.
+    ///
+    /// Defaults to 0.5, an even game.
+    fn evaluate(&self) -> f32 {
+        return 0.5;
+    }
+
+    /// Number of moves after which rollouts are cut off and scored as draws, used as the default
+    /// `Searcher::rollout_move_cap` of trees rooted at this state.
+    ///
+    /// Defaults to 200 moves. Games which always end can return None, playing every rollout to the end.
+    fn rollout_move_cap(&self) -> Option<u32> {
+        return Some(200);
+    }
+
     /// Determines the side that is due to move.
     ///
     /// Some gamestates are identical, except that the opposite player must move. 
     /// This function allows for the differentiation of thoose states.
     ///
+    /// Chance states should report the side that is due to move after the chance event.
+    ///
     /// # Returns
     /// True if the first player is due to move, 
     /// false if the second player is due to move.
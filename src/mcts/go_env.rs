@@ -0,0 +1,670 @@
+use crate::game_state_trait::{GameResult, GameState};
+use crate::rollout::RolloutPolicy;
+use crate::rng::{Rng, SearchRng};
+use crate::serialize::{Decode, DecodeError, Encode};
+use alloc::collections::BTreeSet;
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec;
+use alloc::vec::Vec;
+use core::fmt;
+
+
+/// Default board size.
+pub const DEFAULT_SIZE: usize = 9;
+
+/// Default komi given to white, which avoids drawn games with area scoring.
+pub const DEFAULT_KOMI: f32 = 7.5;
+
+/// Column letters of GTP vertices, which skip the letter I.
+const GTP_COLUMNS: &[u8] = b"ABCDEFGHJKLMNOPQRST";
+
+
+/// Error produced when a string is not a valid Go position.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GoParseError(pub String);
+
+impl fmt::Display for GoParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        return write!(f, "invalid go position: {}", self.0);
+    }
+}
+
+impl core::error::Error for GoParseError {}
+
+
+/// A move in Go, either placing a stone on a point or passing.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum GoMove {
+    /// Places a stone on the point with this index, counted row by row from the top left corner.
+    Place(u16),
+    Pass,
+}
+
+
+/// Holds the state of a Go board under the Tromp-Taylor rules, with area scoring and positional superko.
+///
+/// Black is the first player and moves first. Suicide is legal, removing the group of the player
+/// who moved when it is left without liberties after captures. Positional superko forbids any move
+/// recreating an earlier arrangement of the stones, which also covers simple ko and suicide of a
+/// single stone. The game
+/// ends after two consecutive passes and is scored by area: stones plus the empty regions reaching
+/// only that player's stones, with komi added to white.
+#[derive(Debug, Clone)]
+pub struct GoState {
+    /// Number of points along each edge.
+    pub size: usize,
+
+    /// Stones of every point, with true for black and false for white.
+    pub points: Vec<Option<bool>>,
+
+    /// True if black is the side to move.
+    pub black_to_move: bool,
+
+    /// Number of consecutive passes played, where two end the game.
+    pub passes: u8,
+
+    /// Points given to white for moving second.
+    pub komi: f32,
+
+    /// Zobrist hash of the stones on the board.
+    pub stones_hash: u64,
+
+    /// Stone hashes of every earlier position of the game, for positional superko.
+    pub history: BTreeSet<u64>,
+}
+
+
+impl GoState {
+    /// Creates an empty board with `size` points along each edge.
+    ///
+    /// # Panics
+    /// If `size` is 0 or larger than 19.
+    pub fn new(size: usize, komi: f32) -> Self {
+        assert!(size > 0 && size <= GTP_COLUMNS.len(), "go boards have between 1 and 19 points along each edge");
+        return GoState {
+            size,
+            points: vec![None; size * size],
+            black_to_move: true,
+            passes: 0,
+            komi,
+            stones_hash: 0,
+            history: BTreeSet::new(),
+        };
+    }
+
+    /// Points adjacent to `point`.
+    pub fn neighbours(&self, point: usize) -> impl Iterator<Item = usize> {
+        let size = self.size;
+        let (row, column) = (point / size, point % size);
+        let up = (row > 0).then(|| point - size);
+        let down = (row + 1 < size).then(|| point + size);
+        let left = (column > 0).then(|| point - 1);
+        let right = (column + 1 < size).then(|| point + 1);
+        return [up, down, left, right].into_iter().flatten();
+    }
+
+    /// Collects the group of stones of `player` connected to `point`, which may be
+    /// an empty point a stone is about to be placed on.
+    ///
+    /// # Returns
+    /// The points of the group, and whether it has a liberty other than `point`.
+    fn group(&self, point: usize, player: bool) -> (Vec<usize>, bool) {
+        let mut stones = vec![point];
+        let mut seen = vec![false; self.points.len()];
+        seen[point] = true;
+        let mut has_liberty = false;
+        let mut i = 0;
+        while i < stones.len() {
+            for neighbour in self.neighbours(stones[i]) {
+                if seen[neighbour] {
+                    continue;
+                }
+                seen[neighbour] = true;
+                if self.points[neighbour] == Some(player) {
+                    stones.push(neighbour);
+                }
+                else if self.points[neighbour].is_none() {
+                    has_liberty = true;
+                }
+            }
+            i += 1;
+        }
+        return (stones, has_liberty);
+    }
+
+    /// Works out the stones removed by the side to move placing a stone on `point`.
+    ///
+    /// # Returns
+    /// The removed stones together with the stone hash after the move, or None if the move
+    /// breaks positional superko or the point is occupied. The removed stones are the captured
+    /// opponent stones, or for suicide the group of the new stone, including `point` itself.
+    fn placement(&self, point: usize) -> Option<(Vec<usize>, u64)> {
+        if self.points[point].is_some() {
+            return None;
+        }
+        let player = self.black_to_move;
+        let mut stones_hash = self.stones_hash ^ zobrist_key(point, player);
+
+        // Opponent groups left without liberties are captured.
+        let mut captured: Vec<usize> = Vec::new();
+        for neighbour in self.neighbours(point) {
+            if self.points[neighbour] == Some(!player) && !captured.contains(&neighbour) {
+                let (stones, _) = self.group(neighbour, !player);
+                if self.is_only_liberty(&stones, point) {
+                    captured.extend(stones);
+                }
+            }
+        }
+        for stone in &captured {
+            stones_hash ^= zobrist_key(*stone, !player);
+        }
+
+        // Without captures, the new stone's group is removed if it has no liberty of its own.
+        if captured.is_empty() {
+            let (group, has_liberty) = self.group(point, player);
+            if !has_liberty {
+                for stone in &group {
+                    stones_hash ^= zobrist_key(*stone, player);
+                }
+                captured = group;
+            }
+        }
+        if self.history.contains(&stones_hash) || stones_hash == self.stones_hash {
+            return None;
+        }
+        return Some((captured, stones_hash));
+    }
+
+    /// Determines whether `point` is the only liberty of the group of `stones`.
+    fn is_only_liberty(&self, stones: &[usize], point: usize) -> bool {
+        return stones.iter()
+            .flat_map(|stone| self.neighbours(*stone))
+            .all(|neighbour| neighbour == point || self.points[neighbour].is_some());
+    }
+
+    /// Determines whether `point` is an eye of the side to move: an empty point surrounded by its
+    /// own stones, with at most one diagonal held by the opponent, or none on the edge.
+    pub fn is_eye(&self, point: usize) -> bool {
+        let player = self.black_to_move;
+        if self.points[point].is_some() || self.neighbours(point).any(|neighbour| self.points[neighbour] != Some(player)) {
+            return false;
+        }
+        let (row, column) = ((point / self.size) as i64, (point % self.size) as i64);
+        let size = self.size as i64;
+        let mut off_board = 0;
+        let mut opponent = 0;
+        for (row_offset, column_offset) in [(-1, -1), (-1, 1), (1, -1), (1, 1)] {
+            let (diagonal_row, diagonal_column) = (row + row_offset, column + column_offset);
+            if diagonal_row < 0 || diagonal_row >= size || diagonal_column < 0 || diagonal_column >= size {
+                off_board += 1;
+            }
+            else if self.points[(diagonal_row * size + diagonal_column) as usize] == Some(!player) {
+                opponent += 1;
+            }
+        }
+        return if off_board > 0 { opponent == 0 } else { opponent <= 1 };
+    }
+
+    /// Scores the board by area, as black's points minus white's points and komi.
+    pub fn score(&self) -> f32 {
+        let mut score = 0;
+        let mut seen = vec![false; self.points.len()];
+        for point in 0..self.points.len() {
+            match self.points[point] {
+                Some(true) => score += 1,
+                Some(false) => score -= 1,
+                None if !seen[point] => {
+                    // Empty regions count for the only color they reach.
+                    let mut region = vec![point];
+                    seen[point] = true;
+                    let (mut reaches_black, mut reaches_white) = (false, false);
+                    let mut i = 0;
+                    while i < region.len() {
+                        for neighbour in self.neighbours(region[i]) {
+                            match self.points[neighbour] {
+                                Some(true) => reaches_black = true,
+                                Some(false) => reaches_white = true,
+                                None if !seen[neighbour] => {
+                                    seen[neighbour] = true;
+                                    region.push(neighbour);
+                                },
+                                None => {},
+                            }
+                        }
+                        i += 1;
+                    }
+                    if reaches_black != reaches_white {
+                        score += if reaches_black { region.len() as i32 } else { -(region.len() as i32) };
+                    }
+                },
+                None => {},
+            }
+        }
+        return score as f32 - self.komi;
+    }
+
+    /// Encodes `action` as a GTP vertex, such as `D4` or `pass`, with columns lettered from the left
+    /// skipping `I` and rows numbered from the bottom.
+    pub fn to_gtp(&self, action: &GoMove) -> String {
+        match action {
+            GoMove::Pass => return "pass".to_string(),
+            GoMove::Place(point) => {
+                let (row, column) = (*point as usize / self.size, *point as usize % self.size);
+                return format!("{}{}", GTP_COLUMNS[column] as char, self.size - row);
+            },
+        }
+    }
+
+    /// Decodes a GTP vertex, ignoring case.
+    ///
+    /// # Returns
+    /// The move, or None if the vertex is not on the board.
+    pub fn from_gtp(&self, vertex: &str) -> Option<GoMove> {
+        let vertex = vertex.trim().to_ascii_uppercase();
+        if vertex == "PASS" {
+            return Some(GoMove::Pass);
+        }
+        let column = GTP_COLUMNS[..self.size].iter().position(|letter| Some(*letter) == vertex.bytes().next())?;
+        let row_number = vertex[1..].parse::<usize>().ok().filter(|row| (1..=self.size).contains(row))?;
+        return Some(GoMove::Place(((self.size - row_number) * self.size + column) as u16));
+    }
+}
+
+impl Default for GoState {
+    fn default() -> Self {
+        return GoState::new(DEFAULT_SIZE, DEFAULT_KOMI);
+    }
+}
+
+
+/// Zobrist key of a stone of `player` on `point`, generated with a fixed splitmix64 sequence.
+fn zobrist_key(point: usize, player: bool) -> u64 {
+    let mut key = (point as u64 * 2 + player as u64 + 1).wrapping_mul(0x9E37_79B9_7F4A_7C15);
+    key = (key ^ (key >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    key = (key ^ (key >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    return key ^ (key >> 31);
+}
+
+
+/// Draws the board as one row of points per line from the top, with `X` for black stones, `O` for
+/// white stones and `.` for empty points, followed by the side to move and the komi, such as `b 7.5`.
+/// `GameState::from_str` parses it back, although the history for superko is not kept.
+impl fmt::Display for GoState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for row in self.points.chunks(self.size) {
+            for point in row {
+                let mark = match point {
+                    Some(true) => 'X',
+                    Some(false) => 'O',
+                    None => '.',
+                };
+                write!(f, "{}", mark)?;
+            }
+            writeln!(f)?;
+        }
+        return write!(f, "{} {}", if self.black_to_move { 'b' } else { 'w' }, self.komi);
+    }
+}
+
+
+/// Allows the MCTS engine to build GoState trees.
+impl GameState<GoMove> for GoState {
+    type ParseError = GoParseError;
+
+    fn from_str(game_state: String) -> Self {
+        return Self::try_from_str(&game_state).expect("invalid go position");
+    }
+
+    /// Parses either the board size, giving an empty board such as `9`, or the points of a square
+    /// board as drawn by `Display`. Either can be followed by the side to move, `b` or `w`,
+    /// and the komi, defaulting to black and `DEFAULT_KOMI`.
+    fn try_from_str(game_state: &str) -> Result<Self, GoParseError> {
+        let invalid = || GoParseError(game_state.to_string());
+        let mut tokens: Vec<&str> = game_state.split_whitespace().collect();
+        let komi = match tokens.last().and_then(|token| token.parse::<f32>().ok()) {
+            Some(komi) if tokens.len() > 1 => {
+                tokens.pop();
+                komi
+            },
+            _ => DEFAULT_KOMI,
+        };
+        let black_to_move = match tokens.last() {
+            Some(&"b") | Some(&"B") if tokens.len() > 1 => { tokens.pop(); true },
+            Some(&"w") | Some(&"W") if tokens.len() > 1 => { tokens.pop(); false },
+            _ => true,
+        };
+
+        let board = tokens.concat();
+        let mut state = match board.parse::<usize>() {
+            Ok(size) if size > 0 && size <= GTP_COLUMNS.len() => GoState::new(size, komi),
+            Ok(_) => return Err(invalid()),
+            Err(_) => {
+                let size = (1..=GTP_COLUMNS.len()).find(|size| size * size == board.len()).ok_or_else(invalid)?;
+                let mut state = GoState::new(size, komi);
+                for (point, mark) in board.chars().enumerate() {
+                    let stone = match mark.to_ascii_uppercase() {
+                        'X' | 'B' => Some(true),
+                        'O' | 'W' => Some(false),
+                        '.' | '+' => None,
+                        _ => return Err(invalid()),
+                    };
+                    if let Some(player) = stone {
+                        state.points[point] = stone;
+                        state.stones_hash ^= zobrist_key(point, player);
+                    }
+                }
+                state
+            },
+        };
+        state.black_to_move = black_to_move;
+        return Ok(state);
+    }
+
+    fn apply_action(&self, action: &GoMove) -> Self {
+        let mut new_state = self.clone();
+        new_state.apply_action_mut(action);
+        return new_state;
+    }
+
+    /// Plays `action`, capturing any opponent groups left without liberties, then removing the
+    /// group of the new stone if it has none either.
+    ///
+    /// # Panics
+    /// If `action` places a stone on an occupied point or breaks positional superko.
+    fn apply_action_mut(&mut self, action: &GoMove) {
+        match action {
+            GoMove::Pass => self.passes += 1,
+            GoMove::Place(point) => {
+                let point = *point as usize;
+                let (removed, stones_hash) = self.placement(point).expect("illegal go move");
+                self.points[point] = Some(self.black_to_move);
+                for stone in removed {
+                    self.points[stone] = None;
+                }
+                self.history.insert(self.stones_hash);
+                self.stones_hash = stones_hash;
+                self.passes = 0;
+            },
+        }
+        self.black_to_move = !self.black_to_move;
+    }
+
+    fn status_with_moves_left(&self) -> bool {
+        return self.passes < 2;
+    }
+
+    fn result(&self) -> GameResult {
+        return GameResult::Margin(self.score());
+    }
+
+    fn generate_legal_actions(&self) -> Vec<GoMove> {
+        let mut actions = Vec::with_capacity(self.points.len() + 1);
+        self.legal_actions_into(&mut actions);
+        return actions;
+    }
+
+    fn legal_actions_into(&self, actions: &mut Vec<GoMove>) {
+        actions.clear();
+        if self.passes >= 2 {
+            return;
+        }
+        actions.extend((0..self.points.len()).filter(|point| self.placement(*point).is_some()).map(|point| GoMove::Place(point as u16)));
+        actions.push(GoMove::Pass);
+    }
+
+    fn state_hash(&self) -> Option<u64> {
+        // The side to move and the passes decide how the game continues, so they are mixed in.
+        let side = if self.black_to_move { 0 } else { 0x5851_F42D_4C95_7F2D };
+        return Some(self.stones_hash ^ side ^ (self.passes as u64).wrapping_mul(0x2545_F491_4F6C_DD1D));
+    }
+
+    fn rollout_move_cap(&self) -> Option<u32> {
+        // Random games capture and refill the board several times before running out of moves.
+        return Some(3 * (self.size * self.size) as u32);
+    }
+
+    fn side_to_move(&self) -> bool {
+        return self.black_to_move;
+    }
+}
+
+
+/// Rollout policy for Go, playing random moves except into its own eyes.
+///
+/// Random play fills its own eyes and lets every group be captured, so games
+/// never settle. This policy passes once only eye filling moves are left.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct GoRolloutPolicy;
+
+impl RolloutPolicy<GoMove, GoState> for GoRolloutPolicy {
+    fn choose(&mut self, game_state: &GoState, actions: &[GoMove], random_generator: &mut SearchRng) -> usize {
+        let candidates: Vec<usize> = (0..actions.len())
+            .filter(|i| matches!(actions[*i], GoMove::Place(point) if !game_state.is_eye(point as usize)))
+            .collect();
+        if candidates.is_empty() {
+            return actions.iter().position(|action| *action == GoMove::Pass).expect("pass is always legal");
+        }
+        return candidates[random_generator.gen_range(0, candidates.len())];
+    }
+}
+
+
+impl Encode for GoMove {
+    fn encode(&self, out: &mut Vec<u8>) {
+        match self {
+            GoMove::Place(point) => Some(*point).encode(out),
+            GoMove::Pass => None::<u16>.encode(out),
+        }
+    }
+}
+
+impl Decode for GoMove {
+    fn decode(input: &mut &[u8]) -> Result<Self, DecodeError> {
+        return Ok(Option::<u16>::decode(input)?.map_or(GoMove::Pass, GoMove::Place));
+    }
+}
+
+/// Encodes every field of the state, as the string encoding does not keep the superko history.
+impl Encode for GoState {
+    fn encode(&self, out: &mut Vec<u8>) {
+        self.size.encode(out);
+        self.points.encode(out);
+        self.black_to_move.encode(out);
+        self.passes.encode(out);
+        self.komi.encode(out);
+        self.stones_hash.encode(out);
+        self.history.encode(out);
+    }
+}
+
+impl Decode for GoState {
+    fn decode(input: &mut &[u8]) -> Result<Self, DecodeError> {
+        let state = GoState {
+            size: usize::decode(input)?,
+            points: Vec::decode(input)?,
+            black_to_move: bool::decode(input)?,
+            passes: u8::decode(input)?,
+            komi: f32::decode(input)?,
+            stones_hash: u64::decode(input)?,
+            history: BTreeSet::decode(input)?,
+        };
+        if state.size == 0 || state.size > GTP_COLUMNS.len() {
+            return Err(DecodeError(format!("invalid board size {}", state.size)));
+        }
+        if state.points.len() != state.size * state.size {
+            return Err(DecodeError(format!("{} points on a board of size {}", state.points.len(), state.size)));
+        }
+        return Ok(state);
+    }
+}
+
+
+/// Defines unit tests for the GameState implementation of GoState.
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rng::SeedableRng;
+
+    /// Test if single stones and groups without liberties are captured.
+    #[test]
+    fn test_capture() {
+        let state = GoState::from_str(".X... XOX.. ..... ..... ..... b".to_string());
+        let captured = state.apply_action(&state.from_gtp("B3").unwrap());
+        assert_eq!(captured.points[6], None);
+        assert_eq!(captured.score(), 25.0 - DEFAULT_KOMI);
+
+        // A group of two white stones loses its last liberty.
+        let group = GoState::from_str("XOO.. .XX.. ..... ..... ..... b".to_string());
+        let captured = group.apply_action(&GoMove::Place(3));
+        assert_eq!(captured.points[1], None);
+        assert_eq!(captured.points[2], None);
+        assert!(!captured.side_to_move());
+    }
+
+    /// Test if suicide of a group is legal and removes it, while suicide of a single stone
+    /// repeats the position and breaks positional superko.
+    #[test]
+    fn test_suicide() {
+        let state = GoState::from_str(".O... O.... ..... ..... ..... b".to_string());
+        assert!(!state.generate_legal_actions().contains(&GoMove::Place(0)));
+
+        // Black fills the last liberty of its own two stones, which are removed.
+        let group = GoState::from_str(".XO.. OO... ..... ..... ..... b".to_string());
+        assert!(group.generate_legal_actions().contains(&GoMove::Place(0)));
+        let suicided = group.apply_action(&GoMove::Place(0));
+        assert_eq!(suicided.points[0], None);
+        assert_eq!(suicided.points[1], None);
+        assert_eq!(suicided.points[2], Some(false));
+        assert_eq!(suicided.stones_hash, GoState::from_str("..O.. OO... ..... ..... ..... w".to_string()).stones_hash);
+        assert!(!suicided.side_to_move());
+
+        // Filling the last liberty of a group in atari is legal, as it captures first.
+        let capture = GoState::from_str(".OX.. OX... X.... ..... ..... b".to_string());
+        assert!(capture.generate_legal_actions().contains(&GoMove::Place(0)));
+        let captured = capture.apply_action(&GoMove::Place(0));
+        assert_eq!(captured.points[1], None);
+        assert_eq!(captured.points[5], None);
+    }
+
+    /// Test if retaking a ko immediately is forbidden by positional superko.
+    #[test]
+    fn test_ko() {
+        let state = GoState::from_str(".XO.. XO.O. .XO.. ..... ..... b".to_string());
+        let taken = state.apply_action(&GoMove::Place(7));
+        assert_eq!(taken.points[6], None);
+        assert!(!taken.generate_legal_actions().contains(&GoMove::Place(6)));
+
+        // The ko can be retaken after a move elsewhere.
+        let later = taken.apply_action(&GoMove::Place(24)).apply_action(&GoMove::Place(20));
+        assert!(later.generate_legal_actions().contains(&GoMove::Place(6)));
+    }
+
+    /// Test if two passes end the game, scored by area with komi.
+    #[test]
+    fn test_result() {
+        let state = GoState::from_str(".XO XXO .XO b 0.5".to_string());
+        assert_eq!(state.komi, 0.5);
+        assert_eq!(state.score(), 2.5);
+        let passed = state.apply_action(&GoMove::Pass);
+        assert!(passed.status_with_moves_left());
+        let ended = passed.apply_action(&GoMove::Pass);
+        assert!(!ended.status_with_moves_left());
+        assert!(ended.generate_legal_actions().is_empty());
+        assert_eq!(ended.result(), GameResult::Margin(2.5));
+        assert_eq!(ended.result().winner(), Some(true));
+
+        // Empty regions reaching both colors are neutral.
+        let neutral = GoState::from_str("X.O ... ... w 0".to_string());
+        assert_eq!(neutral.score(), 0.0);
+
+        // A stone played after a pass resets the count.
+        let empty = GoState::from_str("3".to_string()).apply_action(&GoMove::Pass);
+        assert_eq!(empty.apply_action(&GoMove::Place(0)).passes, 0);
+    }
+
+    /// Test if GTP vertices follow the GTP convention, and round trip through moves.
+    #[test]
+    fn test_gtp() {
+        let state = GoState::default();
+        assert_eq!(state.to_gtp(&GoMove::Place(72)), "A1");
+        assert_eq!(state.to_gtp(&GoMove::Place(8)), "J9");
+        assert_eq!(state.to_gtp(&GoMove::Pass), "pass");
+        assert_eq!(state.from_gtp("j9"), Some(GoMove::Place(8)));
+        assert_eq!(state.from_gtp("PASS"), Some(GoMove::Pass));
+        assert_eq!(state.from_gtp("I5"), None);
+        assert_eq!(state.from_gtp("A10"), None);
+        for point in 0..81 {
+            assert_eq!(state.from_gtp(&state.to_gtp(&GoMove::Place(point))), Some(GoMove::Place(point)));
+        }
+    }
+
+    /// Test if positions round trip through their string encoding.
+    #[test]
+    fn test_try_from_str() {
+        let state = GoState::try_from_str("9").unwrap();
+        assert_eq!(state.generate_legal_actions().len(), 82);
+        let played = state.apply_action(&GoMove::Place(40));
+        let reparsed = GoState::from_str(played.to_string());
+        assert_eq!(reparsed.points, played.points);
+        assert_eq!(reparsed.state_hash(), played.state_hash());
+        assert!(!reparsed.side_to_move());
+
+        assert!(GoState::try_from_str("20").is_err());
+        assert!(GoState::try_from_str("XO.").is_err());
+        assert!(GoState::try_from_str("XO.Z").is_err());
+    }
+
+    /// Test if decoding rejects board sizes out of range or not matching the points.
+    #[test]
+    fn test_decode() {
+        let state = GoState::from_str("3".to_string()).apply_action(&GoMove::Place(4));
+        let mut out = Vec::new();
+        state.encode(&mut out);
+        let decoded = GoState::decode(&mut &out[..]).unwrap();
+        assert_eq!((decoded.points, decoded.history), (state.points.clone(), state.history.clone()));
+
+        for size in [0, 2, 20, usize::MAX] {
+            let invalid = GoState { size, ..state.clone() };
+            let mut out = Vec::new();
+            invalid.encode(&mut out);
+            assert!(GoState::decode(&mut &out[..]).is_err());
+        }
+    }
+
+    /// Test if rollouts which avoid filling eyes reach the end of the game.
+    #[test]
+    fn test_rollouts_end() {
+        let seed = 1;
+        let mut random_generator: SearchRng = SeedableRng::from_seed(seed);
+        let mut policy = GoRolloutPolicy;
+        for _game in 0..10 {
+            let mut state = GoState::default();
+            let mut actions = Vec::new();
+            let mut moves = 0;
+            loop {
+                state.legal_actions_into(&mut actions);
+                if actions.is_empty() {
+                    break;
+                }
+                let chosen = policy.choose(&state, &actions, &mut random_generator);
+                state.apply_action_mut(&actions[chosen]);
+                moves += 1;
+            }
+            assert!(moves < 400);
+            assert!(state.result().winner().is_some());
+        }
+    }
+
+    /// Test if searches with the Go rollout policy finish and choose a legal move.
+    #[test]
+    fn test_search() {
+        let state = GoState::from_str("5 0.5".to_string());
+        let mut tree = crate::mcts::MCTSTree::<GoMove, GoState>::with_capacity(1001, Some(1), state.to_string(), 26);
+        tree.searcher.rollout_policy = Box::new(GoRolloutPolicy);
+        tree.search(1000);
+        assert_eq!(tree.arena.stats[0].sims, 1000);
+        let best = tree.get_max_uct_child_at(0, Some(0.0)).unwrap();
+        assert!(state.generate_legal_actions().contains(&tree.arena.actions[best as usize].unwrap()));
+    }
+}
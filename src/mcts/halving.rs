@@ -0,0 +1,108 @@
+//! Sequential halving at the root, an allocation of a small fixed iteration budget across
+//! the root actions which spends it on the contenders instead of exploring with UCT.
+//!
+//! Every child of the root is expanded first. The budget is then split evenly into rounds,
+//! one per halving of the candidates, and every round splits its share evenly among the
+//! remaining candidates, searching each one's subtree with UCT. After a round, the better
+//! half of the candidates by mean score goes through to the next, until a single one is left.
+
+use crate::arena::{NodeId, NodeIndex};
+use crate::game_state_trait::GameState;
+use crate::mcts::MCTSTree;
+use alloc::vec::Vec;
+
+
+/// Sequential halving search driver.
+impl<Action, GameStateObj, Data> MCTSTree<Action, GameStateObj, Data>
+where
+    GameStateObj: GameState<Action> + Clone
+{
+    /// Mean score of `node` for the player choosing it, 0 before any simulation.
+    fn mean_score(&self, node: NodeIndex) -> f64 {
+        let stats = &self.arena.stats[node as usize];
+        return if stats.sims == 0 { 0.0 } else { stats.score / stats.sims as f64 };
+    }
+
+    /// Searches `iterations` iterations from the root, allocated across the children of the
+    /// root by sequential halving. Internal nodes are selected with UCT as usual.
+    ///
+    /// Iterations left over by rounding go to the last candidate, which then has at least as
+    /// many visits as any other child of the root, unless the budget ran out during the
+    /// expansion of the root. Budgets and the other options of the tree apply as usual, except
+    /// for early termination.
+    ///
+    /// # Returns
+    /// The child of the root chosen by the last round, None if the root has no children.
+    pub fn search_sequential_halving(&mut self, iterations: u64) -> Option<NodeId> {
+        let mut searched = 0;
+        while self.arena.has_untried_actions(0) && searched < iterations {
+            let step = self.search_from_with_hooks_at(0, 1, &mut ());
+            if step == 0 {
+                break;
+            }
+            searched += step;
+        }
+
+        let mut candidates: Vec<NodeIndex> = self.arena.expanded(0).collect();
+        if candidates.is_empty() {
+            self.search_with_hooks(iterations - searched, &mut ());
+            return None;
+        }
+        let mut rounds = candidates.len().next_power_of_two().trailing_zeros() as u64;
+        while candidates.len() > 1 {
+            let per_candidate = ((iterations - searched) / rounds / candidates.len() as u64).max(1);
+            for candidate in &candidates {
+                let available = per_candidate.min(iterations - searched);
+                searched += self.search_from_with_hooks_at(*candidate, available, &mut ());
+            }
+
+            // Stable sorting keeps the first expanded of tied candidates ahead.
+            candidates.sort_by(|a, b| self.mean_score(*b).total_cmp(&self.mean_score(*a)));
+            candidates.truncate(candidates.len().div_ceil(2));
+            rounds = rounds.saturating_sub(1).max(1);
+        }
+
+        let winner = candidates[0];
+        self.search_from_with_hooks_at(winner, iterations - searched, &mut ());
+        return Some(self.arena.handle(winner));
+    }
+}
+
+
+/// Defines unit tests for sequential halving.
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tictactoe_env::TicTacToeState;
+
+    /// Test if sequential halving searches its whole budget, and concentrates it on the winner.
+    #[test]
+    fn test_sequential_halving() {
+        let mut tree = MCTSTree::<u8, TicTacToeState>::with_capacity(2000, Some(1), ".........".to_string(), 9);
+        let winner = tree.search_sequential_halving(1000).unwrap();
+        assert_eq!(tree.arena.stats[0].sims, 1000);
+        assert_eq!(tree.arena.expanded_count(0), 9);
+        let winner_sims = tree.node_stats(winner).unwrap().sims;
+        assert!(tree.arena.expanded(0).all(|child| tree.arena.stats[child as usize].sims <= winner_sims));
+        assert!(tree.arena.expanded(0).all(|child| tree.arena.stats[child as usize].sims > 0));
+
+        // The winning move is found with a small budget.
+        let mut tree = MCTSTree::<u8, TicTacToeState>::with_capacity(500, Some(1), "XX.OO....".to_string(), 9);
+        let winner = tree.search_sequential_halving(200).unwrap();
+        assert_eq!(tree.action(winner), Ok(Some(&2)));
+        assert_eq!(tree.arena.stats[0].sims, 200);
+    }
+
+    /// Test if budgets smaller than the number of root actions are spent expanding the root.
+    #[test]
+    fn test_sequential_halving_small_budget() {
+        let mut tree = MCTSTree::<u8, TicTacToeState>::with_capacity(100, Some(1), ".........".to_string(), 9);
+        tree.search_sequential_halving(5);
+        assert_eq!(tree.arena.stats[0].sims, 5);
+        assert_eq!(tree.arena.expanded_count(0), 5);
+
+        // A terminal root has no children to choose from.
+        let mut tree = MCTSTree::<u8, TicTacToeState>::with_capacity(100, Some(1), "XXXOO....".to_string(), 9);
+        assert_eq!(tree.search_sequential_halving(10), None);
+    }
+}
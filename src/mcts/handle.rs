@@ -0,0 +1,234 @@
+//! Searches running on a background thread, controlled through a `SearchHandle`.
+//!
+//! The search can be paused and resumed between iterations, so that a host application can
+//! reprioritize the CPU without losing the state of the search, and stopped to take the tree back.
+
+use crate::anytime::{RootBest, SharedBest};
+use crate::arena::NodeId;
+use crate::game_state_trait::GameState;
+use crate::hooks::{SearchControl, SearchHooks};
+use crate::mcts::MCTSTree;
+use std::sync::{Arc, Condvar, Mutex, MutexGuard};
+use std::thread::JoinHandle;
+
+
+/// Number of iterations between refreshes of the best action published by the search.
+const PUBLISH_INTERVAL: u64 = 64;
+
+
+/// Requests of the handle and state of the search thread.
+#[derive(Debug, Default)]
+struct Signals {
+    /// The handle asked the search to pause.
+    paused: bool,
+
+    /// The search is suspended between iterations.
+    parked: bool,
+
+    /// The handle asked the search to stop.
+    stopped: bool,
+
+    /// The search returned.
+    finished: bool,
+}
+
+
+/// Signals shared by the handle and the search thread, with a condition variable notified on every change.
+#[derive(Debug, Default)]
+struct SharedSignals {
+    signals: Mutex<Signals>,
+    changed: Condvar,
+}
+
+impl SharedSignals {
+    fn lock(&self) -> MutexGuard<'_, Signals> {
+        return self.signals.lock().expect("poisoned signals");
+    }
+
+    /// Applies `update` to the signals and notifies the other side.
+    fn update(&self, update: impl FnOnce(&mut Signals)) {
+        update(&mut self.lock());
+        self.changed.notify_all();
+    }
+}
+
+
+/// Hooks of the search thread, publishing the best action and obeying the signals of the handle.
+struct HandleHooks<Action> {
+    signals: Arc<SharedSignals>,
+    best: SharedBest<Action>,
+}
+
+impl<Action, GameStateObj, Data> SearchHooks<Action, GameStateObj, Data> for HandleHooks<Action>
+where
+    Action: Clone,
+    GameStateObj: GameState<Action> + Clone
+{
+    fn on_iteration(&mut self, tree: &MCTSTree<Action, GameStateObj, Data>, iteration: u64) -> SearchControl {
+        self.best.on_iteration(tree, iteration);
+
+        let mut signals = self.signals.lock();
+        if signals.paused && !signals.stopped {
+            // Publish the exact state of the tree before suspending.
+            self.best.publish(tree);
+            signals.parked = true;
+            self.signals.changed.notify_all();
+            while signals.paused && !signals.stopped {
+                signals = self.signals.changed.wait(signals).expect("poisoned signals");
+            }
+            signals.parked = false;
+        }
+        return if signals.stopped { SearchControl::Stop } else { SearchControl::Continue };
+    }
+
+    fn on_new_best_move(&mut self, tree: &MCTSTree<Action, GameStateObj, Data>, best: NodeId) {
+        SearchHooks::<Action, GameStateObj, Data>::on_new_best_move(&mut self.best, tree, best);
+    }
+}
+
+
+/// Handle of a search running on a background thread, which owns the tree until the search ends.
+pub struct SearchHandle<Action, GameStateObj, Data = ()>
+where
+    GameStateObj: GameState<Action> + Clone
+{
+    signals: Arc<SharedSignals>,
+    best: SharedBest<Action>,
+    thread: JoinHandle<MCTSTree<Action, GameStateObj, Data>>,
+}
+
+
+impl<Action, GameStateObj, Data> SearchHandle<Action, GameStateObj, Data>
+where
+    Action: Clone,
+    GameStateObj: GameState<Action> + Clone
+{
+    /// Suspends the search between two iterations, returning once it is suspended or has ended.
+    pub fn pause(&self) {
+        let mut signals = self.signals.lock();
+        signals.paused = true;
+        while !signals.parked && !signals.finished {
+            signals = self.signals.changed.wait(signals).expect("poisoned signals");
+        }
+    }
+
+    /// Resumes a paused search.
+    pub fn resume(&self) {
+        self.signals.update(|signals| signals.paused = false);
+    }
+
+    /// True if the search is suspended.
+    pub fn is_paused(&self) -> bool {
+        return self.signals.lock().parked;
+    }
+
+    /// True if the search ended, by running all its iterations or being stopped.
+    pub fn is_finished(&self) -> bool {
+        return self.signals.lock().finished;
+    }
+
+    /// The best root action published by the search, see `SharedBest`. Exact while the search is paused.
+    pub fn current_best(&self) -> Option<RootBest<Action>> {
+        return self.best.get();
+    }
+
+    /// Stops the search after its current iteration, paused or not, and takes the tree back.
+    pub fn stop(self) -> MCTSTree<Action, GameStateObj, Data> {
+        self.signals.update(|signals| signals.stopped = true);
+        return self.join();
+    }
+
+    /// Waits for the search to run all its iterations, and takes the tree back.
+    ///
+    /// # Panics
+    /// If the search thread panicked, or if the search is paused, which would wait forever.
+    pub fn join(self) -> MCTSTree<Action, GameStateObj, Data> {
+        let signals = self.signals.lock();
+        assert!(!signals.paused || signals.stopped || signals.finished, "joined a paused search");
+        drop(signals);
+        return self.thread.join().expect("search thread panicked");
+    }
+}
+
+
+/// Background search.
+impl<Action, GameStateObj, Data> MCTSTree<Action, GameStateObj, Data>
+where
+    Action: Clone + Send + 'static,
+    GameStateObj: GameState<Action> + Clone + Send + 'static,
+    Data: Send + 'static
+{
+    /// Moves the tree to a background thread searching `iterations` iterations like
+    /// `search_with_hooks`, returning a handle to control the search.
+    ///
+    /// Hooks are not supported, as the handle holds the only hooks of the search. Budgets,
+    /// early termination and the other options of the tree apply as usual.
+    pub fn spawn_search(self, iterations: u64) -> SearchHandle<Action, GameStateObj, Data> {
+        let signals = Arc::new(SharedSignals::default());
+        let best = SharedBest::new(PUBLISH_INTERVAL);
+        let mut hooks = HandleHooks { signals: signals.clone(), best: best.clone() };
+        let mut tree = self;
+        let thread = std::thread::spawn(move || {
+            tree.search_with_hooks(iterations, &mut hooks);
+            hooks.best.publish(&tree);
+            hooks.signals.update(|signals| signals.finished = true);
+            return tree;
+        });
+        return SearchHandle { signals, best, thread };
+    }
+}
+
+
+/// Defines unit tests for background searches.
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tictactoe_env::TicTacToeState;
+    use std::time::Duration;
+
+    /// Test if paused searches keep their tree intact, and continue once resumed.
+    #[test]
+    fn test_pause_resume() {
+        let tree = MCTSTree::<u8, TicTacToeState>::with_capacity(100000, Some(1), ".........".to_string(), 9);
+        let handle = tree.spawn_search(u64::MAX);
+        std::thread::sleep(Duration::from_millis(10));
+        handle.pause();
+        assert!(handle.is_paused());
+
+        // No iterations are searched while paused.
+        let paused = handle.current_best().unwrap();
+        std::thread::sleep(Duration::from_millis(20));
+        assert_eq!(handle.current_best().unwrap(), paused);
+
+        handle.resume();
+        while handle.current_best().unwrap().root_visits == paused.root_visits {
+            std::thread::yield_now();
+        }
+        assert!(!handle.is_paused());
+
+        // Stopping a paused search takes the tree back with every iteration searched.
+        handle.pause();
+        let tree = handle.stop();
+        assert!(tree.arena.stats[0].sims > paused.root_visits);
+        assert_eq!(tree.counters.iterations, tree.arena.stats[0].sims);
+    }
+
+    /// Test if searches run all their iterations when joined.
+    #[test]
+    fn test_join() {
+        let tree = MCTSTree::<u8, TicTacToeState>::with_capacity(1000, Some(1), ".........".to_string(), 9);
+        let handle = tree.spawn_search(500);
+        let tree = handle.join();
+        assert_eq!(tree.counters.iterations, 500);
+
+        // Pausing a finished search returns immediately.
+        let handle = tree.spawn_search(10);
+        while !handle.is_finished() {
+            std::thread::yield_now();
+        }
+        handle.pause();
+        assert!(!handle.is_paused());
+        assert_eq!(handle.current_best().unwrap().root_visits, 510);
+        assert_eq!(handle.stop().counters.iterations, 510);
+    }
+}
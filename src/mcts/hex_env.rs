@@ -0,0 +1,428 @@
+use crate::game_state_trait::{GameResult, GameState};
+use crate::serialize::{decode_from_str, Decode, DecodeError, Encode};
+use alloc::string::{String, ToString};
+use alloc::vec;
+use alloc::vec::Vec;
+use core::fmt;
+
+
+/// Error produced when a string is not a valid Hex position.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HexParseError(pub String);
+
+impl fmt::Display for HexParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        return write!(f, "invalid hex position: {}", self.0);
+    }
+}
+
+impl core::error::Error for HexParseError {}
+
+
+/// Holds the state of a Hex board of any size.
+///
+/// Cells are indexed row by row from the top left corner, and actions are the index of the
+/// cell to claim. The first player, X, connects the top and bottom edges, while the second
+/// player, O, connects the left and right edges. Every cell touches the cells to its left and
+/// right, the two above it towards the right and the two below it towards the left.
+///
+/// Groups of connected stones are tracked with a union-find structure, which includes one extra
+/// node for each edge, so the game ends as soon as the two edges of a player share a group.
+/// A full board always has exactly one winner, so the game has no draws.
+#[derive(Debug, Clone)]
+pub struct HexState {
+    /// Number of cells along each edge.
+    pub size: usize,
+
+    /// Stones of every cell, with true for X and false for O.
+    pub cells: Vec<Option<bool>>,
+
+    /// True if X is the side to move.
+    pub x_to_move: bool,
+
+    /// Union-find parent of every cell, followed by the top, bottom, left and right edges.
+    parents: Vec<u16>,
+}
+
+
+impl HexState {
+    /// Creates an empty board with `size` cells along each edge.
+    ///
+    /// # Panics
+    /// If `size` is 0 or larger than 255.
+    pub fn new(size: usize) -> Self {
+        assert!(size > 0 && size < 256, "hex boards have between 1 and 255 cells along each edge");
+        let node_count = size * size + 4;
+        return HexState {
+            size,
+            cells: vec![None; size * size],
+            x_to_move: true,
+            parents: (0..node_count as u16).collect(),
+        };
+    }
+
+    /// Union-find nodes of the top, bottom, left and right edges.
+    fn edges(&self) -> [usize; 4] {
+        let first = self.size * self.size;
+        return [first, first + 1, first + 2, first + 3];
+    }
+
+    /// Finds the representative node of the group of `node`, halving the path on the way.
+    fn find(&mut self, mut node: usize) -> usize {
+        while self.parents[node] as usize != node {
+            let grandparent = self.parents[self.parents[node] as usize];
+            self.parents[node] = grandparent;
+            node = grandparent as usize;
+        }
+        return node;
+    }
+
+    /// Finds the representative node of the group of `node` without modifying the structure.
+    fn find_root(&self, mut node: usize) -> usize {
+        while self.parents[node] as usize != node {
+            node = self.parents[node] as usize;
+        }
+        return node;
+    }
+
+    /// Merges the groups of `first` and `second`.
+    fn union(&mut self, first: usize, second: usize) {
+        let (first, second) = (self.find(first), self.find(second));
+        if first != second {
+            // Edges stay representatives, which keeps the winner check to two lookups.
+            let (root, child) = if first > second { (first, second) } else { (second, first) };
+            self.parents[child] = root as u16;
+        }
+    }
+
+    /// Cells adjacent to `cell`.
+    pub fn neighbours(&self, cell: usize) -> Vec<usize> {
+        let (row, column) = ((cell / self.size) as i64, (cell % self.size) as i64);
+        let size = self.size as i64;
+        return [(-1, 0), (-1, 1), (0, -1), (0, 1), (1, -1), (1, 0)]
+            .iter()
+            .map(|(row_offset, column_offset)| (row + row_offset, column + column_offset))
+            .filter(|(row, column)| *row >= 0 && *row < size && *column >= 0 && *column < size)
+            .map(|(row, column)| (row * size + column) as usize)
+            .collect();
+    }
+
+    /// Determines the player who has connected their edges, if any.
+    ///
+    /// # Returns
+    /// True if X has won, false if O has won, or None if neither has connected their edges.
+    pub fn winner(&self) -> Option<bool> {
+        let [top, bottom, left, right] = self.edges();
+        if self.find_root(top) == self.find_root(bottom) {
+            return Some(true);
+        }
+        if self.find_root(left) == self.find_root(right) {
+            return Some(false);
+        }
+        return None;
+    }
+}
+
+
+/// States are equal when their stones are, however their groups were merged.
+impl PartialEq for HexState {
+    fn eq(&self, other: &Self) -> bool {
+        return self.size == other.size && self.cells == other.cells && self.x_to_move == other.x_to_move;
+    }
+}
+
+impl Eq for HexState {}
+
+
+/// Draws the board as one row of cells per line from the top, with `X`, `O` and `.` for empty
+/// cells, which `GameState::from_str` parses back.
+impl fmt::Display for HexState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, cell) in self.cells.iter().enumerate() {
+            let mark = match cell {
+                Some(true) => 'X',
+                Some(false) => 'O',
+                None => '.',
+            };
+            write!(f, "{}", mark)?;
+            if i % self.size == self.size - 1 && i + 1 < self.cells.len() {
+                writeln!(f)?;
+            }
+        }
+        return Ok(());
+    }
+}
+
+
+/// Allows the MCTS engine to build HexState trees.
+impl GameState<u16> for HexState {
+    type ParseError = HexParseError;
+
+    fn from_str(game_state: String) -> Self {
+        return Self::try_from_str(&game_state).expect("invalid hex position");
+    }
+
+    /// Parses either the board size, giving an empty board such as `11`,
+    /// or the cells of a square board as drawn by `Display`, ignoring whitespace.
+    fn try_from_str(game_state: &str) -> Result<Self, HexParseError> {
+        let invalid = || HexParseError(game_state.to_string());
+        if let Ok(size) = game_state.trim().parse::<usize>() {
+            if size == 0 || size >= 256 {
+                return Err(invalid());
+            }
+            return Ok(HexState::new(size));
+        }
+
+        let marks: Vec<char> = game_state.chars().filter(|mark| !mark.is_whitespace()).collect();
+        let size = (1..256).find(|size| size * size >= marks.len()).ok_or_else(invalid)?;
+        if size * size != marks.len() {
+            return Err(invalid());
+        }
+
+        // Stones are placed in turn order, so X always has at most one stone more than O.
+        let x_cells: Vec<u16> = (0..marks.len() as u16).filter(|cell| marks[*cell as usize].eq_ignore_ascii_case(&'X')).collect();
+        let o_cells: Vec<u16> = (0..marks.len() as u16).filter(|cell| marks[*cell as usize].eq_ignore_ascii_case(&'O')).collect();
+        let valid_marks = marks.iter().all(|mark| matches!(mark.to_ascii_uppercase(), 'X' | 'O' | '.'));
+        if !valid_marks || (x_cells.len() != o_cells.len() && x_cells.len() != o_cells.len() + 1) {
+            return Err(invalid());
+        }
+
+        let mut state = HexState::new(size);
+        for (i, x_cell) in x_cells.iter().enumerate() {
+            state.apply_action_mut(x_cell);
+            if let Some(o_cell) = o_cells.get(i) {
+                state.apply_action_mut(o_cell);
+            }
+        }
+        return Ok(state);
+    }
+
+    fn apply_action(&self, action: &u16) -> Self {
+        let mut new_state = self.clone();
+        new_state.apply_action_mut(action);
+        return new_state;
+    }
+
+    fn apply_action_mut(&mut self, action: &u16) {
+        let cell = *action as usize;
+        let player = self.x_to_move;
+        self.cells[cell] = Some(player);
+        self.x_to_move = !player;
+
+        // Stones join the groups of adjacent stones of the same player, and of the edges they touch.
+        for neighbour in self.neighbours(cell) {
+            if self.cells[neighbour] == Some(player) {
+                self.union(cell, neighbour);
+            }
+        }
+        let [top, bottom, left, right] = self.edges();
+        let (row, column) = (cell / self.size, cell % self.size);
+        if player && row == 0 {
+            self.union(cell, top);
+        }
+        if player && row == self.size - 1 {
+            self.union(cell, bottom);
+        }
+        if !player && column == 0 {
+            self.union(cell, left);
+        }
+        if !player && column == self.size - 1 {
+            self.union(cell, right);
+        }
+    }
+
+    fn status_with_moves_left(&self) -> bool {
+        return self.winner().is_none();
+    }
+
+    fn result(&self) -> GameResult {
+        match self.winner() {
+            Some(true) => return GameResult::FirstPlayerWin,
+            _ => return GameResult::SecondPlayerWin,
+        }
+    }
+
+    fn generate_legal_actions(&self) -> Vec<u16> {
+        let mut actions = Vec::with_capacity(self.cells.len());
+        self.legal_actions_into(&mut actions);
+        return actions;
+    }
+
+    fn legal_actions_into(&self, actions: &mut Vec<u16>) {
+        actions.clear();
+        // Connecting the edges ends the game, even with empty cells left.
+        if self.winner().is_some() {
+            return;
+        }
+        actions.extend((0..self.cells.len() as u16).filter(|cell| self.cells[*cell as usize].is_none()));
+    }
+
+    fn state_hash(&self) -> Option<u64> {
+        // Cells are folded in base 3, which is exact for boards of up to 40 cells.
+        let hash = self.cells.iter().fold(self.size as u64, |hash: u64, cell| {
+            return hash.wrapping_mul(3).wrapping_add(match cell { None => 0, Some(true) => 1, Some(false) => 2 });
+        });
+        return Some(hash);
+    }
+
+    fn rollout_move_cap(&self) -> Option<u32> {
+        // Games end once the board is full, if not before.
+        return None;
+    }
+
+    fn side_to_move(&self) -> bool {
+        return self.x_to_move;
+    }
+}
+
+
+/// Encodes the state through its string encoding, which holds every part of it.
+impl Encode for HexState {
+    fn encode(&self, out: &mut Vec<u8>) {
+        self.to_string().encode(out);
+    }
+}
+
+impl Decode for HexState {
+    fn decode(input: &mut &[u8]) -> Result<Self, DecodeError> {
+        return decode_from_str(input);
+    }
+}
+
+
+/// Defines unit tests for the GameState implementation of HexState.
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mcts::MCTSTree;
+    use std::collections::HashMap;
+    use crate::rng::{Rng, SeedableRng, Xorshift128};
+
+    /// Determines whether `player` connects their edges with a flood fill, independently of the union-find.
+    fn connected(state: &HexState, player: bool) -> bool {
+        let size = state.size;
+        let starts = (0..size).map(|i| if player { i } else { i * size });
+        let mut stack: Vec<usize> = starts.filter(|cell| state.cells[*cell] == Some(player)).collect();
+        let mut seen = vec![false; size * size];
+        while let Some(cell) = stack.pop() {
+            if seen[cell] {
+                continue;
+            }
+            seen[cell] = true;
+            if (player && cell / size == size - 1) || (!player && cell % size == size - 1) {
+                return true;
+            }
+            stack.extend(state.neighbours(cell).into_iter().filter(|neighbour| state.cells[*neighbour] == Some(player)));
+        }
+        return false;
+    }
+
+    /// Solves `state` with negamax, memoized by state hash.
+    ///
+    /// # Returns
+    /// True if the side to move wins with perfect play.
+    fn solve(state: &HexState, memo: &mut HashMap<u64, bool>) -> bool {
+        let hash = state.state_hash().unwrap();
+        if let Some(value) = memo.get(&hash) {
+            return *value;
+        }
+        let actions = state.generate_legal_actions();
+        let value = actions.iter().any(|action| !solve(&state.apply_action(action), memo));
+        memo.insert(hash, value);
+        return value;
+    }
+
+    /// Chooses the move of the engine after `iterations` iterations of search from `state`.
+    fn engine_move(state: &HexState, iterations: u32, seed: u64) -> u16 {
+        let mut tree = MCTSTree::<u16, HexState>::with_capacity(iterations as usize + 1, Some(seed), state.to_string(), state.cells.len());
+        tree.search(iterations as u64);
+        let best = tree.get_max_uct_child_at(0, Some(0.0)).expect("no legal moves");
+        return tree.arena.actions[best as usize].expect("no action");
+    }
+
+    /// Test if random games always end with exactly one winner, matching a flood fill of the board.
+    #[test]
+    fn test_random_games() {
+        let seed: &[_] = &[1, 0];
+        let mut random_generator: Xorshift128 = SeedableRng::from_seed(seed);
+        for size in 1..=9 {
+            for _game in 0..50 {
+                let mut state = HexState::new(size);
+                loop {
+                    assert_eq!(state.winner() == Some(true), connected(&state, true));
+                    assert_eq!(state.winner() == Some(false), connected(&state, false));
+                    let actions = state.generate_legal_actions();
+                    if actions.len() == 0 {
+                        break;
+                    }
+                    state.apply_action_mut(&actions[random_generator.gen_range(0, actions.len())]);
+                }
+                assert!(state.winner().is_some());
+                assert!(!state.status_with_moves_left());
+            }
+        }
+    }
+
+    /// Test if positions are parsed with the side to move, and invalid ones are rejected.
+    #[test]
+    fn test_try_from_str() {
+        let empty = HexState::try_from_str("11").unwrap();
+        assert_eq!(empty.size, 11);
+        assert_eq!(empty.generate_legal_actions().len(), 121);
+
+        let state = HexState::try_from_str("X.. .O. ..X").unwrap();
+        assert_eq!(state.size, 3);
+        assert!(!state.side_to_move());
+        assert_eq!(state.to_string(), "X..\n.O.\n..X");
+        assert_eq!(HexState::from_str(state.to_string()), state);
+
+        assert!(HexState::try_from_str("0").is_err());
+        assert!(HexState::try_from_str("X.......").is_err());
+        assert!(HexState::try_from_str("XX..").is_err());
+        assert!(HexState::try_from_str("X..Z").is_err());
+    }
+
+    /// Test if connecting the edges ends the game for the right player.
+    #[test]
+    fn test_result() {
+        // X connects the top and bottom edges along the diagonal links.
+        let x_wins = HexState::from_str("..X .XO XO. ".to_string());
+        assert_eq!(x_wins.winner(), Some(true));
+        assert!(x_wins.generate_legal_actions().is_empty());
+        assert_eq!(x_wins.result(), GameResult::FirstPlayerWin);
+
+        // The other diagonal is not connected.
+        let not_connected = HexState::from_str("X.. .X. O.O".to_string());
+        assert_eq!(not_connected.winner(), None);
+        assert!(not_connected.status_with_moves_left());
+
+        let o_wins = HexState::from_str("XX. OOO X..".to_string());
+        assert_eq!(o_wins.result(), GameResult::SecondPlayerWin);
+    }
+
+    /// Test if the engine wins small boards from the start against a perfect opponent,
+    /// as the first player always has a winning strategy.
+    #[test]
+    fn test_engine_wins() {
+        let mut memo = HashMap::new();
+        for size in [2, 3] {
+            let mut state = HexState::new(size);
+            assert!(solve(&state, &mut memo));
+            while state.generate_legal_actions().len() > 0 {
+                let action = if state.side_to_move() {
+                    engine_move(&state, 5000, state.cells.len() as u64)
+                }
+                else {
+                    // The opponent plays the first move it does not lose to, if any.
+                    let actions = state.generate_legal_actions();
+                    *actions.iter().find(|action| !solve(&state.apply_action(action), &mut memo)).unwrap_or(&actions[0])
+                };
+                state.apply_action_mut(&action);
+                if !state.side_to_move() {
+                    assert!(!solve(&state, &mut memo), "engine lost its win in {}", state);
+                }
+            }
+            assert_eq!(state.result(), GameResult::FirstPlayerWin);
+        }
+    }
+}
@@ -0,0 +1,237 @@
+//! Hooks invoked by the search driver, so applications can stream progress, log principal
+//! variations or implement their own stopping rules without modifying the search.
+
+use crate::arena::{NodeId, NodeIndex};
+use crate::game_state_trait::GameState;
+use crate::mcts::MCTSTree;
+use crate::clock::Instant;
+
+
+/// Tells the search driver whether to keep searching.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SearchControl {
+    Continue,
+    Stop,
+}
+
+
+/// Callbacks of `MCTSTree::search_with_hooks`, each given read-only access to the tree.
+///
+/// Every callback has an empty default, so implementations only override the ones they need.
+/// `Data` is the type of the user data of the nodes, see `MCTSTree::node_data`.
+pub trait SearchHooks<Action, GameStateObj, Data = ()>
+where
+    GameStateObj: GameState<Action> + Clone
+{
+    /// Called after every iteration, once its result has been backpropagated.
+    ///
+    /// # Arguments
+    /// * `iteration` : Number of iterations searched by this call of the driver, including this one.
+    ///
+    /// # Returns
+    /// `SearchControl::Stop` to end the search after this iteration.
+    fn on_iteration(&mut self, _tree: &MCTSTree<Action, GameStateObj, Data>, _iteration: u64) -> SearchControl {
+        return SearchControl::Continue;
+    }
+
+    /// Called when an iteration expands a new node, before it is simulated.
+    fn on_expansion(&mut self, _tree: &MCTSTree<Action, GameStateObj, Data>, _node: NodeId) {}
+
+    /// Called when the most visited child of the root changes, after the iteration which changed it.
+    fn on_new_best_move(&mut self, _tree: &MCTSTree<Action, GameStateObj, Data>, _best: NodeId) {}
+
+    /// Whether `on_new_best_move` should be invoked. The driver only looks for the most visited
+    /// child of the root after every iteration when this is true. Defaults to true.
+    fn tracks_best_move(&self) -> bool {
+        return true;
+    }
+}
+
+/// No hooks, used by `MCTSTree::search`.
+impl<Action, GameStateObj, Data> SearchHooks<Action, GameStateObj, Data> for ()
+where
+    GameStateObj: GameState<Action> + Clone
+{
+    fn tracks_best_move(&self) -> bool {
+        return false;
+    }
+}
+
+
+/// Search driver with hooks.
+impl<Action, GameStateObj, Data> MCTSTree<Action, GameStateObj, Data>
+where
+    GameStateObj: GameState<Action> + Clone
+{
+    /// Runs up to `iterations` iterations of search from the root like `search`, invoking `hooks`.
+    ///
+    /// # Returns
+    /// The number of iterations searched, fewer than `iterations` if a hook stopped the search,
+    /// if the best move was decided early with `early_termination`, or if the `budget` of the tree
+    /// was exhausted.
+    pub fn search_with_hooks<H>(&mut self, iterations: u64, hooks: &mut H) -> u64
+    where
+        H: SearchHooks<Action, GameStateObj, Data> + ?Sized
+    {
+        return self.search_from_with_hooks_at(0, iterations, hooks);
+    }
+
+    /// Runs up to `iterations` iterations of search like `search_with_hooks`, with every
+    /// iteration selecting a leaf in the subtree of `root` instead of the whole tree.
+    ///
+    /// Results are still backpropagated up to the root of the tree. `on_new_best_move` is
+    /// invoked when the most visited child of `root` changes.
+    ///
+    /// # Returns
+    /// The number of iterations searched, fewer than `iterations` if a hook stopped the search.
+    ///
+    /// # Panics
+    /// If `root` is stale.
+    pub fn search_from_with_hooks<H>(&mut self, root: NodeId, iterations: u64, hooks: &mut H) -> u64
+    where
+        H: SearchHooks<Action, GameStateObj, Data> + ?Sized
+    {
+        return self.search_from_with_hooks_at(self.index_of(root), iterations, hooks);
+    }
+
+    /// `search_from_with_hooks` by arena index.
+    pub(crate) fn search_from_with_hooks_at<H>(&mut self, root: NodeIndex, iterations: u64, hooks: &mut H) -> u64
+    where
+        H: SearchHooks<Action, GameStateObj, Data> + ?Sized
+    {
+        let start = Instant::now();
+        let tracks_best_move = hooks.tracks_best_move();
+        let mut best = if tracks_best_move { self.most_visited_child(root) } else { None };
+        let mut searched = 0;
+        while searched < iterations {
+            if self.budget_exhausted() {
+                break;
+            }
+
+            // With the trace feature every phase is timed on its own, leaving the time spent in
+            // hooks out of the phases. Without it the clock is only read around the whole search.
+            #[cfg(feature = "trace")]
+            let select_start = Instant::now();
+            let leaf = self.select_at(root, None);
+            #[cfg(feature = "trace")]
+            let expand_start = Instant::now();
+            let expanded = self.expand_at(leaf);
+            #[cfg(feature = "trace")]
+            let expand_end = Instant::now();
+            if expanded != leaf {
+                hooks.on_expansion(self, self.arena.handle(expanded));
+            }
+            #[cfg(feature = "trace")]
+            let simulate_start = Instant::now();
+            let result = self.simulate_at(expanded);
+            #[cfg(feature = "trace")]
+            let backpropagate_start = Instant::now();
+            self.backpropagate_at(expanded, result);
+            searched += 1;
+
+            #[cfg(feature = "trace")]
+            {
+                let backpropagate_end = Instant::now();
+                let phase_times = &mut self.counters.phase_times;
+                phase_times.select += expand_start - select_start;
+                phase_times.expand += expand_end - expand_start;
+                phase_times.simulate += backpropagate_start - simulate_start;
+                phase_times.backpropagate += backpropagate_end - backpropagate_start;
+            }
+
+            if tracks_best_move {
+                let new_best = self.most_visited_child(root);
+                if new_best != best {
+                    best = new_best;
+                    if let Some(best) = best {
+                        hooks.on_new_best_move(self, self.arena.handle(best));
+                    }
+                }
+            }
+            if hooks.on_iteration(self, searched) == SearchControl::Stop {
+                break;
+            }
+            if self.searcher.early_termination && self.best_move_decided_at(root, iterations - searched) {
+                break;
+            }
+        }
+        self.counters.search_time += start.elapsed();
+        return searched;
+    }
+
+    /// The most visited expanded child of `node`, None if it has no visited children.
+    /// Ties go to the first expanded child.
+    pub(crate) fn most_visited_child(&self, node: NodeIndex) -> Option<NodeIndex> {
+        let mut best: Option<(NodeIndex, u64)> = None;
+        for child in self.arena.expanded(node) {
+            let sims = self.arena.stats[child as usize].sims;
+            if sims > 0 && best.is_none_or(|(_, best_sims)| sims > best_sims) {
+                best = Some((child, sims));
+            }
+        }
+        return best.map(|(child, _)| child);
+    }
+}
+
+
+/// Defines unit tests for search hooks.
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tictactoe_env::TicTacToeState;
+
+    /// Records every callback, stopping once the root has been visited `stop_at` times.
+    struct Recorder {
+        iterations: u64,
+        expansions: Vec<NodeId>,
+        best_moves: Vec<NodeId>,
+        stop_at: u64,
+        tracks_best_move: bool,
+    }
+
+    impl SearchHooks<u8, TicTacToeState> for Recorder {
+        fn on_iteration(&mut self, tree: &MCTSTree<u8, TicTacToeState>, iteration: u64) -> SearchControl {
+            self.iterations = iteration;
+            return if tree.arena.stats[0].sims >= self.stop_at { SearchControl::Stop } else { SearchControl::Continue };
+        }
+
+        fn on_expansion(&mut self, tree: &MCTSTree<u8, TicTacToeState>, node: NodeId) {
+            assert_eq!(tree.node_stats(node).unwrap().sims, 0);
+            self.expansions.push(node);
+        }
+
+        fn on_new_best_move(&mut self, tree: &MCTSTree<u8, TicTacToeState>, best: NodeId) {
+            assert_eq!(tree.parent(best), Ok(Some(tree.root())));
+            self.best_moves.push(best);
+        }
+
+        fn tracks_best_move(&self) -> bool {
+            return self.tracks_best_move;
+        }
+    }
+
+    /// Test if hooks are invoked and can stop the search.
+    #[test]
+    fn test_hooks() {
+        let mut tree = MCTSTree::<u8, TicTacToeState>::with_capacity(1000, Some(1), ".........".to_string(), 9);
+        let mut recorder = Recorder { iterations: 0, expansions: Vec::new(), best_moves: Vec::new(), stop_at: 300, tracks_best_move: true };
+        let searched = tree.search_with_hooks(1000, &mut recorder);
+        assert_eq!(searched, 300);
+        assert_eq!(recorder.iterations, 300);
+        assert_eq!(tree.counters.iterations, 300);
+
+        // Every node but the root was expanded by an iteration.
+        assert_eq!(recorder.expansions.len(), tree.arena.len() - 1);
+        assert_eq!(recorder.best_moves.first(), tree.children(tree.root()).next().map(|(child, _, _)| child).as_ref());
+        assert_eq!(recorder.best_moves.last(), tree.most_visited_child(0).map(|best| tree.node_id(best)).as_ref());
+        assert!(recorder.best_moves.windows(2).all(|pair| pair[0] != pair[1]));
+
+        // Searches without hooks run every iteration.
+        assert_eq!(tree.search_with_hooks(50, &mut ()), 50);
+
+        // Hooks not tracking the best move are never told about it.
+        let mut recorder = Recorder { iterations: 0, expansions: Vec::new(), best_moves: Vec::new(), stop_at: 1000, tracks_best_move: false };
+        assert_eq!(tree.search_with_hooks(50, &mut recorder), 50);
+        assert!(recorder.best_moves.is_empty());
+    }
+}
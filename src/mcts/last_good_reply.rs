@@ -0,0 +1,138 @@
+//! Last-Good-Reply (LGR) rollouts, remembering the last successful reply to every move.
+//!
+//! After every simulation, each move played in reply to another by the side which went on to
+//! win is stored as the good reply to that move, for that side. Rollouts play the stored reply
+//! to the previous move whenever it is legal, and otherwise fall back to a base policy. With
+//! forgetting (LGRF), replies played by the losing side are removed again, so replies which
+//! stopped working are not repeated. Moves are identified by `GameState::action_code`, which
+//! makes the policy usable in any two player game without domain knowledge.
+
+use crate::game_state_trait::{GameResult, GameState};
+use crate::rng::SearchRng;
+use crate::rollout::{RandomRollout, RolloutPolicy, SimulationRewards};
+use alloc::boxed::Box;
+use alloc::collections::BTreeMap;
+use alloc::vec::Vec;
+
+
+/// Rollout policy playing the last good reply to the previous move, falling back to a base policy.
+///
+/// Set it as the `rollout_policy` of a tree, which teaches it the result of every simulation.
+/// The first move of a rollout has no previous move within the rollout, and is always chosen by
+/// the fallback. Rollouts panic if the game state does not support action codes.
+pub struct LastGoodReply<Action, GameStateObj> {
+    /// Chooses the moves without a good reply, and learns from every simulation if it learns itself.
+    pub fallback: Box<dyn RolloutPolicy<Action, GameStateObj> + Send>,
+
+    /// Whether replies of the losing side are forgotten, LGRF rather than LGR.
+    pub forgetting: bool,
+
+    /// Good reply of either side, keyed by whether the first player replies and the code of the move replied to.
+    pub replies: BTreeMap<(bool, u64), u64>,
+
+    /// Moves of the current rollout with whether the first player played them, until they are learned.
+    rollout_moves: Vec<(u64, bool)>,
+}
+
+impl<Action, GameStateObj> LastGoodReply<Action, GameStateObj> {
+    /// Creates a policy with forgetting, falling back to `fallback`.
+    pub fn new(fallback: Box<dyn RolloutPolicy<Action, GameStateObj> + Send>) -> Self {
+        return LastGoodReply { fallback, forgetting: true, replies: BTreeMap::new(), rollout_moves: Vec::new() };
+    }
+}
+
+/// Falls back to random moves.
+impl<Action, GameStateObj> Default for LastGoodReply<Action, GameStateObj> {
+    fn default() -> Self {
+        return Self::new(Box::new(RandomRollout));
+    }
+}
+
+impl<Action, GameStateObj> RolloutPolicy<Action, GameStateObj> for LastGoodReply<Action, GameStateObj>
+where
+    GameStateObj: GameState<Action>
+{
+    fn choose(&mut self, game_state: &GameStateObj, actions: &[Action], random_generator: &mut SearchRng) -> usize {
+        let code = |action: &Action| game_state.action_code(action).expect("game state without action codes");
+        let side = game_state.side_to_move();
+        let reply = self.rollout_moves.last().and_then(|(previous, _)| self.replies.get(&(side, *previous)));
+        let chosen = match reply.and_then(|reply| actions.iter().position(|action| code(action) == *reply)) {
+            Some(index) => index,
+            None => self.fallback.choose(game_state, actions, random_generator),
+        };
+        self.rollout_moves.push((code(&actions[chosen]), side));
+        return chosen;
+    }
+
+    fn cutoff(&mut self, game_state: &GameStateObj, depth: u32) -> Option<GameResult> {
+        return self.fallback.cutoff(game_state, depth);
+    }
+
+    fn learns(&self) -> bool {
+        return true;
+    }
+
+    fn learn(&mut self, tree_moves: &[(u64, bool)], rewards: SimulationRewards) {
+        let moves: Vec<(u64, bool)> = tree_moves.iter().chain(self.rollout_moves.iter()).copied().collect();
+        for pair in moves.windows(2) {
+            let (previous, _) = pair[0];
+            let (reply, side) = pair[1];
+            if rewards.of(side) > rewards.of(!side) {
+                self.replies.insert((side, previous), reply);
+            }
+            else if self.forgetting && self.replies.get(&(side, previous)) == Some(&reply) {
+                self.replies.remove(&(side, previous));
+            }
+        }
+        self.rollout_moves.clear();
+        if self.fallback.learns() {
+            self.fallback.learn(tree_moves, rewards);
+        }
+    }
+}
+
+
+/// Unit tests for Last-Good-Reply rollouts.
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mast::MastRollout;
+    use crate::mcts::MCTSTree;
+    use crate::rng::SeedableRng;
+    use crate::tictactoe_env::TicTacToeState;
+
+    const FIRST_WINS: SimulationRewards = SimulationRewards { first_player: 1.0, second_player: 0.0 };
+    const SECOND_WINS: SimulationRewards = SimulationRewards { first_player: 0.0, second_player: 1.0 };
+
+    /// Test if the winning side's replies are learned and played, and forgotten once they lose.
+    #[test]
+    fn test_last_good_reply() {
+        let mut policy = LastGoodReply::<u8, TicTacToeState>::default();
+        // X takes the center, coded 9, and O replies in the corner, coded 16.
+        policy.learn(&[(9, true), (16, false), (1, true)], SECOND_WINS);
+        assert_eq!(policy.replies.len(), 1);
+        assert_eq!(policy.replies[&(false, 9)], 16);
+
+        // The reply to the previous move of the rollout is played when legal.
+        let seed = 1;
+        let mut random_generator: SearchRng = SeedableRng::from_seed(seed);
+        let state = TicTacToeState::try_from_str("....X....").unwrap();
+        policy.rollout_moves.push((9, true));
+        assert_eq!(policy.choose(&state, &[0, 1, 2, 3, 5, 6, 7, 8], &mut random_generator), 7);
+        assert_eq!(policy.rollout_moves.last(), Some(&(16, false)));
+
+        policy.rollout_moves.clear();
+        policy.learn(&[(9, true), (16, false)], FIRST_WINS);
+        assert!(policy.replies.is_empty());
+    }
+
+    /// Test if trees search with the policy and its learning fallback, finding the winning move.
+    #[test]
+    fn test_search_with_last_good_reply() {
+        let mut tree = MCTSTree::<u8, TicTacToeState>::with_capacity(1000, Some(1), "XX.OO....".into(), 9);
+        tree.searcher.rollout_policy = Box::new(LastGoodReply::new(Box::new(MastRollout::default())));
+        tree.search(500);
+        let best = tree.most_visited_child(0).unwrap();
+        assert_eq!(tree.arena.actions[best as usize], Some(2));
+    }
+}
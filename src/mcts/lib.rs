@@ -19,7 +19,86 @@
 //! The monte carlo tree search structure and implementation provides utilities to
 //! perfom the 4 mcts stages on a given game representation. as well as unit tests 
 //! for each component.
+//!
+//! The core tree and search only need `core` and `alloc`. Disabling the default `std`
+//! feature builds the crate as `no_std`, without the modules relying on I/O, threads,
+//! clocks or the chess crate, for embedded devices and consoles. The chess environment
+//! is built with the default `chess` feature.
+
+#![cfg_attr(not(any(feature = "std", test)), no_std)]
+// The codebase deliberately uses explicit returns, length comparisons and unindented
+// argument lists in doc comments.
+#![allow(clippy::needless_return, clippy::len_zero, clippy::doc_lazy_continuation)]
+// `NodeIndex` is `usize` by default and `u32` under `compact`, so index casts are only
+// redundant without `compact`.
+#![cfg_attr(not(feature = "compact"), allow(clippy::unnecessary_cast))]
+
+extern crate alloc;
 
 pub mod game_state_trait;
+pub mod clock;
+pub mod rng;
+pub mod float;
+pub mod arena;
 pub mod mcts;
-pub mod chess_env;
\ No newline at end of file
+pub mod searcher;
+pub mod children;
+pub mod node;
+pub mod rollout;
+pub mod mast;
+pub mod last_good_reply;
+pub mod ngram;
+pub mod statistics;
+pub mod hooks;
+pub mod evaluator;
+#[cfg(feature = "neural")]
+pub mod neural;
+#[cfg(feature = "std")]
+pub mod progress;
+#[cfg(feature = "std")]
+pub mod anytime;
+#[cfg(feature = "std")]
+pub mod handle;
+#[cfg(feature = "std")]
+pub mod time_manager;
+pub mod ponder;
+pub mod termination;
+pub mod halving;
+pub mod open_loop;
+pub mod nested;
+pub mod nrpa;
+#[cfg(feature = "trace")]
+pub mod trace;
+#[cfg(feature = "chess")]
+pub mod chess_env;
+#[cfg(feature = "chess")]
+pub mod tablebase;
+#[cfg(feature = "chess")]
+pub mod pgn;
+pub mod tictactoe_env;
+pub mod connect4_env;
+pub mod hex_env;
+pub mod go_env;
+pub mod nim_env;
+pub mod game2048_env;
+pub mod serialize;
+pub mod export;
+pub mod report;
+#[cfg(feature = "std")]
+pub mod checkpoint;
+#[cfg(feature = "std")]
+pub mod selfplay;
+#[cfg(feature = "parquet")]
+pub mod parquet;
+pub mod merge;
+#[cfg(feature = "std")]
+pub mod store;
+#[cfg(feature = "std")]
+pub mod replay;
+pub mod diff;
+#[cfg(feature = "std")]
+pub mod bench;
+#[cfg(feature = "std")]
+pub mod service;
+#[cfg(feature = "std")]
+pub mod config;
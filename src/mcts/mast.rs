@@ -0,0 +1,160 @@
+//! Move-Average Sampling Technique (MAST), a rollout policy learning which moves are good
+//! independently of the position they are played in.
+//!
+//! Every simulation credits its reward to each move played in it, in the tree and in the
+//! rollout, to the side which played it. Rollouts then sample moves with Gibbs weights,
+//! `exp(average reward / temperature)`, so moves which did well anywhere in the search are
+//! tried more often. Moves are identified by `GameState::action_code`, which makes the policy
+//! usable in any game without domain knowledge.
+
+use crate::float::Float;
+use crate::game_state_trait::GameState;
+use crate::rng::SearchRng;
+use crate::rollout::{sample_weighted, RolloutPolicy, SimulationRewards};
+use alloc::collections::BTreeMap;
+use alloc::vec::Vec;
+
+
+/// Rewards credited to a move across the simulations it was played in.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct MoveStatistics {
+    /// Total reward of the side which played the move.
+    pub reward: f64,
+
+    /// Number of simulations the move was played in, counting every time it was played.
+    pub visits: u32,
+}
+
+impl MoveStatistics {
+    /// Average reward of the move.
+    pub fn mean(&self) -> f64 {
+        return if self.visits == 0 { 0.0 } else { self.reward / self.visits as f64 };
+    }
+}
+
+
+/// Rollout policy sampling moves by the Gibbs weights of their average rewards.
+///
+/// Set it as the `rollout_policy` of a tree, which teaches it the result of every simulation.
+/// Rollouts panic if the game state does not support action codes.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MastRollout {
+    /// Temperature of the Gibbs weights. Lower temperatures play the best moves more greedily.
+    /// Rewards are on the scale of the results of the game, so single player games with raw
+    /// scores need temperatures on the scale of their scores.
+    pub temperature: f64,
+
+    /// Average reward assumed for moves which were never played, 1 by default so that they are tried.
+    pub unseen_value: f64,
+
+    /// Statistics of every move played so far, by action code.
+    pub table: BTreeMap<u64, MoveStatistics>,
+
+    /// Moves of the current rollout with whether the first player played them, until they are learned.
+    rollout_moves: Vec<(u64, bool)>,
+}
+
+impl Default for MastRollout {
+    fn default() -> Self {
+        return MastRollout { temperature: 1.0, unseen_value: 1.0, table: BTreeMap::new(), rollout_moves: Vec::new() };
+    }
+}
+
+impl MastRollout {
+    /// Average reward of the move with `code`, `unseen_value` if it was never played.
+    pub fn value(&self, code: u64) -> f64 {
+        return self.table.get(&code).map_or(self.unseen_value, MoveStatistics::mean);
+    }
+}
+
+impl<Action, GameStateObj> RolloutPolicy<Action, GameStateObj> for MastRollout
+where
+    GameStateObj: GameState<Action>
+{
+    fn choose(&mut self, game_state: &GameStateObj, actions: &[Action], random_generator: &mut SearchRng) -> usize {
+        let codes: Vec<u64> = actions.iter()
+            .map(|action| game_state.action_code(action).expect("game state without action codes"))
+            .collect();
+        let values: Vec<f64> = codes.iter().map(|code| self.value(*code)).collect();
+
+        // Weights are taken relative to the best move, which keeps the exponentials in range.
+        let max = values.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+        let weights: Vec<f32> = values.iter()
+            .map(|value| Float::powf(core::f64::consts::E, (value - max) / self.temperature) as f32)
+            .collect();
+        let chosen = sample_weighted(&weights, random_generator);
+        self.rollout_moves.push((codes[chosen], game_state.side_to_move()));
+        return chosen;
+    }
+
+    fn learns(&self) -> bool {
+        return true;
+    }
+
+    fn learn(&mut self, tree_moves: &[(u64, bool)], rewards: SimulationRewards) {
+        for (code, first_player) in tree_moves.iter().chain(self.rollout_moves.iter()) {
+            let statistics = self.table.entry(*code).or_default();
+            statistics.reward += rewards.of(*first_player);
+            statistics.visits += 1;
+        }
+        self.rollout_moves.clear();
+    }
+}
+
+
+/// Unit tests for the MAST rollout policy.
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mcts::MCTSTree;
+    use crate::rng::SeedableRng;
+    use crate::tictactoe_env::TicTacToeState;
+    use alloc::boxed::Box;
+
+    /// Test if simulations credit the moves of the tree and the rollout to the side which played them.
+    #[test]
+    fn test_learn() {
+        let mut policy = MastRollout::default();
+        let state = TicTacToeState::new();
+        let seed = 1;
+        let mut random_generator: SearchRng = SeedableRng::from_seed(seed);
+        let chosen = policy.choose(&state, &[4u8], &mut random_generator);
+        assert_eq!(chosen, 0);
+
+        RolloutPolicy::<u8, TicTacToeState>::learn(&mut policy, &[(1, false)], SimulationRewards { first_player: 1.0, second_player: 0.0 });
+        let center = state.action_code(&4).unwrap();
+        assert_eq!(policy.table[&center], MoveStatistics { reward: 1.0, visits: 1 });
+        assert_eq!(policy.table[&1], MoveStatistics { reward: 0.0, visits: 1 });
+        assert_eq!(policy.value(99), 1.0);
+
+        // Rollout moves are forgotten once learned.
+        RolloutPolicy::<u8, TicTacToeState>::learn(&mut policy, &[], SimulationRewards { first_player: 0.0, second_player: 1.0 });
+        assert_eq!(policy.table[&center].visits, 1);
+    }
+
+    /// Test if moves with higher average rewards are sampled more often.
+    #[test]
+    fn test_gibbs_sampling() {
+        let mut policy = MastRollout { temperature: 0.1, ..Default::default() };
+        let state = TicTacToeState::new();
+        policy.table.insert(state.action_code(&0).unwrap(), MoveStatistics { reward: 0.0, visits: 10 });
+        policy.table.insert(state.action_code(&1).unwrap(), MoveStatistics { reward: 10.0, visits: 10 });
+        let seed = 1;
+        let mut random_generator: SearchRng = SeedableRng::from_seed(seed);
+        let mut counts = [0; 2];
+        for _i in 0..1000 {
+            counts[policy.choose(&state, &[0u8, 1], &mut random_generator)] += 1;
+        }
+        assert!(counts[1] > 990);
+    }
+
+    /// Test if trees search with the policy, finding the winning move.
+    #[test]
+    fn test_search_with_mast() {
+        let mut tree = MCTSTree::<u8, TicTacToeState>::with_capacity(1000, Some(1), "XX.OO....".into(), 9);
+        tree.searcher.rollout_policy = Box::new(MastRollout::default());
+        tree.search(500);
+        let best = tree.most_visited_child(0).unwrap();
+        assert_eq!(tree.arena.actions[best as usize], Some(2));
+    }
+}
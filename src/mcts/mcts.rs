@@ -1,60 +1,189 @@
 use crate::game_state_trait::GameState;
 use crate::game_state_trait::GameResult;
+use crate::rollout::{sample_weighted, SimulationRewards};
+use crate::statistics::SearchCounters;
+use crate::termination::SearchBudget;
+#[cfg(feature = "trace")]
+use crate::trace::{self, Phase};
+use crate::float::Float;
+use alloc::borrow::Cow;
+use alloc::string::String;
+use alloc::vec::Vec;
 
-// Psuedorandom selection is used for simualtions/rollouts. Xorshfit is not cryptographically 
-// secure and less random than other implementations, but very fast.
-use xorshift::{Rng, SeedableRng, Xorshift128};
+// Psuedorandom selection is used for simualtions/rollouts. Xoshiro256++ is not cryptographically 
+// secure, but very fast.
+use crate::rng::{Rng, SearchRng};
 
-/// Represents a node in the mcts game tree. 
-/// It holds game tree information as well as mcts statistics.
-///
-/// Trees datastructures in general hold their parent as well as a list of thier children.
-///
-/// Game trees associate each node with a gamestate and each line as an action.
+use crate::arena::add_count;
+pub use crate::arena::{ChildSegment, ChildSlot, MCTSArena, MCTSNode, NodeId, NodeIndex, NodeStats, NodeTopology, StaleNodeError};
+pub use crate::searcher::Searcher;
+
+
+/// Floating point type of the values computed during selection, node values, UCT and PUCT.
 ///
-/// Mcts progressively builds the tree, starting with children being represented as an action before being explored
-/// to having a full node representation once explored.
+/// Defaults to `f32`. The `double-precision` feature switches to `f64`, which keeps logarithms of
+/// visit counts beyond 2^24 exact and the exploration terms of children apart in searches of
+/// millions of simulations, at a small cost in speed.
+#[cfg(not(feature = "double-precision"))]
+pub type Value = f32;
+
+/// Floating point type of the values computed during selection, node values, UCT and PUCT.
 ///
-/// Results of rollouts and child rollouts are kept within the tree for ucb calculation.
+/// Defaults to `f32`. The `double-precision` feature switches to `f64`, which keeps logarithms of
+/// visit counts beyond 2^24 exact and the exploration terms of children apart in searches of
+/// millions of simulations, at a small cost in speed.
+#[cfg(feature = "double-precision")]
+pub type Value = f64;
+
+
+/// Determines how simulation results are credited to the nodes of a tree.
 ///
-/// In order to avoid self referential structure sizing, all references to nodes are 
-/// opaque pointers, with the actual nodes being allocated within a memory arena.
-pub struct MCTSNode<Action, GameStateObj> 
-where
-    GameStateObj: GameState<Action> + Clone
-{
-    // Game state asocciated with the node in the tree.
-    pub game_state: GameStateObj,
-    
-    /// Parent of current node. None if root, as root has no parent.
-    pub parent: Option<usize>,
-    
-    /// Tree indexes for already expanded children.
-    pub expanded: Vec<usize>,
-    
-    /// Legal moves corresponding to unexpanded child nodes.
-    pub unexpanded: Vec<Action>,
-    
-    /// Sum of all simulation wins of the sub-graph with the current node as its root.
-    pub wins: u32,
-    
-    /// Sum of all simulation draws of the sub-graph with the current node as its root.
-    /// technically not needed for MCTS, but allows for differentiating draws and losses.
-    pub draws: u32,
-    
-    /// Sum of all simulations of the sub-graph with the current node as its root.
-    pub sims: u32,
+/// Game states only tell apart two sides through `GameState::side_to_move`, and results only
+/// credit the first player, so games of more than two players can not be searched yet. Backups
+/// for them, such as MaxN vectors of per-player rewards or paranoid backups of one player against
+/// the rest, need the player to move and the reward of every player from the game state first.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PlayerMode {
+    /// Two players alternate, and every node is credited from the perspective 
+    /// of the player who moved into it.
+    TwoPlayer,
+
+    /// A single agent makes every decision, such as in puzzles and optimization problems.
+    /// Every node is credited with the raw simulation score, and selection maximizes
+    /// the expected score. `side_to_move` is ignored.
+    SinglePlayer,
+}
+
+
+/// Determines which player a node is credited to in two player mode, and so whose point of view
+/// selection takes when it picks among the children of a node.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Perspective {
+    /// Nodes are credited to the player who chose the move into them, the side to move of their
+    /// nearest ancestor which is not a chance node. Selection maximizes the value of the side to
+    /// move at every node, negamax style, even where a side moves several times in a row.
+    Mover,
+
+    /// Nodes are credited to the opponent of their own side to move, which assumes the sides
+    /// strictly alternate.
+    Alternating,
+}
+
+
+/// Determines the formula used to pick children during selection.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SelectionPolicy {
+    /// Upper confidence bound applied to trees, `score/sims + c*sqrt(ln(parent_sims)/sims)`.
+    Uct,
+
+    /// Predictor UCT, `score/sims + c*prior*sqrt(parent_sims)/(1 + sims)`.
+    /// Weights exploration by the prior of each child, see `GameState::action_priors`.
+    Puct,
+}
+
+
+/// Determines the value of a visited node, the exploitation term of selection.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BackupPolicy {
+    /// Fraction of the simulations won by the player who moved into the node, with draws credited
+    /// by `Searcher::draw_score`, `(wins + draw_score*draws)/sims`, ignoring scores and margins.
+    ///
+    /// Wins and draws are whole counts, so `GameResult::Score` results count as losses for both
+    /// sides. Leaf evaluators and `Searcher::simulations_per_expansion` above 1 only produce scores,
+    /// and should be searched with `MeanReward` instead.
+    WinRate,
+
+    /// Mean reward of the player who moved into the node, `score/sims`, blended with the best
+    /// child by `Searcher::max_backup_weight`.
+    MeanReward,
+
+    /// Mean reward, except for nodes decided by their terminal children, as in MCTS-Solver.
+    /// In two player mode, a node is lost when the opponent has a reply ending the game in its
+    /// win. A node whose children are all terminal is worth its best child exactly.
+    Solver,
+}
+
+
+/// Determines the exploration factor used by selection over the course of a search,
+/// scheduled by the number of simulations of the root.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ExplorationSchedule {
+    /// The same factor throughout the search.
+    Constant(f32),
+
+    /// Moves linearly from `start` to `end` over the first `visits` simulations of the root,
+    /// and stays at `end` after, such as to explore broadly early and exploit late.
+    Linear { start: f32, end: f32, visits: u64 },
+
+    /// `init + ln((1 + root_sims + base) / base)`, growing slowly with the simulations of the
+    /// root as in AlphaZero, so large searches keep exploring.
+    Logarithmic { init: f32, base: f32 },
+}
+
+impl ExplorationSchedule {
+    /// Exploration factor once the root has `root_sims` simulations.
+    pub fn factor(&self, root_sims: u64) -> f32 {
+        match *self {
+            ExplorationSchedule::Constant(factor) => return factor,
+            ExplorationSchedule::Linear { start, end, visits } => {
+                let progress = if visits == 0 { 1.0 } else { (root_sims as f32 / visits as f32).min(1.0) };
+                return start + (end - start) * progress;
+            },
+            ExplorationSchedule::Logarithmic { init, base } => return init + Float::ln((1.0 + root_sims as f32 + base) / base),
+        }
+    }
+}
+
+
+/// Determines which nodes of a tree keep a copy of their game state.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum StateStorage {
+    /// Every node stores its game state.
+    Full,
+
+    /// Only the root stores its game state. Every other node stores just the action leading
+    /// to it, and its state is re-derived by replaying actions from the root when needed.
+    ///
+    /// Trades extra `apply_action` calls in expansion and simulation for a large
+    /// reduction in memory when game states are big.
+    ActionsOnly,
+}
+
+
+/// Sizes and seed of a new tree, see `MCTSTree::from_state`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TreeConfig {
+    /// The starting size of the memory arena, in nodes. Larger values trade increased
+    /// memory usage for less dynamic allocation of new memory. Defaults to 1024.
+    pub arena_capacity: usize,
+
+    /// The full 128 bit seed that determines the starting state of the rng.
+    /// Defaults to `None`, seeding with 0.
+    pub seed: Option<u128>,
+
+    /// Number of children expected for nodes in the tree. The child slab is reserved for
+    /// `arena_capacity * average_child_count` children. Defaults to 8.
+    pub average_child_count: usize,
+}
+
+impl Default for TreeConfig {
+    fn default() -> Self {
+        return TreeConfig { arena_capacity: 1024, seed: None, average_child_count: 8 };
+    }
 }
 
 
 /// Holds the node memory arena for the mcts tree and 
 /// associated mcts tree properties.
-pub struct MCTSTree<Action, GameStateObj> 
+///
+/// `Data` is the type of the user data nodes may carry, see `MCTSTree::node_data`.
+/// Defaults to `()` for trees without any.
+pub struct MCTSTree<Action, GameStateObj, Data = ()> 
 where
     GameStateObj: GameState<Action> + Clone
 {
     /// Memory arena for mcts nodes.
-    pub arena: Vec<MCTSNode<Action, GameStateObj>>,
+    pub(crate) arena: MCTSArena<Action, GameStateObj, Data>,
 
     /// Average children expected for nodes in the tree.
     ///
@@ -62,15 +191,29 @@ where
     /// allocations at the expense of extra used memory.
     pub average_child_count: usize,
 
-    /// Holds the current random generator state. Random numbers will be generated
-    /// from the current state and the state will be modified.
-    pub random_generator: Xorshift128,
+    /// Configuration, random generator and policies driving the search of the tree.
+    pub searcher: Searcher<Action, GameStateObj>,
+
+    /// Which newly expanded nodes keep their game state. Defaults to `StateStorage::Full`.
+    pub state_storage: StateStorage,
+
+    /// Smallest and largest reward credited to any node, which mean rewards are normalized by.
+    /// Widened by every backpropagation while `Searcher::normalize_rewards` is set, and may be set up front
+    /// when the range of the rewards is known. Defaults to `None`.
+    pub reward_bounds: Option<(f64, f64)>,
+
+    /// Limits on the size of the tree, past which searches stop regardless of their iterations.
+    /// Defaults to no limits.
+    pub budget: SearchBudget,
+
+    /// Counters of the work done by the search, see `MCTSTree::stats`.
+    pub counters: SearchCounters,
 }
 
 
 /// Methods to enable the creation, search and expansion of the MCTSTree.
 /// Based on the supplied game state methods.
-impl<Action, GameStateObj> MCTSTree<Action, GameStateObj> 
+impl<Action, GameStateObj, Data> MCTSTree<Action, GameStateObj, Data> 
 where
     GameStateObj: GameState<Action> + Clone
 {
@@ -85,107 +228,533 @@ where
     /// * `starting_pos` : String encoding the starting position of the game.
     ///
     /// * `average_child_count` : Number of children expected for nodes in the tree.
+    /// The child slab is reserved for `arena_capacity * average_child_count` children.
+    ///
+    /// # Panics
+    /// If the game state panics on an invalid `starting_pos`, see `try_with_capacity`.
     pub fn with_capacity(
         arena_capacity: usize, 
         seed: Option<u64>, 
         starting_pos: String, 
         average_child_count: usize)
     -> Self {
+        let root_game_state = GameStateObj::from_str(starting_pos);
+        return Self::from_state(root_game_state, TreeConfig { arena_capacity, seed: seed.map(u128::from), average_child_count });
+    }
+
+    /// Creates a new mcts tree like `with_capacity`, returning an error 
+    /// instead of panicking if `starting_pos` can not be parsed.
+    ///
+    /// # Returns
+    /// The tree, or the parse error of the starting position.
+    pub fn try_with_capacity(
+        arena_capacity: usize, 
+        seed: Option<u64>, 
+        starting_pos: &str, 
+        average_child_count: usize)
+    -> Result<Self, GameStateObj::ParseError> {
+        let root_game_state = GameStateObj::try_from_str(starting_pos)?;
+        return Ok(Self::from_state(root_game_state, TreeConfig { arena_capacity, seed: seed.map(u128::from), average_child_count }));
+    }
+
+    /// Creates a new mcts tree rooted at `root_game_state`, for callers which already hold the
+    /// state of the position, without encoding it as a string for `with_capacity`.
+    ///
+    /// # Arguments
+    /// * `root_game_state` : The game state of the root.
+    ///
+    /// * `config` : The arena capacity, seed and average child count of the tree.
+    ///
+    /// # Panics
+    /// If the children reserved for the arena capacity overflow `usize`.
+    pub fn from_state(root_game_state: GameStateObj, config: TreeConfig) -> Self {
+        let TreeConfig { arena_capacity, seed, average_child_count } = config;
+
         // Initilize the tree data structures.
-        // Seed is a 128 bit number, or a slice of 2 64 bit ones,
-        // this method expands the 64 bit seed to 128 bit.
-        let seed_formatted: &[_] = &[seed.unwrap_or(0), 0];
-        
+        let child_capacity = arena_capacity.checked_mul(average_child_count).expect("child capacity overflows usize");
         let mut tree = Self {
-            arena: Vec::with_capacity(arena_capacity), 
-            average_child_count: average_child_count,
-            random_generator: SeedableRng::from_seed(seed_formatted), 
+            arena: MCTSArena::with_capacity(arena_capacity, child_capacity), 
+            average_child_count,
+            searcher: Searcher::new(seed, &root_game_state),
+            state_storage: StateStorage::Full,
+            reward_bounds: None,
+            budget: SearchBudget::default(),
+            counters: SearchCounters::default(),
         };
 
         // Create the root node of the tree.
-        let root_game_state = GameStateObj::from_str(starting_pos);
-        let unexpanded = root_game_state.generate_legal_actions();
-        tree.arena.push(MCTSNode {
-            game_state: root_game_state, 
-            parent: None, 
-            expanded: Vec::with_capacity(tree.average_child_count), 
-            unexpanded: unexpanded,
-            wins: 0, draws: 0, sims: 0
-        });
+        tree.push_node(root_game_state, None, None, true);
         
         return tree;
     }
 
+    /// Pushes a new node holding `game_state` to the arena.
+    ///
+    /// The legal actions of the node are generated up front, unless the game state
+    /// generates them lazily, is a chance state or an expansion threshold is set, 
+    /// in which case the node starts with an empty child list.
+    ///
+    /// # Returns
+    /// The index of the new node.
+    pub(crate) fn push_node(
+        &mut self, 
+        game_state: GameStateObj, 
+        action: Option<Action>, 
+        parent: Option<NodeIndex>, 
+        store_state: bool)
+    -> NodeIndex {
+        let deferred = GameStateObj::LAZY_ACTIONS || self.searcher.expansion_threshold > 0 || game_state.is_chance_node();
+        let unexpanded = if deferred { Vec::new() } else { game_state.generate_legal_actions() };
+        let node = self.arena.push(MCTSNode {
+            game_state, 
+            action,
+            parent, 
+            expanded: Vec::new(), 
+            unexpanded,
+            wins: 0, draws: 0, sims: 0, score: 0.0,
+            data: None
+        }, store_state);
+        if deferred {
+            self.arena.topology[node as usize].children = self.arena.allocate_lazy_children();
+        }
+        return node;
+    }
+
+    /// Returns the game state associated with `node`.
+    ///
+    /// If the node did not store its state, it is re-derived by replaying the actions
+    /// leading to it from the nearest ancestor with a stored state.
+    ///
+    /// # Panics
+    /// If `node` is stale.
+    pub fn game_state(&self, node: NodeId) -> Cow<'_, GameStateObj> {
+        return self.game_state_at(self.index_of(node));
+    }
+
+    /// `game_state` by arena index.
+    pub(crate) fn game_state_at(&self, node: NodeIndex) -> Cow<'_, GameStateObj> {
+        if let Some(game_state) = self.arena.stored_state(node) {
+            return Cow::Borrowed(game_state);
+        }
+
+        // Collect the actions from the node up to the nearest stored ancestor.
+        let mut actions: Vec<&Action> = Vec::new();
+        let mut current_node = node;
+        let ancestor_state = loop {
+            if let Some(game_state) = self.arena.stored_state(current_node) {
+                break game_state;
+            }
+            actions.push(self.arena.actions[current_node as usize].as_ref().expect("no action"));
+            current_node = self.arena.topology[current_node as usize].parent.expect("no stored ancestor");
+        };
+
+        // Replay the actions in the order they were originally taken.
+        let mut game_state = ancestor_state.clone();
+        for action in actions.into_iter().rev() {
+            game_state.apply_action_mut(action);
+        }
+        return Cow::Owned(game_state);
+    }
+
+    /// Makes `new_root` the root of the tree, freeing every node outside of its subtree.
+    ///
+    /// Once a move has been played, the subtrees of its siblings can never be reached again.
+    /// The surviving subtree is copied into a fresh arena in breadth first order, so the new
+    /// root is placed at index 0 and the memory held by the rest of the tree is released.
+    ///
+    /// All previously created `NodeId` handles become stale, `root` gives the handle of the new root.
+    ///
+    /// # Arguments
+    /// * `new_root` : The node to become the root. Usually a child or grandchild of the root.
+    ///
+    /// # Panics
+    /// If `new_root` is stale.
+    pub fn advance_root(&mut self, new_root: NodeId) {
+        self.advance_root_at(self.index_of(new_root));
+    }
+
+    /// `advance_root` by arena index.
+    ///
+    /// # Returns
+    /// A mapping from old node indices to the new ones. None for freed nodes.
+    pub(crate) fn advance_root_at(&mut self, new_root: NodeIndex) -> Vec<Option<NodeIndex>> {
+        // The new root loses its parent, so it must always store its game state.
+        let root_game_state = self.game_state_at(new_root).into_owned();
+
+        return self.arena.retain_subtree(new_root, root_game_state);
+    }
+
+    /// Frees every node and roots the tree at `new_root_state`, to search a new position without
+    /// building a new tree.
+    ///
+    /// The arena keeps its allocated capacity, and the searcher keeps its configuration and
+    /// policies, including what learning rollout policies have learned. The state storage, budget
+    /// and reward bounds are kept too, while the counters of the search start over.
+    ///
+    /// All previously created `NodeId` handles become stale.
+    pub fn reset(&mut self, new_root_state: GameStateObj) {
+        self.arena.clear();
+        self.counters = SearchCounters::default();
+        self.push_node(new_root_state, None, None, true);
+    }
+
+    /// Exploration factor of the current point of the search, under `exploration_schedule`.
+    pub fn exploration_factor(&self) -> f32 {
+        return self.searcher.exploration_schedule.factor(self.arena.stats[0].sims);
+    }
+
+    /// Value of a node for the player who moved into it, the exploitation term of selection,
+    /// under `backup_policy`. Unvisited nodes are worth `first_play_urgency`, 0 if it is unset.
+    ///
+    /// Mean rewards are normalized into [0, 1] when `normalize_rewards` is set, and blended with
+    /// the best mean score among the visited children by `max_backup_weight`, `(1 - w)*mean + w*max`.
+    /// In two player mode, the best child is the best reply of the opponent, worth `1 - max`
+    /// assuming the rewards of both players add up to 1.
+    /// Chance nodes and nodes without visited children are valued at their mean.
+    ///
+    /// # Panics
+    /// If `node` is stale.
+    pub fn node_value(&self, node: NodeId) -> Value {
+        return self.node_value_at(self.index_of(node));
+    }
+
+    /// `node_value` by arena index.
+    pub(crate) fn node_value_at(&self, node: NodeIndex) -> Value {
+        if self.arena.stats[node as usize].sims == 0 {
+            return self.searcher.first_play_urgency.unwrap_or(0.0) as Value;
+        }
+        match self.searcher.backup_policy {
+            BackupPolicy::WinRate => {
+                let stats = &self.arena.stats[node as usize];
+                return (stats.wins as Value + self.searcher.draw_score as Value * stats.draws as Value) / stats.sims as Value;
+            },
+            BackupPolicy::MeanReward => return self.mean_value(node),
+            BackupPolicy::Solver => return self.solved_value(node).unwrap_or_else(|| self.mean_value(node)),
+        }
+    }
+
+    /// Whether `node` is a visited terminal node, every legal action of which has been generated
+    /// without finding any.
+    fn is_terminal(&self, node: NodeIndex) -> bool {
+        return self.arena.stats[node as usize].sims > 0
+            && self.arena.expanded_count(node) == 0
+            && !self.arena.has_untried_actions(node);
+    }
+
+    /// Exact value of `node` when its terminal children decide it, for `BackupPolicy::Solver`.
+    fn solved_value(&self, node: NodeIndex) -> Option<Value> {
+        if self.arena.topology[node as usize].is_chance || self.arena.expanded_count(node) == 0 {
+            return None;
+        }
+
+        let mut all_terminal = !self.arena.has_untried_actions(node);
+        let mut best = Value::NEG_INFINITY;
+        for child in self.arena.expanded(node) {
+            if !self.is_terminal(child) {
+                all_terminal = false;
+                continue;
+            }
+            // The opponent has a reply ending the game in its win.
+            let child_stats = &self.arena.stats[child as usize];
+            if self.searcher.player_mode == PlayerMode::TwoPlayer && child_stats.wins == child_stats.sims {
+                return Some(0.0);
+            }
+            best = best.max(self.mean_reward(child_stats));
+        }
+
+        if !all_terminal {
+            return None;
+        }
+        return match self.searcher.player_mode {
+            PlayerMode::SinglePlayer => Some(best),
+            PlayerMode::TwoPlayer => Some(1.0 - best),
+        };
+    }
+
+    /// Mean reward of visited statistics, normalized into [0, 1] when `normalize_rewards` is set.
+    ///
+    /// A range without width, where every reward so far was the same, normalizes to 0.5.
+    fn mean_reward(&self, stats: &NodeStats) -> Value {
+        let mean = stats.score / stats.sims as f64;
+        match self.reward_bounds {
+            Some((min, max)) if self.searcher.normalize_rewards => {
+                return if max > min { ((mean - min) / (max - min)) as Value } else { 0.5 };
+            },
+            _ => return mean as Value,
+        }
+    }
+
+    /// Mean reward of `node`, blended with its best child by `max_backup_weight`.
+    fn mean_value(&self, node: NodeIndex) -> Value {
+        let mean = self.mean_reward(&self.arena.stats[node as usize]);
+        if self.searcher.max_backup_weight == 0.0 || self.arena.topology[node as usize].is_chance {
+            return mean;
+        }
+
+        let best_child = self.arena.expanded(node)
+            .map(|child| &self.arena.stats[child as usize])
+            .filter(|child_stats| child_stats.sims > 0)
+            .map(|child_stats| self.mean_reward(child_stats))
+            .reduce(Value::max);
+        let max = match (best_child, self.searcher.player_mode) {
+            (None, _) => mean,
+            (Some(best), PlayerMode::SinglePlayer) => best,
+            (Some(best), PlayerMode::TwoPlayer) => 1.0 - best,
+        };
+        let weight = self.searcher.max_backup_weight as Value;
+        return (1.0 - weight) * mean + weight * max;
+    }
+
     /// Implementation of the UCT algorithm for a particular node.
     ///
     /// # Arguments
     /// * `exploration_factor` : corresponds to `c` in the UCT algorithm, 
     /// a higher exploration_factor means a preference towards exploration over exploitation. 
-    /// Sqrt(2) is the theoretical optimum and default if unspecified.
+    /// Given by `exploration_schedule` if unspecified, sqrt(2) by default, the theoretical optimum of UCT.
     /// 
     /// # Returns
     /// UCT value associated with the selected node and tree.
     /// 
+    /// Unvisited children have an unbounded exploration term, so their UCT is infinite, unless
+    /// `first_play_urgency` is set, which is their UCT instead.
+    ///
     /// # Panics
     /// If child_index has no parent, the method will panic on unwrap.
     /// A parent is required as it is part of the UCT algorithm.
-    pub fn uct(&self, child: usize, exploration_factor: Option<f32>) -> f32 {
+    /// If `child` is stale.
+    pub fn uct(&self, child: NodeId, exploration_factor: Option<f32>) -> Value {
+        return self.uct_at(self.index_of(child), exploration_factor);
+    }
+
+    /// `uct` by arena index.
+    pub(crate) fn uct_at(&self, child: NodeIndex, exploration_factor: Option<f32>) -> Value {
         
-        let child_obj = &self.arena[child];
+        let child_stats = &self.arena.stats[child as usize];
         
         // Parent must be specified.
-        child_obj.parent.expect("no parent");
+        let parent = self.arena.topology[child as usize].parent.expect("no parent");
+
+        if child_stats.sims == 0 {
+            return self.searcher.first_play_urgency.map_or(Value::INFINITY, |urgency| urgency as Value);
+        }
         
-        let wins = child_obj.wins as f32;
-        let sims = child_obj.sims as f32;
-        let parent_sims = self.arena[child_obj.parent.unwrap()].sims as f32;
+        // Parents are counted as visited at least once, so merged or edited statistics can not
+        // take the logarithm of 0.
+        let sims = child_stats.sims as Value;
+        let parent_sims = self.arena.stats[parent as usize].sims.max(1) as Value;
+
+        // UCT = (score / sims) + c*sqrt(ln(parent_sims) / sims), with the value of the child in place of
+        // its mean score when `max_backup_weight` is set.
+        // For two player games score is the number of wins.
+        let exploration_factor = exploration_factor.unwrap_or_else(|| self.exploration_factor()) as Value;
+        return self.node_value_at(child) + exploration_factor * Float::sqrt(Float::ln(parent_sims) / sims);
+    }
+
+    /// Implementation of the PUCT algorithm for a particular node, weighting exploration
+    /// by the prior probability of the node.
+    ///
+    /// # Arguments
+    /// * `exploration_factor` : corresponds to `c` in the PUCT algorithm, 
+    /// a higher exploration_factor means a preference towards exploration over exploitation. 
+    /// Given by `exploration_schedule` if unspecified, sqrt(2) by default, the theoretical optimum of UCT.
+    /// 
+    /// # Returns
+    /// PUCT value associated with the selected node and tree. The exploitation term of 
+    /// unvisited children is `first_play_urgency`, 0 if it is unset.
+    /// 
+    /// # Panics
+    /// If child_index has no parent, the method will panic on unwrap.
+    /// If `child` is stale.
+    pub fn puct(&self, child: NodeId, exploration_factor: Option<f32>) -> Value {
+        return self.puct_at(self.index_of(child), exploration_factor);
+    }
+
+    /// `puct` by arena index.
+    pub(crate) fn puct_at(&self, child: NodeIndex, exploration_factor: Option<f32>) -> Value {
+        let child_stats = &self.arena.stats[child as usize];
+        let parent = self.arena.topology[child as usize].parent.expect("no parent");
 
-        // UCT = (wins / sims) + c*sqrt(ln(parent_sims) / sims).
-        return (wins / sims) + exploration_factor.unwrap_or(f32::sqrt(2.0)) * f32::sqrt(f32::ln(parent_sims) / sims);
+        let sims = child_stats.sims as Value;
+        let parent_sims = self.arena.stats[parent as usize].sims as Value;
+        let prior = self.arena.topology[child as usize].probability as Value;
+        let exploitation = self.node_value_at(child);
+
+        // PUCT = (score / sims) + c*prior*sqrt(parent_sims) / (1 + sims).
+        let exploration_factor = exploration_factor.unwrap_or_else(|| self.exploration_factor()) as Value;
+        return exploitation + exploration_factor * prior * Float::sqrt(parent_sims) / (1.0 + sims);
     }
 
-    /// Returns the child node of `parent` with the maximum uct.
+    /// Returns the child node of `parent` with the maximum value under the selection policy.
+    /// Ties go to the first expanded child, see `select_child` for random tie-breaking.
     ///
     /// # Arguments
     /// * `parent` : Parent to search the children of.
     ///
     /// * `exploration_factor` : Corresponds to `c` in the UCT algorithm, 
     /// a higher exploration_factor means a preference to exploration over exploitation. 
-    /// Sqrt(2) is the theoretical optimum and is the default if unspecified.
-    pub fn get_max_uct_child(&self, parent: usize, exploration_factor: Option<f32>) -> usize {
-        let mut best_value: f32 = f32::MIN;
-        let mut best_child: usize = 0;
-        for child in &self.arena[parent].expanded {
+    /// Given by `exploration_schedule` if unspecified, sqrt(2) by default, the theoretical optimum of UCT.
+    ///
+    /// # Returns
+    /// The best child, or None if `parent` has no expanded children.
+    ///
+    /// # Panics
+    /// If `parent` is stale.
+    pub fn get_max_uct_child(&self, parent: NodeId, exploration_factor: Option<f32>) -> Option<NodeId> {
+        return self.get_max_uct_child_at(self.index_of(parent), exploration_factor).map(|child| self.arena.handle(child));
+    }
+
+    /// `get_max_uct_child` by arena index.
+    pub(crate) fn get_max_uct_child_at(&self, parent: NodeIndex, exploration_factor: Option<f32>) -> Option<NodeIndex> {
+        let mut best_value: Value = Value::MIN;
+        let mut best_child = None;
+        for child in self.arena.expanded(parent) {
             // If the child has a greater uct than the previous maximum,
             // replace the maximum with the current child.
-            let child_uct = self.uct(*child, exploration_factor);
-            if child_uct > best_value {
+            let child_uct = match self.searcher.selection_policy {
+                SelectionPolicy::Uct => self.uct_at(child, exploration_factor),
+                SelectionPolicy::Puct => self.puct_at(child, exploration_factor),
+            };
+            if best_child.is_none() || child_uct > best_value {
+                best_value = child_uct;
+                best_child = Some(child);
+            }
+        }
+        return best_child;
+    }
+
+    /// Returns the child of `parent` picked by selection, the child with the maximum value
+    /// under the selection policy like `get_max_uct_child`.
+    ///
+    /// Ties are broken uniformly at random with the random generator when `random_tie_breaking`
+    /// is set, so equally valued children, such as the unvisited children of a node, are not
+    /// picked in the order they were expanded.
+    ///
+    /// # Returns
+    /// The selected child, or None if `parent` has no expanded children.
+    ///
+    /// # Panics
+    /// If `parent` is stale.
+    pub fn select_child(&mut self, parent: NodeId, exploration_factor: Option<f32>) -> Option<NodeId> {
+        let child = self.select_child_at(self.index_of(parent), exploration_factor);
+        return child.map(|child| self.arena.handle(child));
+    }
+
+    /// `select_child` by arena index.
+    pub(crate) fn select_child_at(&mut self, parent: NodeIndex, exploration_factor: Option<f32>) -> Option<NodeIndex> {
+        let mut best_value: Value = Value::MIN;
+        let mut best_child = None;
+        let mut ties: u32 = 0;
+        for child in self.arena.expanded(parent) {
+            let child_uct = match self.searcher.selection_policy {
+                SelectionPolicy::Uct => self.uct_at(child, exploration_factor),
+                SelectionPolicy::Puct => self.puct_at(child, exploration_factor),
+            };
+            if best_child.is_none() || child_uct > best_value {
                 best_value = child_uct;
-                best_child = *child;
+                best_child = Some(child);
+                ties = 1;
+            }
+            // Every tied child replaces the best child with a probability of 1 over the number
+            // of ties so far, which keeps each of them with the same probability.
+            else if child_uct == best_value && self.searcher.random_tie_breaking {
+                ties += 1;
+                if self.searcher.random_generator.gen_range(0, ties) == 0 {
+                    best_child = Some(child);
+                }
             }
         }
         return best_child;
     }
 
+    /// Samples a child of the chance node `parent` according to the outcome probabilities.
+    ///
+    /// # Panics
+    /// If `parent` is stale.
+    pub fn sample_chance_child(&mut self, parent: NodeId) -> NodeId {
+        let child = self.sample_chance_child_at(self.index_of(parent));
+        return self.arena.handle(child);
+    }
+
+    /// `sample_chance_child` by arena index.
+    pub(crate) fn sample_chance_child_at(&mut self, parent: NodeIndex) -> NodeIndex {
+        let random_number: f32 = self.searcher.random_generator.gen();
+        let mut cumulative = 0.0;
+        let mut sampled = None;
+        for child in self.arena.expanded(parent) {
+            cumulative += self.arena.topology[child as usize].probability;
+            sampled = Some(child);
+            if random_number < cumulative {
+                break;
+            }
+        }
+        // Probabilities which sum to less than 1 fall back to the last outcome.
+        return sampled.expect("chance node without outcomes");
+    }
+
+    /// Samples an outcome index from a list of chance outcomes according to their probabilities.
+    pub(crate) fn sample_chance_outcome(&mut self, outcomes: &[(Action, f32)]) -> usize {
+        let random_number: f32 = self.searcher.random_generator.gen();
+        let mut cumulative = 0.0;
+        for (i, (_, probability)) in outcomes.iter().enumerate() {
+            cumulative += probability;
+            if random_number < cumulative {
+                return i;
+            }
+        }
+        return outcomes.len() - 1;
+    }
+
     /// Finds the optimal leaf node index in the MCTS Tree according to the path with maximal UCT at each depth.
     /// A leaf node is a node with unexpanded children, or a terminal node.
     ///
+    /// Chance nodes are passed through by sampling one of their outcomes, 
+    /// which uses the random generator.
+    ///
     /// # Arguments
-    /// * `root_index` : The index of the MCTSTree vector to begin selection from.
+    /// * `root` : The node to begin selection from.
     /// 
     /// * `exploration_factor` : Corresponds to `c` in the UCT algorithm, 
     /// a higher exploration_factor means a preference to exploration over exploitation. 
-    /// Sqrt(2) is the theoretical optimum and is the default if unspecified.
-    pub fn select(&self, mut root: usize, exploration_factor: Option<f32>) -> usize {
+    /// Given by `exploration_schedule` if unspecified, sqrt(2) by default, the theoretical optimum of UCT.
+    ///
+    /// # Panics
+    /// If the path of selection is longer than the tree has nodes, which means the child links of the
+    /// tree form a cycle, such as after corrupting them by hand. Selection would never end otherwise.
+    /// If `root` is stale.
+    pub fn select(&mut self, root: NodeId, exploration_factor: Option<f32>) -> NodeId {
+        let leaf = self.select_at(self.index_of(root), exploration_factor);
+        return self.arena.handle(leaf);
+    }
+
+    /// `select` by arena index.
+    pub(crate) fn select_at(&mut self, mut root: NodeIndex, exploration_factor: Option<f32>) -> NodeIndex {
+        #[cfg(feature = "trace")]
+        let _span = trace::span(Phase::Select, root);
+
+        // The scheduled factor only changes between iterations, so it is resolved once per selection.
+        let exploration_factor = Some(exploration_factor.unwrap_or_else(|| self.exploration_factor()));
+
         // Leaf node is found where unexpanded children exist.
-        while self.arena[root].unexpanded.len() == 0 {
+        let mut depth = 0;
+        while !self.arena.has_untried_actions(root) {
+            // A path visits every node of the tree at most once, so a longer one follows a cycle of child links.
+            depth += 1;
+            assert!(depth <= self.arena.len(), "selection cycle through node {}", root);
+
+            // Replace the root index with a sampled outcome of chance nodes,
+            // or otherwise the expanded child with maximal UCT.
+            let child = if self.arena.topology[root as usize].is_chance {
+                (self.arena.expanded_count(root) > 0).then(|| self.sample_chance_child_at(root))
+            }
+            else {
+                self.select_child_at(root, exploration_factor)
+            };
+
             // If both expanded and unexpanded children are empty the node must be terminal and therefore a leaf node.
-            if self.arena[root].expanded.len() == 0 {
-                return root;
+            match child {
+                Some(child) => root = child,
+                None => return root,
             }
-            
-            // Replace the root index with the expanded child with maximal UCT.
-            root = self.get_max_uct_child(root, exploration_factor);
         }
         return root;
     }
@@ -193,6 +762,16 @@ where
     /// Expands a random unexpanded action from `leaf_node` returning its arena pointer.
     /// If the leaf node is terminal, no nodes are expanded and the leaf index is returned.
     ///
+    /// Game states which generate their actions lazily instead expand their next
+    /// generated action. If the leaf turns out to have no actions left to generate, 
+    /// it is marked as complete and its index is returned.
+    ///
+    /// Game states with action priors instead expand every action at once, returning the
+    /// child with the highest prior. The priors are read from the `evaluator` when it is set.
+    ///
+    /// Leaves with fewer simulations than `expansion_threshold` are not expanded,
+    /// and their index is returned.
+    ///
     /// # Invariants
     /// The leaf node is assumed to either have unexpanded children or be a terminal node.
     /// 
@@ -201,165 +780,875 @@ where
     ///
     /// # Returns
     /// A pointer to the newly expanded node, or `leaf_node` if the leaf node is terminal.
-    pub fn expand(&mut self, leaf_node: usize) -> usize {
+    ///
+    /// # Panics
+    /// If `leaf_node` is stale.
+    pub fn expand(&mut self, leaf_node: NodeId) -> NodeId {
+        let expanded = self.expand_at(self.index_of(leaf_node));
+        return self.arena.handle(expanded);
+    }
+
+    /// `expand` by arena index.
+    pub(crate) fn expand_at(&mut self, leaf_node: NodeIndex) -> NodeIndex {
+        #[cfg(feature = "trace")]
+        let _span = trace::span(Phase::Expand, leaf_node);
+
+        // Leaves below the expansion threshold are simulated again instead.
+        if self.arena.stats[leaf_node as usize].sims < self.searcher.expansion_threshold as u64 {
+            return leaf_node;
+        }
+
+        if self.arena.topology[leaf_node as usize].is_chance {
+            return self.expand_chance(leaf_node);
+        }
+
+        if GameStateObj::LAZY_ACTIONS {
+            return self.expand_lazy(leaf_node);
+        }
+
+        // Generate the legal actions of leaves whose generation was deferred.
+        if !self.arena.topology[leaf_node as usize].children.complete {
+            let unexpanded = self.game_state_at(leaf_node).generate_legal_actions();
+            self.arena.set_children(leaf_node, Vec::new(), unexpanded);
+        }
+
         // Return leaf node if its terminal.
-        if self.arena[leaf_node].unexpanded.len() == 0 {
+        if self.arena.unexpanded_count(leaf_node) == 0 {
             return leaf_node;
         }
         
+        // Game states with priors expand every action at once.
+        let priors = match self.searcher.evaluator.take() {
+            Some(mut evaluator) => {
+                let priors = evaluator.evaluate(&self.game_state_at(leaf_node)).priors;
+                self.searcher.evaluator = Some(evaluator);
+                priors
+            },
+            None => self.game_state_at(leaf_node).action_priors(),
+        };
+        if !priors.is_empty() {
+            return self.expand_priors(leaf_node, priors);
+        }
+        
         // Select a random action from potential legal actions.
-        let random_number = self.random_generator.gen_range(0, self.arena[leaf_node].unexpanded.len());
-        let random_action = &self.arena[leaf_node].unexpanded[random_number];
+        let random_number = self.searcher.random_generator.gen_range(0, self.arena.unexpanded_count(leaf_node));
+        let random_action = self.arena.unexpanded_action(leaf_node, random_number);
 
         // Generate resulting game state after random action is applied;
-        let expanded_game_state = self.arena[leaf_node].game_state.apply_action(random_action);
-
-        // Generate possible actions.
-        let expanded_game_state_unexpanded = expanded_game_state.generate_legal_actions();
-
-        // Push new node to arena.
-        self.arena.push(MCTSNode { 
-            game_state: 
-            expanded_game_state, 
-            parent: Some(leaf_node), 
-            expanded: Vec::with_capacity(self.average_child_count), 
-            unexpanded: expanded_game_state_unexpanded, 
-            wins: 0, draws: 0, sims: 0 
-        });
-        let expanded_node = self.arena.len() - 1;
-
-        // Remove node from unexpanded and add to expanded.
-        self.arena[leaf_node].unexpanded.remove(random_number);
-        self.arena[leaf_node].expanded.push(expanded_node);
+        let expanded_game_state = self.game_state_at(leaf_node).apply_action(random_action);
+
+        // Mark the action as expanded into the node about to be pushed.
+        let expanded_node = self.arena.len() as NodeIndex;
+        let action = self.arena.expand_child(leaf_node, random_number, expanded_node);
+
+        // Push new node to arena. Its children are copied into the child slab.
+        let store_state = self.state_storage == StateStorage::Full;
+        self.push_node(expanded_game_state, Some(action), Some(leaf_node), store_state);
 
         return expanded_node;
     }
 
-    /// Randomly selects possible moves for both players 
-    /// until a terminal state is reached.
-    /// returns the result as a GameResult.
+    /// Expands every action of `leaf_node` in descending order of prior,
+    /// recording the priors on the children.
     ///
-    /// # Arguments
-    /// * `node` : The node to start simulating from.
+    /// # Returns
+    /// A pointer to the child with the highest prior.
+    fn expand_priors(&mut self, leaf_node: NodeIndex, mut priors: Vec<(Action, f32)>) -> NodeIndex {
+        priors.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(core::cmp::Ordering::Equal));
+        let leaf_game_state = self.game_state_at(leaf_node).into_owned();
+        let store_state = self.state_storage == StateStorage::Full;
+        let mut children = Vec::with_capacity(priors.len());
+        for (action, prior) in priors {
+            let child_game_state = leaf_game_state.apply_action(&action);
+            let child = self.push_node(child_game_state, Some(action), Some(leaf_node), store_state);
+            self.arena.topology[child as usize].probability = prior;
+            children.push(child);
+        }
+        let first_child = children[0];
+        self.arena.set_children(leaf_node, children, Vec::new());
+        return first_child;
+    }
+
+    /// Expands every outcome of the chance node `leaf_node`, then samples one of them.
     ///
     /// # Returns
-    /// The outcome of the random rollout.
-    pub fn simulate(&mut self, node: usize) -> GameResult {
-        let mut count = 0;
-        let mut game_state = self.arena[node].game_state.clone();
-        let mut actions = game_state.generate_legal_actions();
-        while actions.len() > 0 && game_state.status_with_moves_left() {
-            // Games are hard-capped to 200 moves.
-            if count > 200 {
-                return GameResult::Draw;
+    /// A pointer to the sampled outcome node.
+    fn expand_chance(&mut self, leaf_node: NodeIndex) -> NodeIndex {
+        if !self.arena.topology[leaf_node as usize].children.complete {
+            let leaf_game_state = self.game_state_at(leaf_node).into_owned();
+            let store_state = self.state_storage == StateStorage::Full;
+            for (action, probability) in leaf_game_state.chance_outcomes() {
+                let outcome_game_state = leaf_game_state.apply_action(&action);
+                let outcome_node = self.push_node(outcome_game_state, Some(action), Some(leaf_node), store_state);
+                self.arena.topology[outcome_node as usize].probability = probability;
+                self.arena.push_expanded_child(leaf_node, outcome_node);
             }
-            
-            // Choose random action and replace the state with it.
-            let random_number = self.random_generator.gen_range(0, actions.len());
-            game_state = game_state.apply_action(&actions[random_number]);
-            actions = game_state.generate_legal_actions();
-            count += 1;
+            self.arena.topology[leaf_node as usize].children.complete = true;
         }
-        return game_state.result();
+        return self.sample_chance_child_at(leaf_node);
     }
 
-    /// Backpropagates a game result up the tree, starting at node index.
-    ///
-    /// For every node propagated, if the node is the same side as the winning side, one is added to wins,
-    /// otherwise one is added to either draws or nothing.
-    /// In either case one is added to simulations.
+    /// Generates the next legal action of `leaf_node` and expands it.
     ///
-    /// # Arguments
-    /// * `current_node` : The current node that is being backpropagated.
-    ///
-    /// * `result` : The result of the simulation that is being backpropagated against.
-    pub fn backpropagate(&mut self, mut current_node: usize, result: GameResult) {
-        loop {
-            let current_node_object = &mut self.arena[current_node];
+    /// # Returns
+    /// A pointer to the newly expanded node, or `leaf_node` if it has no actions left to generate.
+    fn expand_lazy(&mut self, leaf_node: NodeIndex) -> NodeIndex {
+        if !self.arena.has_untried_actions(leaf_node) {
+            return leaf_node;
+        }
 
-            if result == GameResult::Draw {
-                current_node_object.draws += 1;
+        // Every generated action of a lazy node is expanded immediately,
+        // so the next action to generate follows the expanded ones.
+        let index = self.arena.expanded_count(leaf_node);
+        let leaf_game_state = self.game_state_at(leaf_node);
+        let action = match leaf_game_state.legal_action_at(index) {
+            Some(action) => action,
+            None => {
+                // Every legal action has been generated, the leaf is terminal or fully expanded.
+                self.arena.topology[leaf_node as usize].children.complete = true;
+                return leaf_node;
+            }
+        };
+        let expanded_game_state = leaf_game_state.apply_action(&action);
+
+        let store_state = self.state_storage == StateStorage::Full;
+        let expanded_node = self.push_node(expanded_game_state, Some(action), Some(leaf_node), store_state);
+        self.arena.push_expanded_child(leaf_node, expanded_node);
+        return expanded_node;
+    }
+
+    /// Selects possible moves for both players with the rollout policy, 
+    /// randomly by default, until a terminal state is reached.
+    /// returns the result as a GameResult.
+    ///
+    /// Chance events are sampled by their outcome probabilities and do not count as moves.
+    /// Moves are applied to a single copy of the starting state with `GameState::apply_action_mut`,
+    /// and legal actions are generated into a single buffer with `GameState::legal_actions_into`.
+    ///
+    /// If the rollout depth limit is reached, the rollout is truncated and the static evaluation
+    /// of the reached state is returned as a `GameResult::Score`. With an `evaluator`, no moves are
+    /// played and the value of the first non-terminal state is returned instead.
+    ///
+    /// Terminal nodes are not played out, their exact result is returned instead. Nodes found to be
+    /// terminal are marked as complete, so selection stops at them and `BackupPolicy::Solver` counts
+    /// them as solved.
+    ///
+    /// With more than one of `simulations_per_expansion`, that many rollouts are played and their
+    /// average is returned as a `GameResult::Score`, the average share of the first player with
+    /// draws split evenly for two player games, or the average score for single player games.
+    /// The average rewards of either side, with draws worth `Searcher::draw_score`, are what the
+    /// following `backpropagate` credits. The value of an `evaluator` does not vary, so it is only
+    /// computed once.
+    ///
+    /// # Arguments
+    /// * `node` : The node to start simulating from.
+    ///
+    /// # Returns
+    /// The outcome of the random rollout.
+    ///
+    /// # Panics
+    /// If `node` is stale.
+    pub fn simulate(&mut self, node: NodeId) -> GameResult {
+        return self.simulate_at(self.index_of(node));
+    }
+
+    /// `simulate` by arena index.
+    pub(crate) fn simulate_at(&mut self, node: NodeIndex) -> GameResult {
+        #[cfg(feature = "trace")]
+        let _span = trace::span(Phase::Simulate, node);
+
+        let game_state = self.game_state_at(node).into_owned();
+        if self.is_terminal_leaf(node, &game_state) {
+            self.arena.topology[node as usize].children.complete = true;
+            self.searcher.simulation_moves = 0;
+            self.searcher.simulation_rewards = None;
+            return game_state.result();
+        }
+        return self.simulate_from(game_state);
+    }
+
+    /// Whether `node`, holding `game_state`, is a terminal node without any legal actions.
+    ///
+    /// Only nodes without children whose actions are yet to be generated need their legal actions generated.
+    /// Lazily generated games only generate their first action, others reuse the buffer of the searcher.
+    fn is_terminal_leaf(&mut self, node: NodeIndex, game_state: &GameStateObj) -> bool {
+        let topology = &self.arena.topology[node as usize];
+        if topology.is_chance || topology.children.len > 0 {
+            return false;
+        }
+        if topology.children.complete || !game_state.status_with_moves_left() {
+            return true;
+        }
+        if GameStateObj::LAZY_ACTIONS {
+            return game_state.legal_action_at(0).is_none();
+        }
+        game_state.legal_actions_into(&mut self.searcher.leaf_actions);
+        return self.searcher.leaf_actions.is_empty();
+    }
+
+    /// Simulates like `simulate`, from `game_state` rather than the state of a node.
+    pub(crate) fn simulate_from(&mut self, game_state: GameStateObj) -> GameResult {
+        let simulations = if self.searcher.evaluator.is_some() { 1 } else { self.searcher.simulations_per_expansion.max(1) };
+        self.searcher.simulation_moves = 0;
+        self.searcher.simulation_rewards = None;
+        if simulations == 1 {
+            return self.counted_rollout(game_state);
+        }
+
+        // The rewards of either side are averaged separately and carried to backpropagation, as
+        // draws worth `draw_score` to both sides do not add up to 1. The result is the share of
+        // the first player.
+        let mut total = SimulationRewards { first_player: 0.0, second_player: 0.0 };
+        let mut moves = 0;
+        for _i in 0..simulations {
+            let result = self.counted_rollout(game_state.clone());
+            moves += self.searcher.simulation_moves;
+            let rewards = self.simulation_rewards(&result);
+            total.first_player += rewards.first_player;
+            total.second_player += rewards.second_player;
+        }
+        let rewards = SimulationRewards {
+            first_player: total.first_player / simulations as f64,
+            second_player: total.second_player / simulations as f64,
+        };
+        self.searcher.simulation_moves = (moves + simulations / 2) / simulations;
+        self.searcher.simulation_rewards = Some(rewards);
+        let score = match self.searcher.player_mode {
+            PlayerMode::TwoPlayer => (1.0 + rewards.first_player - rewards.second_player) / 2.0,
+            PlayerMode::SinglePlayer => rewards.first_player,
+        };
+        return GameResult::Score(score as f32);
+    }
+
+    /// Plays out a rollout from `game_state`, recording it in the counters.
+    fn counted_rollout(&mut self, game_state: GameStateObj) -> GameResult {
+        let (result, moves) = self.rollout_from(game_state);
+        self.counters.record_rollout(moves);
+        self.searcher.simulation_moves = moves;
+        #[cfg(feature = "trace")]
+        trace::counter("rollout_moves", moves as u64);
+        return result;
+    }
+
+    /// Plays out the rollout of `simulate` from `game_state`.
+    ///
+    /// # Returns
+    /// The outcome of the rollout and the number of moves played.
+    fn rollout_from(&mut self, mut game_state: GameStateObj) -> (GameResult, u32) {
+        let mut count = 0;
+
+        // Legal actions of every ply are generated into the same buffer.
+        let mut actions = Vec::with_capacity(self.average_child_count);
+        loop {
+            // Chance events are sampled according to their probabilities.
+            if game_state.is_chance_node() {
+                let outcomes = game_state.chance_outcomes();
+                let outcome = self.sample_chance_outcome(&outcomes);
+                game_state.apply_action_mut(&outcomes[outcome].0);
+                continue;
+            }
+
+            game_state.legal_actions_into(&mut actions);
+            if actions.len() == 0 || !game_state.status_with_moves_left() {
+                break;
+            }
+
+            // Non-terminal states are scored by the evaluator without a rollout.
+            if let Some(evaluator) = self.searcher.evaluator.as_mut() {
+                return (GameResult::Score(evaluator.evaluate(&game_state).value), count);
+            }
+            
+            // The rollout policy can end the rollout early.
+            if let Some(result) = self.searcher.rollout_policy.cutoff(&game_state, count) {
+                return (result, count);
+            }
+
+            // Truncated rollouts are scored by the static evaluation.
+            if self.searcher.rollout_depth_limit.is_some_and(|limit| count >= limit) {
+                return (GameResult::Score(game_state.evaluate()), count);
+            }
+
+            // Rollouts which run past the move cap are scored as draws.
+            if self.searcher.rollout_move_cap.is_some_and(|cap| count >= cap) {
+                return (GameResult::Draw, count);
+            }
+            
+            // Choose an action with the rollout policy and apply it to the state.
+            let chosen = self.searcher.rollout_policy.choose(&game_state, &actions, &mut self.searcher.random_generator);
+            game_state.apply_action_mut(&actions[chosen]);
+            count += 1;
+        }
+        return (game_state.result(), count);
+    }
+
+    /// Backpropagates a game result up the tree, starting at node index.
+    ///
+    /// For every node propagated, if the node is the same side as the winning side, one is added to wins,
+    /// otherwise one is added to either draws or nothing.
+    /// In either case one is added to simulations.
+    ///
+    /// The reward of the result is added to the score of every node. In two player mode it is the reward
+    /// of the player who moved into the node, in single player mode it is the raw simulation score.
+    /// Rewards are discounted by `discount` for every move between the node and the end of the simulation.
+    ///
+    /// # Arguments
+    /// * `current_node` : The current node that is being backpropagated.
+    ///
+    /// * `result` : The result of the simulation that is being backpropagated against.
+    ///
+    /// # Panics
+    /// If `current_node` is stale.
+    pub fn backpropagate(&mut self, current_node: NodeId, result: GameResult) {
+        self.backpropagate_at(self.index_of(current_node), result);
+    }
+
+    /// `backpropagate` by arena index.
+    pub(crate) fn backpropagate_at(&mut self, mut current_node: NodeIndex, result: GameResult) {
+        #[cfg(feature = "trace")]
+        let _span = trace::span(Phase::Backpropagate, current_node);
+
+        self.counters.iterations += 1;
+        let rewards = match self.searcher.simulation_rewards.take() {
+            Some(rewards) => rewards,
+            None => self.simulation_rewards(&result),
+        };
+        if self.searcher.rollout_policy.learns() {
+            self.teach_rollout_policy(current_node, rewards);
+        }
+
+        // The simulated moves are only discounted once.
+        let mut discount_factor = if self.searcher.discount == 1.0 { 1.0 } else { Float::powf(self.searcher.discount, self.searcher.simulation_moves as f64) };
+        self.searcher.simulation_moves = 0;
+        loop {
+            // True if the node is credited to the first player.
+            let side_bool = self.credited_side_at(current_node);
+            let current_stats = &mut self.arena.stats[current_node as usize];
+
+            if result.is_draw() {
+                current_stats.draws = add_count(current_stats.draws, 1);
             }
             
             // There is a winning player.
-            else {
-                // Converts the result into a bool where true indicates the first player has
-                // won and false indicates the second player has won. This is compared to 
-                // the side due to move.
-                // Note: First player being due to move means that it is the second players turn.
-                let result_bool = result == GameResult::FirstPlayerWin;
-                let side_bool = !current_node_object.game_state.side_to_move();
+            // The winner is a bool where true indicates the first player has won and false 
+            // indicates the second player has won. This is compared to the side that moved into the node.
+            else if let Some(result_bool) = result.winner() {
                 if result_bool == side_bool {
-                    current_node_object.wins += 1;
+                    current_stats.wins = add_count(current_stats.wins, 1);
+                }
+            }
+
+            let credited = discount_factor * rewards.of(side_bool);
+            current_stats.score += credited;
+            if self.searcher.normalize_rewards {
+                let (min, max) = self.reward_bounds.unwrap_or((credited, credited));
+                self.reward_bounds = Some((min.min(credited), max.max(credited)));
+            }
+
+            // A simulation count is added for every node that is backpropagated.
+            current_stats.sims = add_count(current_stats.sims, 1);
+
+            // Stop backpropagated if the root node is reached.
+            let parent = self.arena.topology[current_node as usize].parent;
+            if parent.is_none() {
+                break;
+            }
+
+            // The current node becomes the parent.
+            current_node = parent.expect("no parent");
+            if !self.arena.topology[current_node as usize].is_chance {
+                discount_factor *= self.searcher.discount;
+            }
+        }
+    }
+
+    /// The side `node` is credited to under `perspective`, true for the first player.
+    ///
+    /// The root, which no move leads to, is credited to the opponent of its side to move.
+    ///
+    /// # Panics
+    /// If `node` is stale.
+    pub fn credited_side(&self, node: NodeId) -> bool {
+        return self.credited_side_at(self.index_of(node));
+    }
+
+    /// `credited_side` by arena index.
+    pub(crate) fn credited_side_at(&self, node: NodeIndex) -> bool {
+        if self.searcher.perspective == Perspective::Mover {
+            let mut current_node = node;
+            while let Some(parent) = self.arena.topology[current_node as usize].parent {
+                if !self.arena.topology[parent as usize].is_chance {
+                    return self.arena.topology[parent as usize].side_to_move;
                 }
+                current_node = parent;
+            }
+        }
+        return !self.arena.topology[node as usize].side_to_move;
+    }
+
+    /// Rewards of either side for `result`, as credited by backpropagation.
+    fn simulation_rewards(&self, result: &GameResult) -> SimulationRewards {
+        match self.searcher.player_mode {
+            PlayerMode::TwoPlayer => return SimulationRewards {
+                first_player: reward(result, true, self.searcher.margin_weight, self.searcher.draw_score),
+                second_player: reward(result, false, self.searcher.margin_weight, self.searcher.draw_score),
+            },
+            PlayerMode::SinglePlayer => {
+                let score = reward(result, true, self.searcher.margin_weight, self.searcher.draw_score);
+                return SimulationRewards { first_player: score, second_player: score };
+            },
+        }
+    }
+
+    /// Passes the moves selected down to `node` and the `rewards` of the simulation to the rollout policy.
+    ///
+    /// Action codes are read from the state of the parent of every node, which is re-derived
+    /// for parents without a stored state.
+    fn teach_rollout_policy(&mut self, node: NodeIndex, rewards: SimulationRewards) {
+        let mut tree_moves = Vec::new();
+        let mut current_node = node;
+        while let Some(parent) = self.arena.topology[current_node as usize].parent {
+            if !self.arena.topology[parent as usize].is_chance {
+                let parent_game_state = self.game_state_at(parent);
+                let action = self.arena.actions[current_node as usize].as_ref().expect("no action");
+                if let Some(code) = parent_game_state.action_code(action) {
+                    tree_moves.push((code, parent_game_state.side_to_move()));
+                }
+            }
+            current_node = parent;
+        }
+        tree_moves.reverse();
+        self.searcher.rollout_policy.learn(&tree_moves, rewards);
+    }
+
+    /// Gives the list of actions that leads to a specific leaf node in the tree
+    /// from `current_node`.
+    ///
+    /// The action taken to get to the current state is not included in the path.
+    ///
+    /// # Panics
+    /// If `current_node` is stale.
+    pub fn trace_path(&self, current_node: NodeId) -> Vec<NodeId> {
+        return self.trace_path_at(self.index_of(current_node)).into_iter().map(|node| self.arena.handle(node)).collect();
+    }
+
+    /// `trace_path` by arena index.
+    #[inline(always)]
+    pub(crate) fn trace_path_at(&self, mut current_node: NodeIndex) -> Vec<NodeIndex> {
+        let mut path: Vec<NodeIndex> = Vec::new();
+        
+        // Constructs the path backwards, starting from the leaf node, and then reverses it.
+        loop {
+            // If the node has no parent, it is a root node and the path tracing has finished.
+            if self.arena.topology[current_node as usize].parent.is_none() {
+                break;
+            }
+            
+            // Push current node to temporary path and replace 
+            path.push(current_node);
+            current_node = self.arena.topology[current_node as usize].parent.expect("no parent");
+        }
+        return path.into_iter().rev().collect();
+    }
+
+    /// The policy of the search at the root, as the share of the root's visits every action received.
+    ///
+    /// Used as the training target of policy networks, or to sample moves with a temperature.
+    ///
+    /// # Returns
+    /// Every expanded action with the visits of its child and its probability, followed by the
+    /// actions which have not been expanded, with no visits. Probabilities add up to 1 once the
+    /// children of the root have been visited, and are all 0 before.
+    pub fn root_policy(&self) -> Vec<(Action, u64, f32)>
+    where
+        Action: Clone
+    {
+        let visits: u64 = self.arena.expanded(0).map(|child| self.arena.stats[child as usize].sims).sum();
+        let mut policy: Vec<(Action, u64, f32)> = self.arena.expanded(0).map(|child| {
+            let action = self.arena.actions[child as usize].clone().expect("no action");
+            let sims = self.arena.stats[child as usize].sims;
+            return (action, sims, if visits == 0 { 0.0 } else { sims as f32 / visits as f32 });
+        }).collect();
+        policy.extend(self.arena.unexpanded(0).map(|action| (action.clone(), 0, 0.0)));
+        return policy;
+    }
+
+    /// Samples a root action proportionally to `visits^(1/temperature)`, as the move played
+    /// when generating self-play data.
+    ///
+    /// A temperature of 1 samples proportionally to the visits, higher temperatures flatten
+    /// the distribution and lower temperatures sharpen it, down to 0 which always plays the
+    /// most visited action. Actions without visits are never sampled.
+    ///
+    /// # Arguments
+    /// * `temperature` : Non-negative temperature of the distribution.
+    /// * `rng` : Random generator to sample with. The random generator of the tree if None.
+    ///
+    /// # Returns
+    /// The sampled action, None if no child of the root has been visited.
+    ///
+    /// # Panics
+    /// If `temperature` is negative or NaN.
+    pub fn sample_action(&mut self, temperature: f32, rng: Option<&mut SearchRng>) -> Option<Action>
+    where
+        Action: Clone
+    {
+        assert!(temperature >= 0.0, "temperature must be non-negative");
+        let mut policy = self.root_policy();
+        policy.retain(|(_, visits, _)| *visits > 0);
+        let max_visits = policy.iter().map(|(_, visits, _)| *visits).max()?;
+        if temperature == 0.0 {
+            // Ties go to the first expanded child, as for the most visited child.
+            let best = policy.into_iter().find(|(_, visits, _)| *visits == max_visits).expect("no most visited action");
+            return Some(best.0);
+        }
+
+        // Visits are taken relative to the most visited action, so low temperatures cannot overflow.
+        let weights: Vec<f32> = policy.iter()
+            .map(|(_, visits, _)| Float::powf(*visits as f64 / max_visits as f64, 1.0 / temperature as f64) as f32)
+            .collect();
+        let rng = match rng {
+            Some(rng) => rng,
+            None => &mut self.searcher.random_generator,
+        };
+        let chosen = sample_weighted(&weights, rng);
+        return Some(policy.swap_remove(chosen).0);
+    }
+
+    /// Runs `iterations` iterations of search from the root.
+    ///
+    /// Every iteration selects a leaf with the default exploration factor, expands it,
+    /// simulates the expanded node and backpropagates the result. The time spent searching
+    /// is added to `counters`. See `search_with_hooks` for a search invoking callbacks.
+    pub fn search(&mut self, iterations: u64) {
+        self.search_with_hooks(iterations, &mut ());
+    }
+}
+
+
+
+/// Reward a player receives from `result`.
+///
+/// Wins are worth 1, draws `draw_score` and losses nothing.
+///
+/// # Arguments
+/// * `result` : The result of a simulation.
+///
+/// * `first_player` : True for the reward of the first player, false for the second player.
+///
+/// * `margin_weight` : Reward per unit of margin of `GameResult::Margin` results,
+/// added for the player ahead and subtracted for the player behind.
+///
+/// * `draw_score` : Reward of either player for a draw.
+fn reward(result: &GameResult, first_player: bool, margin_weight: f64, draw_score: f64) -> f64 {
+    match result {
+        GameResult::FirstPlayerWin => return if first_player { 1.0 } else { 0.0 },
+        GameResult::SecondPlayerWin => return if first_player { 0.0 } else { 1.0 },
+        GameResult::Draw => return draw_score,
+        GameResult::Margin(margin) if *margin == 0.0 => return draw_score,
+        GameResult::Score(score) => return if first_player { *score as f64 } else { 1.0 - *score as f64 },
+        GameResult::Margin(margin) => {
+            let first_player_margin = *margin as f64 * margin_weight;
+            let (win, margin_reward) = if first_player { (*margin > 0.0, first_player_margin) } else { (*margin < 0.0, -first_player_margin) };
+            return if win { 1.0 } else { 0.0 } + margin_reward;
+        },
+    }
+}
+
+
+/// Unit tests for components of the MCTS tree.
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rng::SeedableRng;
+
+    /// Placeholder game-state which holds only basic internal logic
+    /// it has the neccecary logic to test everything except for 
+    /// the simulation/rollout function.
+    #[derive(Debug, Clone)]
+    struct PlaceHolderState {
+        last_action_made: u16,
+        depth_counter: u16
+    }
+
+    impl GameState<u16> for PlaceHolderState {
+        type ParseError = core::convert::Infallible;
+
+        fn from_str(_starting_fen: String) -> Self {
+            return PlaceHolderState {last_action_made: 0, depth_counter: 0};
+        }
+        
+        fn apply_action(&self, action: &u16) -> Self {
+            return PlaceHolderState {
+                last_action_made: *action, 
+                depth_counter: self.depth_counter + 1
+            };
+        }
+        
+        fn status_with_moves_left(&self) -> bool {
+            return false;
+        }
+        
+        fn result(&self) -> GameResult {
+            return GameResult::Draw;
+        }
+        
+        fn generate_legal_actions(&self) -> Vec<u16> {
+            return Vec::new();
+        }
+        
+        fn side_to_move(&self) -> bool {
+            return self.depth_counter.is_multiple_of(2);
+        }
+    }
+
+    /// Game-state which generates its actions lazily. Every position 
+    /// has three legal actions until a depth of two is reached.
+    #[derive(Debug, Clone)]
+    struct LazyState {
+        depth_counter: u16
+    }
+
+    impl GameState<u16> for LazyState {
+        type ParseError = core::convert::Infallible;
+
+        const LAZY_ACTIONS: bool = true;
+
+        fn from_str(_starting_fen: String) -> Self {
+            return LazyState {depth_counter: 0};
+        }
+        
+        fn apply_action(&self, _action: &u16) -> Self {
+            return LazyState {depth_counter: self.depth_counter + 1};
+        }
+        
+        fn status_with_moves_left(&self) -> bool {
+            return true;
+        }
+        
+        fn result(&self) -> GameResult {
+            return GameResult::Draw;
+        }
+        
+        fn generate_legal_actions(&self) -> Vec<u16> {
+            panic!("lazy states should not generate every action");
+        }
+
+        fn legal_action_at(&self, index: usize) -> Option<u16> {
+            if self.depth_counter >= 2 || index >= 3 {
+                return None;
+            }
+            return Some(index as u16);
+        }
+        
+        fn side_to_move(&self) -> bool {
+            return self.depth_counter.is_multiple_of(2);
+        }
+    }
+
+    /// Game-state with a single chance event. The first player picks
+    /// action 0 or 1, after which a chance event results in outcome 10 
+    /// with probability 0.25 or outcome 11 with probability 0.75.
+    /// The first player wins on outcome 11.
+    #[derive(Debug, Clone)]
+    struct ChanceState {
+        actions_made: Vec<u16>
+    }
+
+    impl GameState<u16> for ChanceState {
+        type ParseError = core::convert::Infallible;
+
+        fn from_str(_starting_fen: String) -> Self {
+            return ChanceState {actions_made: Vec::new()};
+        }
+        
+        fn apply_action(&self, action: &u16) -> Self {
+            let mut actions_made = self.actions_made.clone();
+            actions_made.push(*action);
+            return ChanceState {actions_made};
+        }
+        
+        fn status_with_moves_left(&self) -> bool {
+            return true;
+        }
+        
+        fn result(&self) -> GameResult {
+            if self.actions_made.last() == Some(&11) {
+                return GameResult::FirstPlayerWin;
+            }
+            return GameResult::SecondPlayerWin;
+        }
+        
+        fn generate_legal_actions(&self) -> Vec<u16> {
+            if self.actions_made.len() == 0 {
+                return vec![0, 1];
+            }
+            return Vec::new();
+        }
+
+        fn is_chance_node(&self) -> bool {
+            return self.actions_made.len() == 1;
+        }
+
+        fn chance_outcomes(&self) -> Vec<(u16, f32)> {
+            return vec![(10, 0.25), (11, 0.75)];
+        }
+        
+        fn side_to_move(&self) -> bool {
+            return self.actions_made.len() == 0;
+        }
+    }
+
+    /// Single player state, where two numbers are picked and the score is their sum.
+    #[derive(Clone)]
+    struct SumState {
+        actions_made: Vec<u16>
+    }
+
+    impl GameState<u16> for SumState {
+        type ParseError = core::convert::Infallible;
+
+        fn from_str(_starting_fen: String) -> Self {
+            return SumState {actions_made: Vec::new()};
+        }
+        
+        fn apply_action(&self, action: &u16) -> Self {
+            let mut actions_made = self.actions_made.clone();
+            actions_made.push(*action);
+            return SumState {actions_made};
+        }
+        
+        fn status_with_moves_left(&self) -> bool {
+            return true;
+        }
+        
+        fn result(&self) -> GameResult {
+            return GameResult::Score(self.actions_made.iter().sum::<u16>() as f32);
+        }
+        
+        fn generate_legal_actions(&self) -> Vec<u16> {
+            if self.actions_made.len() < 2 {
+                return vec![1, 2, 3];
+            }
+            return Vec::new();
+        }
+        
+        fn side_to_move(&self) -> bool {
+            return self.actions_made.len().is_multiple_of(2);
+        }
+    }
+
+    /// Two player state where the first player moves twice before the second player moves once,
+    /// and the first player wins if it picked action 1 both times.
+    #[derive(Clone)]
+    struct DoubleMoveState {
+        actions_made: Vec<u16>
+    }
+
+    impl GameState<u16> for DoubleMoveState {
+        type ParseError = core::convert::Infallible;
+
+        fn from_str(_starting_fen: String) -> Self {
+            return DoubleMoveState {actions_made: Vec::new()};
+        }
+
+        fn apply_action(&self, action: &u16) -> Self {
+            let mut actions_made = self.actions_made.clone();
+            actions_made.push(*action);
+            return DoubleMoveState {actions_made};
+        }
+
+        fn status_with_moves_left(&self) -> bool {
+            return true;
+        }
+
+        fn result(&self) -> GameResult {
+            if self.actions_made[..2] == [1, 1] {
+                return GameResult::FirstPlayerWin;
+            }
+            return GameResult::SecondPlayerWin;
+        }
+
+        fn generate_legal_actions(&self) -> Vec<u16> {
+            if self.actions_made.len() < 3 {
+                return vec![0, 1];
+            }
+            return Vec::new();
+        }
+
+        fn side_to_move(&self) -> bool {
+            return self.actions_made.len() < 2;
+        }
+    }
+
+    /// State with a single decision between three actions with priors.
+    #[derive(Clone)]
+    struct PriorState {
+        actions_made: Vec<u16>
+    }
+
+    impl GameState<u16> for PriorState {
+        type ParseError = core::convert::Infallible;
+
+        fn from_str(_starting_fen: String) -> Self {
+            return PriorState {actions_made: Vec::new()};
+        }
+        
+        fn apply_action(&self, action: &u16) -> Self {
+            let mut actions_made = self.actions_made.clone();
+            actions_made.push(*action);
+            return PriorState {actions_made};
+        }
+        
+        fn status_with_moves_left(&self) -> bool {
+            return true;
+        }
+        
+        fn result(&self) -> GameResult {
+            return GameResult::Draw;
+        }
+        
+        fn generate_legal_actions(&self) -> Vec<u16> {
+            if self.actions_made.len() == 0 {
+                return vec![0, 1, 2];
             }
+            return Vec::new();
+        }
 
-            // A simulation count is added for every node that is backpropagated.
-            current_node_object.sims += 1;
-
-            // Stop backpropagated if the root node is reached.
-            if current_node_object.parent.is_none() {
-                break;
+        fn action_priors(&self) -> Vec<(u16, f32)> {
+            if self.actions_made.len() == 0 {
+                return vec![(0, 0.2), (1, 0.5), (2, 0.3)];
             }
-
-            // The current node becomes the parent.
-            current_node = current_node_object.parent.expect("no parent");
+            return Vec::new();
         }
-    }
-
-    /// Gives the list of actions that leads to a specific leaf node in the tree
-    /// from `current_node`.
-    ///
-    /// The action taken to get to the current state is not included in the path.
-    #[inline(always)]
-    pub fn trace_path(&self, mut current_node: usize) -> Vec<usize> {
-        let mut path: Vec<usize> = Vec::new();
         
-        // Constructs the path backwards, starting from the leaf node, and then reverses it.
-        loop {
-            // If the node has no parent, it is a root node and the path tracing has finished.
-            if self.arena[current_node].parent.is_none() {
-                break;
-            }
-            
-            // Push current node to temporary path and replace 
-            path.push(current_node);
-            current_node = self.arena[current_node].parent.expect("no parent");
+        fn side_to_move(&self) -> bool {
+            return self.actions_made.len() == 0;
         }
-        return path.into_iter().rev().collect();
     }
-}
-
-
-
-/// Unit tests for components of the MCTS tree.
-#[cfg(test)]
-mod tests {
-    use super::*;
 
-    /// Placeholder game-state which holds only basic internal logic
-    /// it has the neccecary logic to test everything except for 
-    /// the simulation/rollout function.
-    #[derive(Debug, Clone)]
-    struct PlaceHolderState {
-        last_action_made: u16,
-        depth_counter: u16
+    /// State which only supports in-place actions, with a game of 3 moves.
+    #[derive(Clone)]
+    struct InPlaceState {
+        actions_made: Vec<u16>
     }
 
-    impl GameState<u16> for PlaceHolderState {
+    impl GameState<u16> for InPlaceState {
+        type ParseError = core::convert::Infallible;
+
         fn from_str(_starting_fen: String) -> Self {
-            return PlaceHolderState {last_action_made: 0, depth_counter: 0};
+            return InPlaceState {actions_made: Vec::new()};
         }
         
-        fn apply_action(&self, action: &u16) -> Self {
-            return PlaceHolderState {
-                last_action_made: *action, 
-                depth_counter: self.depth_counter + 1
-            };
+        fn apply_action(&self, _action: &u16) -> Self {
+            panic!("copying apply_action used");
+        }
+
+        fn apply_action_mut(&mut self, action: &u16) {
+            self.actions_made.push(*action);
         }
         
         fn status_with_moves_left(&self) -> bool {
-            return false;
+            return self.actions_made.len() < 3;
         }
         
         fn result(&self) -> GameResult {
@@ -367,11 +1656,11 @@ mod tests {
         }
         
         fn generate_legal_actions(&self) -> Vec<u16> {
-            return Vec::new();
+            return vec![0, 1];
         }
         
         fn side_to_move(&self) -> bool {
-            return (self.depth_counter % 2) == 0;
+            return self.actions_made.len().is_multiple_of(2);
         }
     }
 
@@ -385,98 +1674,121 @@ mod tests {
             "".to_string(), 
             10
         );
-        tree.arena[0].wins = 5;
-        tree.arena[0].sims = 12;
-        tree.arena[0].expanded = vec![1, 8];
+        tree.arena.stats[0].wins = 5;
+        tree.arena.stats[0].score = 5.0;
+        tree.arena.stats[0].sims = 12;
+        tree.arena.set_children(0, vec![1, 8], Vec::new());
         // Left branch in example tree
         tree.arena.push(MCTSNode {
             game_state: PlaceHolderState {last_action_made: 0, depth_counter: 1}, 
+            action: Some(0),
             parent: Some(0), 
             expanded: vec![2, 4, 5], 
             unexpanded: Vec::new(), 
-            wins: 5, draws: 0, sims: 8
-        });
+            wins: 5, draws: 0, sims: 8, score: 5.0,
+            data: None
+        }, true);
         // Left-Left branch in example tree.
         tree.arena.push(MCTSNode {
             game_state: PlaceHolderState {last_action_made: 0, depth_counter: 2}, 
+            action: Some(0),
             parent: Some(1), 
             expanded: vec![3], 
             unexpanded: Vec::new(), 
-            wins: 1, draws: 0, sims: 2
-        });        
+            wins: 1, draws: 0, sims: 2, score: 1.0,
+            data: None
+        }, true);        
         // Left-Left-Mid branch in example tree.
         tree.arena.push(MCTSNode {
             game_state: PlaceHolderState {last_action_made: 0, depth_counter: 3}, 
+            action: Some(0),
             parent: Some(2), 
             expanded: vec![], 
             unexpanded: vec![10, 11], 
-            wins: 1, draws: 0, sims: 1
-        });        
+            wins: 1, draws: 0, sims: 1, score: 1.0,
+            data: None
+        }, true);        
         // Left-Mid branch in example tree.
         tree.arena.push(MCTSNode {
             game_state: PlaceHolderState {last_action_made: 0, depth_counter: 2}, 
+            action: Some(0),
             parent: Some(1), 
             expanded: vec![], 
             unexpanded: Vec::new(), 
-            wins: 0, draws: 0, sims: 1
-        });     
+            wins: 0, draws: 0, sims: 1, score: 0.0,
+            data: None
+        }, true);     
         // Left-Right branch in example tree.
         tree.arena.push(MCTSNode {
             game_state: PlaceHolderState {last_action_made: 0, depth_counter: 2}, 
+            action: Some(0),
             parent: Some(1), 
             expanded: vec![6, 7], 
             unexpanded: Vec::new(), 
-            wins: 2, draws: 0, sims: 4
-        });     
+            wins: 2, draws: 0, sims: 4, score: 2.0,
+            data: None
+        }, true);     
         // Left-Right-Left branch in example tree.
         tree.arena.push(MCTSNode {
             game_state: PlaceHolderState {last_action_made: 0, depth_counter: 3}, 
+            action: Some(0),
             parent: Some(5), 
             expanded: vec![], 
             unexpanded: Vec::new(), 
-            wins: 0, draws: 0, sims: 1
-        });     
+            wins: 0, draws: 0, sims: 1, score: 0.0,
+            data: None
+        }, true);     
         // Left-Right-Right branch in example tree.
         tree.arena.push(MCTSNode {
             game_state: PlaceHolderState {last_action_made: 0, depth_counter: 3}, 
+            action: Some(0),
             parent: Some(5), 
             expanded: vec![], 
             unexpanded: Vec::new(), 
-            wins: 2, draws: 0, sims: 2
-        });     
+            wins: 2, draws: 0, sims: 2, score: 2.0,
+            data: None
+        }, true);     
 
         // Right branch in example tree.
         tree.arena.push(MCTSNode {
             game_state: PlaceHolderState {last_action_made: 0, depth_counter: 1}, 
+            action: Some(0),
             parent: Some(0), 
             expanded: vec![9, 10], 
             unexpanded: Vec::new(), 
-            wins: 2, draws: 0, sims: 4
-        });    
+            wins: 2, draws: 0, sims: 4, score: 2.0,
+            data: None
+        }, true);    
         // Right-Left in example tree.
         tree.arena.push(MCTSNode {
             game_state: PlaceHolderState {last_action_made: 0, depth_counter: 2}, 
+            action: Some(0),
             parent: Some(8), 
             expanded: vec![], 
             unexpanded: Vec::new(), 
-            wins: 1, draws: 0, sims: 1
-        });    
+            wins: 1, draws: 0, sims: 1, score: 1.0,
+            data: None
+        }, true);    
         // Right-Right in example tree.
         tree.arena.push(MCTSNode {
             game_state: PlaceHolderState {last_action_made: 0, depth_counter: 2}, 
+            action: Some(0),
             parent: Some(8), 
             expanded: vec![11], 
             unexpanded: Vec::new(), 
-            wins: 1, draws: 0, sims: 2
-        });    
+            wins: 1, draws: 0, sims: 2, score: 1.0,
+            data: None
+        }, true);    
         // Right-Right-Mid branch in example tree.
         tree.arena.push(MCTSNode {
             game_state: PlaceHolderState {last_action_made: 0, depth_counter: 3}, 
+            action: Some(0),
             parent: Some(10),
             expanded: vec![], 
             unexpanded: Vec::new(), 
-            wins: 0, draws: 0, sims: 1
-        });    
+            wins: 0, draws: 0, sims: 1, score: 0.0,
+            data: None
+        }, true);    
 
         return tree;
     }
@@ -486,7 +1798,98 @@ mod tests {
     #[should_panic]
     fn test_uct_root() {
         let tree = test_generate_example_tree();
-        tree.uct(0, Some(f32::sqrt(2.0)));
+        tree.uct_at(0, Some(f32::sqrt(2.0)));
+    }
+
+    /// Test if selection breaks ties between equally valued children uniformly at random.
+    #[test]
+    fn test_random_tie_breaking() {
+        let mut tree = MCTSTree::<u16, SumState>::with_capacity(100, Some(1), "".to_string(), 3);
+        tree.searcher.player_mode = PlayerMode::SinglePlayer;
+        let children: Vec<NodeIndex> = (0..3).map(|_| tree.expand_at(0)).collect();
+        for child in &children {
+            tree.backpropagate_at(*child, GameResult::Score(1.0));
+        }
+
+        let mut counts = [0; 3];
+        for _i in 0..300 {
+            let selected = tree.select_child_at(0, None).unwrap();
+            counts[children.iter().position(|child| *child == selected).unwrap()] += 1;
+        }
+        assert!(counts.iter().all(|count| *count > 70));
+
+        tree.searcher.random_tie_breaking = false;
+        assert_eq!(tree.select_child_at(0, None), Some(children[0]));
+        assert_eq!(tree.get_max_uct_child_at(0, None), Some(children[0]));
+    }
+
+    /// Test if nodes without expanded children have no best child, rather than the root.
+    #[test]
+    fn test_max_uct_child_without_children() {
+        let mut tree = MCTSTree::<u16, SumState>::with_capacity(100, Some(1), "".to_string(), 3);
+        assert_eq!(tree.get_max_uct_child_at(0, None), None);
+        assert_eq!(tree.select_child_at(0, None), None);
+
+        // Leaves and terminal nodes have no best child either.
+        let child = tree.expand_at(0);
+        assert_eq!(tree.get_max_uct_child_at(child, None), None);
+        let grandchild = tree.expand_at(child);
+        assert_eq!(tree.get_max_uct_child_at(grandchild, None), None);
+        assert_eq!(tree.select_at(grandchild, None), grandchild);
+
+        // Children whose values can not be compared are still picked.
+        tree.arena.stats[child as usize].sims = 1;
+        tree.arena.stats[child as usize].score = f64::NAN;
+        assert_eq!(tree.get_max_uct_child_at(0, None), Some(child));
+        assert_eq!(tree.select_child_at(0, None), Some(child));
+    }
+
+    /// Test if selection fails loudly on child links forming a cycle, rather than never ending.
+    #[test]
+    #[should_panic(expected = "selection cycle")]
+    fn test_select_cycle() {
+        let mut tree = MCTSTree::<u16, SumState>::with_capacity(100, Some(1), "".to_string(), 3);
+        for _ in 0..3 {
+            let child = tree.expand_at(0);
+            tree.backpropagate_at(child, GameResult::Score(1.0));
+        }
+        let children: Vec<NodeIndex> = tree.arena.expanded(0).collect();
+        for child in children.iter() {
+            tree.arena.set_children(*child, vec![0], vec![]);
+        }
+        tree.arena.topology[0].parent = Some(children[0]);
+        tree.select_at(0, None);
+    }
+
+    /// Test if children differing by a single simulation out of tens of millions have distinct UCTs.
+    #[test]
+    #[cfg(feature = "double-precision")]
+    fn test_double_precision() {
+        let mut tree = MCTSTree::<u16, SumState>::with_capacity(100, Some(1), "".to_string(), 3);
+        let first = tree.expand_at(0);
+        let second = tree.expand_at(0);
+        tree.arena.stats[0].sims = 1 << 26;
+        tree.arena.stats[first as usize].sims = 1 << 25;
+        tree.arena.stats[second as usize].sims = (1 << 25) + 1;
+        assert!(tree.uct_at(first, None) > tree.uct_at(second, None));
+    }
+
+    /// Test if unvisited children have an infinite UCT, or the first play urgency when it is set.
+    #[test]
+    fn test_uct_unvisited() {
+        let mut tree = MCTSTree::<u16, SumState>::with_capacity(100, Some(1), "".to_string(), 3);
+        let child = tree.expand_at(0);
+        assert_eq!(tree.uct_at(child, None), Value::INFINITY);
+        assert_eq!(tree.puct_at(child, Some(1.0)), 0.0);
+        assert_eq!(tree.node_value_at(child), 0.0);
+
+        tree.searcher.first_play_urgency = Some(0.25);
+        assert_eq!(tree.uct_at(child, None), 0.25);
+        assert_eq!(tree.puct_at(child, Some(1.0)), 0.25);
+
+        // A visited child of a parent without simulations has a finite UCT.
+        tree.arena.stats[child as usize].sims = 1;
+        assert_eq!(tree.uct_at(child, None), 0.0);
     }
 
     /// Tests if the uct function generates the correct uct values.
@@ -494,29 +1897,82 @@ mod tests {
     #[test]
     fn test_uct() {
         let tree = test_generate_example_tree();
-        assert!(format!("{:.3}", tree.uct(1, Some(f32::sqrt(2.0)))) == "1.413");
-        assert!(format!("{:.3}", tree.uct(2, Some(f32::sqrt(2.0)))) == "1.942");
-        assert!(format!("{:.3}", tree.uct(3, Some(f32::sqrt(2.0)))) == "2.177");
-        assert!(format!("{:.3}", tree.uct(4, Some(f32::sqrt(2.0)))) == "2.039");
-        assert!(format!("{:.3}", tree.uct(5, Some(f32::sqrt(2.0)))) == "1.520");
-        assert!(format!("{:.3}", tree.uct(6, Some(f32::sqrt(2.0)))) == "1.665");
-        assert!(format!("{:.3}", tree.uct(7, Some(f32::sqrt(2.0)))) == "2.177");
-        assert!(format!("{:.3}", tree.uct(8, Some(f32::sqrt(2.0)))) == "1.615");
-        assert!(format!("{:.3}", tree.uct(9, Some(f32::sqrt(2.0)))) == "2.665");
-        assert!(format!("{:.3}", tree.uct(10,Some(f32::sqrt(2.0)))) == "1.677");
-        assert!(format!("{:.3}", tree.uct(11,Some(f32::sqrt(2.0)))) == "1.177");
+        assert!(format!("{:.3}", tree.uct_at(1, Some(f32::sqrt(2.0)))) == "1.413");
+        assert!(format!("{:.3}", tree.uct_at(2, Some(f32::sqrt(2.0)))) == "1.942");
+        assert!(format!("{:.3}", tree.uct_at(3, Some(f32::sqrt(2.0)))) == "2.177");
+        assert!(format!("{:.3}", tree.uct_at(4, Some(f32::sqrt(2.0)))) == "2.039");
+        assert!(format!("{:.3}", tree.uct_at(5, Some(f32::sqrt(2.0)))) == "1.520");
+        assert!(format!("{:.3}", tree.uct_at(6, Some(f32::sqrt(2.0)))) == "1.665");
+        assert!(format!("{:.3}", tree.uct_at(7, Some(f32::sqrt(2.0)))) == "2.177");
+        assert!(format!("{:.3}", tree.uct_at(8, Some(f32::sqrt(2.0)))) == "1.615");
+        assert!(format!("{:.3}", tree.uct_at(9, Some(f32::sqrt(2.0)))) == "2.665");
+        assert!(format!("{:.3}", tree.uct_at(10,Some(f32::sqrt(2.0)))) == "1.677");
+        assert!(format!("{:.3}", tree.uct_at(11,Some(f32::sqrt(2.0)))) == "1.177");
     }
 
     /// Tests if the select function selects the correct node from
     /// the tree based on the example tree.
+    #[test]
+    fn test_puct() {
+        let tree = test_generate_example_tree();
+        assert!(format!("{:.3}", tree.puct_at(1, Some(f32::sqrt(2.0)))) == "1.169");
+        assert!(format!("{:.3}", tree.puct_at(9, Some(f32::sqrt(2.0)))) == "2.414");
+    }
+
+    #[test]
+    fn test_expand_priors() {
+        let mut tree = MCTSTree::<u16, PriorState>::with_capacity(100, Some(1), "".to_string(), 3);
+        tree.searcher.selection_policy = SelectionPolicy::Puct;
+        assert!(tree.expand_at(0) == 1);
+
+        // Every action is expanded in order of prior.
+        let children: Vec<NodeIndex> = tree.arena.expanded(0).collect();
+        let actions: Vec<u16> = children.iter().map(|child| tree.arena.actions[*child as usize].expect("no action")).collect();
+        let priors: Vec<f32> = children.iter().map(|child| tree.arena.topology[*child as usize].probability).collect();
+        assert_eq!(actions, vec![1, 2, 0]);
+        assert_eq!(priors, vec![0.5, 0.3, 0.2]);
+        assert!(!tree.arena.has_untried_actions(0));
+
+        // Unvisited children are picked by prior, once the visited child is worth nothing.
+        tree.searcher.draw_score = 0.0;
+        tree.backpropagate_at(1, GameResult::Draw);
+        assert!(tree.select_at(0, None) == 2);
+    }
+
     #[test]
     fn test_select() {
         let mut tree = test_generate_example_tree();
-        println!("{}", tree.select(0, Some(f32::sqrt(2.0))));
-        assert!(tree.select(0, Some(f32::sqrt(2.0))) == 9);
-        tree.arena[1].wins = 8;
-        assert!(tree.select(0, Some(f32::sqrt(2.0))) == 4);
+        println!("{}", tree.select_at(0, Some(f32::sqrt(2.0))));
+        assert!(tree.select_at(0, Some(f32::sqrt(2.0))) == 9);
+        tree.arena.stats[1].wins = 8;
+        tree.arena.stats[1].score = 8.0;
+        assert!(tree.select_at(0, Some(f32::sqrt(2.0))) == 4);
+
+    }
+
+    /// Tests if exploration schedules give the expected factors, and if selection follows
+    /// the schedule of the tree when no factor is given.
+    #[test]
+    fn test_exploration_schedule() {
+        let linear = ExplorationSchedule::Linear { start: 2.0, end: 1.0, visits: 100 };
+        assert_eq!(linear.factor(0), 2.0);
+        assert_eq!(linear.factor(50), 1.5);
+        assert_eq!(linear.factor(1000), 1.0);
+        let logarithmic = ExplorationSchedule::Logarithmic { init: 1.25, base: 19652.0 };
+        assert!((logarithmic.factor(0) - 1.25).abs() < 1e-4);
+        assert!(logarithmic.factor(100000) > logarithmic.factor(1000));
 
+        // Selections are compared with each other, so ties are broken deterministically.
+        let mut tree = test_generate_example_tree();
+        tree.searcher.random_tie_breaking = false;
+        assert_eq!(tree.exploration_factor(), f32::sqrt(2.0));
+        tree.arena.stats[1].wins = 8;
+        tree.arena.stats[1].score = 8.0;
+        tree.searcher.exploration_schedule = ExplorationSchedule::Constant(f32::sqrt(2.0));
+        assert_eq!(tree.select_at(0, None), tree.select_at(0, Some(f32::sqrt(2.0))));
+        tree.searcher.exploration_schedule = ExplorationSchedule::Constant(0.0);
+        assert_eq!(tree.select_at(0, None), tree.select_at(0, Some(0.0)));
+        assert_ne!(tree.select_at(0, None), tree.select_at(0, Some(f32::sqrt(2.0))));
     }
 
     /// Tests if the expansion function expands and reconfigures
@@ -524,20 +1980,621 @@ mod tests {
     #[test]
     fn test_expand() {
         let mut tree = test_generate_example_tree();
-        let i0 = tree.expand(3);
-        let i1 = tree.expand(3);
-        let i2 = tree.expand(3);
-        assert!(tree.arena[i0].game_state.last_action_made == 11 || 
-            tree.arena[i0].game_state.last_action_made == 10);
-        if tree.arena[i0].game_state.last_action_made == 11 {
-            assert!(tree.arena[i1].game_state.last_action_made == 10);
+        let i0 = tree.expand_at(3);
+        let i1 = tree.expand_at(3);
+        let i2 = tree.expand_at(3);
+        assert!(tree.game_state_at(i0).last_action_made == 11 || 
+            tree.game_state_at(i0).last_action_made == 10);
+        if tree.game_state_at(i0).last_action_made == 11 {
+            assert!(tree.game_state_at(i1).last_action_made == 10);
         }
         else {
-            assert!(tree.arena[i1].game_state.last_action_made == 11);
+            assert!(tree.game_state_at(i1).last_action_made == 11);
+        }
+        assert!(tree.game_state_at(i2).last_action_made == 0);
+        assert!(tree.arena.expanded(3).collect::<Vec<_>>() == vec![i0, i1]);
+        assert!(tree.arena.unexpanded_count(3) == 0);
+    }
+
+    /// Tests that lazily generated actions are expanded in order,
+    /// and that exhausted nodes are treated as fully expanded.
+    #[test]
+    fn test_expand_lazy() {
+        let mut tree = MCTSTree::<u16, LazyState>::with_capacity(100, None, "".to_string(), 1);
+        for i in 0..3 {
+            let child = tree.expand_at(0);
+            assert!(tree.arena.actions[child as usize] == Some(i));
+            assert!(tree.arena.has_untried_actions(0));
+            tree.backpropagate_at(child, GameResult::Draw);
+        }
+
+        // The fourth expansion finds no more actions and marks the root complete.
+        assert!(tree.expand_at(0) == 0);
+        assert!(!tree.arena.has_untried_actions(0));
+        assert!(tree.arena.expanded(0).collect::<Vec<_>>() == vec![1, 2, 3]);
+
+        // Selection now descends into a child, whose actions are still ungenerated.
+        let leaf = tree.select_at(0, None);
+        assert!(leaf != 0 && tree.arena.has_untried_actions(leaf));
+        let grandchild = tree.expand_at(leaf);
+        assert!(tree.arena.topology[grandchild as usize].parent == Some(leaf));
+
+        // Fresh leaves are found terminal from their first action, without generating every action.
+        assert!(tree.simulate_at(grandchild) == GameResult::Draw);
+
+        // Nodes at a depth of two have no actions, so they are terminal once generated.
+        assert!(tree.expand_at(grandchild) == grandchild);
+        assert!(tree.select_at(grandchild, None) == grandchild);
+    }
+
+    /// Tests that leaves are only expanded once they have been 
+    /// simulated as many times as the expansion threshold.
+    #[test]
+    fn test_expand_threshold() {
+        let mut tree = test_generate_example_tree();
+        tree.searcher.expansion_threshold = 2;
+        assert!(tree.expand_at(3) == 3);
+        tree.backpropagate_at(3, GameResult::Draw);
+        let child = tree.expand_at(3);
+        assert!(child != 3 && tree.arena.topology[child as usize].parent == Some(3));
+
+        // Legal actions of the new child are not generated until it reaches the threshold.
+        assert!(tree.arena.has_untried_actions(child));
+        assert!(tree.expand_at(child) == child);
+        tree.backpropagate_at(child, GameResult::Draw);
+        tree.backpropagate_at(child, GameResult::Draw);
+        assert!(tree.expand_at(child) == child);
+        assert!(!tree.arena.has_untried_actions(child));
+    }
+
+    /// Tests that chance nodes expand all of their outcomes, and 
+    /// that selection samples outcomes by their probabilities.
+    #[test]
+    fn test_chance_nodes() {
+        let mut tree = MCTSTree::<u16, ChanceState>::with_capacity(100, Some(1), "".to_string(), 2);
+        let chance_node = tree.expand_at(0);
+        assert!(tree.arena.topology[chance_node as usize].is_chance);
+        assert!(tree.arena.has_untried_actions(chance_node));
+
+        let outcome = tree.expand_at(chance_node);
+        let outcomes: Vec<NodeIndex> = tree.arena.expanded(chance_node).collect();
+        assert!(outcomes.len() == 2 && outcomes.contains(&outcome));
+        assert!(tree.arena.topology[outcomes[0] as usize].probability == 0.25);
+        assert!(tree.arena.topology[outcomes[1] as usize].probability == 0.75);
+        tree.backpropagate_at(outcome, GameResult::Draw);
+
+        // Expand the second action, so selection from the root passes through a chance node.
+        let other = tree.expand_at(0);
+        tree.expand_at(other);
+        tree.backpropagate_at(other, GameResult::Draw);
+
+        let mut likely_outcome = 0;
+        for _i in 0..1000 {
+            let leaf = tree.select_at(0, None);
+            assert!(tree.game_state_at(leaf).actions_made.len() == 2);
+            if tree.game_state_at(leaf).actions_made[1] == 11 {
+                likely_outcome += 1;
+            }
+        }
+        assert!(likely_outcome > 700 && likely_outcome < 800);
+    }
+
+    /// Tests that simulations sample chance events by their probabilities.
+    #[test]
+    fn test_simulate_chance() {
+        let mut tree = MCTSTree::<u16, ChanceState>::with_capacity(100, Some(1), "".to_string(), 2);
+        let mut first_player_wins = 0;
+        for _i in 0..1000 {
+            if tree.simulate_at(0) == GameResult::FirstPlayerWin {
+                first_player_wins += 1;
+            }
+        }
+        assert!(first_player_wins > 700 && first_player_wins < 800);
+    }
+
+    /// Tests if nodes without stored states are re-derived
+    /// correctly by replaying from their nearest stored ancestor.
+    #[test]
+    fn test_single_player() {
+        let mut tree = MCTSTree::<u16, SumState>::with_capacity(100, Some(1), "".to_string(), 3);
+        tree.searcher.player_mode = PlayerMode::SinglePlayer;
+        tree.search(2000);
+
+        // Both picks should maximize the sum, regardless of side to move.
+        let best_leaf = tree.select_at(0, Some(0.0));
+        let path = tree.trace_path_at(best_leaf);
+        let actions: Vec<u16> = path.iter().map(|node| tree.arena.actions[*node as usize].expect("no action")).collect();
+        assert_eq!(actions, vec![3, 3]);
+    }
+
+    #[test]
+    fn test_backpropagate_score() {
+        let mut tree = test_generate_example_tree();
+        tree.searcher.player_mode = PlayerMode::SinglePlayer;
+        tree.backpropagate_at(6, GameResult::Score(4.5));
+        for node in [6, 5, 1, 0] {
+            assert_eq!(tree.arena.stats[node].score, tree.arena.stats[node].wins as f64 + 4.5);
+        }
+
+        // Two player scores are split between the players.
+        tree.searcher.player_mode = PlayerMode::TwoPlayer;
+        tree.backpropagate_at(6, GameResult::Score(0.25));
+        let child_reward = tree.arena.stats[6].score - tree.arena.stats[6].wins as f64 - 4.5;
+        let parent_reward = tree.arena.stats[5].score - tree.arena.stats[5].wins as f64 - 4.5;
+        assert!(child_reward == 0.25 || child_reward == 0.75);
+        assert_eq!(child_reward + parent_reward, 1.0);
+    }
+
+    #[test]
+    fn test_simulate_depth_limit() {
+        let mut tree = MCTSTree::<u16, SumState>::with_capacity(100, Some(1), "".to_string(), 3);
+        tree.searcher.rollout_depth_limit = Some(1);
+        assert_eq!(tree.simulate_at(0), GameResult::Score(0.5));
+
+        // Rollouts that finish before the limit return the real result.
+        tree.searcher.rollout_depth_limit = Some(2);
+        assert!(tree.simulate_at(0) != GameResult::Score(0.5));
+    }
+
+    /// Test if rewards are discounted by the moves of the rollout and of the tree above the simulated node.
+    #[test]
+    fn test_discount() {
+        let mut tree = MCTSTree::<u16, SumState>::with_capacity(100, Some(1), "".to_string(), 3);
+        tree.searcher.player_mode = PlayerMode::SinglePlayer;
+        tree.searcher.discount = 0.5;
+        let child = tree.expand_at(0);
+        let result = tree.simulate_at(child);
+        tree.backpropagate_at(child, result);
+        let GameResult::Score(score) = result else { panic!("no score") };
+        assert_eq!(tree.arena.stats[child as usize].score, 0.5 * score as f64);
+        assert_eq!(tree.arena.stats[0].score, 0.25 * score as f64);
+
+        // Results backpropagated without a simulation are not discounted by a rollout.
+        tree.backpropagate_at(child, GameResult::Score(4.0));
+        assert_eq!(tree.arena.stats[child as usize].score, 0.5 * score as f64 + 4.0);
+    }
+
+    /// Test if terminal leaves are scored by their exact result and marked as solved.
+    #[test]
+    fn test_terminal_leaf() {
+        let mut tree = MCTSTree::<u8, crate::tictactoe_env::TicTacToeState>::with_capacity(100, Some(1), "XX.OO.X..".into(), 9);
+        tree.searcher.expansion_threshold = 1;
+        tree.searcher.simulations_per_expansion = 10;
+        tree.backpropagate_at(0, GameResult::Draw);
+        let children: Vec<NodeIndex> = (0..4).map(|_| tree.expand_at(0)).collect();
+        let winning = *children.iter().find(|child| tree.arena.actions[**child as usize] == Some(5)).unwrap();
+        assert!(tree.arena.has_untried_actions(winning));
+
+        assert_eq!(tree.simulate_at(winning), GameResult::SecondPlayerWin);
+        assert!(tree.counters.rollout_lengths.is_empty());
+        assert!(!tree.arena.has_untried_actions(winning));
+
+        // Selection and expansion stop at the terminal leaf.
+        tree.backpropagate_at(winning, GameResult::SecondPlayerWin);
+        assert_eq!(tree.select_at(winning, None), winning);
+        assert_eq!(tree.expand_at(winning), winning);
+        tree.searcher.backup_policy = BackupPolicy::Solver;
+        assert_eq!(tree.node_value_at(0), 0.0);
+    }
+
+    /// Test if nodes are credited to the player who moved into them, even when a side moves twice in a row.
+    #[test]
+    fn test_perspective() {
+        let mut tree = MCTSTree::<u16, DoubleMoveState>::with_capacity(100, Some(1), "".to_string(), 2);
+        let first = tree.expand_at(0);
+        let second = tree.expand_at(first);
+        let third = tree.expand_at(second);
+        assert!(!tree.credited_side_at(0));
+        assert!(tree.credited_side_at(first));
+        assert!(tree.credited_side_at(second));
+        assert!(!tree.credited_side_at(third));
+
+        tree.backpropagate_at(third, GameResult::FirstPlayerWin);
+        assert_eq!(tree.arena.stats[first as usize].wins, 1);
+        assert_eq!(tree.arena.stats[second as usize].wins, 1);
+        assert_eq!(tree.arena.stats[third as usize].wins, 0);
+
+        // Alternation credits the first move of a double move to the wrong side.
+        tree.searcher.perspective = Perspective::Alternating;
+        assert!(!tree.credited_side_at(first));
+        assert!(tree.credited_side_at(second));
+    }
+
+    /// Test if searches find the winning double move, maximizing the value of the side to move at every node.
+    #[test]
+    fn test_search_perspective() {
+        let mut tree = MCTSTree::<u16, DoubleMoveState>::with_capacity(1000, Some(1), "".to_string(), 2);
+        tree.search(200);
+        let first = tree.most_visited_child(0).unwrap();
+        let second = tree.most_visited_child(first).unwrap();
+        assert_eq!(tree.arena.actions[first as usize], Some(1));
+        assert_eq!(tree.arena.actions[second as usize], Some(1));
+        assert_eq!(tree.arena.stats[second as usize].wins, tree.arena.stats[second as usize].sims);
+    }
+
+    /// Test if rollouts are cut off at the move cap, which defaults to the hint of the root state.
+    #[test]
+    fn test_rollout_move_cap() {
+        let mut tree = MCTSTree::<u16, DoubleMoveState>::with_capacity(100, Some(1), "".to_string(), 2);
+        assert_eq!(tree.searcher.rollout_move_cap, Some(200));
+        tree.searcher.rollout_move_cap = Some(2);
+        assert_eq!(tree.simulate_at(0), GameResult::Draw);
+        assert_eq!(tree.counters.rollout_lengths, vec![0, 0, 1]);
+
+        tree.searcher.rollout_move_cap = None;
+        assert_ne!(tree.simulate_at(0), GameResult::Draw);
+        assert_eq!(tree.counters.rollout_lengths, vec![0, 0, 1, 1]);
+
+        let tree = MCTSTree::<u8, crate::tictactoe_env::TicTacToeState>::with_capacity(100, Some(1), ".........".into(), 9);
+        assert_eq!(tree.searcher.rollout_move_cap, None);
+    }
+
+    /// Test if draws are credited to either side by the draw score, in backups and win rates.
+    #[test]
+    fn test_draw_score() {
+        let mut tree = MCTSTree::<u16, InPlaceState>::with_capacity(100, Some(1), "".to_string(), 2);
+        tree.backpropagate_at(0, GameResult::Draw);
+        assert_eq!(tree.arena.stats[0].score, 0.5);
+
+        // Contempt makes draws worth less, and margins of 0 are draws.
+        tree.searcher.draw_score = 0.25;
+        tree.backpropagate_at(0, GameResult::Margin(0.0));
+        assert_eq!(tree.arena.stats[0].score, 0.75);
+        assert_eq!(tree.arena.stats[0].draws, 2);
+
+        tree.searcher.backup_policy = BackupPolicy::WinRate;
+        assert_eq!(tree.node_value_at(0), 0.25);
+
+        // Draws keep their score when several rollouts are averaged.
+        let mut tree = MCTSTree::<u16, InPlaceState>::with_capacity(100, Some(1), "".to_string(), 2);
+        tree.searcher.draw_score = 0.25;
+        tree.searcher.simulations_per_expansion = 4;
+        let result = tree.simulate_at(0);
+        tree.backpropagate_at(0, result);
+        assert_eq!(tree.arena.stats[0].score, 0.25);
+        assert_eq!(tree.searcher.simulation_rewards, None);
+    }
+
+    /// Test if mean rewards are normalized by the running bounds of the rewards.
+    #[test]
+    fn test_normalize_rewards() {
+        let mut tree = MCTSTree::<u16, SumState>::with_capacity(100, Some(1), "".to_string(), 3);
+        tree.searcher.player_mode = PlayerMode::SinglePlayer;
+        tree.searcher.normalize_rewards = true;
+        let low = tree.expand_at(0);
+        let high = tree.expand_at(0);
+        tree.backpropagate_at(low, GameResult::Score(2.0));
+        assert_eq!(tree.node_value_at(low), 0.5);
+
+        tree.backpropagate_at(high, GameResult::Score(6.0));
+        tree.backpropagate_at(high, GameResult::Score(5.0));
+        assert_eq!(tree.reward_bounds, Some((2.0, 6.0)));
+        assert_eq!(tree.node_value_at(low), 0.0);
+        assert_eq!(tree.node_value_at(high), 0.875);
+        assert!((tree.node_value_at(0) - 7.0 / 12.0).abs() < 1e-6);
+
+        tree.searcher.normalize_rewards = false;
+        assert_eq!(tree.node_value_at(high), 5.5);
+    }
+
+    /// Test if nodes are valued by blending their mean score with the best mean of their children.
+    #[test]
+    fn test_max_backup_weight() {
+        let mut tree = MCTSTree::<u16, SumState>::with_capacity(100, Some(1), "".to_string(), 3);
+        tree.searcher.player_mode = PlayerMode::SinglePlayer;
+        let child = tree.expand_at(0);
+        let worse = tree.expand_at(child);
+        let better = tree.expand_at(child);
+        tree.backpropagate_at(worse, GameResult::Score(1.0));
+        tree.backpropagate_at(better, GameResult::Score(3.0));
+        assert_eq!(tree.node_value_at(child), 2.0);
+
+        tree.searcher.max_backup_weight = 0.5;
+        assert_eq!(tree.node_value_at(child), 2.5);
+        tree.searcher.max_backup_weight = 1.0;
+        assert_eq!(tree.node_value_at(child), 3.0);
+
+        // Leaves are valued at their mean.
+        assert_eq!(tree.node_value_at(better), 3.0);
+
+        // In two player mode, the best child is the best reply of the opponent.
+        tree.searcher.player_mode = PlayerMode::TwoPlayer;
+        tree.searcher.max_backup_weight = 1.0;
+        tree.arena.stats[worse as usize].score = 0.25;
+        tree.arena.stats[better as usize].score = 0.75;
+        assert_eq!(tree.node_value_at(child), 0.25);
+    }
+
+    /// Test if node values follow the backup policy, with solved values for terminal children.
+    #[test]
+    fn test_backup_policy() {
+        let mut tree = MCTSTree::<u16, SumState>::with_capacity(100, Some(1), "".to_string(), 3);
+        tree.searcher.player_mode = PlayerMode::SinglePlayer;
+        let child = tree.expand_at(0);
+        let action = *tree.arena.actions[child as usize].as_ref().unwrap() as Value;
+        for _ in 0..3 {
+            let grandchild = tree.expand_at(child);
+            let result = tree.simulate_at(grandchild);
+            tree.backpropagate_at(grandchild, result);
+        }
+        assert_eq!(tree.node_value_at(child), action + 2.0);
+        tree.searcher.backup_policy = BackupPolicy::Solver;
+        assert_eq!(tree.node_value_at(child), action + 3.0);
+        tree.searcher.backup_policy = BackupPolicy::WinRate;
+        assert_eq!(tree.node_value_at(child), 0.0);
+
+        // O has a reply winning the game, so the position is lost for X.
+        let mut tree = MCTSTree::<u8, crate::tictactoe_env::TicTacToeState>::with_capacity(100, Some(1), "XX.OO.X..".into(), 9);
+        for _ in 0..4 {
+            let child = tree.expand_at(0);
+            let result = tree.simulate_at(child);
+            tree.backpropagate_at(child, result);
+        }
+        tree.backpropagate_at(0, GameResult::Draw);
+        assert!(tree.node_value_at(0) > 0.0);
+        tree.searcher.backup_policy = BackupPolicy::Solver;
+        assert_eq!(tree.node_value_at(0), 0.0);
+
+        // The win rate counts wins and credits draws by the draw score.
+        tree.searcher.backup_policy = BackupPolicy::WinRate;
+        let stats = tree.arena.stats[0];
+        assert_eq!(tree.node_value_at(0), (stats.wins as Value + 0.5 * stats.draws as Value) / 5.0);
+    }
+
+    /// Test if several rollouts per simulation are averaged into a single score.
+    #[test]
+    fn test_simulations_per_expansion() {
+        let mut tree = MCTSTree::<u16, SumState>::with_capacity(100, Some(1), "".to_string(), 3);
+        tree.searcher.player_mode = PlayerMode::SinglePlayer;
+        tree.searcher.simulations_per_expansion = 1000;
+        let GameResult::Score(score) = tree.simulate_at(0) else { panic!("no score") };
+        assert!((score - 4.0).abs() < 0.1);
+        assert_eq!(tree.counters.rollout_lengths[2], 1000);
+
+        // Two player draws are split evenly.
+        let mut tree = MCTSTree::<u16, InPlaceState>::with_capacity(100, Some(1), "".to_string(), 2);
+        tree.searcher.simulations_per_expansion = 2;
+        assert_eq!(tree.simulate_at(0), GameResult::Score(0.5));
+    }
+
+    #[test]
+    fn test_simulate_in_place() {
+        let mut tree = MCTSTree::<u16, InPlaceState>::with_capacity(100, Some(1), "".to_string(), 2);
+        assert!(tree.simulate_at(0) == GameResult::Draw);
+    }
+
+    #[test]
+    fn test_legal_actions_into() {
+        let game_state = SumState::from_str("".to_string());
+        let mut actions = vec![7, 7, 7, 7];
+        game_state.legal_actions_into(&mut actions);
+        assert_eq!(actions, game_state.generate_legal_actions());
+    }
+
+    #[test]
+    fn test_backpropagate_margin() {
+        let mut tree = test_generate_example_tree();
+        let scores = |tree: &MCTSTree<u16, PlaceHolderState>| (tree.arena.stats[6].score, tree.arena.stats[5].score);
+
+        // Margins only decide the winner by default.
+        let (child_before, parent_before) = scores(&tree);
+        tree.backpropagate_at(6, GameResult::Margin(-3.0));
+        let (child_after, parent_after) = scores(&tree);
+        assert_eq!((child_after - child_before) + (parent_after - parent_before), 1.0);
+        let child_won = child_after > child_before;
+
+        // Weighted margins are added for the winner and subtracted for the loser.
+        tree.searcher.margin_weight = 0.1;
+        tree.backpropagate_at(6, GameResult::Margin(-3.0));
+        let (child_margin, parent_margin) = scores(&tree);
+        let (winner_gain, loser_gain) = if child_won { 
+            (child_margin - child_after, parent_margin - parent_after) 
+        } 
+        else { 
+            (parent_margin - parent_after, child_margin - child_after) 
+        };
+        assert!(f64::abs(winner_gain - 1.3) < 1e-6 && f64::abs(loser_gain + 0.3) < 1e-6);
+
+        // A margin of 0 is a draw.
+        tree.backpropagate_at(6, GameResult::Margin(0.0));
+        assert!(tree.arena.stats[6].draws == 1 && tree.arena.stats[5].draws == 1);
+    }
+
+    #[test]
+    fn test_game_result_winner() {
+        assert_eq!(GameResult::FirstPlayerWin.winner(), Some(true));
+        assert_eq!(GameResult::Margin(-2.0).winner(), Some(false));
+        assert_eq!(GameResult::Margin(0.0).winner(), None);
+        assert!(GameResult::Margin(0.0).is_draw() && GameResult::Draw.is_draw());
+        assert_eq!(GameResult::Score(0.5).winner(), None);
+    }
+
+    #[test]
+    fn test_try_with_capacity() {
+        let tree = MCTSTree::<u16, SumState>::try_with_capacity(100, None, "", 3);
+        assert!(tree.is_ok_and(|tree| tree.arena.len() == 1));
+    }
+
+    #[test]
+    fn test_game_state_actions_only() {
+        let mut tree = test_generate_example_tree();
+        tree.state_storage = StateStorage::ActionsOnly;
+        let i0 = tree.expand_at(3);
+        assert!(tree.arena.stored_state(i0).is_none());
+        assert!(tree.game_state_at(i0).depth_counter == 4);
+        assert!(tree.game_state_at(i0).last_action_made == tree.arena.actions[i0 as usize].unwrap());
+
+        // Chain a second state-free node below the first.
+        let i1 = tree.arena.push(MCTSNode {
+            game_state: PlaceHolderState {last_action_made: 7, depth_counter: 5}, 
+            action: Some(7),
+            parent: Some(i0), 
+            expanded: vec![], 
+            unexpanded: Vec::new(), 
+            wins: 0, draws: 0, sims: 0, score: 0.0,
+            data: None
+        }, false);
+        assert!(tree.game_state_at(i1).depth_counter == 5);
+        assert!(tree.game_state_at(i1).last_action_made == 7);
+    }
+
+    /// Tests that node handles resolve until their nodes are freed,
+    /// even when the freed indices are reused by new nodes.
+    #[test]
+    fn test_node_id_generations() {
+        let mut tree = test_generate_example_tree();
+        let id = tree.arena.handle(3);
+        assert!(tree.arena.resolve(id) == Ok(3));
+        assert!(tree.arena.resolve(NodeId { index: 100, generation: 0 }).is_err());
+
+        tree.arena.clear();
+        assert!(tree.arena.resolve(id) == Err(StaleNodeError(id)));
+        for _i in 0..4 {
+            tree.arena.push(MCTSNode {
+                game_state: PlaceHolderState {last_action_made: 0, depth_counter: 0}, 
+                action: None,
+                parent: None, 
+                expanded: vec![], 
+                unexpanded: Vec::new(), 
+                wins: 0, draws: 0, sims: 0, score: 0.0,
+                data: None
+            }, true);
+        }
+        assert!(tree.arena.resolve(id).is_err());
+        assert!(tree.arena.resolve(tree.arena.handle(3)) == Ok(3));
+    }
+
+    /// Tests that advancing the root keeps only the new root's subtree,
+    /// with its statistics and links intact.
+    #[test]
+    fn test_advance_root() {
+        let mut tree = test_generate_example_tree();
+        let id = tree.arena.handle(5);
+        let memory_usage = tree.arena.memory_usage();
+        let remap = tree.advance_root_at(1);
+        assert!(tree.arena.memory_usage() < memory_usage);
+        
+        assert!(tree.arena.len() == 7);
+        assert!(remap[0].is_none() && remap[8].is_none() && remap[11].is_none());
+        assert!(remap[1] == Some(0));
+        assert!(tree.arena.resolve(id).is_err());
+
+        // Old node 5 and its children 6 and 7.
+        let node = remap[5].unwrap();
+        assert!(tree.arena.topology[0].parent.is_none());
+        assert!(tree.arena.topology[node as usize].parent == Some(0));
+        assert!(tree.arena.expanded(node).collect::<Vec<_>>() == vec![remap[6].unwrap(), remap[7].unwrap()]);
+        assert!(tree.arena.stats[0].wins == 5 && tree.arena.stats[0].sims == 8);
+        assert!(tree.arena.stats[node as usize].wins == 2 && tree.arena.stats[node as usize].sims == 4);
+        assert!(tree.game_state_at(remap[3].unwrap()).depth_counter == 3);
+        assert!(tree.arena.unexpanded(remap[3].unwrap()).copied().collect::<Vec<_>>() == vec![10, 11]);
+        assert!(tree.select_at(0, None) == remap[4].unwrap());
+    }
+
+    /// Tests that trees created from a game state are rooted at it, with the sizes and seed of their config.
+    #[test]
+    fn test_from_state() {
+        let config = TreeConfig { arena_capacity: 50, seed: Some(3), average_child_count: 4 };
+        let mut tree = MCTSTree::<u16, PlaceHolderState>::from_state(PlaceHolderState { last_action_made: 7, depth_counter: 3 }, config);
+        assert!(tree.arena.len() == 1);
+        assert!(tree.game_state(tree.root()).depth_counter == 3 && tree.game_state(tree.root()).last_action_made == 7);
+        assert!(tree.arena.stats.capacity() >= 50 && tree.arena.children.capacity() >= 200);
+        assert!(tree.average_child_count == 4);
+
+        // The seed of the config seeds the random generator like `with_capacity`.
+        let mut parsed = MCTSTree::<u16, PlaceHolderState>::with_capacity(50, Some(3), "".to_string(), 4);
+        assert!(tree.searcher.random_generator.gen::<u64>() == parsed.searcher.random_generator.gen::<u64>());
+        assert!(TreeConfig::default() == TreeConfig { arena_capacity: 1024, seed: None, average_child_count: 8 });
+
+        // Seeds wider than 64 bits are kept whole.
+        let wide = TreeConfig { arena_capacity: 50, seed: Some(3 | 1 << 64), average_child_count: 4 };
+        let mut wide = MCTSTree::<u16, PlaceHolderState>::from_state(PlaceHolderState { last_action_made: 7, depth_counter: 3 }, wide);
+        assert!(wide.searcher.random_generator.gen::<u64>() != parsed.searcher.random_generator.gen::<u64>());
+    }
+
+    /// Tests that resetting the tree leaves only the new root, keeping the capacity of the
+    /// arena and the configuration of the searcher.
+    #[test]
+    fn test_reset() {
+        let mut tree = test_generate_example_tree();
+        tree.searcher.draw_score = 0.25;
+        tree.searcher.random_tie_breaking = false;
+        tree.counters.iterations = 8;
+        let id = tree.root();
+        let memory_usage = tree.arena.memory_usage();
+
+        tree.reset(PlaceHolderState { last_action_made: 0, depth_counter: 10 });
+        assert!(tree.arena.len() == 1);
+        assert!(tree.arena.memory_usage() == memory_usage);
+        assert!(tree.arena.resolve(id).is_err());
+        assert!(tree.arena.stats[0].sims == 0 && tree.arena.topology[0].parent.is_none());
+        assert!(tree.game_state(tree.root()).depth_counter == 10);
+        assert!(tree.searcher.draw_score == 0.25 && !tree.searcher.random_tie_breaking);
+        assert!(tree.counters.iterations == 0);
+        assert!(tree.select(tree.root(), None) == tree.root());
+    }
+
+    /// Tests if the root policy is the visit distribution over the children of the root.
+    #[test]
+    fn test_root_policy() {
+        let tree = test_generate_example_tree();
+        let policy = tree.root_policy();
+        assert!(policy.len() == 2);
+        for (child, (action, visits, probability)) in [1, 8].into_iter().zip(&policy) {
+            assert!(Some(*action) == tree.arena.actions[child]);
+            assert!(*visits == tree.arena.stats[child].sims);
+            assert!(*probability == *visits as f32 / (policy[0].1 + policy[1].1) as f32);
+        }
+        assert!((policy.iter().map(|(_, _, probability)| probability).sum::<f32>() - 1.0).abs() < 1e-6);
+
+        // Before any visits, every action has a probability of 0.
+        let mut tree = MCTSTree::<u16, SumState>::with_capacity(10, None, "".to_string(), 10);
+        tree.expand_at(0);
+        let policy = tree.root_policy();
+        assert!(policy.len() == 3);
+        assert!(policy.iter().all(|(_, visits, probability)| *visits == 0 && *probability == 0.0));
+    }
+
+    /// Tests if sampled root actions follow the visits raised to the inverse temperature.
+    #[test]
+    fn test_sample_action() {
+        let mut tree = MCTSTree::<u16, SumState>::with_capacity(10, Some(1), "".to_string(), 10);
+        assert!(tree.sample_action(1.0, None).is_none());
+        let first = tree.expand_at(0);
+        let second = tree.expand_at(0);
+        tree.expand_at(0);
+        for _i in 0..3 {
+            tree.backpropagate_at(first, GameResult::Draw);
+        }
+        tree.backpropagate_at(second, GameResult::Draw);
+        let (first_action, second_action) = (tree.arena.actions[first as usize].unwrap(), tree.arena.actions[second as usize].unwrap());
+        for _i in 0..10 {
+            assert!(tree.sample_action(0.0, None) == Some(first_action));
         }
-        assert!(tree.arena[i2].game_state.last_action_made == 0);
-        assert!(tree.arena[3].expanded == (vec![i0, i1] as Vec<usize>));
-        assert!(tree.arena[3].unexpanded.len() == 0);
+
+        // At a temperature of 1, actions are sampled proportionally to their visits, and
+        // unvisited children are never sampled.
+        let samples = 20000;
+        let mut firsts = 0;
+        for _i in 0..samples {
+            let action = tree.sample_action(1.0, None).unwrap();
+            assert!(action == first_action || action == second_action);
+            firsts += (action == first_action) as u32;
+        }
+        assert!((firsts as f64 / samples as f64 - 0.75).abs() < 0.02);
+
+        // Higher temperatures flatten the distribution.
+        let firsts = (0..samples).filter(|_| tree.sample_action(100.0, None) == Some(first_action)).count();
+        assert!((firsts as f64 / samples as f64 - 0.5).abs() < 0.03);
+
+        // An external generator gives reproducible samples, without advancing the generator of the tree.
+        let sample = |tree: &mut MCTSTree<u16, SumState>| {
+            let mut rng: SearchRng = SeedableRng::from_seed(7 | 11 << 64);
+            return (0..20).map(|_| tree.sample_action(2.0, Some(&mut rng)).unwrap()).collect::<Vec<_>>();
+        };
+        let before = tree.searcher.random_generator.clone().next_u64();
+        assert!(sample(&mut tree) == sample(&mut tree));
+        assert!(tree.searcher.random_generator.next_u64() == before);
     }
 
     /// Tests if the tree backpropagation correctly feeds 
@@ -545,53 +2602,53 @@ mod tests {
     #[test]
     fn test_backpropagate() {
         let mut tree = test_generate_example_tree();
-        tree.backpropagate(0, GameResult::SecondPlayerWin);
-        tree.backpropagate(6, GameResult::SecondPlayerWin);
-        tree.backpropagate(10, GameResult::SecondPlayerWin);
-        assert!(tree.arena[0].wins == 8 && tree.arena[0].sims == 15);
-        assert!(tree.arena[1].wins == 5 && tree.arena[1].sims == 9);
-        assert!(tree.arena[2].wins == 1 && tree.arena[2].sims == 2);
-        assert!(tree.arena[3].wins == 1 && tree.arena[3].sims == 1);
-        assert!(tree.arena[4].wins == 0 && tree.arena[4].sims == 1);
-        assert!(tree.arena[5].wins == 3 && tree.arena[5].sims == 5);
-        assert!(tree.arena[6].wins == 0 && tree.arena[6].sims == 2);
-        assert!(tree.arena[7].wins == 2 && tree.arena[7].sims == 2);
-        assert!(tree.arena[8].wins == 2 && tree.arena[8].sims == 5);
-        assert!(tree.arena[9].wins == 1 && tree.arena[9].sims == 1);
-        assert!(tree.arena[10].wins == 2 && tree.arena[10].sims == 3);
-        assert!(tree.arena[11].wins == 0 && tree.arena[11].sims == 1);
-
-        tree.backpropagate(11, GameResult::FirstPlayerWin);
-        tree.backpropagate(3, GameResult::FirstPlayerWin);
-        tree.backpropagate(1, GameResult::FirstPlayerWin);
-        assert!(tree.arena[0].wins == 8 && tree.arena[0].sims == 18);
-        assert!(tree.arena[1].wins == 7 && tree.arena[1].sims == 11);
-        assert!(tree.arena[2].wins == 1 && tree.arena[2].sims == 3);
-        assert!(tree.arena[3].wins == 2 && tree.arena[3].sims == 2);
-        assert!(tree.arena[4].wins == 0 && tree.arena[4].sims == 1);
-        assert!(tree.arena[5].wins == 3 && tree.arena[5].sims == 5);
-        assert!(tree.arena[6].wins == 0 && tree.arena[6].sims == 2);
-        assert!(tree.arena[7].wins == 2 && tree.arena[7].sims == 2);
-        assert!(tree.arena[8].wins == 3 && tree.arena[8].sims == 6);
-        assert!(tree.arena[9].wins == 1 && tree.arena[9].sims == 1);
-        assert!(tree.arena[10].wins == 2 && tree.arena[10].sims == 4);
-        assert!(tree.arena[11].wins == 1 && tree.arena[11].sims == 2);
-
-
-        tree.backpropagate(4, GameResult::Draw);
-        tree.backpropagate(7, GameResult::Draw);
-        tree.backpropagate(9, GameResult::Draw);
-        assert!(tree.arena[0].wins == 8 && tree.arena[0].sims == 21);
-        assert!(tree.arena[1].wins == 7 && tree.arena[1].sims == 13);
-        assert!(tree.arena[2].wins == 1 && tree.arena[2].sims == 3);
-        assert!(tree.arena[3].wins == 2 && tree.arena[3].sims == 2);
-        assert!(tree.arena[4].wins == 0 && tree.arena[4].sims == 2);
-        assert!(tree.arena[5].wins == 3 && tree.arena[5].sims == 6);
-        assert!(tree.arena[6].wins == 0 && tree.arena[6].sims == 2);
-        assert!(tree.arena[7].wins == 2 && tree.arena[7].sims == 3);
-        assert!(tree.arena[8].wins == 3 && tree.arena[8].sims == 7);
-        assert!(tree.arena[9].wins == 1 && tree.arena[9].sims == 2);
-        assert!(tree.arena[10].wins == 2 && tree.arena[10].sims == 4);
-        assert!(tree.arena[11].wins == 1 && tree.arena[11].sims == 2);
+        tree.backpropagate_at(0, GameResult::SecondPlayerWin);
+        tree.backpropagate_at(6, GameResult::SecondPlayerWin);
+        tree.backpropagate_at(10, GameResult::SecondPlayerWin);
+        assert!(tree.arena.stats[0].wins == 8 && tree.arena.stats[0].sims == 15);
+        assert!(tree.arena.stats[1].wins == 5 && tree.arena.stats[1].sims == 9);
+        assert!(tree.arena.stats[2].wins == 1 && tree.arena.stats[2].sims == 2);
+        assert!(tree.arena.stats[3].wins == 1 && tree.arena.stats[3].sims == 1);
+        assert!(tree.arena.stats[4].wins == 0 && tree.arena.stats[4].sims == 1);
+        assert!(tree.arena.stats[5].wins == 3 && tree.arena.stats[5].sims == 5);
+        assert!(tree.arena.stats[6].wins == 0 && tree.arena.stats[6].sims == 2);
+        assert!(tree.arena.stats[7].wins == 2 && tree.arena.stats[7].sims == 2);
+        assert!(tree.arena.stats[8].wins == 2 && tree.arena.stats[8].sims == 5);
+        assert!(tree.arena.stats[9].wins == 1 && tree.arena.stats[9].sims == 1);
+        assert!(tree.arena.stats[10].wins == 2 && tree.arena.stats[10].sims == 3);
+        assert!(tree.arena.stats[11].wins == 0 && tree.arena.stats[11].sims == 1);
+
+        tree.backpropagate_at(11, GameResult::FirstPlayerWin);
+        tree.backpropagate_at(3, GameResult::FirstPlayerWin);
+        tree.backpropagate_at(1, GameResult::FirstPlayerWin);
+        assert!(tree.arena.stats[0].wins == 8 && tree.arena.stats[0].sims == 18);
+        assert!(tree.arena.stats[1].wins == 7 && tree.arena.stats[1].sims == 11);
+        assert!(tree.arena.stats[2].wins == 1 && tree.arena.stats[2].sims == 3);
+        assert!(tree.arena.stats[3].wins == 2 && tree.arena.stats[3].sims == 2);
+        assert!(tree.arena.stats[4].wins == 0 && tree.arena.stats[4].sims == 1);
+        assert!(tree.arena.stats[5].wins == 3 && tree.arena.stats[5].sims == 5);
+        assert!(tree.arena.stats[6].wins == 0 && tree.arena.stats[6].sims == 2);
+        assert!(tree.arena.stats[7].wins == 2 && tree.arena.stats[7].sims == 2);
+        assert!(tree.arena.stats[8].wins == 3 && tree.arena.stats[8].sims == 6);
+        assert!(tree.arena.stats[9].wins == 1 && tree.arena.stats[9].sims == 1);
+        assert!(tree.arena.stats[10].wins == 2 && tree.arena.stats[10].sims == 4);
+        assert!(tree.arena.stats[11].wins == 1 && tree.arena.stats[11].sims == 2);
+
+
+        tree.backpropagate_at(4, GameResult::Draw);
+        tree.backpropagate_at(7, GameResult::Draw);
+        tree.backpropagate_at(9, GameResult::Draw);
+        assert!(tree.arena.stats[0].wins == 8 && tree.arena.stats[0].sims == 21);
+        assert!(tree.arena.stats[1].wins == 7 && tree.arena.stats[1].sims == 13);
+        assert!(tree.arena.stats[2].wins == 1 && tree.arena.stats[2].sims == 3);
+        assert!(tree.arena.stats[3].wins == 2 && tree.arena.stats[3].sims == 2);
+        assert!(tree.arena.stats[4].wins == 0 && tree.arena.stats[4].sims == 2);
+        assert!(tree.arena.stats[5].wins == 3 && tree.arena.stats[5].sims == 6);
+        assert!(tree.arena.stats[6].wins == 0 && tree.arena.stats[6].sims == 2);
+        assert!(tree.arena.stats[7].wins == 2 && tree.arena.stats[7].sims == 3);
+        assert!(tree.arena.stats[8].wins == 3 && tree.arena.stats[8].sims == 7);
+        assert!(tree.arena.stats[9].wins == 1 && tree.arena.stats[9].sims == 2);
+        assert!(tree.arena.stats[10].wins == 2 && tree.arena.stats[10].sims == 4);
+        assert!(tree.arena.stats[11].wins == 1 && tree.arena.stats[11].sims == 2);
     }
 }
@@ -0,0 +1,299 @@
+//! Merging of independently searched trees, for root-parallel workers, consolidation of
+//! checkpoints and ensembles of searches.
+
+use crate::arena::{NodeIndex, NodeTopology};
+use crate::game_state_trait::GameState;
+use crate::mcts::{MCTSTree, StateStorage};
+use alloc::collections::VecDeque;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::fmt;
+
+
+/// Decides which nodes of two trees are the same node.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MergeMatching {
+    /// Children match if they were reached by the same action.
+    ActionPath,
+
+    /// Children match if their game states have the same `GameState::state_hash`.
+    /// Children whose states have no hash match by their action.
+    StateHash,
+}
+
+
+/// Error produced when merging trees searched from different root states.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RootMismatchError {
+    /// State hash of the root of the tree merged into.
+    pub root_hash: u64,
+
+    /// State hash of the root of the merged tree.
+    pub other_root_hash: u64,
+}
+
+impl fmt::Display for RootMismatchError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        return write!(f, "root state {:x} does not match root state {:x}", self.root_hash, self.other_root_hash);
+    }
+}
+
+impl core::error::Error for RootMismatchError {}
+
+
+/// Merging of trees.
+impl<Action, GameStateObj, Data> MCTSTree<Action, GameStateObj, Data>
+where
+    Action: Clone + PartialEq,
+    GameStateObj: GameState<Action> + Clone
+{
+    /// Merges the statistics of `other` into the tree, matching nodes by their action path.
+    ///
+    /// See `merge_with`.
+    pub fn merge(&mut self, other: &Self) -> Result<(), RootMismatchError> {
+        return self.merge_with(other, MergeMatching::ActionPath);
+    }
+
+    /// Merges the statistics of `other` into the tree.
+    ///
+    /// Starting from the roots, the wins, draws, simulations and score of every node of `other`
+    /// are added to its matching node. Nodes of `other` without a match are copied into the
+    /// tree along with their subtrees, expanding their action in their parent. The merged
+    /// tree holds the statistics of both searches, as if they had been one longer search.
+    /// Nodes keep their own user data, and copied nodes have none.
+    ///
+    /// # Arguments
+    /// * `other` : A tree searched from the same root state, such as by another worker.
+    ///
+    /// * `matching` : Decides which children of matching nodes match.
+    ///
+    /// # Returns
+    /// A `RootMismatchError` if the root states of the trees have different state hashes,
+    /// in which case the tree is left unchanged.
+    pub fn merge_with(&mut self, other: &Self, matching: MergeMatching) -> Result<(), RootMismatchError> {
+        if let (Some(root_hash), Some(other_root_hash)) = (self.game_state_at(0).state_hash(), other.game_state_at(0).state_hash()) {
+            if root_hash != other_root_hash {
+                return Err(RootMismatchError { root_hash, other_root_hash });
+            }
+        }
+
+        let mut pairs: Vec<(NodeIndex, NodeIndex)> = vec![(0, 0)];
+        while let Some((node, other_node)) = pairs.pop() {
+            self.arena.stats[node as usize].add(&other.arena.stats[other_node as usize]);
+
+            for other_child in other.arena.expanded(other_node) {
+                match self.matching_child(node, other, other_child, matching) {
+                    Some(child) => pairs.push((child, other_child)),
+                    None => self.copy_subtree(node, other, other_child),
+                }
+            }
+
+            // Chance and lazily generated nodes generate all of their children at once, or in order,
+            // so they are done once either tree has generated all of them.
+            let other_complete = other.arena.topology[other_node as usize].children.complete;
+            let topology = &mut self.arena.topology[node as usize];
+            if (topology.is_chance || GameStateObj::LAZY_ACTIONS) && other_complete {
+                topology.children.complete = true;
+            }
+        }
+        return Ok(());
+    }
+
+    /// Finds the expanded child of `node` matching `other_child`, a node of `other`.
+    fn matching_child(&self, node: NodeIndex, other: &Self, other_child: NodeIndex, matching: MergeMatching) -> Option<NodeIndex> {
+        let other_action = other.arena.actions[other_child as usize].as_ref().expect("no action");
+        let other_hash = match matching {
+            MergeMatching::ActionPath => None,
+            MergeMatching::StateHash => other.game_state_at(other_child).state_hash(),
+        };
+        return self.arena.expanded(node).find(|child| {
+            if let Some(other_hash) = other_hash {
+                if let Some(hash) = self.game_state_at(*child).state_hash() {
+                    return hash == other_hash;
+                }
+            }
+            return self.arena.actions[*child as usize].as_ref() == Some(other_action);
+        });
+    }
+
+    /// Copies `other_root`, a node of `other`, and its subtree into the tree as a child of `parent`.
+    ///
+    /// The nodes are copied in breadth first order, so the indices of their children are
+    /// known before they are pushed.
+    fn copy_subtree(&mut self, parent: NodeIndex, other: &Self, other_root: NodeIndex) {
+        let mut order = Vec::new();
+        let mut queue = VecDeque::from([other_root]);
+        while let Some(other_node) = queue.pop_front() {
+            order.push(other_node);
+            queue.extend(other.arena.expanded(other_node));
+        }
+
+        let copied_root = self.arena.len() as NodeIndex;
+        self.attach_child(parent, other.arena.actions[other_root as usize].as_ref().expect("no action"), copied_root);
+
+        let mut copied = vec![0; other.arena.len()];
+        for (i, other_node) in order.iter().enumerate() {
+            copied[*other_node as usize] = copied_root + i as NodeIndex;
+        }
+        let store_state = self.state_storage == StateStorage::Full;
+        for other_node in order {
+            let other_topology = &other.arena.topology[other_node as usize];
+            let expanded = other.arena.expanded(other_node).map(|child| copied[child as usize]);
+            let unexpanded = other.arena.unexpanded(other_node).cloned();
+            let mut children = self.arena.allocate_children(expanded, unexpanded);
+            children.complete = other_topology.children.complete;
+
+            let topology = NodeTopology {
+                parent: Some(if other_node == other_root { parent } else { copied[other_topology.parent.expect("no parent") as usize] }),
+                side_to_move: other_topology.side_to_move,
+                is_chance: other_topology.is_chance,
+                probability: other_topology.probability,
+                children,
+            };
+            let game_state = if store_state { Some(other.game_state_at(other_node).into_owned()) } else { None };
+            let action = other.arena.actions[other_node as usize].clone();
+            self.arena.push_parts(other.arena.stats[other_node as usize], topology, action, game_state, None);
+        }
+    }
+
+    /// Records `child`, the index of a node about to be pushed, as the expanded child of `parent` reached by `action`.
+    pub(crate) fn attach_child(&mut self, parent: NodeIndex, action: &Action, child: NodeIndex) {
+        let topology = self.arena.topology[parent as usize];
+        if topology.is_chance || GameStateObj::LAZY_ACTIONS {
+            self.arena.push_expanded_child(parent, child);
+            return;
+        }
+
+        // Generate the legal actions of nodes whose generation was deferred, as `expand` does.
+        if !topology.children.complete {
+            let unexpanded = self.game_state_at(parent).generate_legal_actions();
+            self.arena.set_children(parent, Vec::new(), unexpanded);
+        }
+        let position = self.arena.unexpanded(parent).position(|unexpanded| unexpanded == action);
+        match position {
+            Some(index) => {
+                self.arena.expand_child(parent, index, child);
+            }
+
+            // Actions expanded with priors are never unexpanded.
+            None => {
+                let mut expanded: Vec<NodeIndex> = self.arena.expanded(parent).collect();
+                expanded.push(child);
+                let unexpanded = self.arena.unexpanded(parent).cloned().collect();
+                self.arena.set_children(parent, expanded, unexpanded);
+            }
+        }
+    }
+}
+
+
+/// Defines unit tests for merging trees.
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game2048_env::{Game2048Move, Game2048State};
+    use crate::mcts::PlayerMode;
+    use crate::tictactoe_env::TicTacToeState;
+
+    /// Asserts that the simulations of every node of `tree` add up with its children.
+    fn assert_consistent<Action, GameStateObj>(tree: &MCTSTree<Action, GameStateObj>)
+    where
+        GameStateObj: GameState<Action> + Clone
+    {
+        for node in 0..tree.arena.len() {
+            let child_sims: u64 = tree.arena.expanded(node as NodeIndex).map(|child| tree.arena.stats[child as usize].sims).sum();
+            assert!(child_sims <= tree.arena.stats[node].sims);
+            for child in tree.arena.expanded(node as NodeIndex) {
+                assert_eq!(tree.arena.topology[child as usize].parent, Some(node as NodeIndex));
+            }
+        }
+    }
+
+    /// Test if merging trees of two workers adds up their statistics, and copies nodes only one of them expanded.
+    #[test]
+    fn test_merge() {
+        let mut tree = MCTSTree::<u8, TicTacToeState>::with_capacity(1000, Some(1), "X...O....".to_string(), 9);
+        let mut other = MCTSTree::<u8, TicTacToeState>::with_capacity(1000, Some(2), "X...O....".to_string(), 9);
+        tree.search(300);
+        other.search(200);
+        let tree_root = tree.arena.stats[0];
+        let other_best = other.get_max_uct_child_at(0, Some(0.0)).unwrap();
+        let other_best_action = other.arena.actions[other_best as usize];
+        let other_best_sims = other.arena.stats[other_best as usize].sims;
+        let tree_before: u64 = tree.arena.expanded(0)
+            .filter(|child| tree.arena.actions[*child as usize] == other_best_action)
+            .map(|child| tree.arena.stats[child as usize].sims)
+            .sum();
+
+        tree.merge(&other).unwrap();
+        assert_eq!(tree.arena.stats[0].sims, tree_root.sims + 200);
+        assert_eq!(tree.arena.stats[0].wins, tree_root.wins + other.arena.stats[0].wins);
+        let merged = tree.arena.expanded(0).find(|child| tree.arena.actions[*child as usize] == other_best_action).unwrap();
+        assert_eq!(tree.arena.stats[merged as usize].sims, tree_before + other_best_sims);
+        assert_eq!(tree.arena.expanded_count(0) + tree.arena.unexpanded_count(0), 7);
+        assert_consistent(&tree);
+
+        // Every leaf of the other tree is reachable by its action path in the merged tree.
+        for other_node in 0..other.arena.len() {
+            let mut node = 0;
+            for step in other.trace_path_at(other_node as NodeIndex) {
+                let action = other.arena.actions[step as usize];
+                node = tree.arena.expanded(node).find(|child| tree.arena.actions[*child as usize] == action).unwrap();
+            }
+            assert_eq!(tree.game_state_at(node).into_owned(), other.game_state_at(other_node as NodeIndex).into_owned());
+        }
+
+        // The merged tree can be searched further.
+        tree.search(100);
+        assert_eq!(tree.arena.stats[0].sims, tree_root.sims + 300);
+    }
+
+    /// Test if merged counters go beyond 32 bits, and overflowing them fails loudly in debug builds.
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "visit counter overflow")]
+    fn test_merge_counter_overflow() {
+        let mut tree = MCTSTree::<u8, TicTacToeState>::with_capacity(10, Some(1), "X...O....".to_string(), 9);
+        let mut other = MCTSTree::<u8, TicTacToeState>::with_capacity(10, Some(2), "X...O....".to_string(), 9);
+        tree.arena.stats[0].sims = u32::MAX as u64;
+        other.arena.stats[0].sims = u32::MAX as u64;
+        tree.merge(&other).unwrap();
+        assert_eq!(tree.arena.stats[0].sims, 2 * u32::MAX as u64);
+
+        other.arena.stats[0].sims = u64::MAX;
+        let _ = tree.merge(&other);
+    }
+
+    /// Test if trees of different root states are not merged.
+    #[test]
+    fn test_merge_root_mismatch() {
+        let mut tree = MCTSTree::<u8, TicTacToeState>::with_capacity(100, Some(1), "X...O....".to_string(), 9);
+        let other = MCTSTree::<u8, TicTacToeState>::with_capacity(100, Some(1), "X....O...".to_string(), 9);
+        assert!(tree.merge(&other).is_err());
+        assert_eq!(tree.arena.len(), 1);
+    }
+
+    /// Test if chance nodes are merged by state hash without duplicating outcomes.
+    #[test]
+    fn test_merge_state_hash_chance() {
+        let mut tree = MCTSTree::<Game2048Move, Game2048State>::with_capacity(1000, Some(1), String::new(), 4);
+        let mut other = MCTSTree::<Game2048Move, Game2048State>::with_capacity(1000, Some(2), String::new(), 4);
+        for searched in [&mut tree, &mut other] {
+            searched.searcher.player_mode = PlayerMode::SinglePlayer;
+            searched.searcher.rollout_depth_limit = Some(1);
+        }
+        tree.search(100);
+        other.search(150);
+        let expected_sims = tree.arena.stats[0].sims + other.arena.stats[0].sims;
+
+        tree.merge_with(&other, MergeMatching::StateHash).unwrap();
+        assert_eq!(tree.arena.stats[0].sims, expected_sims);
+        assert_consistent(&tree);
+        for node in 0..tree.arena.len() {
+            if tree.arena.topology[node].is_chance && tree.arena.topology[node].children.complete {
+                let outcomes = tree.game_state_at(node as NodeIndex).chance_outcomes().len();
+                assert_eq!(tree.arena.expanded_count(node as NodeIndex), outcomes);
+            }
+        }
+    }
+}
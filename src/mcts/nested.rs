@@ -0,0 +1,214 @@
+//! Nested Monte Carlo Search (NMCS), for single player puzzles and optimization problems.
+//!
+//! A search of level 0 is a single rollout. A search of level N plays a game move by move,
+//! trying every legal action of the current state with a search of level N-1 and following
+//! the best sequence found so far, which is remembered across moves so a good sequence is
+//! never lost to a worse later try. Unlike UCT, no tree is kept and only the best sequence
+//! matters rather than averages, which suits puzzles such as Morpion Solitaire and SameGame
+//! where a single high scoring line is sought.
+
+use crate::game_state_trait::{GameResult, GameState};
+use crate::rng::{SearchRng, SeedableRng};
+use crate::rollout::{RandomRollout, RolloutPolicy};
+use alloc::boxed::Box;
+use alloc::vec::Vec;
+
+
+/// Best sequence of actions found by a search, with the score it reached.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScoredSequence<Action> {
+    /// Score of the final state of the sequence, see `sequence_score`.
+    pub score: f64,
+
+    /// Actions from the searched state to a terminal state.
+    pub actions: Vec<Action>,
+}
+
+
+/// Score maximized by nested searches: the raw score of `GameResult::Score` and the margin of
+/// `GameResult::Margin`, while wins of the first player are worth 1, draws 0.5 and losses 0.
+pub(crate) fn sequence_score(result: &GameResult) -> f64 {
+    match result {
+        GameResult::FirstPlayerWin => return 1.0,
+        GameResult::SecondPlayerWin => return 0.0,
+        GameResult::Draw => return 0.5,
+        GameResult::Score(score) => return *score as f64,
+        GameResult::Margin(margin) => return *margin as f64,
+    }
+}
+
+/// Whether `game_state` has ended, with `actions` holding its legal actions.
+pub(crate) fn is_terminal<Action, GameStateObj>(game_state: &GameStateObj, actions: &[Action]) -> bool
+where
+    GameStateObj: GameState<Action>
+{
+    return actions.is_empty() || !game_state.status_with_moves_left();
+}
+
+
+/// Nested Monte Carlo Search of a fixed level.
+pub struct NestedSearch<Action, GameStateObj> {
+    /// Level of the search, 0 for a single rollout. Every level multiplies the cost of the
+    /// search by about the number of actions of a game times its branching factor.
+    pub level: u32,
+
+    /// Chooses the moves of the rollouts of level 0, randomly by default.
+    pub rollout_policy: Box<dyn RolloutPolicy<Action, GameStateObj> + Send>,
+
+    pub random_generator: SearchRng,
+
+    /// Number of rollouts played so far.
+    pub rollouts: u64,
+}
+
+impl<Action, GameStateObj> NestedSearch<Action, GameStateObj>
+where
+    GameStateObj: GameState<Action> + Clone,
+    Action: Clone
+{
+    /// Creates a search of `level` with random rollouts, seeded like `MCTSTree::with_capacity`.
+    pub fn new(level: u32, seed: Option<u64>) -> Self {
+        let seed_formatted = seed.unwrap_or(0) as u128;
+        return NestedSearch {
+            level,
+            rollout_policy: Box::new(RandomRollout),
+            random_generator: SeedableRng::from_seed(seed_formatted),
+            rollouts: 0,
+        };
+    }
+
+    /// Searches `game_state` at the level of the search.
+    ///
+    /// # Invariants
+    /// Assumes the game has no chance events, and that every game ends.
+    ///
+    /// # Returns
+    /// The best sequence found from `game_state` to a terminal state.
+    pub fn search(&mut self, game_state: &GameStateObj) -> ScoredSequence<Action> {
+        return self.nested(game_state.clone(), self.level);
+    }
+
+    /// Searches `game_state` at `level`.
+    fn nested(&mut self, mut game_state: GameStateObj, level: u32) -> ScoredSequence<Action> {
+        if level == 0 {
+            return self.rollout(game_state);
+        }
+
+        let mut best: Option<ScoredSequence<Action>> = None;
+        let mut played: Vec<Action> = Vec::new();
+        let mut actions = game_state.generate_legal_actions();
+        while !is_terminal(&game_state, &actions) {
+            for action in &actions {
+                let tried = self.nested(game_state.apply_action(action), level - 1);
+                if best.as_ref().is_none_or(|best| tried.score > best.score) {
+                    let mut sequence = played.clone();
+                    sequence.push(action.clone());
+                    sequence.extend(tried.actions);
+                    best = Some(ScoredSequence { score: tried.score, actions: sequence });
+                }
+            }
+
+            // Follow the best sequence found so far by one move.
+            let action = best.as_ref().expect("no sequence").actions[played.len()].clone();
+            game_state.apply_action_mut(&action);
+            played.push(action);
+            game_state.legal_actions_into(&mut actions);
+        }
+        return best.unwrap_or_else(|| ScoredSequence { score: sequence_score(&game_state.result()), actions: played });
+    }
+
+    /// Plays `game_state` out with the rollout policy.
+    fn rollout(&mut self, mut game_state: GameStateObj) -> ScoredSequence<Action> {
+        self.rollouts += 1;
+        let mut sequence = Vec::new();
+        let mut actions = Vec::new();
+        loop {
+            game_state.legal_actions_into(&mut actions);
+            if is_terminal(&game_state, &actions) {
+                return ScoredSequence { score: sequence_score(&game_state.result()), actions: sequence };
+            }
+            let chosen = self.rollout_policy.choose(&game_state, &actions, &mut self.random_generator);
+            game_state.apply_action_mut(&actions[chosen]);
+            sequence.push(actions.swap_remove(chosen));
+        }
+    }
+}
+
+
+/// Unit tests for Nested Monte Carlo Search.
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::string::String;
+    use alloc::vec;
+
+    /// Puzzle of choosing 6 digits, scoring the length of their common prefix with 3 1 4 1 5 9.
+    #[derive(Debug, Clone)]
+    struct DigitPuzzle {
+        digits: Vec<u8>,
+    }
+
+    const TARGET: [u8; 6] = [3, 1, 4, 1, 5, 9];
+
+    impl GameState<u8> for DigitPuzzle {
+        type ParseError = ();
+
+        fn from_str(_game_state: String) -> Self {
+            return DigitPuzzle { digits: Vec::new() };
+        }
+
+        fn apply_action(&self, action: &u8) -> Self {
+            let mut digits = self.digits.clone();
+            digits.push(*action);
+            return DigitPuzzle { digits };
+        }
+
+        fn status_with_moves_left(&self) -> bool {
+            return true;
+        }
+
+        fn result(&self) -> GameResult {
+            let prefix = self.digits.iter().zip(TARGET).take_while(|(digit, target)| **digit == *target).count();
+            return GameResult::Score(prefix as f32);
+        }
+
+        fn generate_legal_actions(&self) -> Vec<u8> {
+            return if self.digits.len() < TARGET.len() { (0..10).collect() } else { Vec::new() };
+        }
+
+        fn side_to_move(&self) -> bool {
+            return true;
+        }
+    }
+
+    /// Test if rollouts of level 0 reach a terminal state, scored by its result.
+    #[test]
+    fn test_level_zero() {
+        let mut search = NestedSearch::new(0, Some(1));
+        let sequence = search.search(&DigitPuzzle::from_str(String::new()));
+        assert_eq!(sequence.actions.len(), 6);
+        let mut state = DigitPuzzle::from_str(String::new());
+        for action in &sequence.actions {
+            state.apply_action_mut(action);
+        }
+        assert_eq!(sequence.score, sequence_score(&state.result()));
+        assert_eq!(search.rollouts, 1);
+    }
+
+    /// Test if a search of level 1 solves the puzzle, trying every digit at every position.
+    #[test]
+    fn test_level_one() {
+        let mut search = NestedSearch::new(1, Some(1));
+        let sequence = search.search(&DigitPuzzle::from_str(String::new()));
+        assert_eq!(sequence, ScoredSequence { score: 6.0, actions: vec![3, 1, 4, 1, 5, 9] });
+        assert_eq!(search.rollouts, 60);
+    }
+
+    /// Test if searches of terminal states return the empty sequence.
+    #[test]
+    fn test_terminal() {
+        let solved = DigitPuzzle { digits: TARGET.to_vec() };
+        let sequence = NestedSearch::new(2, Some(1)).search(&solved);
+        assert_eq!(sequence, ScoredSequence { score: 6.0, actions: Vec::new() });
+    }
+}
@@ -0,0 +1,226 @@
+//! Policy and value networks as leaf evaluators, for AlphaZero style searches.
+//!
+//! Game states describe themselves to a network through `TensorEncoding`, and networks are
+//! run through `InferenceModel`, which wraps the session of an inference runtime such as
+//! ONNX Runtime. `NeuralEvaluator` connects the two, encoding batches of leaves, running the
+//! model and decoding its outputs into the priors and values of `LeafEvaluator`.
+//!
+//! Models of pure Rust frameworks such as candle or tch plug in through `ForwardModel`, which
+//! hands the batch to a forward pass with the full shape of the input tensor, so the same
+//! networks can be trained and searched with in one pipeline.
+
+use crate::evaluator::{Evaluation, LeafEvaluator};
+use crate::float::Float;
+use crate::game_state_trait::GameState;
+use alloc::boxed::Box;
+use alloc::vec;
+use alloc::vec::Vec;
+
+
+/// Encoding of a game state as the input tensor of a network, and of its actions as
+/// indices into the policy output of the network.
+pub trait TensorEncoding<Action> {
+    /// Shape of the tensor of a single state, without the batch dimension, such as
+    /// `[planes, rows, columns]`.
+    const INPUT_SHAPE: &'static [usize];
+
+    /// Number of policy logits the network outputs for a state, one for every action of the game.
+    const POLICY_SIZE: usize;
+
+    /// Number of values in the tensor of a single state.
+    fn input_size() -> usize {
+        return Self::INPUT_SHAPE.iter().product();
+    }
+
+    /// Writes the tensor of the state into `input`, in row major order.
+    ///
+    /// # Invariants
+    /// `input` has `input_size` values, all 0.
+    fn encode(&self, input: &mut [f32]);
+
+    /// Index of the logit of `action` in the policy output, below `POLICY_SIZE`.
+    fn action_index(&self, action: &Action) -> usize;
+}
+
+
+/// Outputs of a network for a batch of states.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ModelOutput {
+    /// Policy logits, `POLICY_SIZE` per state in the order of the batch.
+    pub policy: Vec<f32>,
+
+    /// Value of every state for its side to move, between -1 for a loss and 1 for a win.
+    pub values: Vec<f32>,
+}
+
+
+/// A policy and value network, such as an ONNX model loaded into an ONNX Runtime session.
+pub trait InferenceModel {
+    /// Runs the network on a batch of states.
+    ///
+    /// # Arguments
+    /// * `input` : Tensors of `batch` states of the shape `TensorEncoding::INPUT_SHAPE`, one after the other.
+    ///
+    /// * `batch` : Number of states in the batch.
+    ///
+    /// # Panics
+    /// Implementations may panic if inference fails, as a search can not continue without it.
+    fn run(&mut self, input: &[f32], batch: usize) -> ModelOutput;
+}
+
+
+/// Adapter running a forward pass of a tensor framework as an `InferenceModel`.
+///
+/// The forward pass receives the input of a batch with its shape, `[batch]` followed by the
+/// shape of a state, from which a framework tensor is built, such as with candle's
+/// `Tensor::from_slice(input, shape, &device)`. It returns the flattened policy logits and values.
+pub struct ForwardModel<Forward> {
+    /// Shape of the tensor of a single state, see `TensorEncoding::INPUT_SHAPE`.
+    pub input_shape: Vec<usize>,
+
+    pub forward: Forward,
+}
+
+impl<Forward> ForwardModel<Forward>
+where
+    Forward: FnMut(&[f32], &[usize]) -> ModelOutput
+{
+    pub fn new(input_shape: &[usize], forward: Forward) -> Self {
+        return ForwardModel { input_shape: input_shape.to_vec(), forward };
+    }
+}
+
+impl<Forward> InferenceModel for ForwardModel<Forward>
+where
+    Forward: FnMut(&[f32], &[usize]) -> ModelOutput
+{
+    fn run(&mut self, input: &[f32], batch: usize) -> ModelOutput {
+        let mut shape = Vec::with_capacity(self.input_shape.len() + 1);
+        shape.push(batch);
+        shape.extend_from_slice(&self.input_shape);
+        return (self.forward)(input, &shape);
+    }
+}
+
+/// Models chosen at runtime, such as between an ONNX Runtime session and a candle network.
+impl InferenceModel for Box<dyn InferenceModel + Send> {
+    fn run(&mut self, input: &[f32], batch: usize) -> ModelOutput {
+        return self.as_mut().run(input, batch);
+    }
+}
+
+
+/// Leaf evaluator running a policy and value network.
+///
+/// Priors are the softmax of the policy logits over the legal actions of a state, and values
+/// are converted from the side to move to the share of the win of the first player.
+#[derive(Debug, Clone)]
+pub struct NeuralEvaluator<Model> {
+    pub model: Model,
+}
+
+impl<Model> NeuralEvaluator<Model> {
+    pub fn new(model: Model) -> Self {
+        return NeuralEvaluator { model };
+    }
+}
+
+impl<Action, GameStateObj, Model> LeafEvaluator<Action, GameStateObj> for NeuralEvaluator<Model>
+where
+    GameStateObj: GameState<Action> + TensorEncoding<Action>,
+    Model: InferenceModel
+{
+    fn evaluate_batch(&mut self, states: &[&GameStateObj]) -> Vec<Evaluation<Action>> {
+        let input_size = GameStateObj::input_size();
+        let mut input = vec![0.0; states.len() * input_size];
+        for (state, tensor) in states.iter().zip(input.chunks_mut(input_size)) {
+            state.encode(tensor);
+        }
+        let output = self.model.run(&input, states.len());
+        assert_eq!(output.policy.len(), states.len() * GameStateObj::POLICY_SIZE, "policy of the wrong size");
+        assert_eq!(output.values.len(), states.len(), "values of the wrong size");
+
+        return states.iter().zip(output.policy.chunks(GameStateObj::POLICY_SIZE)).zip(output.values).map(|((state, logits), value)| {
+            let actions = state.generate_legal_actions();
+            let legal_logits: Vec<f32> = actions.iter().map(|action| logits[state.action_index(action)]).collect();
+            let priors = actions.into_iter().zip(softmax(&legal_logits)).collect();
+            let value = if state.side_to_move() { (1.0 + value) / 2.0 } else { (1.0 - value) / 2.0 };
+            return Evaluation { priors, value };
+        }).collect();
+    }
+}
+
+
+/// Normalizes `logits` into probabilities.
+fn softmax(logits: &[f32]) -> Vec<f32> {
+    let max = logits.iter().copied().fold(f32::NEG_INFINITY, f32::max);
+    let exponentials: Vec<f32> = logits.iter().map(|logit| Float::powf(core::f32::consts::E, logit - max)).collect();
+    let total: f32 = exponentials.iter().sum();
+    return exponentials.into_iter().map(|exponential| exponential / total).collect();
+}
+
+
+/// Unit tests for neural leaf evaluation.
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mcts::{MCTSTree, SelectionPolicy};
+    use crate::tictactoe_env::TicTacToeState;
+
+    /// Network scoring the cells by their index, and every state as won for the side to move.
+    struct IndexModel {
+        batches: Vec<usize>,
+    }
+
+    impl InferenceModel for IndexModel {
+        fn run(&mut self, input: &[f32], batch: usize) -> ModelOutput {
+            assert_eq!(input.len(), batch * 18);
+            self.batches.push(batch);
+            let policy = (0..batch).flat_map(|_| (0..9).map(|cell| cell as f32)).collect();
+            return ModelOutput { policy, values: vec![1.0; batch] };
+        }
+    }
+
+    /// Test if model outputs are decoded into priors over the legal actions and first player values.
+    #[test]
+    fn test_neural_evaluator() {
+        let mut evaluator = NeuralEvaluator::new(IndexModel { batches: Vec::new() });
+        let x_to_move = TicTacToeState::try_from_str("XO.......").unwrap();
+        let o_to_move = TicTacToeState::try_from_str("XOX......").unwrap();
+        let evaluations = evaluator.evaluate_batch(&[&x_to_move, &o_to_move]);
+        assert_eq!(evaluator.model.batches, vec![2]);
+
+        let priors = &evaluations[0].priors;
+        assert_eq!(priors.len(), 7);
+        assert!((priors.iter().map(|(_, prior)| prior).sum::<f32>() - 1.0).abs() < 1e-5);
+        assert!(priors.windows(2).all(|pair| pair[0].1 < pair[1].1));
+        assert_eq!((evaluations[0].value, evaluations[1].value), (1.0, 0.0));
+    }
+
+    /// Test if forward passes receive the shape of the batch, also when boxed.
+    #[test]
+    fn test_forward_model() {
+        let model = ForwardModel::new(TicTacToeState::INPUT_SHAPE, |input: &[f32], shape: &[usize]| {
+            assert_eq!(shape, &[2, 2, 3, 3]);
+            assert_eq!(input.len(), shape.iter().product::<usize>());
+            return ModelOutput { policy: vec![0.0; 18], values: vec![0.0; 2] };
+        });
+        let mut evaluator = NeuralEvaluator::new(Box::new(model) as Box<dyn InferenceModel + Send>);
+        let state = TicTacToeState::new();
+        let evaluations = evaluator.evaluate_batch(&[&state, &state]);
+        assert!(evaluations[1].priors.iter().all(|(_, prior)| (prior - 1.0 / 9.0).abs() < 1e-6));
+        assert_eq!(evaluations[1].value, 0.5);
+    }
+
+    /// Test if trees search with the priors of the network.
+    #[test]
+    fn test_search_with_neural_evaluator() {
+        let mut tree = MCTSTree::<u8, TicTacToeState>::with_capacity(1000, Some(1), ".........".into(), 9);
+        tree.searcher.selection_policy = SelectionPolicy::Puct;
+        tree.searcher.evaluator = Some(Box::new(NeuralEvaluator::new(IndexModel { batches: Vec::new() })));
+        tree.search(100);
+        let first = tree.arena.expanded(0).next().unwrap();
+        assert_eq!(tree.arena.actions[first as usize], Some(8));
+        assert_eq!(tree.arena.stats[0].sims, 100);
+    }
+}
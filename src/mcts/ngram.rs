@@ -0,0 +1,187 @@
+//! N-gram rollouts, biasing rollouts towards the continuations which scored well across the search.
+//!
+//! A generalization of MAST from single moves to sequences: every simulation credits its reward
+//! to each n-gram of consecutive moves played in it, up to `max_length` moves long, to the side
+//! which played the last move of the n-gram. Rollouts score every legal move by averaging the
+//! means of the n-grams it ends after the previous moves of the rollout, and play the best one,
+//! with a random move instead at a rate of `epsilon`. N-grams longer than a single move only count
+//! once they have enough visits to be trusted.
+
+use crate::game_state_trait::GameState;
+use crate::mast::MoveStatistics;
+use crate::rng::{Rng, SearchRng};
+use crate::rollout::{RolloutPolicy, SimulationRewards};
+use alloc::collections::BTreeMap;
+use alloc::vec::Vec;
+
+
+/// Rollout policy playing the move whose n-grams have the best average rewards.
+///
+/// Set it as the `rollout_policy` of a tree, which teaches it the result of every simulation.
+/// Moves are identified by `GameState::action_code`, and rollouts panic if the game state does not
+/// support action codes. The first moves of a rollout only extend the previous moves within the rollout.
+#[derive(Debug, Clone, PartialEq)]
+pub struct NGramRollout {
+    /// Length of the longest n-grams, 3 by default. A length of 1 is MAST with epsilon-greedy rollouts.
+    pub max_length: usize,
+
+    /// Rate of random moves, 0.1 by default.
+    pub epsilon: f64,
+
+    /// Visits n-grams longer than a single move need to count towards the score of a move, 7 by default.
+    pub min_visits: u32,
+
+    /// Score of moves without visited n-grams, 1 by default so that they are tried.
+    pub unseen_value: f64,
+
+    /// Statistics of every n-gram played so far, by the action codes of its moves in order.
+    pub table: BTreeMap<Vec<u64>, MoveStatistics>,
+
+    /// Moves of the current rollout with whether the first player played them, until they are learned.
+    rollout_moves: Vec<(u64, bool)>,
+}
+
+impl Default for NGramRollout {
+    fn default() -> Self {
+        return NGramRollout {
+            max_length: 3,
+            epsilon: 0.1,
+            min_visits: 7,
+            unseen_value: 1.0,
+            table: BTreeMap::new(),
+            rollout_moves: Vec::new(),
+        };
+    }
+}
+
+impl NGramRollout {
+    /// Score of playing the move with `code` after the moves of `history`, the average of the means
+    /// of the n-grams it ends, `unseen_value` if none of them counts.
+    ///
+    /// # Arguments
+    /// * `history` : Action codes of the previous moves, the last one played last.
+    pub fn value(&self, history: &[u64], code: u64) -> f64 {
+        let mut gram = Vec::with_capacity(self.max_length);
+        gram.push(code);
+        let mut total = 0.0;
+        let mut counted = 0;
+        for length in 1..=self.max_length {
+            if length > 1 {
+                match history.len().checked_sub(length - 1) {
+                    Some(index) => gram.insert(0, history[index]),
+                    None => break,
+                }
+            }
+            if let Some(statistics) = self.table.get(gram.as_slice()) {
+                if statistics.visits > 0 && (length == 1 || statistics.visits >= self.min_visits) {
+                    total += statistics.mean();
+                    counted += 1;
+                }
+            }
+        }
+        return if counted == 0 { self.unseen_value } else { total / counted as f64 };
+    }
+}
+
+impl<Action, GameStateObj> RolloutPolicy<Action, GameStateObj> for NGramRollout
+where
+    GameStateObj: GameState<Action>
+{
+    fn choose(&mut self, game_state: &GameStateObj, actions: &[Action], random_generator: &mut SearchRng) -> usize {
+        let codes: Vec<u64> = actions.iter()
+            .map(|action| game_state.action_code(action).expect("game state without action codes"))
+            .collect();
+
+        let chosen = if random_generator.gen::<f64>() < self.epsilon {
+            random_generator.gen_range(0, actions.len())
+        }
+        else {
+            let start = self.rollout_moves.len().saturating_sub(self.max_length.saturating_sub(1));
+            let history: Vec<u64> = self.rollout_moves[start..].iter().map(|(code, _)| *code).collect();
+            let mut best = (0, f64::NEG_INFINITY);
+            for (i, code) in codes.iter().enumerate() {
+                let value = self.value(&history, *code);
+                if value > best.1 {
+                    best = (i, value);
+                }
+            }
+            best.0
+        };
+        self.rollout_moves.push((codes[chosen], game_state.side_to_move()));
+        return chosen;
+    }
+
+    fn learns(&self) -> bool {
+        return true;
+    }
+
+    fn learn(&mut self, tree_moves: &[(u64, bool)], rewards: SimulationRewards) {
+        let moves: Vec<(u64, bool)> = tree_moves.iter().chain(self.rollout_moves.iter()).copied().collect();
+        for end in 0..moves.len() {
+            let reward = rewards.of(moves[end].1);
+            for length in 1..=self.max_length.min(end + 1) {
+                let gram: Vec<u64> = moves[end + 1 - length..=end].iter().map(|(code, _)| *code).collect();
+                let statistics = self.table.entry(gram).or_default();
+                statistics.reward += reward;
+                statistics.visits += 1;
+            }
+        }
+        self.rollout_moves.clear();
+    }
+}
+
+
+/// Unit tests for n-gram rollouts.
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mcts::MCTSTree;
+    use crate::rng::SeedableRng;
+    use crate::tictactoe_env::TicTacToeState;
+    use alloc::boxed::Box;
+    use alloc::vec;
+
+    /// Test if simulations credit every n-gram to the side which played its last move.
+    #[test]
+    fn test_learn() {
+        let mut policy = NGramRollout::default();
+        RolloutPolicy::<u8, TicTacToeState>::learn(&mut policy, &[(9, true), (16, false)], SimulationRewards { first_player: 0.0, second_player: 1.0 });
+        assert_eq!(policy.table.len(), 3);
+        assert_eq!(policy.table[&vec![9]], MoveStatistics { reward: 0.0, visits: 1 });
+        assert_eq!(policy.table[&vec![9, 16]], MoveStatistics { reward: 1.0, visits: 1 });
+        assert_eq!(policy.table[&vec![16]], MoveStatistics { reward: 1.0, visits: 1 });
+    }
+
+    /// Test if trusted n-grams bias the choice of continuation.
+    #[test]
+    fn test_continuation() {
+        let mut policy = NGramRollout { epsilon: 0.0, ..Default::default() };
+        let state = TicTacToeState::try_from_str("....X....").unwrap();
+        let (corner, edge) = (state.action_code(&0).unwrap(), state.action_code(&1).unwrap());
+        policy.table.insert(vec![corner], MoveStatistics { reward: 5.0, visits: 10 });
+        policy.table.insert(vec![edge], MoveStatistics { reward: 4.0, visits: 10 });
+        policy.table.insert(vec![9, edge], MoveStatistics { reward: 10.0, visits: 10 });
+        assert_eq!(policy.value(&[9], edge), 0.7);
+
+        let seed = 1;
+        let mut random_generator: SearchRng = SeedableRng::from_seed(seed);
+        assert_eq!(policy.choose(&state, &[0, 1], &mut random_generator), 0);
+        policy.rollout_moves = vec![(9, true)];
+        assert_eq!(policy.choose(&state, &[0, 1], &mut random_generator), 1);
+
+        // N-grams with too few visits are not trusted.
+        policy.min_visits = 20;
+        policy.rollout_moves = vec![(9, true)];
+        assert_eq!(policy.choose(&state, &[0, 1], &mut random_generator), 0);
+    }
+
+    /// Test if trees search with the policy, finding the winning move.
+    #[test]
+    fn test_search_with_ngrams() {
+        let mut tree = MCTSTree::<u8, TicTacToeState>::with_capacity(1000, Some(1), "XX.OO....".into(), 9);
+        tree.searcher.rollout_policy = Box::new(NGramRollout::default());
+        tree.search(500);
+        let best = tree.most_visited_child(0).unwrap();
+        assert_eq!(tree.arena.actions[best as usize], Some(2));
+    }
+}
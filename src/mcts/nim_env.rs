@@ -0,0 +1,277 @@
+use crate::game_state_trait::{GameResult, GameState};
+use crate::serialize::{decode_from_str, Decode, DecodeError, Encode};
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::fmt;
+
+
+/// Error produced when a string is not a valid Nim position.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NimParseError(pub String);
+
+impl fmt::Display for NimParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        return write!(f, "invalid nim position: {}", self.0);
+    }
+}
+
+impl core::error::Error for NimParseError {}
+
+
+/// Holds the state of a game of Nim with any number of piles.
+///
+/// Actions are a pile index and the number of objects to take from it, at least one.
+/// Under normal play the player taking the last object wins, under misère play they lose.
+/// Optimal play is known analytically from the nim-sum of the piles, see `winning_moves`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct NimState {
+    /// Number of objects left in every pile.
+    pub piles: Vec<u32>,
+
+    /// True if the first player is the side to move.
+    pub first_to_move: bool,
+
+    /// True if the player taking the last object loses.
+    pub misere: bool,
+}
+
+
+impl NimState {
+    /// Creates a normal play game with the given piles, with the first player to move.
+    pub fn new(piles: Vec<u32>) -> Self {
+        return NimState { piles, first_to_move: true, misere: false };
+    }
+
+    /// Exclusive or of every pile size.
+    pub fn nim_sum(&self) -> u32 {
+        return self.piles.iter().fold(0, |sum, pile| sum ^ pile);
+    }
+
+    /// Determines whether the side to move wins with perfect play.
+    ///
+    /// Under normal play the side to move wins when the nim-sum is not 0. Misère play
+    /// is the same, except once every pile has at most one object, where the side to move
+    /// wins when an even number of piles is left.
+    pub fn is_winning(&self) -> bool {
+        if self.misere && self.piles.iter().all(|pile| *pile <= 1) {
+            return self.piles.iter().filter(|pile| **pile == 1).count() % 2 == 0;
+        }
+        return self.nim_sum() != 0;
+    }
+
+    /// Lists every move leaving the opponent in a losing position.
+    ///
+    /// # Returns
+    /// The winning moves, which is empty if the side to move loses with perfect play.
+    pub fn winning_moves(&self) -> Vec<(usize, u32)> {
+        return self.generate_legal_actions()
+            .into_iter()
+            .filter(|action| !self.apply_action(action).is_winning())
+            .collect();
+    }
+
+    /// Reverts `action`, which must be the last action applied with `apply_action_mut`.
+    pub fn undo_action(&mut self, action: &(usize, u32)) {
+        self.piles[action.0] += action.1;
+        self.first_to_move = !self.first_to_move;
+    }
+}
+
+
+/// Encodes the piles separated by spaces, followed by `p2` if the second player is to move and
+/// `misere` for misère play, such as `3 4 5 p2`. `GameState::from_str` parses it back.
+impl fmt::Display for NimState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let piles: Vec<String> = self.piles.iter().map(|pile| pile.to_string()).collect();
+        write!(f, "{}", piles.join(" "))?;
+        if !self.first_to_move {
+            write!(f, " p2")?;
+        }
+        if self.misere {
+            write!(f, " misere")?;
+        }
+        return Ok(());
+    }
+}
+
+
+/// Allows the MCTS engine to build NimState trees.
+impl GameState<(usize, u32)> for NimState {
+    type ParseError = NimParseError;
+
+    fn from_str(game_state: String) -> Self {
+        return Self::try_from_str(&game_state).expect("invalid nim position");
+    }
+
+    fn try_from_str(game_state: &str) -> Result<Self, NimParseError> {
+        let mut state = NimState::new(Vec::new());
+        for token in game_state.split_whitespace() {
+            match token {
+                "p1" => state.first_to_move = true,
+                "p2" => state.first_to_move = false,
+                "misere" => state.misere = true,
+                _ => state.piles.push(token.parse::<u32>().map_err(|_| NimParseError(game_state.to_string()))?),
+            }
+        }
+        return Ok(state);
+    }
+
+    fn apply_action(&self, action: &(usize, u32)) -> Self {
+        let mut new_state = self.clone();
+        new_state.apply_action_mut(action);
+        return new_state;
+    }
+
+    fn apply_action_mut(&mut self, action: &(usize, u32)) {
+        self.piles[action.0] -= action.1;
+        self.first_to_move = !self.first_to_move;
+    }
+
+    fn status_with_moves_left(&self) -> bool {
+        return true;
+    }
+
+    fn result(&self) -> GameResult {
+        // Every pile is empty, so the last player to move took the last object.
+        let side_to_move_wins = self.misere;
+        if side_to_move_wins == self.first_to_move {
+            return GameResult::FirstPlayerWin;
+        }
+        return GameResult::SecondPlayerWin;
+    }
+
+    fn generate_legal_actions(&self) -> Vec<(usize, u32)> {
+        let mut actions = Vec::new();
+        self.legal_actions_into(&mut actions);
+        return actions;
+    }
+
+    fn legal_actions_into(&self, actions: &mut Vec<(usize, u32)>) {
+        actions.clear();
+        for (pile, size) in self.piles.iter().enumerate() {
+            actions.extend((1..=*size).map(|taken| (pile, taken)));
+        }
+    }
+
+    fn state_hash(&self) -> Option<u64> {
+        let hash = self.piles.iter().fold(self.first_to_move as u64, |hash: u64, pile| {
+            return hash.wrapping_mul(0x100_0000_01B3).wrapping_add(*pile as u64 + 1);
+        });
+        return Some(hash ^ self.misere as u64);
+    }
+
+    fn rollout_move_cap(&self) -> Option<u32> {
+        // Every move removes objects, so games end once they run out.
+        return None;
+    }
+
+    fn side_to_move(&self) -> bool {
+        return self.first_to_move;
+    }
+}
+
+
+/// Encodes the state through its string encoding, which holds every part of it.
+impl Encode for NimState {
+    fn encode(&self, out: &mut Vec<u8>) {
+        self.to_string().encode(out);
+    }
+}
+
+impl Decode for NimState {
+    fn decode(input: &mut &[u8]) -> Result<Self, DecodeError> {
+        return decode_from_str(input);
+    }
+}
+
+
+/// Defines unit tests for the GameState implementation of NimState,
+/// checking the engine against the analytical solution.
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mcts::MCTSTree;
+
+    /// Solves `state` by exhaustive search.
+    ///
+    /// # Returns
+    /// True if the side to move wins with perfect play.
+    fn solve(state: &mut NimState) -> bool {
+        let actions = state.generate_legal_actions();
+        if actions.len() == 0 {
+            return state.result().winner() == Some(state.side_to_move());
+        }
+        for action in actions {
+            state.apply_action_mut(&action);
+            let opponent_wins = solve(state);
+            state.undo_action(&action);
+            if !opponent_wins {
+                return true;
+            }
+        }
+        return false;
+    }
+
+    /// Chooses the move of the engine after `iterations` iterations of search from `state`.
+    fn engine_move(state: &NimState, iterations: u32, seed: u64) -> (usize, u32) {
+        let mut tree = MCTSTree::<(usize, u32), NimState>::with_capacity(iterations as usize + 1, Some(seed), state.to_string(), 12);
+        tree.search(iterations as u64);
+        let best = tree.get_max_uct_child_at(0, Some(0.0)).expect("no legal moves");
+        return tree.arena.actions[best as usize].expect("no action");
+    }
+
+    /// Test if the analytical solution matches exhaustive search on every small position.
+    #[test]
+    fn test_is_winning() {
+        for misere in [false, true] {
+            for a in 0..5 {
+                for b in 0..5 {
+                    for c in 0..5 {
+                        let mut state = NimState { piles: vec![a, b, c], first_to_move: true, misere };
+                        assert_eq!(state.is_winning(), solve(&mut state), "{}", state);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Test if positions round trip through their string encoding.
+    #[test]
+    fn test_try_from_str() {
+        let state = NimState::from_str("3 4 5".to_string());
+        assert_eq!(state.piles, vec![3, 4, 5]);
+        assert!(state.side_to_move());
+        assert_eq!(state.generate_legal_actions().len(), 12);
+
+        let misere = NimState::from_str("1 2 p2 misere".to_string());
+        assert!(!misere.side_to_move());
+        assert!(misere.misere);
+        assert_eq!(NimState::from_str(misere.to_string()), misere);
+        assert!(NimState::try_from_str("3 x").is_err());
+    }
+
+    /// Test if taking the last object wins under normal play and loses under misère play.
+    #[test]
+    fn test_result() {
+        let taken = NimState::new(vec![0, 2]).apply_action(&(1, 2));
+        assert!(taken.generate_legal_actions().is_empty());
+        assert_eq!(taken.result(), GameResult::FirstPlayerWin);
+
+        let misere = NimState { misere: true, ..NimState::new(vec![0, 2]) }.apply_action(&(1, 2));
+        assert_eq!(misere.result(), GameResult::SecondPlayerWin);
+    }
+
+    /// Test if the engine converges to the winning moves known from the nim-sum.
+    #[test]
+    fn test_engine_plays_perfectly() {
+        let positions = ["1 2 4", "1 3 5", "2 3 4", "1 2 3 4", "1 1 2 misere", "2 3 misere"];
+        for (seed, position) in positions.iter().enumerate() {
+            let state = NimState::from_str(position.to_string());
+            let winning_moves = state.winning_moves();
+            assert!(winning_moves.len() > 0);
+            let action = engine_move(&state, 20000, seed as u64);
+            assert!(winning_moves.contains(&action), "{:?} is not winning in {}", action, state);
+        }
+        assert_eq!(NimState::from_str("3 4 5".to_string()).winning_moves(), vec![(0, 2)]);
+    }
+}
@@ -0,0 +1,246 @@
+//! Read-only access to the nodes of a tree through generation checked `NodeId` handles, the
+//! stable surface for inspecting a tree without depending on how the arena lays nodes out.
+
+use crate::arena::{NodeId, NodeIndex, NodeStats, StaleNodeError};
+use crate::children::ChildStats;
+use crate::game_state_trait::GameState;
+use crate::mcts::MCTSTree;
+use alloc::borrow::Cow;
+
+
+/// View of a single node of a tree, borrowed from it.
+pub struct NodeRef<'a, Action, GameStateObj, Data = ()>
+where
+    GameStateObj: GameState<Action> + Clone
+{
+    tree: &'a MCTSTree<Action, GameStateObj, Data>,
+    index: NodeIndex,
+}
+
+impl<'a, Action, GameStateObj, Data> NodeRef<'a, Action, GameStateObj, Data>
+where
+    GameStateObj: GameState<Action> + Clone
+{
+    /// Handle to the node.
+    pub fn id(&self) -> NodeId {
+        return self.tree.arena.handle(self.index);
+    }
+
+    /// Action applied to the state of the parent to reach the node, None for the root.
+    pub fn action(&self) -> Option<&'a Action> {
+        return self.tree.arena.actions[self.index as usize].as_ref();
+    }
+
+    /// Parent of the node, None for the root.
+    pub fn parent(&self) -> Option<NodeId> {
+        return self.tree.arena.topology[self.index as usize].parent.map(|parent| self.tree.arena.handle(parent));
+    }
+
+    /// Simulation statistics of the node.
+    pub fn stats(&self) -> &'a NodeStats {
+        return &self.tree.arena.stats[self.index as usize];
+    }
+
+    /// Whether the node is a chance node, whose children are outcomes rather than decisions.
+    pub fn is_chance(&self) -> bool {
+        return self.tree.arena.topology[self.index as usize].is_chance;
+    }
+
+    /// Expanded children of the node, see `MCTSTree::children`.
+    pub fn children(&self) -> impl Iterator<Item = (NodeId, &'a Action, ChildStats)> + 'a {
+        return self.tree.children_at(self.index);
+    }
+
+    /// Number of legal actions of the node which have not been expanded yet.
+    pub fn unexpanded_count(&self) -> usize {
+        return self.tree.arena.unexpanded_count(self.index);
+    }
+
+    /// Game state of the node, borrowed if the node stores it and re-derived otherwise.
+    pub fn game_state(&self) -> Cow<'a, GameStateObj> {
+        return self.tree.game_state_at(self.index);
+    }
+
+    /// User data attached to the node, None until set through `MCTSTree::node_data_mut`.
+    pub fn data(&self) -> Option<&'a Data> {
+        return self.tree.arena.data[self.index as usize].as_ref();
+    }
+}
+
+
+/// Accessors of nodes by handle.
+impl<Action, GameStateObj, Data> MCTSTree<Action, GameStateObj, Data>
+where
+    GameStateObj: GameState<Action> + Clone
+{
+    /// Handle to the root of the tree.
+    pub fn root(&self) -> NodeId {
+        return self.arena.handle(0);
+    }
+
+    /// Handle to the node at arena index `index`.
+    ///
+    /// # Panics
+    /// If `index` is not a node of the tree.
+    pub(crate) fn node_id(&self, index: NodeIndex) -> NodeId {
+        return self.arena.handle(index);
+    }
+
+    /// Arena index of the node `id` refers to, for the methods taking handles.
+    ///
+    /// # Panics
+    /// If `id` is stale.
+    pub(crate) fn index_of(&self, id: NodeId) -> NodeIndex {
+        return self.arena.resolve(id).expect("stale node");
+    }
+
+    /// View of the node `id` refers to.
+    ///
+    /// # Returns
+    /// The node, or a `StaleNodeError` if it has been freed, such as by advancing the root.
+    pub fn node(&self, id: NodeId) -> Result<NodeRef<'_, Action, GameStateObj, Data>, StaleNodeError> {
+        let index = self.arena.resolve(id)?;
+        return Ok(NodeRef { tree: self, index });
+    }
+
+    /// Parent of the node `id` refers to, None for the root.
+    ///
+    /// # Returns
+    /// The parent, or a `StaleNodeError` if the node has been freed.
+    pub fn parent(&self, id: NodeId) -> Result<Option<NodeId>, StaleNodeError> {
+        return Ok(self.node(id)?.parent());
+    }
+
+    /// Simulation statistics of the node `id` refers to. Named apart from `stats`, which reports
+    /// on the search as a whole.
+    ///
+    /// # Returns
+    /// The statistics, or a `StaleNodeError` if the node has been freed.
+    pub fn node_stats(&self, id: NodeId) -> Result<&NodeStats, StaleNodeError> {
+        return Ok(self.node(id)?.stats());
+    }
+
+    /// Action leading to the node `id` refers to, None for the root.
+    ///
+    /// # Returns
+    /// The action, or a `StaleNodeError` if the node has been freed.
+    pub fn action(&self, id: NodeId) -> Result<Option<&Action>, StaleNodeError> {
+        return Ok(self.node(id)?.action());
+    }
+
+    /// User data attached to the node `id` refers to, None until set.
+    ///
+    /// # Returns
+    /// The data, or a `StaleNodeError` if the node has been freed.
+    pub fn node_data(&self, id: NodeId) -> Result<Option<&Data>, StaleNodeError> {
+        return Ok(self.node(id)?.data());
+    }
+
+    /// Mutable slot of the user data of the node `id` refers to, to attach, update or take
+    /// the data. The search never reads the data. It moves with its node when the root is
+    /// advanced, and is dropped when the node is freed.
+    ///
+    /// # Returns
+    /// The slot, or a `StaleNodeError` if the node has been freed.
+    pub fn node_data_mut(&mut self, id: NodeId) -> Result<&mut Option<Data>, StaleNodeError> {
+        let index = self.arena.resolve(id)?;
+        return Ok(&mut self.arena.data[index as usize]);
+    }
+}
+
+
+/// Unit tests for the node accessors.
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tictactoe_env::TicTacToeState;
+    use alloc::string::ToString;
+    use alloc::vec;
+    use alloc::vec::Vec;
+
+    /// Test if nodes are reached from the root by handle, and handles to freed nodes are rejected.
+    #[test]
+    fn test_node_accessors() {
+        let mut tree = MCTSTree::<u8, TicTacToeState>::with_capacity(1000, Some(1), "XX.OO....".to_string(), 9);
+        tree.search(200);
+
+        let root = tree.node(tree.root()).unwrap();
+        assert_eq!(root.id(), tree.root());
+        assert_eq!((root.action(), root.parent()), (None, None));
+        assert_eq!(root.stats().sims, 200);
+        assert_eq!(root.unexpanded_count(), 0);
+        assert!(!root.is_chance());
+
+        let (child, action, child_stats) = root.children().max_by_key(|(_, _, stats)| stats.visits).unwrap();
+        assert_eq!(*action, 2);
+        assert_eq!(tree.parent(child), Ok(Some(tree.root())));
+        assert_eq!(tree.action(child), Ok(Some(&2)));
+        assert_eq!(tree.node_stats(child).unwrap().sims, child_stats.visits);
+        assert_eq!(tree.node(child).unwrap().game_state().into_owned(), TicTacToeState::try_from_str("XXXOO....").unwrap());
+
+        // Advancing the root frees every node, so earlier handles become stale.
+        let index = tree.arena.resolve(child).unwrap();
+        assert_eq!(tree.node_id(index), child);
+        tree.advance_root_at(index);
+        assert!(tree.node(child).is_err());
+        assert_eq!(tree.node_stats(tree.root()).unwrap().sims, child_stats.visits);
+    }
+    /// Test if the steps of the search take and return handles, and the path of a leaf leads back to the root.
+    #[test]
+    fn test_search_by_handle() {
+        let mut tree = MCTSTree::<u8, TicTacToeState>::with_capacity(1000, Some(1), "XX.OO....".to_string(), 9);
+        for _i in 0..300 {
+            let leaf = tree.select(tree.root(), None);
+            let expanded = tree.expand(leaf);
+            let result = tree.simulate(expanded);
+            tree.backpropagate(expanded, result);
+        }
+        assert_eq!(tree.node_stats(tree.root()).unwrap().sims, 300);
+
+        let leaf = tree.select(tree.root(), Some(0.0));
+        let path = tree.trace_path(leaf);
+        assert_eq!(path.last(), Some(&leaf));
+        assert_eq!(tree.parent(path[0]), Ok(Some(tree.root())));
+        assert!(path.windows(2).all(|pair| tree.parent(pair[1]) == Ok(Some(pair[0]))));
+        assert_eq!(tree.get_max_uct_child(tree.root(), Some(0.0)), Some(path[0]));
+        assert_eq!(tree.action(path[0]), Ok(Some(&2)));
+    }
+
+    /// Test if search steps reject handles to freed nodes.
+    #[test]
+    #[should_panic(expected = "stale node")]
+    fn test_search_stale_handle() {
+        let mut tree = MCTSTree::<u8, TicTacToeState>::with_capacity(1000, Some(1), "XX.OO....".to_string(), 9);
+        tree.search(100);
+        let root = tree.root();
+        let (child, _, _) = tree.children(root).next().unwrap();
+        tree.advance_root(child);
+        tree.select(root, None);
+    }
+
+    /// Test if user data attached to nodes is read back, and follows its node when the root is advanced.
+    #[test]
+    fn test_node_data() {
+        let mut tree = MCTSTree::<u8, TicTacToeState, Vec<f32>>::with_capacity(1000, Some(1), "XX.OO....".to_string(), 9);
+        tree.search(200);
+        let (child, _, _) = tree.children(tree.root()).max_by_key(|(_, _, stats)| stats.visits).unwrap();
+        assert_eq!(tree.node_data(child), Ok(None));
+
+        tree.node_data_mut(tree.root()).unwrap().replace(vec![0.5]);
+        tree.node_data_mut(child).unwrap().get_or_insert_with(Vec::new).push(1.0);
+        assert_eq!(tree.node(tree.root()).unwrap().data(), Some(&vec![0.5]));
+        assert_eq!(tree.node_data(child), Ok(Some(&vec![1.0])));
+
+        // Searching leaves the data untouched, and new nodes have none.
+        tree.search(100);
+        assert_eq!(tree.node_data(child), Ok(Some(&vec![1.0])));
+        assert!(tree.children(child).all(|(grandchild, _, _)| tree.node_data(grandchild) == Ok(None)));
+
+        tree.advance_root(child);
+        assert!(tree.node_data(child).is_err());
+        assert!(tree.node_data_mut(child).is_err());
+        assert_eq!(tree.node_data(tree.root()), Ok(Some(&vec![1.0])));
+        assert_eq!(tree.node_data_mut(tree.root()).unwrap().take(), Some(vec![1.0]));
+        assert_eq!(tree.node_data(tree.root()), Ok(None));
+    }
+}
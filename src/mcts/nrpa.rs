@@ -0,0 +1,292 @@
+//! Nested Rollout Policy Adaptation (NRPA), for single player puzzles and optimization problems.
+//!
+//! Rollouts choose their actions from a softmax over learned weights, one per action code given
+//! by `GameState::action_code`. A search of level N runs a number of searches of level N-1, each
+//! starting from a copy of its policy, and after every one adapts the policy towards the best
+//! sequence found so far, raising the weights of its actions and lowering those of the
+//! alternatives in proportion to their probabilities. Searches of level 0 are single rollouts.
+//!
+//! Weights are kept by code rather than by state, so what is learned about an action carries
+//! over to every state it is legal in, as in Morpion Solitaire and TSP-like routing puzzles
+//! where a code such as a line or an edge has the same worth throughout a game.
+
+use crate::float::Float;
+use crate::game_state_trait::GameState;
+use crate::nested::{is_terminal, sequence_score, ScoredSequence};
+use crate::rng::{SearchRng, SeedableRng};
+use crate::rollout::{sample_weighted, RolloutPolicy};
+use alloc::collections::BTreeMap;
+use alloc::vec::Vec;
+
+
+/// Code of `action` in `game_state`.
+///
+/// # Panics
+/// If the game state does not support action codes.
+fn code<Action, GameStateObj>(game_state: &GameStateObj, action: &Action) -> u64
+where
+    GameStateObj: GameState<Action>
+{
+    return game_state.action_code(action).expect("game state without action codes");
+}
+
+
+/// Rollout policy choosing actions from a softmax over the weights of their codes.
+///
+/// Usable as the `rollout_policy` of a tree, to play the rollouts of a search with a policy
+/// learned by NRPA.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SoftmaxPolicy {
+    /// Weight of every action code, 0 for codes without a weight.
+    pub weights: BTreeMap<u64, f64>,
+}
+
+impl SoftmaxPolicy {
+    /// Weight of `code`.
+    pub fn weight(&self, code: u64) -> f64 {
+        return self.weights.get(&code).copied().unwrap_or(0.0);
+    }
+
+    /// Unnormalized softmax probabilities of the codes of `actions`, relative to the largest weight.
+    fn exponentials<Action, GameStateObj>(&self, game_state: &GameStateObj, actions: &[Action]) -> Vec<f64>
+    where
+        GameStateObj: GameState<Action>
+    {
+        let weights: Vec<f64> = actions.iter().map(|action| self.weight(code(game_state, action))).collect();
+        let max = weights.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+        return weights.into_iter().map(|weight| Float::powf(core::f64::consts::E, weight - max)).collect();
+    }
+}
+
+impl<Action, GameStateObj> RolloutPolicy<Action, GameStateObj> for SoftmaxPolicy
+where
+    GameStateObj: GameState<Action>
+{
+    fn choose(&mut self, game_state: &GameStateObj, actions: &[Action], random_generator: &mut SearchRng) -> usize {
+        let exponentials: Vec<f32> = self.exponentials(game_state, actions).into_iter().map(|exponential| exponential as f32).collect();
+        return sample_weighted(&exponentials, random_generator);
+    }
+}
+
+
+/// Nested Rollout Policy Adaptation of a fixed level.
+#[derive(Debug, Clone)]
+pub struct NestedPolicyAdaptation {
+    /// Level of the search, at least 1. A search plays `iterations` to the power of `level` rollouts.
+    pub level: u32,
+
+    /// Number of searches of the level below run by every search of a level.
+    pub iterations: u32,
+
+    /// Step size of every adaptation of the policy.
+    pub alpha: f64,
+
+    /// Policy the search starts from, replaced by the policy adapted by the top level once it ends,
+    /// so that later searches continue from what was learned.
+    pub policy: SoftmaxPolicy,
+
+    pub random_generator: SearchRng,
+
+    /// Number of rollouts played so far.
+    pub rollouts: u64,
+}
+
+impl NestedPolicyAdaptation {
+    /// Creates a search of `level` with 100 iterations per level and a step size of 1, starting
+    /// from the uniform policy and seeded like `MCTSTree::with_capacity`.
+    pub fn new(level: u32, seed: Option<u64>) -> Self {
+        let seed_formatted = seed.unwrap_or(0) as u128;
+        return NestedPolicyAdaptation {
+            level,
+            iterations: 100,
+            alpha: 1.0,
+            policy: SoftmaxPolicy::default(),
+            random_generator: SeedableRng::from_seed(seed_formatted),
+            rollouts: 0,
+        };
+    }
+
+    /// Searches `game_state` at the level of the search.
+    ///
+    /// # Invariants
+    /// Assumes the game has no chance events, and that every game ends.
+    ///
+    /// # Returns
+    /// The best sequence found from `game_state` to a terminal state.
+    ///
+    /// # Panics
+    /// If the game state does not support action codes.
+    pub fn search<Action, GameStateObj>(&mut self, game_state: &GameStateObj) -> ScoredSequence<Action>
+    where
+        GameStateObj: GameState<Action> + Clone,
+        Action: Clone
+    {
+        let mut policy = core::mem::take(&mut self.policy);
+        let best = self.nested(game_state, self.level, &mut policy);
+        self.policy = policy;
+        return best;
+    }
+
+    /// Searches `game_state` at `level`, adapting `policy`.
+    fn nested<Action, GameStateObj>(&mut self, game_state: &GameStateObj, level: u32, policy: &mut SoftmaxPolicy) -> ScoredSequence<Action>
+    where
+        GameStateObj: GameState<Action> + Clone,
+        Action: Clone
+    {
+        if level == 0 {
+            return self.rollout(game_state.clone(), policy);
+        }
+
+        let mut best: Option<ScoredSequence<Action>> = None;
+        for _ in 0..self.iterations.max(1) {
+            let mut level_policy = policy.clone();
+            let tried = self.nested(game_state, level - 1, &mut level_policy);
+
+            // Ties go to the latest sequence, which lets the search drift across plateaus.
+            if best.as_ref().is_none_or(|best| tried.score >= best.score) {
+                best = Some(tried);
+            }
+            self.adapt(game_state, policy, &best.as_ref().expect("no sequence").actions);
+        }
+        return best.expect("no sequence");
+    }
+
+    /// Adapts `policy` towards playing `sequence` from `game_state`.
+    ///
+    /// Along the sequence, the weight of every played action rises by `alpha`, while the weights
+    /// of the legal actions fall by `alpha` times their probability under the policy before adaptation.
+    fn adapt<Action, GameStateObj>(&self, game_state: &GameStateObj, policy: &mut SoftmaxPolicy, sequence: &[Action])
+    where
+        GameStateObj: GameState<Action> + Clone
+    {
+        let previous = policy.clone();
+        let mut game_state = game_state.clone();
+        let mut actions = Vec::new();
+        for action in sequence {
+            game_state.legal_actions_into(&mut actions);
+            let exponentials = previous.exponentials(&game_state, &actions);
+            let total: f64 = exponentials.iter().sum();
+            for (legal, exponential) in actions.iter().zip(exponentials) {
+                *policy.weights.entry(code(&game_state, legal)).or_insert(0.0) -= self.alpha * exponential / total;
+            }
+            *policy.weights.entry(code(&game_state, action)).or_insert(0.0) += self.alpha;
+            game_state.apply_action_mut(action);
+        }
+    }
+
+    /// Plays `game_state` out with `policy`.
+    fn rollout<Action, GameStateObj>(&mut self, mut game_state: GameStateObj, policy: &mut SoftmaxPolicy) -> ScoredSequence<Action>
+    where
+        GameStateObj: GameState<Action>
+    {
+        self.rollouts += 1;
+        let mut sequence = Vec::new();
+        let mut actions = Vec::new();
+        loop {
+            game_state.legal_actions_into(&mut actions);
+            if is_terminal(&game_state, &actions) {
+                return ScoredSequence { score: sequence_score(&game_state.result()), actions: sequence };
+            }
+            let chosen = policy.choose(&game_state, &actions, &mut self.random_generator);
+            game_state.apply_action_mut(&actions[chosen]);
+            sequence.push(actions.swap_remove(chosen));
+        }
+    }
+}
+
+
+/// Unit tests for Nested Rollout Policy Adaptation.
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game_state_trait::GameResult;
+    use crate::tictactoe_env::TicTacToeState;
+    use alloc::string::String;
+    use alloc::vec;
+
+    /// Puzzle of choosing 6 digits, scoring the length of their common prefix with 3 1 4 1 5 9.
+    #[derive(Debug, Clone)]
+    struct DigitPuzzle {
+        digits: Vec<u8>,
+    }
+
+    const TARGET: [u8; 6] = [3, 1, 4, 1, 5, 9];
+
+    impl GameState<u8> for DigitPuzzle {
+        type ParseError = ();
+
+        fn from_str(_game_state: String) -> Self {
+            return DigitPuzzle { digits: Vec::new() };
+        }
+
+        fn apply_action(&self, action: &u8) -> Self {
+            let mut digits = self.digits.clone();
+            digits.push(*action);
+            return DigitPuzzle { digits };
+        }
+
+        fn status_with_moves_left(&self) -> bool {
+            return true;
+        }
+
+        fn result(&self) -> GameResult {
+            let prefix = self.digits.iter().zip(TARGET).take_while(|(digit, target)| **digit == *target).count();
+            return GameResult::Score(prefix as f32);
+        }
+
+        fn generate_legal_actions(&self) -> Vec<u8> {
+            return if self.digits.len() < TARGET.len() { (0..10).collect() } else { Vec::new() };
+        }
+
+        fn action_code(&self, action: &u8) -> Option<u64> {
+            return Some(self.digits.len() as u64 * 10 + *action as u64);
+        }
+
+        fn side_to_move(&self) -> bool {
+            return true;
+        }
+    }
+
+    /// Test if the softmax policy prefers actions by the weights of their codes.
+    #[test]
+    fn test_softmax_policy() {
+        let mut policy = SoftmaxPolicy::default();
+        policy.weights.insert(7, 20.0);
+        let state = DigitPuzzle::from_str(String::new());
+        let actions: Vec<u8> = (0..10).collect();
+        let mut random_generator = NestedPolicyAdaptation::new(1, Some(1)).random_generator;
+        for _ in 0..100 {
+            assert_eq!(policy.choose(&state, &actions, &mut random_generator), 7);
+        }
+    }
+
+    /// Test if adaptation raises the weights of the sequence and keeps the total weight of every state.
+    #[test]
+    fn test_adapt() {
+        let search = NestedPolicyAdaptation::new(1, Some(1));
+        let mut policy = SoftmaxPolicy::default();
+        search.adapt(&DigitPuzzle::from_str(String::new()), &mut policy, &[3, 1]);
+        assert!((policy.weight(3) - 0.9).abs() < 1e-9);
+        assert!((policy.weight(11) - 0.9).abs() < 1e-9);
+        assert!((policy.weight(0) + 0.1).abs() < 1e-9);
+        assert!(policy.weights.values().sum::<f64>().abs() < 1e-9);
+    }
+
+    /// Test if a search of level 2 learns the puzzle, keeping the learned policy.
+    #[test]
+    fn test_search() {
+        let mut search = NestedPolicyAdaptation::new(2, Some(1));
+        search.iterations = 30;
+        let sequence = search.search(&DigitPuzzle::from_str(String::new()));
+        assert_eq!(sequence, ScoredSequence { score: 6.0, actions: vec![3, 1, 4, 1, 5, 9] });
+        assert_eq!(search.rollouts, 900);
+        assert!(search.policy.weight(59) > search.policy.weight(58));
+    }
+
+    /// Test if bundled games code their actions for either side.
+    #[test]
+    fn test_action_codes() {
+        let state = TicTacToeState::new();
+        assert_ne!(state.action_code(&4), state.apply_action(&0).action_code(&4));
+    }
+}
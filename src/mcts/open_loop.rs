@@ -0,0 +1,217 @@
+//! Open-loop search, where nodes stand for sequences of actions rather than game states.
+//!
+//! Every iteration replays the actions of its path from a copy of the root state, sampling
+//! chance events anew each time, so the statistics of a node average over every state the
+//! action sequence can lead to. Chance events never become nodes, which keeps trees of
+//! stochastic games small where chance nodes would branch on every outcome, at the cost of
+//! mixing the values of the different states behind a node.
+//!
+//! Children are only considered by selection when their action is legal in the state
+//! sampled by the iteration, and actions without a child are expanded first.
+
+use crate::arena::{MCTSNode, NodeIndex};
+use crate::clock::Instant;
+use crate::game_state_trait::GameState;
+use crate::hooks::{SearchControl, SearchHooks};
+use crate::mcts::{MCTSTree, SelectionPolicy, StateStorage, Value};
+use crate::rng::Rng;
+use alloc::vec::Vec;
+
+
+/// Open-loop search driver.
+impl<Action, GameStateObj, Data> MCTSTree<Action, GameStateObj, Data>
+where
+    GameStateObj: GameState<Action> + Clone,
+    Action: PartialEq
+{
+    /// Runs up to `iterations` open-loop iterations from the root.
+    ///
+    /// See `search_open_loop_with_hooks` for a search invoking callbacks.
+    pub fn search_open_loop(&mut self, iterations: u64) {
+        self.search_open_loop_with_hooks(iterations, &mut ());
+    }
+
+    /// Runs up to `iterations` open-loop iterations from the root, invoking `hooks` like
+    /// `search_with_hooks`.
+    ///
+    /// Every iteration descends from a copy of the root state, applying the action of every
+    /// selected child and sampling chance events by their probabilities. Once it reaches a node
+    /// with a legal action that has no child, one such action is chosen at random and expanded
+    /// into a new node, which is then simulated from the sampled state. Nodes store the state of the iteration which
+    /// expanded them under `StateStorage::Full`, one sample of the states they stand for.
+    ///
+    /// Budgets apply as usual, while early termination, the expansion threshold and priors do not.
+    /// A tree should be searched either open-loop or with `search`, as the two expand nodes differently.
+    ///
+    /// # Returns
+    /// The number of iterations searched, fewer than `iterations` if a hook stopped the search
+    /// or the `budget` of the tree was exhausted.
+    ///
+    /// # Panics
+    /// If the root has children expanded by `search` and actions left to expand.
+    pub fn search_open_loop_with_hooks<H>(&mut self, iterations: u64, hooks: &mut H) -> u64
+    where
+        H: SearchHooks<Action, GameStateObj, Data> + ?Sized
+    {
+        let start = Instant::now();
+
+        // Children of open-loop nodes are only known once they are expanded.
+        if self.arena.expanded_count(0) == 0 {
+            self.arena.topology[0].children = self.arena.allocate_lazy_children();
+        }
+
+        let root_game_state = self.game_state_at(0).into_owned();
+        let mut actions = Vec::with_capacity(self.average_child_count);
+        let mut best = self.most_visited_child(0);
+        let mut searched = 0;
+        while searched < iterations {
+            if self.budget_exhausted() {
+                break;
+            }
+
+            let (node, game_state, expanded) = self.descend_open_loop(root_game_state.clone(), &mut actions);
+            if expanded {
+                hooks.on_expansion(self, self.arena.handle(node));
+            }
+            let result = self.simulate_from(game_state);
+            self.backpropagate_at(node, result);
+            searched += 1;
+
+            let new_best = self.most_visited_child(0);
+            if new_best != best {
+                best = new_best;
+                if let Some(best) = best {
+                    hooks.on_new_best_move(self, self.arena.handle(best));
+                }
+            }
+            if hooks.on_iteration(self, searched) == SearchControl::Stop {
+                break;
+            }
+        }
+        self.counters.search_time += start.elapsed();
+        return searched;
+    }
+
+    /// Selects and expands the node of an open-loop iteration, replaying its actions on `game_state`.
+    ///
+    /// # Arguments
+    /// * `actions` : Buffer the legal actions of every visited state are generated into.
+    ///
+    /// # Returns
+    /// The node to simulate, the sampled state it was reached in, and whether the node was just expanded.
+    fn descend_open_loop(&mut self, mut game_state: GameStateObj, actions: &mut Vec<Action>) -> (NodeIndex, GameStateObj, bool) {
+        let exploration_factor = Some(self.exploration_factor());
+        let mut node: NodeIndex = 0;
+        loop {
+            self.sample_chance_events(&mut game_state);
+            game_state.legal_actions_into(actions);
+            if actions.is_empty() || !game_state.status_with_moves_left() {
+                return (node, game_state, false);
+            }
+
+            // Legal actions of the sampled state are paired with the children expanded for them.
+            let children: Vec<Option<NodeIndex>> = actions.iter()
+                .map(|action| self.arena.expanded(node).find(|child| self.arena.actions[*child as usize].as_ref() == Some(action)))
+                .collect();
+
+            let untried: Vec<usize> = (0..actions.len()).filter(|i| children[*i].is_none()).collect();
+            if !untried.is_empty() {
+                let index = untried[self.searcher.random_generator.gen_range(0, untried.len())];
+                let action = actions.swap_remove(index);
+                game_state.apply_action_mut(&action);
+                self.sample_chance_events(&mut game_state);
+                let child = self.push_open_loop_node(game_state.clone(), action, node);
+                return (child, game_state, true);
+            }
+
+            // Ties are broken like in `select_child`.
+            let mut best_value = Value::MIN;
+            let mut best_index = 0;
+            let mut ties: u32 = 0;
+            for (i, child) in children.iter().enumerate() {
+                let child = child.expect("untried action");
+                let value = match self.searcher.selection_policy {
+                    SelectionPolicy::Uct => self.uct_at(child, exploration_factor),
+                    SelectionPolicy::Puct => self.puct_at(child, exploration_factor),
+                };
+                if value > best_value {
+                    best_value = value;
+                    best_index = i;
+                    ties = 1;
+                }
+                else if value == best_value && self.searcher.random_tie_breaking {
+                    ties += 1;
+                    if self.searcher.random_generator.gen_range(0, ties) == 0 {
+                        best_index = i;
+                    }
+                }
+            }
+            game_state.apply_action_mut(&actions[best_index]);
+            node = children[best_index].expect("untried action");
+        }
+    }
+
+    /// Applies sampled outcomes to `game_state` until it is no longer a chance state.
+    fn sample_chance_events(&mut self, game_state: &mut GameStateObj) {
+        while game_state.is_chance_node() {
+            let outcomes = game_state.chance_outcomes();
+            let outcome = self.sample_chance_outcome(&outcomes);
+            game_state.apply_action_mut(&outcomes[outcome].0);
+        }
+    }
+
+    /// Pushes an open-loop child of `parent` reached by `action`, with the sampled `game_state`.
+    ///
+    /// # Returns
+    /// The index of the new node.
+    fn push_open_loop_node(&mut self, game_state: GameStateObj, action: Action, parent: NodeIndex) -> NodeIndex {
+        let store_state = self.state_storage == StateStorage::Full;
+        let node = self.arena.push(MCTSNode {
+            game_state,
+            action: Some(action),
+            parent: Some(parent),
+            expanded: Vec::new(),
+            unexpanded: Vec::new(),
+            wins: 0, draws: 0, sims: 0, score: 0.0,
+            data: None
+        }, store_state);
+        self.arena.topology[node as usize].children = self.arena.allocate_lazy_children();
+        self.arena.push_expanded_child(parent, node);
+        return node;
+    }
+}
+
+
+/// Unit tests for open-loop search.
+#[cfg(test)]
+mod tests {
+    use crate::game2048_env::{Game2048Move, Game2048State};
+    use crate::mcts::{MCTSTree, PlayerMode};
+    use crate::tictactoe_env::TicTacToeState;
+
+    /// Test if open-loop trees of stochastic games hold no chance nodes, only the slides of the player.
+    #[test]
+    fn test_open_loop_chance() {
+        let mut tree = MCTSTree::<Game2048Move, Game2048State>::with_capacity(1000, Some(1), ". . . .  . 2 . .  . . . .  . . 2 . score 0".into(), 4);
+        tree.searcher.player_mode = PlayerMode::SinglePlayer;
+        tree.searcher.rollout_depth_limit = Some(10);
+        tree.search_open_loop(500);
+
+        assert_eq!(tree.arena.stats[0].sims, 500);
+        assert_eq!(tree.arena.len(), 501);
+        assert_eq!(tree.arena.expanded_count(0), 4);
+        for node in 1..tree.arena.len() {
+            assert!(!tree.arena.topology[node].is_chance);
+            assert!(matches!(tree.arena.actions[node], Some(Game2048Move::Slide(_))));
+        }
+    }
+
+    /// Test if open-loop search finds the winning move of a deterministic game.
+    #[test]
+    fn test_open_loop_win() {
+        let mut tree = MCTSTree::<u8, TicTacToeState>::with_capacity(3000, Some(1), "XX.OO....".into(), 9);
+        tree.search_open_loop(2000);
+        let best = tree.most_visited_child(0).unwrap();
+        assert_eq!(tree.arena.actions[best as usize], Some(2));
+    }
+}
@@ -0,0 +1,453 @@
+//! Export of self-play training samples as Parquet files, which pandas, polars and PyTorch
+//! data loaders read directly.
+//!
+//! Every sample is a row with the columns:
+//! * `state` : String encoding of the root state, as parsed by `GameState::from_str`.
+//! * `side_to_move` : True if the first player was to move.
+//! * `input` : The state encoded by `TensorEncoding::encode`, as a flat list of floats.
+//! * `policy` : The policy target, a list of `POLICY_SIZE` probabilities indexed by `TensorEncoding::action_index`.
+//! * `value` : The value target, the outcome for the side to move between -1 and 1, on the scale of `ModelOutput::values`.
+//!
+//! The shape of `input` and the size of `policy` are stored in the metadata of the file as
+//! `input_shape` and `policy_size`. Files hold a single uncompressed row group with plain
+//! encoded values, written without any Parquet library.
+
+use crate::game_state_trait::GameState;
+use crate::neural::TensorEncoding;
+use crate::selfplay::SelfPlayRecorder;
+use std::io;
+
+
+/// Magic bytes starting and ending a Parquet file.
+const MAGIC: &[u8] = b"PAR1";
+
+
+/// Type identifiers of the Thrift compact protocol.
+const THRIFT_I32: u8 = 5;
+const THRIFT_I64: u8 = 6;
+const THRIFT_BINARY: u8 = 8;
+const THRIFT_LIST: u8 = 9;
+const THRIFT_STRUCT: u8 = 12;
+
+
+/// Physical types, encodings and repetitions of the Parquet format.
+const TYPE_BOOLEAN: i32 = 0;
+const TYPE_FLOAT: i32 = 4;
+const TYPE_BYTE_ARRAY: i32 = 6;
+const ENCODING_PLAIN: i32 = 0;
+const ENCODING_RLE: i32 = 3;
+const REQUIRED: i32 = 0;
+const REPEATED: i32 = 2;
+const CONVERTED_UTF8: i32 = 0;
+const CONVERTED_LIST: i32 = 3;
+
+
+/// Writer of Thrift compact protocol structures, the encoding of Parquet metadata.
+#[derive(Default)]
+struct CompactWriter {
+    out: Vec<u8>,
+
+    /// Identifier of the last field written in the current struct.
+    last_field: i16,
+
+    /// Last field identifiers of the enclosing structs.
+    enclosing: Vec<i16>,
+}
+
+impl CompactWriter {
+    fn varint(&mut self, mut value: u64) {
+        while value >= 0x80 {
+            self.out.push((value as u8 & 0x7f) | 0x80);
+            value >>= 7;
+        }
+        self.out.push(value as u8);
+    }
+
+    fn field(&mut self, id: i16, kind: u8) {
+        let delta = id - self.last_field;
+        if delta > 0 && delta <= 15 {
+            self.out.push(((delta as u8) << 4) | kind);
+        } else {
+            self.out.push(kind);
+            self.varint(((id << 1) ^ (id >> 15)) as u16 as u64);
+        }
+        self.last_field = id;
+    }
+
+    fn i32_field(&mut self, id: i16, value: i32) {
+        self.field(id, THRIFT_I32);
+        self.varint(((value << 1) ^ (value >> 31)) as u32 as u64);
+    }
+
+    fn i64_field(&mut self, id: i16, value: i64) {
+        self.field(id, THRIFT_I64);
+        self.varint(((value << 1) ^ (value >> 63)) as u64);
+    }
+
+    fn binary(&mut self, bytes: &[u8]) {
+        self.varint(bytes.len() as u64);
+        self.out.extend_from_slice(bytes);
+    }
+
+    fn binary_field(&mut self, id: i16, bytes: &[u8]) {
+        self.field(id, THRIFT_BINARY);
+        self.binary(bytes);
+    }
+
+    /// Starts a list field of `size` elements of type `kind`, which are written next.
+    fn list_field(&mut self, id: i16, kind: u8, size: usize) {
+        self.field(id, THRIFT_LIST);
+        if size < 15 {
+            self.out.push(((size as u8) << 4) | kind);
+        } else {
+            self.out.push(0xf0 | kind);
+            self.varint(size as u64);
+        }
+    }
+
+    fn i32_list_field(&mut self, id: i16, values: &[i32]) {
+        self.list_field(id, THRIFT_I32, values.len());
+        for value in values {
+            self.varint(((value << 1) ^ (value >> 31)) as u32 as u64);
+        }
+    }
+
+    /// Starts a struct, as a field when `id` is given or as an element of a list otherwise.
+    fn begin_struct(&mut self, id: Option<i16>) {
+        if let Some(id) = id {
+            self.field(id, THRIFT_STRUCT);
+        }
+        self.enclosing.push(self.last_field);
+        self.last_field = 0;
+    }
+
+    fn end_struct(&mut self) {
+        self.out.push(0);
+        self.last_field = self.enclosing.pop().unwrap_or(0);
+    }
+}
+
+
+/// Values of a column of the file.
+enum ColumnValues {
+    Strings(Vec<String>),
+    Booleans(Vec<bool>),
+    Floats(Vec<f32>),
+    FloatLists(Vec<Vec<f32>>),
+}
+
+
+/// A column of the file, with its name and values.
+struct Column {
+    name: &'static str,
+    values: ColumnValues,
+}
+
+impl Column {
+    fn physical_type(&self) -> i32 {
+        match self.values {
+            ColumnValues::Strings(_) => return TYPE_BYTE_ARRAY,
+            ColumnValues::Booleans(_) => return TYPE_BOOLEAN,
+            ColumnValues::Floats(_) | ColumnValues::FloatLists(_) => return TYPE_FLOAT,
+        }
+    }
+
+    /// Path of the leaf of the column in the schema. Lists nest their values in a repeated
+    /// `list` group holding an `element`.
+    fn path(&self) -> Vec<&'static str> {
+        match self.values {
+            ColumnValues::FloatLists(_) => return vec![self.name, "list", "element"],
+            _ => return vec![self.name],
+        }
+    }
+
+    /// Writes the schema elements of the column.
+    fn write_schema(&self, thrift: &mut CompactWriter) {
+        thrift.begin_struct(None);
+        match &self.values {
+            ColumnValues::FloatLists(_) => {
+                thrift.i32_field(3, REQUIRED);
+                thrift.binary_field(4, self.name.as_bytes());
+                thrift.i32_field(5, 1);
+                thrift.i32_field(6, CONVERTED_LIST);
+                thrift.end_struct();
+
+                thrift.begin_struct(None);
+                thrift.i32_field(3, REPEATED);
+                thrift.binary_field(4, b"list");
+                thrift.i32_field(5, 1);
+                thrift.end_struct();
+
+                thrift.begin_struct(None);
+                thrift.i32_field(1, TYPE_FLOAT);
+                thrift.i32_field(3, REQUIRED);
+                thrift.binary_field(4, b"element");
+            },
+            values => {
+                thrift.i32_field(1, self.physical_type());
+                thrift.i32_field(3, REQUIRED);
+                thrift.binary_field(4, self.name.as_bytes());
+                if let ColumnValues::Strings(_) = values {
+                    thrift.i32_field(6, CONVERTED_UTF8);
+                }
+            },
+        }
+        thrift.end_struct();
+    }
+
+    /// Number of schema elements written by `write_schema`.
+    fn schema_len(&self) -> usize {
+        return self.path().len();
+    }
+
+    /// Encodes the data page of the column.
+    ///
+    /// # Returns
+    /// The contents of the page and its number of values.
+    fn page(&self) -> (Vec<u8>, usize) {
+        let mut page = Vec::new();
+        match &self.values {
+            ColumnValues::Strings(strings) => {
+                for string in strings {
+                    page.extend_from_slice(&(string.len() as u32).to_le_bytes());
+                    page.extend_from_slice(string.as_bytes());
+                }
+                return (page, strings.len());
+            },
+            ColumnValues::Booleans(booleans) => {
+                page.resize(booleans.len().div_ceil(8), 0);
+                for (i, boolean) in booleans.iter().enumerate() {
+                    page[i / 8] |= (*boolean as u8) << (i % 8);
+                }
+                return (page, booleans.len());
+            },
+            ColumnValues::Floats(floats) => {
+                for float in floats {
+                    page.extend_from_slice(&float.to_le_bytes());
+                }
+                return (page, floats.len());
+            },
+            ColumnValues::FloatLists(lists) => {
+                // Every list starts a new row at repetition level 0 and continues it at level 1.
+                // Lists are never empty, so every value is defined at level 1.
+                let mut repetitions = Vec::new();
+                for list in lists {
+                    assert!(list.len() > 0, "empty list");
+                    push_rle_run(&mut repetitions, 1, 0);
+                    if list.len() > 1 {
+                        push_rle_run(&mut repetitions, list.len() - 1, 1);
+                    }
+                }
+                let count = lists.iter().map(|list| list.len()).sum();
+                let mut definitions = Vec::new();
+                push_rle_run(&mut definitions, count, 1);
+
+                for levels in [repetitions, definitions] {
+                    page.extend_from_slice(&(levels.len() as u32).to_le_bytes());
+                    page.extend_from_slice(&levels);
+                }
+                for float in lists.iter().flatten() {
+                    page.extend_from_slice(&float.to_le_bytes());
+                }
+                return (page, count);
+            },
+        }
+    }
+}
+
+
+/// Appends a run of `count` repeats of the level `value` to RLE encoded levels of bit width 1.
+fn push_rle_run(levels: &mut Vec<u8>, count: usize, value: u8) {
+    let mut header = (count as u64) << 1;
+    while header >= 0x80 {
+        levels.push((header as u8 & 0x7f) | 0x80);
+        header >>= 7;
+    }
+    levels.push(header as u8);
+    levels.push(value);
+}
+
+
+/// Writes `columns` of `rows` rows as a Parquet file, with `metadata` as key-value metadata.
+fn write_columns<W: io::Write>(writer: &mut W, columns: &[Column], rows: usize, metadata: &[(&str, String)]) -> io::Result<()> {
+    let mut file = MAGIC.to_vec();
+
+    // Column chunks of a single data page each, with their offsets and sizes.
+    let mut chunks = Vec::with_capacity(columns.len());
+    for column in columns {
+        let (page, values) = column.page();
+        let mut header = CompactWriter::default();
+        header.i32_field(1, 0);
+        header.i32_field(2, page.len() as i32);
+        header.i32_field(3, page.len() as i32);
+        header.begin_struct(Some(5));
+        header.i32_field(1, values as i32);
+        header.i32_field(2, ENCODING_PLAIN);
+        header.i32_field(3, ENCODING_RLE);
+        header.i32_field(4, ENCODING_RLE);
+        header.end_struct();
+        header.out.push(0);
+
+        let offset = file.len();
+        file.extend_from_slice(&header.out);
+        file.extend_from_slice(&page);
+        chunks.push((offset, file.len() - offset, values));
+    }
+
+    let mut footer = CompactWriter::default();
+    footer.i32_field(1, 1);
+    footer.list_field(2, THRIFT_STRUCT, 1 + columns.iter().map(Column::schema_len).sum::<usize>());
+    footer.begin_struct(None);
+    footer.binary_field(4, b"schema");
+    footer.i32_field(5, columns.len() as i32);
+    footer.end_struct();
+    for column in columns {
+        column.write_schema(&mut footer);
+    }
+    footer.i64_field(3, rows as i64);
+
+    footer.list_field(4, THRIFT_STRUCT, 1);
+    footer.begin_struct(None);
+    footer.list_field(1, THRIFT_STRUCT, columns.len());
+    for (column, (offset, size, values)) in columns.iter().zip(&chunks) {
+        footer.begin_struct(None);
+        footer.i64_field(2, *offset as i64);
+        footer.begin_struct(Some(3));
+        footer.i32_field(1, column.physical_type());
+        footer.i32_list_field(2, &[ENCODING_PLAIN, ENCODING_RLE]);
+        let path = column.path();
+        footer.list_field(3, THRIFT_BINARY, path.len());
+        for name in path {
+            footer.binary(name.as_bytes());
+        }
+        footer.i32_field(4, 0);
+        footer.i64_field(5, *values as i64);
+        footer.i64_field(6, *size as i64);
+        footer.i64_field(7, *size as i64);
+        footer.i64_field(9, *offset as i64);
+        footer.end_struct();
+        footer.end_struct();
+    }
+    footer.i64_field(2, chunks.iter().map(|(_, size, _)| *size as i64).sum());
+    footer.i64_field(3, rows as i64);
+    footer.end_struct();
+
+    footer.list_field(5, THRIFT_STRUCT, metadata.len());
+    for (key, value) in metadata {
+        footer.begin_struct(None);
+        footer.binary_field(1, key.as_bytes());
+        footer.binary_field(2, value.as_bytes());
+        footer.end_struct();
+    }
+    footer.binary_field(6, b"mcts");
+    footer.out.push(0);
+
+    file.extend_from_slice(&footer.out);
+    file.extend_from_slice(&(footer.out.len() as u32).to_le_bytes());
+    file.extend_from_slice(MAGIC);
+    return writer.write_all(&file);
+}
+
+
+/// Parquet export of self-play samples.
+impl<Action: Clone> SelfPlayRecorder<Action> {
+    /// Writes the samples of finished games as a Parquet file, with the columns described in
+    /// the `parquet` module.
+    ///
+    /// States are parsed back from their string encoding into `GameStateObj` to be encoded.
+    ///
+    /// # Returns
+    /// An error of kind `InvalidData` if the state of a sample can not be parsed, or the error of `writer`.
+    pub fn write_parquet<GameStateObj, W>(&self, writer: &mut W) -> io::Result<()>
+    where
+        GameStateObj: GameState<Action> + TensorEncoding<Action>,
+        W: io::Write
+    {
+        let mut inputs = Vec::with_capacity(self.samples.len());
+        let mut policies = Vec::with_capacity(self.samples.len());
+        for sample in &self.samples {
+            let game_state = GameStateObj::try_from_str(&sample.state)
+                .map_err(|_| io::Error::new(io::ErrorKind::InvalidData, format!("invalid state: {}", sample.state)))?;
+            let mut input = vec![0.0; GameStateObj::input_size()];
+            game_state.encode(&mut input);
+            inputs.push(input);
+
+            let mut policy = vec![0.0; GameStateObj::POLICY_SIZE];
+            for (action, probability) in &sample.policy {
+                policy[game_state.action_index(action)] = *probability;
+            }
+            policies.push(policy);
+        }
+
+        let columns = [
+            Column { name: "state", values: ColumnValues::Strings(self.samples.iter().map(|sample| sample.state.clone()).collect()) },
+            Column { name: "side_to_move", values: ColumnValues::Booleans(self.samples.iter().map(|sample| sample.side_to_move).collect()) },
+            Column { name: "input", values: ColumnValues::FloatLists(inputs) },
+            Column { name: "policy", values: ColumnValues::FloatLists(policies) },
+            Column {
+                name: "value",
+                values: ColumnValues::Floats(self.samples.iter().map(|sample| 2.0 * sample.outcome.expect("no outcome") - 1.0).collect()),
+            },
+        ];
+        let input_shape: Vec<String> = GameStateObj::INPUT_SHAPE.iter().map(|size| size.to_string()).collect();
+        let metadata = [("input_shape", input_shape.join(",")), ("policy_size", GameStateObj::POLICY_SIZE.to_string())];
+        return write_columns(writer, &columns, self.samples.len(), &metadata);
+    }
+}
+
+
+/// Defines unit tests for the Parquet export.
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::selfplay::TrainingSample;
+    use crate::tictactoe_env::TicTacToeState;
+
+    /// Test if the Thrift compact protocol encodes fields, integers and structs.
+    #[test]
+    fn test_compact_writer() {
+        let mut thrift = CompactWriter::default();
+        thrift.i32_field(1, -2);
+        thrift.begin_struct(Some(2));
+        thrift.i64_field(20, 300);
+        thrift.end_struct();
+        thrift.binary_field(3, b"ab");
+        assert_eq!(thrift.out, vec![0x15, 0x03, 0x1c, 0x06, 0x28, 0xd8, 0x04, 0x00, 0x18, 0x02, b'a', b'b']);
+
+        let mut levels = Vec::new();
+        push_rle_run(&mut levels, 100, 1);
+        assert_eq!(levels, vec![0xc8, 0x01, 0x01]);
+    }
+
+    /// Test if samples are written as a Parquet file with their encoded columns.
+    #[test]
+    fn test_write_parquet() {
+        let mut recorder = SelfPlayRecorder::<u8>::new();
+        recorder.samples.push(TrainingSample { state: "X........".to_string(), side_to_move: false, policy: vec![(4, 0.75), (8, 0.25)], outcome: Some(0.5) });
+        recorder.samples.push(TrainingSample { state: "X...O....".to_string(), side_to_move: true, policy: vec![(0, 1.0)], outcome: Some(1.0) });
+        let mut file = Vec::new();
+        recorder.write_parquet::<TicTacToeState, _>(&mut file).unwrap();
+
+        assert!(file.starts_with(MAGIC) && file.ends_with(MAGIC));
+        let footer_length = u32::from_le_bytes(file[file.len() - 8..file.len() - 4].try_into().unwrap()) as usize;
+        let footer = &file[file.len() - 8 - footer_length..file.len() - 8];
+        let contains = |haystack: &[u8], needle: &[u8]| haystack.windows(needle.len()).any(|window| window == needle);
+        for name in ["state", "side_to_move", "input", "policy", "value", "element", "input_shape", "2,3,3"] {
+            assert!(contains(footer, name.as_bytes()), "{}", name);
+        }
+
+        // Values are stored in plain little endian, the policy densely by cell.
+        let mut policy = Vec::new();
+        for probability in [0.0f32, 0.0, 0.0, 0.0, 0.75, 0.0, 0.0, 0.0, 0.25] {
+            policy.extend_from_slice(&probability.to_le_bytes());
+        }
+        assert!(contains(&file, &policy));
+        let values: Vec<u8> = [0.0f32, 1.0].iter().flat_map(|value| value.to_le_bytes()).collect();
+        assert!(contains(&file, &values));
+
+        let mut recorder = SelfPlayRecorder::<u8>::new();
+        recorder.samples.push(TrainingSample { state: "XX".to_string(), side_to_move: true, policy: Vec::new(), outcome: Some(0.0) });
+        let error = recorder.write_parquet::<TicTacToeState, _>(&mut Vec::new()).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+    }
+}
@@ -0,0 +1,464 @@
+use crate::chess_env::{make_move, ChessState};
+use crate::game_state_trait::{GameResult, GameState};
+use chess::{Board, BoardStatus, ChessMove, MoveGen, Piece};
+use std::fmt;
+
+
+/// Maximum length of a line of movetext when exporting.
+const LINE_WIDTH: usize = 80;
+
+
+/// Error produced when a PGN can not be loaded.
+#[derive(Debug, Clone, PartialEq)]
+pub enum PgnError {
+    /// A header could not be parsed, holding the offending line.
+    InvalidHeader(String),
+
+    /// The `FEN` header does not hold a valid position.
+    InvalidFen(String),
+
+    /// A move is not legal in the position it is played from, holding the move text.
+    InvalidMove(String),
+}
+
+impl fmt::Display for PgnError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PgnError::InvalidHeader(line) => return write!(f, "invalid pgn header `{}`", line),
+            PgnError::InvalidFen(fen) => return write!(f, "invalid fen `{}`", fen),
+            PgnError::InvalidMove(san) => return write!(f, "illegal or invalid move `{}`", san),
+        }
+    }
+}
+
+impl std::error::Error for PgnError {}
+
+
+/// A recorded chess game, consisting of PGN headers and the moves played.
+///
+/// Games can start from any position, which is recorded with the `SetUp` and `FEN` headers.
+#[derive(Debug, Clone)]
+pub struct PgnGame {
+    /// Header tags in the order they are written, such as `("White", "mcts")`.
+    pub headers: Vec<(String, String)>,
+
+    /// Moves played from the starting position.
+    pub moves: Vec<ChessMove>,
+}
+
+
+impl PgnGame {
+    /// Creates an empty game from the standard starting position,
+    /// with the seven tag roster set to unknown values.
+    pub fn new() -> Self {
+        let headers = ["Event", "Site", "Date", "Round", "White", "Black", "Result"]
+            .iter()
+            .map(|tag| (tag.to_string(), if *tag == "Result" { "*" } else { "?" }.to_string()))
+            .collect();
+        return PgnGame { headers, moves: Vec::new() };
+    }
+
+    /// Creates an empty game starting from the position encoded by `fen`.
+    /// Chess960 positions are marked with a `Variant` header.
+    ///
+    /// # Returns
+    /// The game, or an error if the fen is invalid.
+    pub fn from_position(fen: &str) -> Result<Self, PgnError> {
+        let game_state = ChessState::try_from_str(fen).map_err(|_| PgnError::InvalidFen(fen.to_string()))?;
+        let mut game = PgnGame::new();
+        game.set_header("SetUp", "1");
+        game.set_header("FEN", fen);
+        if game_state.chess960.is_some() {
+            game.set_header("Variant", "Chess960");
+        }
+        return Ok(game);
+    }
+
+    /// Returns the value of the header `tag`, if it is set.
+    pub fn header(&self, tag: &str) -> Option<&str> {
+        return self.headers.iter().find(|(key, _)| key == tag).map(|(_, value)| value.as_str());
+    }
+
+    /// Sets the header `tag` to `value`, replacing any previous value.
+    pub fn set_header(&mut self, tag: &str, value: &str) {
+        match self.headers.iter_mut().find(|(key, _)| key == tag) {
+            Some(header) => header.1 = value.to_string(),
+            None => self.headers.push((tag.to_string(), value.to_string())),
+        }
+    }
+
+    /// Game state the game starts from, given by the `FEN` header or the standard starting position.
+    pub fn starting_state(&self) -> ChessState {
+        let fen = self.header("FEN").unwrap_or(STARTING_FEN);
+        return ChessState::try_from_str(fen).expect("invalid fen header");
+    }
+
+    /// Game state reached after every recorded move, for continuing or analysing the game.
+    pub fn game_state(&self) -> ChessState {
+        let mut game_state = self.starting_state();
+        for action in &self.moves {
+            game_state.apply_action_mut(action);
+        }
+        return game_state;
+    }
+
+    /// Records `action` and updates the `Result` header if it ends the game.
+    ///
+    /// # Invariants
+    /// `action` is assumed to be legal in the current game state.
+    pub fn push(&mut self, action: ChessMove) {
+        self.moves.push(action);
+        let game_state = self.game_state();
+        let finished = game_state.generate_legal_actions().len() == 0 || !game_state.status_with_moves_left();
+        if finished {
+            self.set_header("Result", result_token(&game_state.result()));
+        }
+    }
+
+    /// Exports the game as PGN, with headers followed by numbered movetext in SAN.
+    pub fn to_pgn(&self) -> String {
+        let mut pgn = String::new();
+        for (tag, value) in &self.headers {
+            pgn += &format!("[{} \"{}\"]\n", tag, value.replace('\\', "\\\\").replace('"', "\\\""));
+        }
+        pgn += "\n";
+
+        // Move numbers follow the fullmove counter of the starting position.
+        let mut game_state = self.starting_state();
+        let fen = self.header("FEN").unwrap_or(STARTING_FEN).to_string();
+        let mut move_number: u32 = fen.split_whitespace().nth(5).and_then(|field| field.parse().ok()).unwrap_or(1);
+        let mut tokens: Vec<String> = Vec::new();
+        for (i, action) in self.moves.iter().enumerate() {
+            let white_to_move = game_state.side_to_move();
+            if white_to_move {
+                tokens.push(format!("{}.", move_number));
+            }
+            else if i == 0 {
+                tokens.push(format!("{}...", move_number));
+            }
+            tokens.push(move_to_san(&game_state.board, *action));
+            if !white_to_move {
+                move_number += 1;
+            }
+            game_state.apply_action_mut(action);
+        }
+        tokens.push(self.header("Result").unwrap_or("*").to_string());
+
+        // Wrap the movetext into lines of limited width.
+        let mut line = String::new();
+        for token in tokens {
+            if line.len() > 0 && line.len() + 1 + token.len() > LINE_WIDTH {
+                pgn += &line;
+                pgn += "\n";
+                line.clear();
+            }
+            if line.len() > 0 {
+                line += " ";
+            }
+            line += &token;
+        }
+        pgn += &line;
+        pgn += "\n";
+        return pgn;
+    }
+
+    /// Loads the first game of a PGN.
+    ///
+    /// Comments, variations, numeric annotation glyphs and move annotations are skipped.
+    ///
+    /// # Returns
+    /// The game, or the first error encountered while parsing.
+    pub fn from_pgn(pgn: &str) -> Result<Self, PgnError> {
+        let mut game = PgnGame { headers: Vec::new(), moves: Vec::new() };
+        let mut movetext = String::new();
+        for line in pgn.lines() {
+            let line = line.trim();
+            if line.starts_with('[') {
+                let (tag, value) = parse_header(line).ok_or_else(|| PgnError::InvalidHeader(line.to_string()))?;
+                game.set_header(&tag, &value);
+            }
+            else if !line.starts_with('%') {
+                movetext += line;
+                movetext += "\n";
+            }
+        }
+
+        if let Some(fen) = game.header("FEN") {
+            if ChessState::try_from_str(fen).is_err() {
+                return Err(PgnError::InvalidFen(fen.to_string()));
+            }
+        }
+
+        let mut game_state = game.starting_state();
+        for token in movetext_tokens(&movetext) {
+            let action = san_to_move(&game_state, &token).ok_or(PgnError::InvalidMove(token))?;
+            game_state.apply_action_mut(&action);
+            game.moves.push(action);
+        }
+        return Ok(game);
+    }
+}
+
+impl Default for PgnGame {
+    fn default() -> Self {
+        return PgnGame::new();
+    }
+}
+
+
+/// Fen of the standard starting position.
+const STARTING_FEN: &str = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+
+
+/// PGN result token of a finished game.
+pub fn result_token(result: &GameResult) -> &'static str {
+    match result.winner() {
+        Some(true) => return "1-0",
+        Some(false) => return "0-1",
+        None => return "1/2-1/2",
+    }
+}
+
+
+/// Parses a header line of the form `[Tag "Value"]`.
+fn parse_header(line: &str) -> Option<(String, String)> {
+    let inner = line.strip_prefix('[')?.strip_suffix(']')?.trim();
+    let (tag, value) = inner.split_once(char::is_whitespace)?;
+    let value = value.trim().strip_prefix('"')?.strip_suffix('"')?;
+    return Some((tag.to_string(), value.replace("\\\"", "\"").replace("\\\\", "\\")));
+}
+
+
+/// Splits movetext into SAN moves, dropping move numbers, results,
+/// comments, variations and annotations.
+fn movetext_tokens(movetext: &str) -> Vec<String> {
+    let mut tokens = Vec::new();
+    let mut current = String::new();
+    let mut variation_depth = 0;
+    let mut chars = movetext.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '{' => {
+                for c in chars.by_ref() {
+                    if c == '}' {
+                        break;
+                    }
+                }
+            },
+            ';' => {
+                for c in chars.by_ref() {
+                    if c == '\n' {
+                        break;
+                    }
+                }
+            },
+            '(' => variation_depth += 1,
+            ')' => variation_depth -= 1,
+            _ if variation_depth > 0 => {},
+            _ if c.is_whitespace() => tokens.push(std::mem::take(&mut current)),
+            _ => current.push(c),
+        }
+    }
+    tokens.push(current);
+
+    return tokens.into_iter()
+        .map(|token| token.trim_start_matches(|c: char| c.is_ascii_digit() || c == '.').to_string())
+        .filter(|token| token.len() > 0 && !token.starts_with('$'))
+        .filter(|token| !["1-0", "0-1", "1/2-1/2", "*"].contains(&token.as_str()))
+        .collect();
+}
+
+
+/// Letter of a piece in SAN.
+fn piece_letter(piece: Piece) -> &'static str {
+    match piece {
+        Piece::Pawn => return "",
+        Piece::Knight => return "N",
+        Piece::Bishop => return "B",
+        Piece::Rook => return "R",
+        Piece::Queen => return "Q",
+        Piece::King => return "K",
+    }
+}
+
+
+/// Encodes `action` in standard algebraic notation.
+///
+/// # Invariants
+/// `action` is assumed to be legal on `board`.
+pub fn move_to_san(board: &Board, action: ChessMove) -> String {
+    let source = action.get_source();
+    let dest = action.get_dest();
+    let piece = board.piece_on(source).expect("no piece on source square");
+
+    let mut san = String::new();
+    let file_distance = (source.get_file().to_index() as i32 - dest.get_file().to_index() as i32).abs();
+    let is_chess960_castle = board.color_on(dest) == board.color_on(source);
+    if piece == Piece::King && (file_distance == 2 || is_chess960_castle) {
+        // Chess960 castling moves are encoded as the king capturing its own rook.
+        san += if dest.get_file().to_index() > source.get_file().to_index() { "O-O" } else { "O-O-O" };
+    }
+    else {
+        let is_capture = board.piece_on(dest).is_some() || (piece == Piece::Pawn && file_distance == 1);
+        san += piece_letter(piece);
+
+        // Pieces are disambiguated by file, then rank, then both.
+        if piece != Piece::Pawn {
+            let others: Vec<ChessMove> = MoveGen::new_legal(board)
+                .filter(|other| other.get_dest() == dest && other.get_source() != source)
+                .filter(|other| board.piece_on(other.get_source()) == Some(piece))
+                .collect();
+            if others.len() > 0 {
+                let source_text = source.to_string();
+                let shares_file = others.iter().any(|other| other.get_source().get_file() == source.get_file());
+                let shares_rank = others.iter().any(|other| other.get_source().get_rank() == source.get_rank());
+                if !shares_file {
+                    san += &source_text[0..1];
+                }
+                else if !shares_rank {
+                    san += &source_text[1..2];
+                }
+                else {
+                    san += &source_text;
+                }
+            }
+        }
+        else if is_capture {
+            san += &source.to_string()[0..1];
+        }
+
+        if is_capture {
+            san += "x";
+        }
+        san += &dest.to_string();
+        if let Some(promotion) = action.get_promotion() {
+            san += "=";
+            san += piece_letter(promotion);
+        }
+    }
+
+    // Checks and checkmates are marked after the move.
+    let after = make_move(board, action);
+    if after.status() == BoardStatus::Checkmate {
+        san += "#";
+    }
+    else if after.checkers() != &chess::EMPTY {
+        san += "+";
+    }
+    return san;
+}
+
+
+/// Decodes a move in standard algebraic notation, ignoring check marks and annotations.
+///
+/// # Returns
+/// The matching legal move, or None if no legal move matches.
+pub fn san_to_move(game_state: &ChessState, san: &str) -> Option<ChessMove> {
+    let board = &game_state.board;
+    let stripped = |text: &str| text.trim_end_matches(['+', '#', '!', '?']).replace('0', "O");
+    let target = stripped(san);
+    let matched = game_state.generate_legal_actions().into_iter().find(|action| stripped(&move_to_san(board, *action)) == target);
+    if matched.is_some() {
+        return matched;
+    }
+
+    // Fall back to the more lenient parser, accepting over-disambiguated moves.
+    return ChessMove::from_san(board, &target).ok().filter(|action| board.legal(*action));
+}
+
+
+/// Unit tests for PGN import and export.
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chess::Square;
+    use std::str::FromStr;
+
+    /// Test if moves are encoded in SAN, including disambiguation and special moves.
+    #[test]
+    fn test_move_to_san() {
+        let board = Board::default();
+        assert_eq!(move_to_san(&board, ChessMove::new(Square::E2, Square::E4, None)), "e4");
+        assert_eq!(move_to_san(&board, ChessMove::new(Square::G1, Square::F3, None)), "Nf3");
+
+        let knights = Board::from_str("4k3/8/8/8/8/8/8/1N2KN2 w - - 0 1").unwrap();
+        assert_eq!(move_to_san(&knights, ChessMove::new(Square::B1, Square::D2, None)), "Nbd2");
+
+        let castling = Board::from_str("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1").unwrap();
+        assert_eq!(move_to_san(&castling, ChessMove::new(Square::E1, Square::G1, None)), "O-O");
+        assert_eq!(move_to_san(&castling, ChessMove::new(Square::E1, Square::C1, None)), "O-O-O");
+
+        let promotion = Board::from_str("8/4P3/8/8/8/8/k7/4K3 w - - 0 1").unwrap();
+        assert_eq!(move_to_san(&promotion, ChessMove::new(Square::E7, Square::E8, Some(Piece::Queen))), "e8=Q");
+
+        let mate = Board::from_str("rnbqkbnr/pppp1ppp/8/4p3/6P1/5P2/PPPPP2P/RNBQKBNR b KQkq - 0 2").unwrap();
+        assert_eq!(move_to_san(&mate, ChessMove::new(Square::D8, Square::H4, None)), "Qh4#");
+    }
+
+    /// Test if a recorded game exports with headers, numbering and result.
+    /// Test if Chess960 castling moves, encoded as the king capturing its own rook, are written and read as castles.
+    #[test]
+    fn test_chess960_castling_san() {
+        let game_state = ChessState::from_str("1r2k1r1/1p4p1/8/8/8/8/1P4P1/1R2K1R1 w GBgb - 0 1".to_string());
+        let kingside = ChessMove::new(Square::E1, Square::G1, None);
+        let queenside = ChessMove::new(Square::E1, Square::B1, None);
+        assert_eq!(move_to_san(&game_state.board, kingside), "O-O");
+        assert_eq!(move_to_san(&game_state.board, queenside), "O-O-O");
+        assert_eq!(san_to_move(&game_state, "O-O"), Some(kingside));
+        assert_eq!(san_to_move(&game_state, "O-O-O"), Some(queenside));
+
+        let mut game = PgnGame::from_position(&game_state.to_fen()).unwrap();
+        assert_eq!(game.header("Variant"), Some("Chess960"));
+        game.push(queenside);
+        let reloaded = PgnGame::from_pgn(&game.to_pgn()).unwrap();
+        assert_eq!(reloaded.moves, vec![queenside]);
+    }
+
+    #[test]
+    fn test_to_pgn() {
+        let mut game = PgnGame::new();
+        game.set_header("White", "user");
+        for san in ["f3", "e5", "g4", "Qh4#"] {
+            let action = san_to_move(&game.game_state(), san).unwrap();
+            game.push(action);
+        }
+        let pgn = game.to_pgn();
+        assert!(pgn.contains("[White \"user\"]"));
+        assert!(pgn.contains("[Result \"0-1\"]"));
+        assert!(pgn.ends_with("1. f3 e5 2. g4 Qh4# 0-1\n"));
+    }
+
+    /// Test if games starting with black to move are numbered from the fen.
+    #[test]
+    fn test_to_pgn_from_position() {
+        let mut game = PgnGame::from_position("4k3/8/8/8/8/8/4P3/4K3 b - - 0 12").unwrap();
+        game.push(ChessMove::new(Square::E8, Square::D7, None));
+        game.push(ChessMove::new(Square::E2, Square::E4, None));
+        assert!(game.to_pgn().ends_with("12... Kd7 13. e4 *\n"));
+        assert!(PgnGame::from_position("not a fen").is_err());
+    }
+
+    /// Test if a PGN round trips through export and import.
+    #[test]
+    fn test_from_pgn() {
+        let pgn = "[Event \"Test\"]\n[White \"a\"]\n\n\
+            1. e4 e5 {opening} 2. Nf3 (2. f4 exf4) Nc6 $1 3. Bb5 a6?! ; Ruy Lopez\n\
+            4. Ba4 Nf6 5. O-O *\n";
+        let game = PgnGame::from_pgn(pgn).unwrap();
+        assert_eq!(game.header("Event"), Some("Test"));
+        assert_eq!(game.moves.len(), 9);
+        assert_eq!(game.moves[8], ChessMove::new(Square::E1, Square::G1, None));
+
+        let reloaded = PgnGame::from_pgn(&game.to_pgn()).unwrap();
+        assert_eq!(reloaded.moves, game.moves);
+        assert_eq!(reloaded.game_state().board, game.game_state().board);
+    }
+
+    /// Test if invalid PGNs are reported.
+    #[test]
+    fn test_from_pgn_errors() {
+        assert_eq!(PgnGame::from_pgn("1. e4 e4").unwrap_err(), PgnError::InvalidMove("e4".to_string()));
+        assert!(matches!(PgnGame::from_pgn("[Event]\n1. e4"), Err(PgnError::InvalidHeader(_))));
+        assert!(matches!(PgnGame::from_pgn("[FEN \"bad\"]\n1. e4"), Err(PgnError::InvalidFen(_))));
+    }
+}
@@ -0,0 +1,183 @@
+//! Pondering, searching during the opponent's turn from the reply it is expected to play.
+//!
+//! Once the engine has played its move and advanced the root of the tree to it, the most
+//! visited reply of the opponent is pondered: iterations are searched in its subtree only.
+//! When the opponent moves, a ponder hit keeps the pondered subtree as the new root, and a
+//! miss re-roots the tree at the reply actually played, keeping its subtree if it was expanded.
+
+use crate::arena::{NodeId, NodeIndex};
+use crate::game_state_trait::GameState;
+use crate::hooks::SearchHooks;
+use crate::mcts::MCTSTree;
+
+
+/// State of a `Ponderer`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum PonderState<Action> {
+    /// Not pondering, the tree is searched from its root.
+    Idle,
+
+    /// Pondering the subtree of `node`, reached from the root by the expected reply.
+    Pondering { node: NodeId, expected: Action },
+}
+
+
+/// Whether the opponent played the pondered reply.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PonderOutcome {
+    /// The pondered reply was played, and the pondered subtree became the root.
+    Hit,
+
+    /// Another reply was played, and the tree was re-rooted at it.
+    Miss,
+}
+
+
+/// State machine of pondering.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Ponderer<Action> {
+    /// Current state, idle until `start` is called.
+    pub state: PonderState<Action>,
+}
+
+
+impl<Action> Ponderer<Action>
+where
+    Action: Clone + PartialEq
+{
+    /// Creates an idle ponderer.
+    pub fn new() -> Self {
+        return Ponderer { state: PonderState::Idle };
+    }
+
+    /// Starts pondering the most visited reply at the root of `tree`, the position after the engine's move.
+    ///
+    /// # Returns
+    /// The pondered reply, None if the root has no visited children, in which case the ponderer stays idle.
+    pub fn start<GameStateObj, Data>(&mut self, tree: &MCTSTree<Action, GameStateObj, Data>) -> Option<Action>
+    where
+        GameStateObj: GameState<Action> + Clone
+    {
+        self.state = match tree.most_visited_child(0) {
+            Some(node) => PonderState::Pondering { node: tree.node_id(node), expected: tree.arena.actions[node as usize].clone().expect("no action") },
+            None => PonderState::Idle,
+        };
+        return match &self.state {
+            PonderState::Pondering { expected, .. } => Some(expected.clone()),
+            PonderState::Idle => None,
+        };
+    }
+
+    /// Searches up to `iterations` iterations in the pondered subtree, invoking `hooks`, which can stop the search.
+    ///
+    /// # Returns
+    /// The number of iterations searched, 0 if the ponderer is idle.
+    pub fn search<GameStateObj, Data, H>(&mut self, tree: &mut MCTSTree<Action, GameStateObj, Data>, iterations: u64, hooks: &mut H) -> u64
+    where
+        GameStateObj: GameState<Action> + Clone,
+        H: SearchHooks<Action, GameStateObj, Data> + ?Sized
+    {
+        return match self.state {
+            PonderState::Pondering { node, .. } => tree.search_from_with_hooks(node, iterations, hooks),
+            PonderState::Idle => 0,
+        };
+    }
+
+    /// Re-roots `tree` at the reply `actual` the opponent played, and returns to idle.
+    ///
+    /// The subtree of the reply is kept if it was expanded, pondered or not, so the search
+    /// continues from its statistics. Otherwise the tree is left with only the new root.
+    ///
+    /// # Invariants
+    /// `actual` is a legal action at the root of `tree`.
+    pub fn opponent_moved<GameStateObj, Data>(&mut self, tree: &mut MCTSTree<Action, GameStateObj, Data>, actual: &Action) -> PonderOutcome
+    where
+        GameStateObj: GameState<Action> + Clone
+    {
+        let outcome = match &self.state {
+            PonderState::Pondering { expected, .. } if expected == actual => PonderOutcome::Hit,
+            _ => PonderOutcome::Miss,
+        };
+        self.state = PonderState::Idle;
+
+        let reply = tree.arena.expanded(0).find(|child| tree.arena.actions[*child as usize].as_ref() == Some(actual));
+        let reply = match reply {
+            Some(reply) => reply,
+            None => {
+                let game_state = tree.game_state_at(0).apply_action(actual);
+                let child = tree.arena.len() as NodeIndex;
+                tree.attach_child(0, actual, child);
+                tree.push_node(game_state, Some(actual.clone()), Some(0), true)
+            },
+        };
+        tree.advance_root_at(reply);
+        return outcome;
+    }
+}
+
+impl<Action> Default for Ponderer<Action>
+where
+    Action: Clone + PartialEq
+{
+    fn default() -> Self {
+        return Ponderer::new();
+    }
+}
+
+
+/// Defines unit tests for pondering.
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game_state_trait::GameResult;
+    use crate::tictactoe_env::TicTacToeState;
+
+    /// Searches `tree`, plays its most visited move and starts pondering the expected reply.
+    fn play_and_ponder(tree: &mut MCTSTree<u8, TicTacToeState>, ponderer: &mut Ponderer<u8>) -> u8 {
+        tree.search(2000);
+        let best = tree.most_visited_child(0).unwrap();
+        tree.advance_root_at(best);
+        let expected = ponderer.start(tree).unwrap();
+        assert_eq!(ponderer.search(tree, 500, &mut ()), 500);
+        return expected;
+    }
+
+    /// Test if a ponder hit continues the pondered subtree.
+    #[test]
+    fn test_ponder_hit() {
+        let mut tree = MCTSTree::<u8, TicTacToeState>::with_capacity(5000, Some(1), ".........".to_string(), 9);
+        let mut ponderer = Ponderer::new();
+        let expected = play_and_ponder(&mut tree, &mut ponderer);
+        let PonderState::Pondering { node, .. } = ponderer.state else { panic!("not pondering") };
+        let pondered_sims = tree.node_stats(node).unwrap().sims;
+        assert!(pondered_sims >= 500);
+
+        assert_eq!(ponderer.opponent_moved(&mut tree, &expected), PonderOutcome::Hit);
+        assert_eq!(ponderer.state, PonderState::Idle);
+        assert_eq!(tree.arena.stats[0].sims, pondered_sims);
+        assert_eq!(tree.arena.topology[0].parent, None);
+    }
+
+    /// Test if a ponder miss re-roots the tree at the reply played, expanded or not.
+    #[test]
+    fn test_ponder_miss() {
+        let mut tree = MCTSTree::<u8, TicTacToeState>::with_capacity(5000, Some(1), ".........".to_string(), 9);
+        let mut ponderer = Ponderer::new();
+        let expected = play_and_ponder(&mut tree, &mut ponderer);
+        let root_state = tree.game_state_at(0).into_owned();
+        let actual = tree.root_policy().into_iter().map(|(action, _, _)| action).find(|action| *action != expected).unwrap();
+        assert_eq!(ponderer.opponent_moved(&mut tree, &actual), PonderOutcome::Miss);
+        assert_eq!(tree.game_state_at(0).to_string(), root_state.apply_action(&actual).to_string());
+
+        // Replies which were never expanded leave only the new root.
+        let mut tree = MCTSTree::<u8, TicTacToeState>::with_capacity(10, Some(1), "X...O....".to_string(), 9);
+        let child = tree.expand_at(0);
+        tree.backpropagate_at(child, GameResult::Draw);
+        ponderer.start(&tree);
+        let unexpanded = *tree.arena.unexpanded(0).next().unwrap();
+        assert_eq!(ponderer.opponent_moved(&mut tree, &unexpanded), PonderOutcome::Miss);
+        assert_eq!(tree.arena.len(), 1);
+        tree.search(100);
+        assert_eq!(tree.arena.stats[0].sims, 100);
+    }
+}
@@ -0,0 +1,182 @@
+//! Progress reports of running searches, sent over a channel so that user interfaces can
+//! display live progress from another thread without polling the tree.
+
+use crate::arena::NodeId;
+use crate::game_state_trait::GameState;
+use crate::hooks::{SearchControl, SearchHooks};
+use crate::mcts::MCTSTree;
+use std::sync::mpsc::Sender;
+use crate::clock::Instant;
+use std::time::Duration;
+
+
+/// Maximum number of actions in the principal variation of a `SearchProgress`.
+pub const PV_LENGTH: usize = 16;
+
+
+/// A snapshot of the progress of a search.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SearchProgress<Action> {
+    /// Action of the most visited child of the root, None before any child was visited.
+    pub best: Option<Action>,
+
+    /// Simulations of the most visited child of the root.
+    pub best_visits: u64,
+
+    /// Principal variation, following the most visited children from the root for up to
+    /// `PV_LENGTH` actions, and starting with `best`.
+    pub pv: Vec<Action>,
+
+    /// Simulations of the root.
+    pub visits: u64,
+
+    /// Number of iterations searched so far.
+    pub iterations: u64,
+
+    /// Depth of the deepest node expanded so far, the root being at depth 0.
+    pub depth: u32,
+
+    /// Time since the search started.
+    pub elapsed: Duration,
+}
+
+
+/// Hooks sending a `SearchProgress` down a channel at a fixed interval of time.
+///
+/// Messages which cannot be sent, as the receiver was dropped, are discarded without
+/// stopping the search.
+pub struct ProgressReporter<Action> {
+    /// Channel the progress is sent to.
+    pub sender: Sender<SearchProgress<Action>>,
+
+    /// Minimum time between two progress messages.
+    pub interval: Duration,
+
+    /// Start of the search.
+    start: Instant,
+
+    /// Time the last message was sent, or the start of the search.
+    last_report: Instant,
+
+    /// Depth of the deepest node expanded so far.
+    depth: u32,
+}
+
+
+impl<Action: Clone> ProgressReporter<Action> {
+    /// Creates a reporter sending to `sender` every `interval`, starting the clock of the search.
+    pub fn new(sender: Sender<SearchProgress<Action>>, interval: Duration) -> Self {
+        let start = Instant::now();
+        return ProgressReporter { sender, interval, start, last_report: start, depth: 0 };
+    }
+
+    /// Sends the progress of `tree` now, regardless of the interval.
+    pub fn report<GameStateObj, Data>(&mut self, tree: &MCTSTree<Action, GameStateObj, Data>, iterations: u64)
+    where
+        GameStateObj: GameState<Action> + Clone
+    {
+        let best = tree.arena.expanded(0).max_by_key(|child| tree.arena.stats[*child as usize].sims);
+        let best_visits = best.map_or(0, |best| tree.arena.stats[best as usize].sims);
+        let pv = tree.most_visited_line(0, PV_LENGTH + 1).into_iter()
+            .skip(1)
+            .map(|node| tree.arena.actions[node as usize].clone().expect("no action"))
+            .collect();
+        let progress = SearchProgress {
+            best: best.filter(|_| best_visits > 0).map(|best| tree.arena.actions[best as usize].clone().expect("no action")),
+            best_visits,
+            pv,
+            visits: tree.arena.stats[0].sims,
+            iterations,
+            depth: self.depth,
+            elapsed: self.start.elapsed(),
+        };
+        self.last_report = Instant::now();
+        let _ = self.sender.send(progress);
+    }
+}
+
+impl<Action, GameStateObj, Data> SearchHooks<Action, GameStateObj, Data> for ProgressReporter<Action>
+where
+    Action: Clone,
+    GameStateObj: GameState<Action> + Clone
+{
+    fn on_iteration(&mut self, tree: &MCTSTree<Action, GameStateObj, Data>, iteration: u64) -> SearchControl {
+        if self.last_report.elapsed() >= self.interval {
+            self.report(tree, iteration);
+        }
+        return SearchControl::Continue;
+    }
+
+    fn on_expansion(&mut self, tree: &MCTSTree<Action, GameStateObj, Data>, node: NodeId) {
+        self.depth = self.depth.max(tree.trace_path(node).len() as u32);
+    }
+
+    fn tracks_best_move(&self) -> bool {
+        return false;
+    }
+}
+
+
+/// Search driver with progress reports.
+impl<Action, GameStateObj, Data> MCTSTree<Action, GameStateObj, Data>
+where
+    Action: Clone,
+    GameStateObj: GameState<Action> + Clone
+{
+    /// Runs `iterations` iterations of search like `search`, sending its progress to `sender`
+    /// every `interval`, and once more when the search ends.
+    ///
+    /// The search can run on its own thread while the receiver is read from another.
+    pub fn search_with_progress(&mut self, iterations: u64, sender: Sender<SearchProgress<Action>>, interval: Duration) {
+        let mut reporter = ProgressReporter::new(sender, interval);
+        let searched = self.search_with_hooks(iterations, &mut reporter);
+        reporter.report(self, searched);
+    }
+}
+
+
+/// Defines unit tests for progress reports.
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tictactoe_env::TicTacToeState;
+    use std::sync::mpsc;
+
+    /// Test if progress is sent while searching on another thread, ending with the final progress.
+    #[test]
+    fn test_search_with_progress() {
+        let (sender, receiver) = mpsc::channel();
+        let search = std::thread::spawn(move || {
+            let mut tree = MCTSTree::<u8, TicTacToeState>::with_capacity(3000, Some(1), ".........".to_string(), 9);
+            tree.search_with_progress(2000, sender, Duration::ZERO);
+            return tree.root_policy().into_iter().max_by_key(|(_, visits, _)| *visits).map(|(action, _, _)| action);
+        });
+        let messages: Vec<SearchProgress<u8>> = receiver.iter().collect();
+        let best = search.join().unwrap();
+
+        // A message is sent after every iteration, and once more at the end.
+        assert_eq!(messages.len(), 2001);
+        assert!(messages.windows(2).all(|pair| pair[0].iterations <= pair[1].iterations && pair[0].elapsed <= pair[1].elapsed));
+        assert!(messages.windows(2).all(|pair| pair[0].depth <= pair[1].depth));
+        let last = messages.last().unwrap();
+        assert_eq!((last.iterations, last.visits), (2000, 2000));
+        assert!(last.depth >= 2 && last.depth <= 9);
+        assert_eq!(last.best, best);
+        assert!(last.pv.len() >= 2 && last.pv.len() <= 9);
+        assert_eq!(last.pv.first(), last.best.as_ref());
+    }
+
+    /// Test if messages are not sent more often than the interval, and a dropped receiver does not stop the search.
+    #[test]
+    fn test_progress_interval() {
+        let (sender, receiver) = mpsc::channel();
+        let mut tree = MCTSTree::<u8, TicTacToeState>::with_capacity(1000, Some(1), ".........".to_string(), 9);
+        tree.search_with_progress(100, sender, Duration::from_secs(3600));
+        assert_eq!(receiver.iter().count(), 1);
+
+        let (sender, receiver) = mpsc::channel();
+        drop(receiver);
+        tree.search_with_progress(100, sender, Duration::ZERO);
+        assert_eq!(tree.counters.iterations, 200);
+    }
+}
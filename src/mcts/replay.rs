@@ -0,0 +1,299 @@
+//! Recording and replaying of searches, one iteration at a time, for debugging
+//! nondeterministic strength regressions.
+//!
+//! A recorded search reseeds the random generator of the tree with a logged seed before every
+//! iteration, then logs the path it selected, the action it expanded and the result of its
+//! simulation. Replaying the log reseeds every iteration with the same seed, so the replayed
+//! search makes the same random choices, and checks every iteration against the log. The
+//! first iteration which differs points at the change in selection, expansion, simulation or
+//! backpropagation behind the regression.
+
+use crate::arena::NodeIndex;
+use crate::game_state_trait::{GameResult, GameState};
+use crate::mcts::MCTSTree;
+use crate::serialize::{decode_varint, encode_varint, Decode, DecodeError, Encode};
+use std::fmt;
+use crate::rng::{Rng, SeedableRng};
+
+
+/// Header written at the start of every encoded replay log.
+pub const REPLAY_MAGIC: &[u8; 4] = b"MCTR";
+
+
+/// A single recorded iteration of search.
+#[derive(Debug, Clone, PartialEq)]
+pub struct IterationRecord<Action> {
+    /// Seed of the random generator at the start of the iteration.
+    pub seed: [u64; 2],
+
+    /// Nodes selected from the root to the leaf, without the root.
+    pub path: Vec<NodeIndex>,
+
+    /// Action of the node expanded from the leaf, or None if the leaf was simulated itself.
+    pub expanded: Option<Action>,
+
+    /// Result of the simulation.
+    pub result: GameResult,
+}
+
+impl<Action: Encode> Encode for IterationRecord<Action> {
+    fn encode(&self, out: &mut Vec<u8>) {
+        self.seed[0].encode(out);
+        self.seed[1].encode(out);
+
+        // Paths are written as varints, as most node indices are far below the width of an index.
+        encode_varint(self.path.len() as u64, out);
+        for node in &self.path {
+            encode_varint(*node as u64, out);
+        }
+        self.expanded.encode(out);
+        self.result.encode(out);
+    }
+}
+
+impl<Action: Decode> Decode for IterationRecord<Action> {
+    fn decode(input: &mut &[u8]) -> Result<Self, DecodeError> {
+        let seed = [u64::decode(input)?, u64::decode(input)?];
+        let len = decode_varint(input)? as usize;
+        let mut path = Vec::with_capacity(len.min(input.len()));
+        for _i in 0..len {
+            let node = decode_varint(input)?;
+            path.push(NodeIndex::try_from(node).map_err(|_| DecodeError(format!("node {} out of range", node)))?);
+        }
+        return Ok(IterationRecord {
+            seed,
+            path,
+            expanded: Option::decode(input)?,
+            result: GameResult::decode(input)?,
+        });
+    }
+}
+
+
+/// Error produced when a replayed iteration differs from the recorded one.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReplayDivergence<Action> {
+    /// Index of the iteration within the log.
+    pub iteration: usize,
+
+    /// The iteration as recorded.
+    pub expected: IterationRecord<Action>,
+
+    /// The iteration as replayed.
+    pub found: IterationRecord<Action>,
+}
+
+impl<Action: fmt::Debug + PartialEq> fmt::Display for ReplayDivergence<Action> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (expected, found) = (&self.expected, &self.found);
+        write!(f, "iteration {} diverged: ", self.iteration)?;
+        if expected.path != found.path {
+            return write!(f, "selected {:?} instead of {:?}", found.path, expected.path);
+        }
+        if expected.expanded != found.expanded {
+            return write!(f, "expanded {:?} instead of {:?}", found.expanded, expected.expanded);
+        }
+        return write!(f, "simulated {:?} instead of {:?}", found.result, expected.result);
+    }
+}
+
+impl<Action: fmt::Debug + PartialEq> std::error::Error for ReplayDivergence<Action> {}
+
+
+/// Log of every iteration of a recorded search.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReplayLog<Action> {
+    /// Exploration factor used by selection, see `MCTSTree::select`.
+    pub exploration_factor: Option<f32>,
+
+    /// Recorded iterations, in order.
+    pub records: Vec<IterationRecord<Action>>,
+}
+
+
+impl<Action> ReplayLog<Action> {
+    /// Creates an empty log for searches selecting with `exploration_factor`.
+    pub fn new(exploration_factor: Option<f32>) -> Self {
+        return ReplayLog { exploration_factor, records: Vec::new() };
+    }
+
+    /// Runs `iterations` iterations of search on `tree`, logging each of them.
+    ///
+    /// The seeds of the iterations are drawn from the random generator of the tree, so
+    /// recorded searches make different random choices than unrecorded ones, but two recorded
+    /// searches of equally seeded trees are identical.
+    pub fn record<GameStateObj, Data>(&mut self, tree: &mut MCTSTree<Action, GameStateObj, Data>, iterations: u32)
+    where
+        Action: Clone,
+        GameStateObj: GameState<Action> + Clone
+    {
+        for _i in 0..iterations {
+            let seed = [tree.searcher.random_generator.next_u64(), tree.searcher.random_generator.next_u64()];
+            let record = Self::run_iteration(tree, seed, self.exploration_factor);
+            self.records.push(record);
+        }
+    }
+
+    /// Replays every iteration of the log on `tree`, see `replay_step`.
+    ///
+    /// # Returns
+    /// The first iteration which diverged from the log, if any.
+    pub fn replay<GameStateObj, Data>(&self, tree: &mut MCTSTree<Action, GameStateObj, Data>) -> Result<(), ReplayDivergence<Action>>
+    where
+        Action: Clone + PartialEq,
+        GameStateObj: GameState<Action> + Clone
+    {
+        for iteration in 0..self.records.len() {
+            self.replay_step(tree, iteration)?;
+        }
+        return Ok(());
+    }
+
+    /// Replays iteration `iteration` of the log on `tree`, with the seed it was recorded with.
+    ///
+    /// Stepping through the log one iteration at a time reconstructs the tree as it was after
+    /// every iteration, as long as `tree` starts out as the recorded tree did, with the same root
+    /// state and configuration, and every earlier iteration has been replayed.
+    ///
+    /// # Returns
+    /// A `ReplayDivergence` if the replayed iteration differs from the recorded one. The
+    /// iteration is still applied to the tree.
+    pub fn replay_step<GameStateObj, Data>(&self, tree: &mut MCTSTree<Action, GameStateObj, Data>, iteration: usize) -> Result<(), ReplayDivergence<Action>>
+    where
+        Action: Clone + PartialEq,
+        GameStateObj: GameState<Action> + Clone
+    {
+        let expected = &self.records[iteration];
+        let found = Self::run_iteration(tree, expected.seed, self.exploration_factor);
+        if found != *expected {
+            return Err(ReplayDivergence { iteration, expected: expected.clone(), found });
+        }
+        return Ok(());
+    }
+
+    /// Runs a single iteration of search on `tree`, seeded with `seed`.
+    fn run_iteration<GameStateObj, Data>(tree: &mut MCTSTree<Action, GameStateObj, Data>, seed: [u64; 2], exploration_factor: Option<f32>) -> IterationRecord<Action>
+    where
+        Action: Clone,
+        GameStateObj: GameState<Action> + Clone
+    {
+        tree.searcher.random_generator.reseed(seed[0] as u128 | (seed[1] as u128) << 64);
+        let leaf = tree.select_at(0, exploration_factor);
+        let expanded = tree.expand_at(leaf);
+        let result = tree.simulate_at(expanded);
+        tree.backpropagate_at(expanded, result);
+        return IterationRecord {
+            seed,
+            path: tree.trace_path_at(leaf),
+            expanded: if expanded == leaf { None } else { tree.arena.actions[expanded as usize].clone() },
+            result,
+        };
+    }
+
+    /// Encodes the log, which `from_bytes` decodes.
+    pub fn to_bytes(&self) -> Vec<u8>
+    where
+        Action: Encode
+    {
+        let mut out = Vec::new();
+        out.extend_from_slice(REPLAY_MAGIC);
+        self.exploration_factor.encode(&mut out);
+        self.records.encode(&mut out);
+        return out;
+    }
+
+    /// Decodes a log encoded by `to_bytes`.
+    ///
+    /// # Returns
+    /// The log, or a `DecodeError` if `bytes` is not a valid log encoding.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, DecodeError>
+    where
+        Action: Decode
+    {
+        let mut input = bytes;
+        if input.len() < REPLAY_MAGIC.len() || &input[..REPLAY_MAGIC.len()] != REPLAY_MAGIC {
+            return Err(DecodeError("missing replay log header".to_string()));
+        }
+        input = &input[REPLAY_MAGIC.len()..];
+        let log = ReplayLog {
+            exploration_factor: Option::decode(&mut input)?,
+            records: Vec::decode(&mut input)?,
+        };
+        if !input.is_empty() {
+            return Err(DecodeError(format!("{} trailing bytes", input.len())));
+        }
+        return Ok(log);
+    }
+}
+
+
+/// Defines unit tests for recording and replaying searches.
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game2048_env::{Game2048Move, Game2048State};
+    use crate::mcts::PlayerMode;
+    use crate::tictactoe_env::TicTacToeState;
+
+    /// Test if a replayed search reconstructs the recorded tree.
+    #[test]
+    fn test_replay() {
+        let mut tree = MCTSTree::<u8, TicTacToeState>::with_capacity(1000, Some(1), ".........".to_string(), 9);
+        let mut log = ReplayLog::new(None);
+        log.record(&mut tree, 300);
+        assert_eq!(log.records.len(), 300);
+        assert!(log.records[0].path.is_empty());
+        assert!(log.records[0].expanded.is_some());
+
+        let decoded = ReplayLog::<u8>::from_bytes(&log.to_bytes()).unwrap();
+        assert_eq!(decoded, log);
+
+        let mut replayed = MCTSTree::<u8, TicTacToeState>::with_capacity(1000, Some(7), ".........".to_string(), 9);
+        decoded.replay(&mut replayed).unwrap();
+        assert_eq!(replayed.arena.len(), tree.arena.len());
+        for node in 0..tree.arena.len() {
+            assert_eq!(replayed.arena.stats[node], tree.arena.stats[node]);
+        }
+    }
+
+    /// Test if the first diverging iteration is reported.
+    #[test]
+    fn test_replay_divergence() {
+        let mut tree = MCTSTree::<u8, TicTacToeState>::with_capacity(1000, Some(2), ".........".to_string(), 9);
+        let mut log = ReplayLog::new(Some(1.0));
+        log.record(&mut tree, 100);
+
+        // A search with another expansion threshold simulates the root again on its first iteration.
+        let mut changed = MCTSTree::<u8, TicTacToeState>::with_capacity(1000, Some(2), ".........".to_string(), 9);
+        changed.searcher.expansion_threshold = 1;
+        let divergence = log.replay(&mut changed).unwrap_err();
+        assert_eq!(divergence.iteration, 0);
+        assert_eq!(divergence.found.expanded, None);
+        assert!(divergence.to_string().starts_with("iteration 0 diverged: expanded None"));
+
+        // A changed log diverges at the changed iteration.
+        let mut edited = log.clone();
+        edited.records[40].result = GameResult::Draw;
+        let mut replayed = MCTSTree::<u8, TicTacToeState>::with_capacity(1000, Some(2), ".........".to_string(), 9);
+        assert_eq!(edited.replay(&mut replayed).unwrap_err().iteration, 40);
+        assert!(ReplayLog::<u8>::from_bytes(&log.to_bytes()[..10]).is_err());
+    }
+
+    /// Test if searches with chance nodes and score results are replayed.
+    #[test]
+    fn test_replay_chance() {
+        let mut tree = MCTSTree::<Game2048Move, Game2048State>::with_capacity(1000, Some(3), String::new(), 4);
+        tree.searcher.player_mode = PlayerMode::SinglePlayer;
+        tree.searcher.rollout_depth_limit = Some(2);
+        let mut log = ReplayLog::new(Some(100.0));
+        log.record(&mut tree, 100);
+        assert!(log.records.iter().any(|record| matches!(record.result, GameResult::Score(_))));
+
+        let mut replayed = MCTSTree::<Game2048Move, Game2048State>::with_capacity(1000, Some(4), String::new(), 4);
+        replayed.searcher.player_mode = PlayerMode::SinglePlayer;
+        replayed.searcher.rollout_depth_limit = Some(2);
+        let decoded = ReplayLog::<Game2048Move>::from_bytes(&log.to_bytes()).unwrap();
+        decoded.replay(&mut replayed).unwrap();
+        assert_eq!(replayed.arena.stats[0], tree.arena.stats[0]);
+    }
+}
@@ -0,0 +1,267 @@
+//! Human readable reports of searches, showing why the engine prefers its moves.
+
+use crate::arena::NodeIndex;
+use crate::game_state_trait::GameState;
+use crate::mcts::{MCTSTree, SelectionPolicy};
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::fmt;
+use core::fmt::Write;
+
+
+/// Most visited children shown below every node of `MCTSTree::fmt_tree`.
+pub const TREE_BRANCHES: usize = 3;
+
+
+/// Reports of the tree.
+impl<Action, GameStateObj, Data> MCTSTree<Action, GameStateObj, Data>
+where
+    GameStateObj: GameState<Action> + Clone
+{
+    /// Renders the children of the root as a text table, labelling actions with their `Display` encoding.
+    ///
+    /// See `root_move_table_with`.
+    pub fn root_move_table(&self, max_rows: Option<usize>, pv_length: usize) -> String
+    where
+        Action: fmt::Display
+    {
+        return self.root_move_table_with(max_rows, pv_length, |action| action.to_string());
+    }
+
+    /// Renders the expanded children of the root as a text table, one row per child sorted by
+    /// visits, with the action, the visits, the win rate of the player choosing the action, the
+    /// value under the selection policy, and the principal variation starting with the action.
+    ///
+    /// The table only reads the tree, so it can be rendered between iterations to follow a
+    /// search live, such as from `SearchHooks::on_iteration`, or once the search ends.
+    ///
+    /// # Arguments
+    /// * `max_rows` : Number of most visited children shown. None to show every child.
+    ///
+    /// * `pv_length` : Number of actions of the principal variation shown, following the most
+    /// visited child from every node.
+    ///
+    /// * `label` : Encodes an action as the string shown in the table.
+    ///
+    /// # Returns
+    /// The table, with a header line and a line per child, each ending with a newline.
+    pub fn root_move_table_with<F>(&self, max_rows: Option<usize>, pv_length: usize, label: F) -> String
+    where
+        F: Fn(&Action) -> String
+    {
+        // Most visited children first, breaking ties by their order of expansion.
+        let mut children: Vec<NodeIndex> = self.arena.expanded(0).collect();
+        children.sort_by_key(|child| core::cmp::Reverse(self.arena.stats[*child as usize].sims));
+        children.truncate(max_rows.unwrap_or(children.len()));
+
+        let rows: Vec<[String; 5]> = children.iter().map(|child| {
+            let stats = &self.arena.stats[*child as usize];
+            let action = label(self.arena.actions[*child as usize].as_ref().expect("no action"));
+            let win_rate = if stats.sims == 0 { "-".to_string() } else { format!("{:.1}%", 100.0 * stats.score / stats.sims as f64) };
+            let value = match self.searcher.selection_policy {
+                SelectionPolicy::Uct => self.uct_at(*child, None),
+                SelectionPolicy::Puct => self.puct_at(*child, None),
+            };
+            let pv: Vec<String> = self.most_visited_line(*child, pv_length).iter()
+                .map(|node| label(self.arena.actions[*node as usize].as_ref().expect("no action")))
+                .collect();
+            return [action, stats.sims.to_string(), win_rate, format!("{:.3}", value), pv.join(" ")];
+        }).collect();
+
+        let header = ["move", "visits", "win", "uct", "pv"];
+        let mut widths = header.map(|title| title.len());
+        for row in &rows {
+            for (width, cell) in widths.iter_mut().zip(row) {
+                *width = (*width).max(cell.len());
+            }
+        }
+
+        let mut out = String::new();
+        for line in core::iter::once(header.map(|title| title.to_string())).chain(rows) {
+            // Numeric columns are right aligned, and the last column is not padded.
+            writeln!(
+                out,
+                "{:<w0$}  {:>w1$}  {:>w2$}  {:>w3$}  {}",
+                line[0], line[1], line[2], line[3], line[4],
+                w0 = widths[0], w1 = widths[1], w2 = widths[2], w3 = widths[3]
+            ).expect("write to string");
+        }
+        return out;
+    }
+
+    /// Renders the top of the tree as an indented outline, one line per node with its action, visits
+    /// and value under `backup_policy`, labelling actions with their `Display` encoding.
+    ///
+    /// Only the `TREE_BRANCHES` most visited children of every node are shown, with a line counting
+    /// the others, so that the outline of a large tree stays readable in a REPL or debugger.
+    ///
+    /// # Arguments
+    /// * `depth` : Levels of children shown below the root, 0 for the root alone.
+    ///
+    /// # Returns
+    /// The outline, each line ending with a newline.
+    pub fn fmt_tree(&self, depth: usize) -> String
+    where
+        Action: fmt::Display
+    {
+        let mut out = String::new();
+        self.write_tree_node(&mut out, 0, 0, depth);
+        return out;
+    }
+
+    /// Writes the line of `node` at `level` of the outline of `fmt_tree`, followed by its children.
+    fn write_tree_node(&self, out: &mut String, node: NodeIndex, level: usize, depth: usize)
+    where
+        Action: fmt::Display
+    {
+        let label = match &self.arena.actions[node as usize] {
+            Some(action) => action.to_string(),
+            None => "root".to_string(),
+        };
+        let stats = &self.arena.stats[node as usize];
+        writeln!(out, "{:indent$}{}: {} visits, value {:.3}", "", label, stats.sims, self.node_value_at(node), indent = 2 * level)
+            .expect("write to string");
+        if level == depth {
+            return;
+        }
+
+        // Most visited children first, breaking ties by their order of expansion.
+        let mut children: Vec<NodeIndex> = self.arena.expanded(node).collect();
+        children.sort_by_key(|child| core::cmp::Reverse(self.arena.stats[*child as usize].sims));
+        for child in children.iter().take(TREE_BRANCHES) {
+            self.write_tree_node(out, *child, level + 1, depth);
+        }
+        let hidden = children.len().saturating_sub(TREE_BRANCHES) + self.arena.unexpanded_count(node);
+        if hidden > 0 {
+            writeln!(out, "{:indent$}... {} more", "", hidden, indent = 2 * (level + 1)).expect("write to string");
+        }
+    }
+
+    /// The line of play expected by the search, following the most visited child from the root
+    /// for up to `plies` moves, to display after a search.
+    ///
+    /// # Returns
+    /// The action of every move with the visits of its node and its win rate, the mean score of
+    /// the player choosing the action. The line ends early at a node without visited children.
+    pub fn best_line(&self, plies: usize) -> Vec<(Action, u64, f64)>
+    where
+        Action: Clone
+    {
+        return self.most_visited_line(0, plies + 1).into_iter().skip(1).map(|node| {
+            let stats = &self.arena.stats[node as usize];
+            let action = self.arena.actions[node as usize].clone().expect("no action");
+            return (action, stats.sims, stats.score / stats.sims as f64);
+        }).collect();
+    }
+
+    /// Follows the most visited child from `node` for up to `length` nodes, starting with `node`.
+    pub(crate) fn most_visited_line(&self, mut node: NodeIndex, length: usize) -> Vec<NodeIndex> {
+        let mut line = Vec::new();
+        while line.len() < length {
+            line.push(node);
+            match self.arena.expanded(node).max_by_key(|child| self.arena.stats[*child as usize].sims) {
+                Some(child) if self.arena.stats[child as usize].sims > 0 => node = child,
+                _ => break,
+            }
+        }
+        return line;
+    }
+}
+
+
+/// Outline of the root and its two most visited levels of children, see `MCTSTree::fmt_tree`.
+impl<Action, GameStateObj, Data> fmt::Display for MCTSTree<Action, GameStateObj, Data>
+where
+    Action: fmt::Display,
+    GameStateObj: GameState<Action> + Clone
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        return f.write_str(&self.fmt_tree(2));
+    }
+}
+
+
+/// Defines unit tests for the reports of searches.
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::arena::MCTSNode;
+    use crate::tictactoe_env::TicTacToeState;
+
+    /// Test if the root move table lists the children of the root by visits.
+    #[test]
+    fn test_root_move_table() {
+        let mut tree = MCTSTree::<u8, TicTacToeState>::with_capacity(3000, Some(1), "X...O....".to_string(), 9);
+        tree.search(1000);
+        let table = tree.root_move_table(None, 3);
+        let lines: Vec<&str> = table.lines().collect();
+        assert_eq!(lines.len(), 8);
+        assert!(lines[0].starts_with("move"));
+
+        // Rows are sorted by visits, which add up to the visits of the root.
+        let visits: Vec<u32> = lines[1..].iter().map(|line| line.split_whitespace().nth(1).unwrap().parse().unwrap()).collect();
+        assert!(visits.windows(2).all(|pair| pair[0] >= pair[1]));
+        assert_eq!(visits.iter().sum::<u32>(), 1000);
+
+        // The principal variation starts with the action of the row, and is at most 3 actions long.
+        for line in &lines[1..] {
+            let cells: Vec<&str> = line.split_whitespace().collect();
+            assert_eq!(cells[4], cells[0]);
+            assert!(cells.len() <= 7);
+        }
+
+        let top = tree.root_move_table_with(Some(2), 1, |action| format!("#{}", action));
+        assert_eq!(top.lines().count(), 3);
+        assert!(top.lines().nth(1).unwrap().starts_with('#'));
+    }
+
+    /// Test if the best line follows the most visited children from the root, and ends at unvisited nodes.
+    #[test]
+    fn test_best_line() {
+        let mut tree = MCTSTree::<u8, TicTacToeState>::with_capacity(3000, Some(1), "X...O....".to_string(), 9);
+        assert!(tree.best_line(3).is_empty());
+        tree.search(1000);
+
+        let line = tree.best_line(3);
+        assert_eq!(line.len(), 3);
+        assert!(line.iter().all(|(_, _, win_rate)| (0.0..=1.0).contains(win_rate)));
+        assert!(line.windows(2).all(|pair| pair[0].1 > pair[1].1));
+        let path = tree.most_visited_line(0, 4);
+        for ((action, visits, _), node) in line.iter().zip(&path[1..]) {
+            assert_eq!(tree.arena.actions[*node as usize], Some(*action));
+            assert_eq!(tree.arena.stats[*node as usize].sims, *visits);
+        }
+        assert!(tree.best_line(0).is_empty());
+
+        // Moves which end the game have no line after them.
+        let mut tree = MCTSTree::<u8, TicTacToeState>::with_capacity(3000, Some(1), "XX.OO....".to_string(), 9);
+        tree.search(1000);
+        assert_eq!(tree.best_line(3), vec![(2, tree.best_line(1)[0].1, 1.0)]);
+    }
+
+    /// Test if the outline of the tree is bounded in depth and branches, most visited branches first.
+    #[test]
+    fn test_fmt_tree() {
+        let mut tree = MCTSTree::<u8, TicTacToeState>::with_capacity(3000, Some(1), "X...O....".to_string(), 9);
+        assert_eq!(tree.fmt_tree(2), "root: 0 visits, value 0.000\n  ... 7 more\n");
+        tree.search(1000);
+
+        let outline = tree.fmt_tree(1);
+        let lines: Vec<&str> = outline.lines().collect();
+        assert_eq!(lines.len(), 2 + TREE_BRANCHES);
+        assert!(lines[0].starts_with("root: 1000 visits"));
+        assert_eq!(lines[1 + TREE_BRANCHES], "  ... 4 more");
+        let visits: Vec<u64> = lines[1..=TREE_BRANCHES].iter().map(|line| line.split_whitespace().nth(1).unwrap().parse().unwrap()).collect();
+        assert!(visits.windows(2).all(|pair| pair[0] >= pair[1]));
+
+        // Display shows two levels of children, indented below their parents.
+        let displayed = tree.to_string();
+        assert!(displayed.starts_with(&outline.lines().take(2).collect::<Vec<_>>().join("\n")));
+        assert!(displayed.lines().any(|line| line.starts_with("    ") && !line.starts_with("     ")));
+
+        let node: MCTSNode<u8, TicTacToeState> = MCTSNode { game_state: TicTacToeState::new(), action: Some(4), parent: Some(0), expanded: Vec::new(), unexpanded: Vec::new(), wins: 1, draws: 0, sims: 2, score: 1.0, data: None };
+        assert!(format!("{:?}", node).starts_with("MCTSNode { game_state: TicTacToeState"));
+        assert!(format!("{:?}", node).contains("action: Some(4), parent: Some(0)"));
+    }
+}
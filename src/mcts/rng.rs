@@ -0,0 +1,491 @@
+//! Pseudorandom number generation of the engine, without the standard library.
+//!
+//! Implements the xoshiro256++ and xorshift128+ generators and the sampling methods the engine
+//! uses. Searches draw from a `SearchRng`, which holds any generator seedable from 128 bits,
+//! xoshiro256++ by default. Xorshift128+ has the same outputs as the `xorshift` and `rand` crates
+//! it replaces for the same seeds, for reproducing searches seeded with older versions. Neither
+//! generator is cryptographically secure, but both are very fast.
+
+use alloc::boxed::Box;
+use core::any::Any;
+
+
+/// A random number generator.
+pub trait Rng {
+    /// Next 64 random bits.
+    fn next_u64(&mut self) -> u64;
+
+    /// Next 32 random bits, the low bits of `next_u64`.
+    fn next_u32(&mut self) -> u32 {
+        return self.next_u64() as u32;
+    }
+
+    /// Uniform float in `[0, 1)`, from 23 random mantissa bits.
+    fn next_f32(&mut self) -> f32 {
+        return f32::from_bits(0x3F800000 | (self.next_u32() & 0x7FFFFF)) - 1.0;
+    }
+
+    /// Uniform float in `[0, 1)`, from 52 random mantissa bits.
+    fn next_f64(&mut self) -> f64 {
+        return f64::from_bits(0x3FF0000000000000 | (self.next_u64() & 0xFFFFFFFFFFFFF)) - 1.0;
+    }
+
+    /// Uniform random value of type `T`, in `[0, 1)` for floats.
+    fn gen<T: Random>(&mut self) -> T
+    where
+        Self: Sized
+    {
+        return T::random(self);
+    }
+
+    /// Uniform random value in `[low, high)`.
+    ///
+    /// # Panics
+    /// If `low >= high`.
+    fn gen_range<T: SampleRange>(&mut self, low: T, high: T) -> T
+    where
+        Self: Sized
+    {
+        assert!(low < high, "gen_range called with low >= high");
+        return T::sample_range(low, high, self);
+    }
+}
+
+
+/// A random number generator which can be seeded, to reproduce its outputs.
+pub trait SeedableRng<Seed> {
+    /// Creates a generator from `seed`.
+    fn from_seed(seed: Seed) -> Self;
+
+    /// Resets the generator to the state of `seed`.
+    fn reseed(&mut self, seed: Seed);
+}
+
+
+/// Types which can be drawn uniformly from a random number generator.
+pub trait Random {
+    /// Draws a uniform value from `rng`.
+    fn random<R: Rng>(rng: &mut R) -> Self;
+}
+
+impl Random for f32 {
+    fn random<R: Rng>(rng: &mut R) -> f32 {
+        return rng.next_f32();
+    }
+}
+
+impl Random for f64 {
+    fn random<R: Rng>(rng: &mut R) -> f64 {
+        return rng.next_f64();
+    }
+}
+
+impl Random for u8 {
+    fn random<R: Rng>(rng: &mut R) -> u8 {
+        return rng.next_u32() as u8;
+    }
+}
+
+impl Random for u16 {
+    fn random<R: Rng>(rng: &mut R) -> u16 {
+        return rng.next_u32() as u16;
+    }
+}
+
+impl Random for u32 {
+    fn random<R: Rng>(rng: &mut R) -> u32 {
+        return rng.next_u32();
+    }
+}
+
+impl Random for u64 {
+    fn random<R: Rng>(rng: &mut R) -> u64 {
+        return rng.next_u64();
+    }
+}
+
+impl Random for usize {
+    fn random<R: Rng>(rng: &mut R) -> usize {
+        return if usize::BITS == 32 { rng.next_u32() as usize } else { rng.next_u64() as usize };
+    }
+}
+
+
+/// Types which can be drawn uniformly from a range.
+pub trait SampleRange: PartialOrd + Sized {
+    /// Uniform random value in `[low, high)`, where `low < high`.
+    fn sample_range<R: Rng>(low: Self, high: Self, rng: &mut R) -> Self;
+}
+
+/// Samples integers by rejection, from the largest zone of random values the range divides evenly.
+macro_rules! integer_sample_range {
+    ($($integer:ty),*) => {
+        $(impl SampleRange for $integer {
+            fn sample_range<R: Rng>(low: $integer, high: $integer, rng: &mut R) -> $integer {
+                let range = high - low;
+                let zone = <$integer>::MAX - <$integer>::MAX % range;
+                loop {
+                    let value: $integer = rng.gen();
+                    if value < zone {
+                        return low + value % range;
+                    }
+                }
+            }
+        })*
+    };
+}
+
+integer_sample_range!(u8, u16, u32, u64, usize);
+
+impl SampleRange for f32 {
+    fn sample_range<R: Rng>(low: f32, high: f32, rng: &mut R) -> f32 {
+        return low + (high - low) * rng.next_f32();
+    }
+}
+
+impl SampleRange for f64 {
+    fn sample_range<R: Rng>(low: f64, high: f64, rng: &mut R) -> f64 {
+        return low + (high - low) * rng.next_f64();
+    }
+}
+
+
+/// The xorshift128+ generator of Sebastiano Vigna.
+///
+/// # Invariants
+/// The state should not be all zeros, from which the generator only outputs zeros.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Xorshift128([u64; 2]);
+
+impl Xorshift128 {
+    /// Creates a generator with the raw `state`.
+    pub fn from_state(state: [u64; 2]) -> Self {
+        return Xorshift128(state);
+    }
+
+    /// The raw state of the generator.
+    pub fn state(&self) -> [u64; 2] {
+        return self.0;
+    }
+}
+
+impl Rng for Xorshift128 {
+    fn next_u64(&mut self) -> u64 {
+        let mut s1 = self.0[0];
+        let s0 = self.0[1];
+        let result = s0.wrapping_add(s1);
+        self.0[0] = s0;
+        s1 ^= s1 << 23;
+        self.0[1] = s1 ^ s0 ^ (s1 >> 18) ^ (s0 >> 5);
+        return result;
+    }
+}
+
+impl<'a> SeedableRng<&'a [u64]> for Xorshift128 {
+    /// # Panics
+    /// If `seed` has fewer than 2 words.
+    fn from_seed(seed: &'a [u64]) -> Xorshift128 {
+        let mut rng = Xorshift128([0, 0]);
+        rng.reseed(seed);
+        return rng;
+    }
+
+    /// # Panics
+    /// If `seed` has fewer than 2 words.
+    fn reseed(&mut self, seed: &'a [u64]) {
+        assert!(seed.len() >= 2, "Xorshift128 seeds need at least two words");
+        self.0 = [seed[0], seed[1]];
+    }
+}
+
+/// Full 128 bit seeds, the low word first and the high word second.
+impl SeedableRng<u128> for Xorshift128 {
+    fn from_seed(seed: u128) -> Xorshift128 {
+        return Xorshift128([seed as u64, (seed >> 64) as u64]);
+    }
+
+    fn reseed(&mut self, seed: u128) {
+        *self = Self::from_seed(seed);
+    }
+}
+
+
+/// Next output of the SplitMix64 generator with `state`, used to expand seeds.
+fn split_mix(state: &mut u64) -> u64 {
+    *state = state.wrapping_add(0x9E3779B97F4A7C15);
+    let mut z = *state;
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58476D1CE4E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D049BB133111EB);
+    return z ^ (z >> 31);
+}
+
+
+/// The xoshiro256++ generator of David Blackman and Sebastiano Vigna, the default generator of searches.
+///
+/// # Invariants
+/// The state should not be all zeros, which seeding from 128 bits never produces.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Xoshiro256PlusPlus([u64; 4]);
+
+impl Xoshiro256PlusPlus {
+    /// Creates a generator with the raw `state`.
+    pub fn from_state(state: [u64; 4]) -> Self {
+        return Xoshiro256PlusPlus(state);
+    }
+
+    /// The raw state of the generator.
+    pub fn state(&self) -> [u64; 4] {
+        return self.0;
+    }
+}
+
+impl Rng for Xoshiro256PlusPlus {
+    fn next_u64(&mut self) -> u64 {
+        let s = &mut self.0;
+        let result = s[0].wrapping_add(s[3]).rotate_left(23).wrapping_add(s[0]);
+        let t = s[1] << 17;
+        s[2] ^= s[0];
+        s[3] ^= s[1];
+        s[1] ^= s[2];
+        s[0] ^= s[3];
+        s[2] ^= t;
+        s[3] = s[3].rotate_left(45);
+        return result;
+    }
+}
+
+/// Seeds are expanded to the full state with SplitMix64, as recommended by the authors, the
+/// first half from the low word and the second half from the high word of the seed.
+impl SeedableRng<u128> for Xoshiro256PlusPlus {
+    fn from_seed(seed: u128) -> Xoshiro256PlusPlus {
+        let (mut low, mut high) = (seed as u64, (seed >> 64) as u64);
+        return Xoshiro256PlusPlus([split_mix(&mut low), split_mix(&mut low), split_mix(&mut high), split_mix(&mut high)]);
+    }
+
+    fn reseed(&mut self, seed: u128) {
+        *self = Self::from_seed(seed);
+    }
+}
+
+
+/// Generators a `SearchRng` can hold, seedable from 128 bits and cloneable behind a box.
+///
+/// Implemented for every `Rng + SeedableRng<u128> + Clone + Send`.
+pub trait SearchRngCore: Rng + Send {
+    /// Resets the generator to the state of `seed`.
+    fn reseed_u128(&mut self, seed: u128);
+
+    /// Clones the generator into a new box.
+    fn clone_box(&self) -> Box<dyn SearchRngCore>;
+
+    /// The generator as `Any`, so the state of known generators can be read.
+    fn as_any(&self) -> &dyn Any;
+}
+
+impl<R> SearchRngCore for R
+where
+    R: Rng + SeedableRng<u128> + Clone + Send + 'static
+{
+    fn reseed_u128(&mut self, seed: u128) {
+        self.reseed(seed);
+    }
+
+    fn clone_box(&self) -> Box<dyn SearchRngCore> {
+        return Box::new(self.clone());
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        return self;
+    }
+}
+
+
+/// Snapshot of the state of a `SearchRng`, see `SearchRng::state`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RngState {
+    /// State of a xoshiro256++ generator.
+    Xoshiro256PlusPlus([u64; 4]),
+
+    /// State of a xorshift128+ generator.
+    Xorshift128([u64; 2]),
+
+    /// Seed of a xoshiro256++ generator, standing in for a generator whose state can not be read.
+    Seed(u128),
+}
+
+
+/// Random number generator of a search, rollout policy and nested search.
+///
+/// Holds any generator seedable from 128 bits, so a different generator can be plugged into a
+/// tree without changing its type. Seeding it creates the default generator, xoshiro256++.
+pub struct SearchRng(Box<dyn SearchRngCore>);
+
+impl SearchRng {
+    /// Wraps `rng`, which is reseeded as itself by `reseed`.
+    pub fn new<R>(rng: R) -> Self
+    where
+        R: Rng + SeedableRng<u128> + Clone + Send + 'static
+    {
+        return SearchRng(Box::new(rng));
+    }
+
+    /// Snapshot of the state of the generator, which `from_state` restores without changing this one.
+    ///
+    /// The states of the bundled generators are read as they are. Other generators can not be read,
+    /// so their snapshot is a seed drawn from a clone, and restores to a xoshiro256++ generator
+    /// making different choices than this one.
+    pub fn state(&self) -> RngState {
+        let any = self.0.as_any();
+        if let Some(rng) = any.downcast_ref::<Xoshiro256PlusPlus>() {
+            return RngState::Xoshiro256PlusPlus(rng.state());
+        }
+        if let Some(rng) = any.downcast_ref::<Xorshift128>() {
+            return RngState::Xorshift128(rng.state());
+        }
+        let mut clone = self.0.clone_box();
+        return RngState::Seed(clone.next_u64() as u128 | (clone.next_u64() as u128) << 64);
+    }
+
+    /// Creates a generator in the state of a snapshot taken by `state`.
+    pub fn from_state(state: RngState) -> Self {
+        match state {
+            RngState::Xoshiro256PlusPlus(state) => return SearchRng::new(Xoshiro256PlusPlus::from_state(state)),
+            RngState::Xorshift128(state) => return SearchRng::new(Xorshift128::from_state(state)),
+            RngState::Seed(seed) => return SeedableRng::from_seed(seed),
+        }
+    }
+}
+
+impl Rng for SearchRng {
+    fn next_u64(&mut self) -> u64 {
+        return self.0.next_u64();
+    }
+}
+
+impl SeedableRng<u128> for SearchRng {
+    /// Creates a xoshiro256++ generator from `seed`.
+    fn from_seed(seed: u128) -> SearchRng {
+        return SearchRng::new(Xoshiro256PlusPlus::from_seed(seed));
+    }
+
+    /// Reseeds the held generator, keeping its kind.
+    fn reseed(&mut self, seed: u128) {
+        self.0.reseed_u128(seed);
+    }
+}
+
+impl Clone for SearchRng {
+    fn clone(&self) -> Self {
+        return SearchRng(self.0.clone_box());
+    }
+}
+
+impl core::fmt::Debug for SearchRng {
+    fn fmt(&self, formatter: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        return formatter.write_str("SearchRng");
+    }
+}
+
+
+/// Defines unit tests for random number generation.
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Test if the generator matches the reference implementation of xorshift128+.
+    #[test]
+    fn test_xorshift128() {
+        let seed = 1477776990746309507;
+        let mut rng: Xorshift128 = SeedableRng::from_seed(&[seed, seed][..]);
+        let expected = [2955553981492619014, 4599697141668829146, 4670196263639928724, 16937752213077027105];
+        for value in expected {
+            assert_eq!(rng.next_u64(), value);
+        }
+    }
+
+    /// Test if the generator matches the reference implementation of xoshiro256++, seeded by SplitMix64.
+    #[test]
+    fn test_xoshiro256_plus_plus() {
+        let mut rng = Xoshiro256PlusPlus::from_state([1, 2, 3, 4]);
+        let expected = [41943041, 58720359, 3588806011781223, 3591011842654386];
+        for value in expected {
+            assert_eq!(rng.next_u64(), value);
+        }
+
+        let mut state = 0;
+        assert_eq!(split_mix(&mut state), 0xE220A8397B1DCDAF);
+
+        // Both words of the seed count.
+        let mut low: Xoshiro256PlusPlus = SeedableRng::from_seed(1);
+        let mut high: Xoshiro256PlusPlus = SeedableRng::from_seed(1 | 1 << 64);
+        assert_ne!(low.next_u64(), high.next_u64());
+    }
+
+    /// Test if search generators reseed and clone the generator they hold.
+    #[test]
+    fn test_search_rng() {
+        let mut rng = SearchRng::new(Xorshift128::from_seed(7u128));
+        let mut reference: Xorshift128 = SeedableRng::from_seed(&[7, 0][..]);
+        assert_eq!(rng.next_u64(), reference.next_u64());
+        assert_eq!(rng.clone().next_u64(), rng.next_u64());
+
+        rng.reseed(7);
+        reference.reseed(&[7, 0][..]);
+        assert_eq!(rng.next_u64(), reference.next_u64());
+
+        let mut default: SearchRng = SeedableRng::from_seed(7);
+        assert_eq!(default.next_u64(), Xoshiro256PlusPlus::from_seed(7).next_u64());
+    }
+
+    /// Test if generators restored from a snapshot continue like the generator it was taken from.
+    #[test]
+    fn test_search_rng_state() {
+        let mut default: SearchRng = SeedableRng::from_seed(7);
+        let mut xorshift = SearchRng::new(Xorshift128::from_seed(7u128));
+        for rng in [&mut default, &mut xorshift] {
+            rng.next_u64();
+            let mut restored = SearchRng::from_state(rng.state());
+            for _i in 0..10 {
+                assert_eq!(restored.next_u64(), rng.next_u64());
+            }
+        }
+
+        // Generators whose state can not be read are snapshot as a seed, without advancing them.
+        #[derive(Clone)]
+        struct Counter(u64);
+        impl Rng for Counter {
+            fn next_u64(&mut self) -> u64 {
+                self.0 += 1;
+                return self.0;
+            }
+        }
+        impl SeedableRng<u128> for Counter {
+            fn from_seed(seed: u128) -> Counter {
+                return Counter(seed as u64);
+            }
+
+            fn reseed(&mut self, seed: u128) {
+                self.0 = seed as u64;
+            }
+        }
+        let mut counter = SearchRng::new(Counter(0));
+        assert_eq!(counter.state(), RngState::Seed(1 | 2 << 64));
+        assert_eq!(counter.next_u64(), 1);
+    }
+
+    /// Test if sampled values stay within their ranges, and cover them.
+    #[test]
+    fn test_sampling() {
+        let mut rng: Xorshift128 = SeedableRng::from_seed(&[1, 0][..]);
+        let mut seen = [false; 7];
+        for _i in 0..1000 {
+            let value = rng.gen_range(3usize, 10);
+            assert!((3..10).contains(&value));
+            seen[value - 3] = true;
+            let float: f32 = rng.gen();
+            assert!((0.0..1.0).contains(&float));
+            assert!((-1.0..1.0).contains(&rng.gen_range(-1.0f64, 1.0)));
+        }
+        assert!(seen.iter().all(|seen| *seen));
+    }
+}
@@ -0,0 +1,135 @@
+use crate::game_state_trait::GameResult;
+use crate::rng::{Rng, SearchRng};
+
+
+/// Chooses the moves played during simulations/rollouts.
+///
+/// Random rollouts know nothing about the game, which makes their results very noisy in
+/// games like chess. A policy can use domain knowledge to play more realistic moves,
+/// at the cost of slower simulations.
+pub trait RolloutPolicy<Action, GameStateObj> {
+    /// Chooses the next move of a rollout.
+    ///
+    /// # Arguments
+    /// * `game_state` : The current state of the rollout.
+    ///
+    /// * `actions` : The legal actions of `game_state`, which is never empty.
+    ///
+    /// * `random_generator` : The random generator of the tree, used for any random choices
+    /// so that searches stay reproducible from their seed.
+    ///
+    /// # Returns
+    /// The index of the chosen action within `actions`.
+    fn choose(&mut self, game_state: &GameStateObj, actions: &[Action], random_generator: &mut SearchRng) -> usize;
+
+    /// Decides whether a rollout ends early at a non-terminal state, such as once the position is quiet 
+    /// enough for a static evaluation to be trusted.
+    ///
+    /// # Arguments
+    /// * `game_state` : The current state of the rollout.
+    ///
+    /// * `depth` : Number of moves played in the rollout so far.
+    ///
+    /// # Returns
+    /// The result the rollout ends with, or None to keep playing. Defaults to None.
+    fn cutoff(&mut self, _game_state: &GameStateObj, _depth: u32) -> Option<GameResult> {
+        return None;
+    }
+
+    /// Whether the policy learns from the results of simulations through `learn`.
+    /// Defaults to false, which spares the tree from collecting the moves of its iterations.
+    fn learns(&self) -> bool {
+        return false;
+    }
+
+    /// Learns from a finished simulation, called by `MCTSTree::backpropagate` when `learns` is true.
+    ///
+    /// The moves of the rollout are not passed, policies which learn from them record
+    /// them as they choose them, and forget them here.
+    ///
+    /// # Arguments
+    /// * `tree_moves` : Action codes of the moves selected in the tree, see `GameState::action_code`,
+    /// from the root down, paired with whether the first player played them. Moves without a code
+    /// and chance outcomes are left out.
+    ///
+    /// * `rewards` : Rewards of the simulation, as credited to the nodes of either side.
+    fn learn(&mut self, _tree_moves: &[(u64, bool)], _rewards: SimulationRewards) {}
+}
+
+
+/// Rewards of a finished simulation for either side, as credited by backpropagation.
+///
+/// In single player mode, both sides receive the raw simulation score.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SimulationRewards {
+    pub first_player: f64,
+    pub second_player: f64,
+}
+
+impl SimulationRewards {
+    /// Reward of the first player if `first_player` is true, otherwise of the second player.
+    pub fn of(&self, first_player: bool) -> f64 {
+        return if first_player { self.first_player } else { self.second_player };
+    }
+}
+
+
+/// Rollout policy choosing uniformly random moves. The default policy of a tree.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RandomRollout;
+
+impl<Action, GameStateObj> RolloutPolicy<Action, GameStateObj> for RandomRollout {
+    fn choose(&mut self, _game_state: &GameStateObj, actions: &[Action], random_generator: &mut SearchRng) -> usize {
+        return random_generator.gen_range(0, actions.len());
+    }
+}
+
+
+/// Samples an index from a list of non-negative weights, proportionally to the weights.
+///
+/// # Returns
+/// The sampled index, or the last index if every weight is 0.
+pub fn sample_weighted(weights: &[f32], random_generator: &mut SearchRng) -> usize {
+    let total: f32 = weights.iter().sum();
+    let mut remaining = random_generator.gen::<f32>() * total;
+    for (i, weight) in weights.iter().enumerate() {
+        if remaining < *weight {
+            return i;
+        }
+        remaining -= weight;
+    }
+    return weights.len() - 1;
+}
+
+
+/// Unit tests for the provided rollout policies.
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rng::SeedableRng;
+
+    #[test]
+    fn test_random_rollout() {
+        let seed = 1;
+        let mut random_generator: SearchRng = SeedableRng::from_seed(seed);
+        let mut policy = RandomRollout;
+        let actions = [5, 6, 7];
+        let mut chosen = [false; 3];
+        for _i in 0..100 {
+            chosen[RolloutPolicy::<u16, ()>::choose(&mut policy, &(), &actions, &mut random_generator)] = true;
+        }
+        assert_eq!(chosen, [true; 3]);
+    }
+
+    #[test]
+    fn test_sample_weighted() {
+        let seed = 1;
+        let mut random_generator: SearchRng = SeedableRng::from_seed(seed);
+        let mut counts = [0; 3];
+        for _i in 0..1000 {
+            counts[sample_weighted(&[0.0, 1.0, 3.0], &mut random_generator)] += 1;
+        }
+        assert!(counts[0] == 0);
+        assert!(counts[2] > 650 && counts[2] < 850);
+    }
+}
@@ -0,0 +1,203 @@
+//! The search algorithm of a tree, its configuration, random generator and policies, kept apart
+//! from the nodes of the tree.
+//!
+//! A `MCTSTree` is the storage of a search, its nodes and their statistics, and owns the
+//! `Searcher` driving its iterations. Searchers can be swapped between trees, so a tree keeps
+//! its statistics while it is searched under another configuration or policy, and a single
+//! searcher, such as one with a learning rollout policy, can drive several trees in turn.
+
+use crate::evaluator::LeafEvaluator;
+use crate::float::Float;
+use crate::game_state_trait::GameState;
+use crate::mcts::{BackupPolicy, ExplorationSchedule, MCTSTree, Perspective, PlayerMode, SelectionPolicy};
+use crate::rng::{SearchRng, SeedableRng};
+use crate::rollout::{RandomRollout, RolloutPolicy, SimulationRewards};
+use alloc::boxed::Box;
+use alloc::vec::Vec;
+
+
+/// Configuration, random generator and policies of a search, the `searcher` of a tree.
+pub struct Searcher<Action, GameStateObj> {
+    /// Holds the current random generator state. Random numbers will be generated
+    /// from the current state and the state will be modified.
+    ///
+    /// Reseed it with `SeedableRng::reseed` for a full 128 bit seed, or replace it with
+    /// `SearchRng::new` to search with a different generator.
+    pub random_generator: SearchRng,
+
+    /// How simulation results are credited. Defaults to `PlayerMode::TwoPlayer`.
+    pub player_mode: PlayerMode,
+
+    /// Which player the nodes are credited to in two player mode. Defaults to `Perspective::Mover`.
+    pub perspective: Perspective,
+
+    /// Reward per unit of margin of `GameResult::Margin` results, letting the search prefer
+    /// bigger wins over narrow ones. Defaults to 0, where only the winner is considered.
+    pub margin_weight: f64,
+
+    /// Reward credited to either side for a drawn simulation, including margins of 0.
+    /// Lower values add contempt, making the search avoid draws, and higher ones make it seek them.
+    /// Defaults to 0.5, half a win.
+    pub draw_score: f64,
+
+    /// Factor the reward of a simulation is multiplied by for every move between a node and the
+    /// end of the simulation, so that long simulations are worth less than short decisive ones.
+    ///
+    /// The moves are those of the rollout of the last `simulate`, plus the moves from the node to
+    /// the simulated node, not counting chance events. Win and draw counts are not discounted.
+    /// Defaults to 1, without discounting.
+    pub discount: f64,
+
+    /// Weight of the best child in the value of a node during selection, `(1 - w)*mean + w*max`,
+    /// as in MixMax. Helps where averaging washes out rare good lines, such as in single player
+    /// planning. Defaults to 0, valuing nodes at their mean score.
+    pub max_backup_weight: f32,
+
+    /// Whether mean rewards are rescaled into [0, 1] by `reward_bounds` during selection, so the
+    /// exploitation term stays on the scale of the exploration term for raw scores, such as disc
+    /// differentials. Defaults to false.
+    pub normalize_rewards: bool,
+
+    /// How the value of a node is derived from its statistics during selection.
+    /// Defaults to `BackupPolicy::MeanReward`.
+    pub backup_policy: BackupPolicy,
+
+    /// Number of moves of the rollouts of the last simulation, averaged over its rollouts, for `discount`.
+    pub(crate) simulation_moves: u32,
+
+    /// Rewards of the last simulation averaged over its rollouts, None if it ran a single rollout
+    /// and its rewards follow from its result.
+    pub(crate) simulation_rewards: Option<SimulationRewards>,
+
+    /// Buffer the legal actions of unexpanded leaves are generated into when checking whether they are terminal.
+    pub(crate) leaf_actions: Vec<Action>,
+
+    /// First play urgency, the value of unvisited children during selection, their UCT and the
+    /// exploitation term of their PUCT. Defaults to `None`, where the UCT of unvisited children is
+    /// infinite, so every child is tried once before any is revisited, and their exploitation term is 0.
+    pub first_play_urgency: Option<f32>,
+
+    /// Whether selection breaks ties between equally valued children at random, rather than
+    /// picking the first of them. Defaults to true.
+    pub random_tie_breaking: bool,
+
+    /// Formula used to pick children during selection. Defaults to `SelectionPolicy::Uct`.
+    pub selection_policy: SelectionPolicy,
+
+    /// Chooses the moves played in rollouts. Defaults to `RandomRollout`.
+    pub rollout_policy: Box<dyn RolloutPolicy<Action, GameStateObj> + Send>,
+
+    /// Evaluates leaves instead of `GameState::action_priors` and rollouts, such as a policy and
+    /// value model. Defaults to `None`.
+    pub evaluator: Option<Box<dyn LeafEvaluator<Action, GameStateObj> + Send>>,
+
+    /// Maximum number of moves played in a rollout before it is cut off and the state is 
+    /// scored with `GameState::evaluate`. Defaults to `None`, playing rollouts to the end.
+    pub rollout_depth_limit: Option<u32>,
+
+    /// Maximum number of moves played in a rollout before it is cut off and scored as a draw,
+    /// so games which might never end still finish their rollouts. `None` plays rollouts to the end.
+    /// Defaults to `GameState::rollout_move_cap` of the root state.
+    pub rollout_move_cap: Option<u32>,
+
+    /// Number of simulations a leaf requires before its children are expanded.
+    ///
+    /// Below the threshold, expansion returns the leaf itself so it is simulated again,
+    /// and the legal actions of new nodes are only generated once they reach it.
+    /// Trades tree growth and memory for noisier statistics near the leaves.
+    /// Defaults to 0, expanding on every visit.
+    pub expansion_threshold: u32,
+
+    /// Number of rollouts simulated from every simulated node, whose results are averaged into
+    /// a single `GameResult::Score` before they are backpropagated.
+    ///
+    /// Spends more evaluation effort per node for cheap environments, growing the tree more
+    /// slowly. Defaults to 1, backpropagating the result of a single rollout as it is.
+    pub simulations_per_expansion: u32,
+
+    /// Exploration factor of selection when no factor is passed to `select`, `uct` or `puct`.
+    /// Defaults to a constant sqrt(2).
+    pub exploration_schedule: ExplorationSchedule,
+
+    /// Stops searches as soon as the most visited child of the search root can no longer be
+    /// overtaken by the runner-up within the remaining iterations, or within the iterations
+    /// expected to fit in the remaining time of a `TimeControl`. Defaults to false.
+    pub early_termination: bool,
+}
+
+impl<Action, GameStateObj> Searcher<Action, GameStateObj>
+where
+    GameStateObj: GameState<Action> + Clone
+{
+    /// Creates a searcher with the default configuration, random rollouts and no evaluator.
+    ///
+    /// # Arguments
+    /// * `seed` : The 128 bit seed that determines the starting state of the rng, 0 if `None`.
+    ///
+    /// * `game_state` : A state of the game searched, which gives the default `rollout_move_cap`.
+    pub fn new(seed: Option<u128>, game_state: &GameStateObj) -> Self {
+        return Searcher {
+            random_generator: SeedableRng::from_seed(seed.unwrap_or(0)),
+            player_mode: PlayerMode::TwoPlayer,
+            perspective: Perspective::Mover,
+            margin_weight: 0.0,
+            draw_score: 0.5,
+            discount: 1.0,
+            max_backup_weight: 0.0,
+            normalize_rewards: false,
+            backup_policy: BackupPolicy::MeanReward,
+            simulation_moves: 0,
+            simulation_rewards: None,
+            leaf_actions: Vec::new(),
+            first_play_urgency: None,
+            random_tie_breaking: true,
+            selection_policy: SelectionPolicy::Uct,
+            rollout_policy: Box::new(RandomRollout),
+            evaluator: None,
+            rollout_depth_limit: None,
+            rollout_move_cap: game_state.rollout_move_cap(),
+            expansion_threshold: 0,
+            simulations_per_expansion: 1,
+            exploration_schedule: ExplorationSchedule::Constant(Float::sqrt(2.0)),
+            early_termination: false,
+        };
+    }
+
+    /// Runs up to `iterations` iterations of search on `tree` with this searcher in place of its own,
+    /// like `MCTSTree::search_with_hooks` without hooks. The searcher of the tree is left unchanged.
+    ///
+    /// # Returns
+    /// The number of iterations searched.
+    pub fn search<Data>(&mut self, tree: &mut MCTSTree<Action, GameStateObj, Data>, iterations: u64) -> u64 {
+        core::mem::swap(self, &mut tree.searcher);
+        let searched = tree.search_with_hooks(iterations, &mut ());
+        core::mem::swap(self, &mut tree.searcher);
+        return searched;
+    }
+}
+
+
+/// Unit tests for searchers.
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tictactoe_env::TicTacToeState;
+    use alloc::string::ToString;
+
+    /// Test if a searcher drives a tree under its own configuration, leaving the searcher of the tree in place.
+    #[test]
+    fn test_searcher() {
+        let mut tree = MCTSTree::<u8, TicTacToeState>::with_capacity(1000, Some(1), "X...O....".to_string(), 9);
+        let mut searcher = Searcher::new(Some(2), &TicTacToeState::new());
+        searcher.expansion_threshold = 1000;
+        assert_eq!(searcher.search(&mut tree, 50), 50);
+        assert_eq!(tree.arena.len(), 1);
+        assert_eq!(tree.arena.stats[0].sims, 50);
+        assert_eq!((searcher.expansion_threshold, tree.searcher.expansion_threshold), (1000, 0));
+
+        // The statistics of the tree carry over to searches with its own searcher.
+        tree.search(50);
+        assert_eq!(tree.arena.stats[0].sims, 100);
+        assert!(tree.arena.len() > 1);
+    }
+}
@@ -0,0 +1,282 @@
+//! Recording of self-play games as training data for policy and value networks.
+//!
+//! After every searched move of a game, the root state, the side to move and the visit
+//! distribution over the root actions are recorded. Once the game ends, every sample of
+//! the game is labelled with its outcome for the side to move.
+
+use crate::arena::NodeIndex;
+use crate::export::write_json_string;
+use crate::game_state_trait::{GameResult, GameState};
+use crate::mcts::MCTSTree;
+use crate::serialize::{Decode, DecodeError, Encode};
+use std::fmt;
+use std::fmt::Write as _;
+use std::io;
+
+
+/// A single training sample, the searched root of a position in a self-play game.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TrainingSample<Action> {
+    /// String encoding of the root state, as parsed by `GameState::from_str`.
+    pub state: String,
+
+    /// True if the first player was to move.
+    pub side_to_move: bool,
+
+    /// Root actions with the share of the root's visits their children received.
+    pub policy: Vec<(Action, f32)>,
+
+    /// Outcome of the game for the side to move, 1 for a win, 0 for a loss and 0.5 for a draw.
+    /// None until the game has ended.
+    pub outcome: Option<f32>,
+}
+
+
+impl<Action> TrainingSample<Action> {
+    /// Encodes the sample as a single line JSON object, with the fields of the sample
+    /// and the policy as a list of objects with an `action` and a `probability`.
+    ///
+    /// # Arguments
+    /// * `label` : Encodes an action as the string written to its `action` field.
+    pub fn to_json_with<F>(&self, label: F) -> String
+    where
+        F: Fn(&Action) -> String
+    {
+        let mut out = String::from("{\"state\":");
+        write_json_string(&mut out, &self.state);
+        write!(out, ",\"side_to_move\":{},\"policy\":[", self.side_to_move).expect("write to string");
+        for (i, (action, probability)) in self.policy.iter().enumerate() {
+            if i > 0 {
+                out.push(',');
+            }
+            out.push_str("{\"action\":");
+            write_json_string(&mut out, &label(action));
+            write!(out, ",\"probability\":{}}}", probability).expect("write to string");
+        }
+        out.push_str("],\"outcome\":");
+        match self.outcome {
+            Some(outcome) => write!(out, "{}}}", outcome).expect("write to string"),
+            None => out.push_str("null}"),
+        }
+        return out;
+    }
+}
+
+impl<Action: Encode> Encode for TrainingSample<Action> {
+    fn encode(&self, out: &mut Vec<u8>) {
+        self.state.encode(out);
+        self.side_to_move.encode(out);
+        self.policy.encode(out);
+        self.outcome.encode(out);
+    }
+}
+
+impl<Action: Decode> Decode for TrainingSample<Action> {
+    fn decode(input: &mut &[u8]) -> Result<Self, DecodeError> {
+        return Ok(TrainingSample {
+            state: String::decode(input)?,
+            side_to_move: bool::decode(input)?,
+            policy: Vec::decode(input)?,
+            outcome: Option::decode(input)?,
+        });
+    }
+}
+
+
+/// Outcome of `result` for a player, 1 for a win, 0 for a loss and 0.5 for a draw.
+///
+/// Scores give the first player's share of the win, and margins count by their sign.
+///
+/// # Arguments
+/// * `first_player` : True for the outcome of the first player, false for the second player.
+pub fn outcome_for(result: &GameResult, first_player: bool) -> f32 {
+    let first_player_outcome = match result {
+        GameResult::Score(score) => *score,
+        _ if result.is_draw() => 0.5,
+        _ => if result.winner() == Some(true) { 1.0 } else { 0.0 },
+    };
+    return if first_player { first_player_outcome } else { 1.0 - first_player_outcome };
+}
+
+
+/// Collects the training samples of self-play games.
+///
+/// `record` is called with the searched tree before every move is played, and `finish`
+/// once the game has ended, which labels the samples of the game with its outcome.
+pub struct SelfPlayRecorder<Action> {
+    /// Samples of the game in progress, without outcomes.
+    pub pending: Vec<TrainingSample<Action>>,
+
+    /// Samples of finished games.
+    pub samples: Vec<TrainingSample<Action>>,
+}
+
+
+impl<Action: Clone> SelfPlayRecorder<Action> {
+    /// Creates a recorder without any samples.
+    pub fn new() -> Self {
+        return SelfPlayRecorder { pending: Vec::new(), samples: Vec::new() };
+    }
+
+    /// Records the root of a searched tree as a sample of the game in progress.
+    ///
+    /// The policy is the share of visits of every expanded child of the root. Unvisited
+    /// children, and actions which have not been expanded, have a probability of 0.
+    pub fn record<GameStateObj, Data>(&mut self, tree: &MCTSTree<Action, GameStateObj, Data>)
+    where
+        GameStateObj: GameState<Action> + Clone + fmt::Display
+    {
+        let root: NodeIndex = 0;
+        let policy: Vec<(Action, f32)> = tree.root_policy().into_iter().map(|(action, _, probability)| (action, probability)).collect();
+
+        let game_state = tree.game_state_at(root);
+        self.pending.push(TrainingSample {
+            state: game_state.to_string(),
+            side_to_move: game_state.side_to_move(),
+            policy,
+            outcome: None,
+        });
+    }
+
+    /// Ends the game in progress, labelling its samples with their outcome and moving them to `samples`.
+    pub fn finish(&mut self, result: &GameResult) {
+        for mut sample in self.pending.drain(..) {
+            sample.outcome = Some(outcome_for(result, sample.side_to_move));
+            self.samples.push(sample);
+        }
+    }
+
+    /// Writes the samples of finished games as newline delimited JSON, one sample per line.
+    ///
+    /// # Arguments
+    /// * `label` : Encodes an action as a string, see `TrainingSample::to_json_with`.
+    pub fn write_ndjson_with<W, F>(&self, writer: &mut W, label: F) -> io::Result<()>
+    where
+        W: io::Write,
+        F: Fn(&Action) -> String
+    {
+        for sample in &self.samples {
+            writeln!(writer, "{}", sample.to_json_with(&label))?;
+        }
+        return Ok(());
+    }
+
+    /// Writes the samples of finished games as newline delimited JSON,
+    /// labelling actions with their `Display` encoding.
+    pub fn write_ndjson<W: io::Write>(&self, writer: &mut W) -> io::Result<()>
+    where
+        Action: fmt::Display
+    {
+        return self.write_ndjson_with(writer, |action| action.to_string());
+    }
+
+    /// Writes the samples of finished games one after another, encoded with `Encode`.
+    /// `read_binary` reads them back.
+    pub fn write_binary<W: io::Write>(&self, writer: &mut W) -> io::Result<()>
+    where
+        Action: Encode
+    {
+        let mut out = Vec::new();
+        for sample in &self.samples {
+            sample.encode(&mut out);
+        }
+        return writer.write_all(&out);
+    }
+}
+
+impl<Action: Clone> Default for SelfPlayRecorder<Action> {
+    fn default() -> Self {
+        return SelfPlayRecorder::new();
+    }
+}
+
+
+/// Reads every sample written by `SelfPlayRecorder::write_binary`.
+///
+/// # Returns
+/// The samples, or a `DecodeError` if `bytes` holds an invalid or truncated sample.
+pub fn read_binary<Action: Decode>(bytes: &[u8]) -> Result<Vec<TrainingSample<Action>>, DecodeError> {
+    let mut input = bytes;
+    let mut samples = Vec::new();
+    while !input.is_empty() {
+        samples.push(TrainingSample::decode(&mut input)?);
+    }
+    return Ok(samples);
+}
+
+
+/// Defines unit tests for the recording of self-play games.
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mcts::TreeConfig;
+    use crate::tictactoe_env::TicTacToeState;
+
+    /// Plays a game of Tic-Tac-Toe against itself, recording every move.
+    fn self_play(recorder: &mut SelfPlayRecorder<u8>, seed: u64) -> GameResult {
+        let mut state = TicTacToeState::new();
+        while state.generate_legal_actions().len() > 0 {
+            let mut tree = MCTSTree::<u8, TicTacToeState>::from_state(state, TreeConfig { arena_capacity: 201, seed: Some(seed as u128), average_child_count: 9 });
+            tree.search(200);
+            recorder.record(&tree);
+            let best = tree.get_max_uct_child_at(0, Some(0.0)).expect("no legal moves");
+            state.apply_action_mut(&tree.arena.actions[best as usize].expect("no action"));
+        }
+        let result = state.result();
+        recorder.finish(&result);
+        return result;
+    }
+
+    /// Test if outcomes are given from the perspective of each player.
+    #[test]
+    fn test_outcome_for() {
+        assert_eq!(outcome_for(&GameResult::FirstPlayerWin, true), 1.0);
+        assert_eq!(outcome_for(&GameResult::FirstPlayerWin, false), 0.0);
+        assert_eq!(outcome_for(&GameResult::Draw, false), 0.5);
+        assert_eq!(outcome_for(&GameResult::Score(0.25), false), 0.75);
+        assert_eq!(outcome_for(&GameResult::Margin(-3.0), false), 1.0);
+    }
+
+    /// Test if a self-play game records a labelled sample for every move.
+    #[test]
+    fn test_record() {
+        let mut recorder = SelfPlayRecorder::new();
+        let result = self_play(&mut recorder, 1);
+        assert!(recorder.pending.is_empty());
+        let moves = recorder.samples.len();
+        assert!((5..=9).contains(&moves));
+
+        let first = &recorder.samples[0];
+        assert_eq!(first.state, ".........");
+        assert!(first.side_to_move);
+        assert_eq!(first.policy.len(), 9);
+        let total: f32 = first.policy.iter().map(|(_, probability)| probability).sum();
+        assert!((total - 1.0).abs() < 1e-5);
+        assert_eq!(first.outcome, Some(outcome_for(&result, true)));
+
+        // Players alternate, so their outcomes add up to 1.
+        let second = &recorder.samples[1];
+        assert!(!second.side_to_move);
+        assert_eq!(first.outcome.unwrap() + second.outcome.unwrap(), 1.0);
+    }
+
+    /// Test if samples are written as JSON lines and binary records.
+    #[test]
+    fn test_write() {
+        let mut recorder = SelfPlayRecorder::new();
+        self_play(&mut recorder, 2);
+        self_play(&mut recorder, 3);
+
+        let mut json = Vec::new();
+        recorder.write_ndjson(&mut json).unwrap();
+        let json = String::from_utf8(json).unwrap();
+        assert_eq!(json.lines().count(), recorder.samples.len());
+        assert!(json.starts_with("{\"state\":\".........\",\"side_to_move\":true,\"policy\":[{\"action\":\""));
+        assert!(json.lines().all(|line| line.ends_with('}') && !line.contains("null")));
+
+        let mut binary = Vec::new();
+        recorder.write_binary(&mut binary).unwrap();
+        assert_eq!(read_binary::<u8>(&binary).unwrap(), recorder.samples);
+        assert!(read_binary::<u8>(&binary[..binary.len() - 1]).is_err());
+    }
+}
@@ -0,0 +1,1287 @@
+//! Dependency-free serialization of trees, so long analyses can be saved to disk and resumed,
+//! or trees shipped between processes.
+//!
+//! Values are written with the `Encode` trait and read back with `Decode`, as little endian
+//! fixed width integers, with lengths written before sequences. Implementations are provided
+//! for the primitive types, options, tuples, vectors and ordered sets, and by every bundled environment
+//! for its actions and game states.
+
+use crate::arena::{MCTSArena, NodeIndex, NodeStats, NodeTopology};
+use crate::game_state_trait::{GameResult, GameState};
+use crate::mcts::{BackupPolicy, ExplorationSchedule, MCTSTree, Perspective, PlayerMode, SelectionPolicy, StateStorage, TreeConfig};
+use crate::rng::{RngState, SearchRng};
+use crate::termination::SearchBudget;
+use crate::float::Float;
+use alloc::collections::BTreeSet;
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec;
+use alloc::vec::Vec;
+use core::fmt;
+#[cfg(feature = "std")]
+use std::io;
+
+
+/// Bytes every tree encoded by `MCTSTree::to_bytes` starts with.
+pub const TREE_MAGIC: &[u8; 4] = b"MCTS";
+
+/// Bytes every tree encoded by `MCTSTree::to_compact_bytes` starts with.
+pub const COMPACT_TREE_MAGIC: &[u8; 4] = b"MCTC";
+
+/// Version of both tree encodings, written after their magic bytes.
+pub const TREE_FORMAT_VERSION: u32 = 1;
+
+
+/// Error produced when bytes can not be decoded into a value.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DecodeError(pub String);
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        return write!(f, "invalid encoding: {}", self.0);
+    }
+}
+
+impl core::error::Error for DecodeError {}
+
+#[cfg(feature = "std")]
+impl From<DecodeError> for io::Error {
+    fn from(error: DecodeError) -> Self {
+        return io::Error::new(io::ErrorKind::InvalidData, error);
+    }
+}
+
+
+/// Writes a value as bytes, which `Decode` reads back.
+pub trait Encode {
+    /// Appends the encoding of the value to `out`.
+    fn encode(&self, out: &mut Vec<u8>);
+}
+
+
+/// Reads a value written by `Encode`.
+pub trait Decode: Sized {
+    /// Reads a value from the front of `input`, advancing it past the bytes read.
+    ///
+    /// # Returns
+    /// The value, or a `DecodeError` if `input` is too short or holds an invalid value.
+    fn decode(input: &mut &[u8]) -> Result<Self, DecodeError>;
+}
+
+
+/// Splits the first `len` bytes from the front of `input`.
+fn take<'a>(input: &mut &'a [u8], len: usize) -> Result<&'a [u8], DecodeError> {
+    if input.len() < len {
+        return Err(DecodeError(format!("expected {} more bytes, found {}", len, input.len())));
+    }
+    let (taken, rest) = input.split_at(len);
+    *input = rest;
+    return Ok(taken);
+}
+
+
+/// Implements `Encode` and `Decode` for primitive numbers as little endian bytes.
+macro_rules! impl_number {
+    ($($number:ty),*) => {$(
+        impl Encode for $number {
+            fn encode(&self, out: &mut Vec<u8>) {
+                out.extend_from_slice(&self.to_le_bytes());
+            }
+        }
+
+        impl Decode for $number {
+            fn decode(input: &mut &[u8]) -> Result<Self, DecodeError> {
+                let bytes = take(input, core::mem::size_of::<$number>())?;
+                return Ok(<$number>::from_le_bytes(bytes.try_into().expect("length checked")));
+            }
+        }
+    )*};
+}
+
+impl_number!(u8, u16, u32, u64, u128, f32, f64);
+
+
+/// Sizes are written as 64 bits, so encodings do not depend on the platform.
+impl Encode for usize {
+    fn encode(&self, out: &mut Vec<u8>) {
+        (*self as u64).encode(out);
+    }
+}
+
+impl Decode for usize {
+    fn decode(input: &mut &[u8]) -> Result<Self, DecodeError> {
+        let value = u64::decode(input)?;
+        return usize::try_from(value).map_err(|_| DecodeError(format!("size {} does not fit in usize", value)));
+    }
+}
+
+impl Encode for bool {
+    fn encode(&self, out: &mut Vec<u8>) {
+        out.push(*self as u8);
+    }
+}
+
+impl Decode for bool {
+    fn decode(input: &mut &[u8]) -> Result<Self, DecodeError> {
+        match u8::decode(input)? {
+            0 => return Ok(false),
+            1 => return Ok(true),
+            byte => return Err(DecodeError(format!("invalid bool {}", byte))),
+        }
+    }
+}
+
+impl<T: Encode> Encode for Option<T> {
+    fn encode(&self, out: &mut Vec<u8>) {
+        match self {
+            Some(value) => {
+                out.push(1);
+                value.encode(out);
+            }
+            None => out.push(0),
+        }
+    }
+}
+
+impl<T: Decode> Decode for Option<T> {
+    fn decode(input: &mut &[u8]) -> Result<Self, DecodeError> {
+        if bool::decode(input)? {
+            return Ok(Some(T::decode(input)?));
+        }
+        return Ok(None);
+    }
+}
+
+impl<A: Encode, B: Encode> Encode for (A, B) {
+    fn encode(&self, out: &mut Vec<u8>) {
+        self.0.encode(out);
+        self.1.encode(out);
+    }
+}
+
+impl<A: Decode, B: Decode> Decode for (A, B) {
+    fn decode(input: &mut &[u8]) -> Result<Self, DecodeError> {
+        return Ok((A::decode(input)?, B::decode(input)?));
+    }
+}
+
+impl<T: Encode> Encode for Vec<T> {
+    fn encode(&self, out: &mut Vec<u8>) {
+        self.len().encode(out);
+        for value in self {
+            value.encode(out);
+        }
+    }
+}
+
+impl<T: Decode> Decode for Vec<T> {
+    fn decode(input: &mut &[u8]) -> Result<Self, DecodeError> {
+        let len = usize::decode(input)?;
+        // The length is not trusted for the allocation, as every value takes at least a byte.
+        let mut values = Vec::with_capacity(len.min(input.len()));
+        for _i in 0..len {
+            values.push(T::decode(input)?);
+        }
+        return Ok(values);
+    }
+}
+
+/// Sets are written like vectors, in ascending order.
+impl<T: Encode> Encode for BTreeSet<T> {
+    fn encode(&self, out: &mut Vec<u8>) {
+        self.len().encode(out);
+        for value in self {
+            value.encode(out);
+        }
+    }
+}
+
+impl<T: Decode + Ord> Decode for BTreeSet<T> {
+    fn decode(input: &mut &[u8]) -> Result<Self, DecodeError> {
+        return Ok(Vec::decode(input)?.into_iter().collect());
+    }
+}
+
+impl Encode for String {
+    fn encode(&self, out: &mut Vec<u8>) {
+        self.len().encode(out);
+        out.extend_from_slice(self.as_bytes());
+    }
+}
+
+impl Decode for String {
+    fn decode(input: &mut &[u8]) -> Result<Self, DecodeError> {
+        let len = usize::decode(input)?;
+        let bytes = take(input, len)?;
+        return String::from_utf8(bytes.to_vec()).map_err(|_| DecodeError("invalid utf-8 string".to_string()));
+    }
+}
+
+/// Results are encoded as a tag byte, followed by the value of scores and margins.
+impl Encode for GameResult {
+    fn encode(&self, out: &mut Vec<u8>) {
+        match self {
+            GameResult::FirstPlayerWin => out.push(0),
+            GameResult::SecondPlayerWin => out.push(1),
+            GameResult::Draw => out.push(2),
+            GameResult::Score(score) => {
+                out.push(3);
+                score.encode(out);
+            }
+            GameResult::Margin(margin) => {
+                out.push(4);
+                margin.encode(out);
+            }
+        }
+    }
+}
+
+impl Decode for GameResult {
+    fn decode(input: &mut &[u8]) -> Result<Self, DecodeError> {
+        match u8::decode(input)? {
+            0 => return Ok(GameResult::FirstPlayerWin),
+            1 => return Ok(GameResult::SecondPlayerWin),
+            2 => return Ok(GameResult::Draw),
+            3 => return Ok(GameResult::Score(f32::decode(input)?)),
+            4 => return Ok(GameResult::Margin(f32::decode(input)?)),
+            tag => return Err(DecodeError(format!("invalid game result tag {}", tag))),
+        }
+    }
+}
+
+/// Perspectives are encoded as a tag byte.
+impl Encode for Perspective {
+    fn encode(&self, out: &mut Vec<u8>) {
+        match self {
+            Perspective::Mover => out.push(0),
+            Perspective::Alternating => out.push(1),
+        }
+    }
+}
+
+impl Decode for Perspective {
+    fn decode(input: &mut &[u8]) -> Result<Self, DecodeError> {
+        match u8::decode(input)? {
+            0 => return Ok(Perspective::Mover),
+            1 => return Ok(Perspective::Alternating),
+            tag => return Err(DecodeError(format!("invalid perspective tag {}", tag))),
+        }
+    }
+}
+
+/// Backup policies are encoded as a tag byte.
+impl Encode for BackupPolicy {
+    fn encode(&self, out: &mut Vec<u8>) {
+        match self {
+            BackupPolicy::WinRate => out.push(0),
+            BackupPolicy::MeanReward => out.push(1),
+            BackupPolicy::Solver => out.push(2),
+        }
+    }
+}
+
+impl Decode for BackupPolicy {
+    fn decode(input: &mut &[u8]) -> Result<Self, DecodeError> {
+        match u8::decode(input)? {
+            0 => return Ok(BackupPolicy::WinRate),
+            1 => return Ok(BackupPolicy::MeanReward),
+            2 => return Ok(BackupPolicy::Solver),
+            tag => return Err(DecodeError(format!("invalid backup policy tag {}", tag))),
+        }
+    }
+}
+
+/// Schedules are encoded as a tag byte, followed by their parameters.
+impl Encode for ExplorationSchedule {
+    fn encode(&self, out: &mut Vec<u8>) {
+        match self {
+            ExplorationSchedule::Constant(factor) => {
+                out.push(0);
+                factor.encode(out);
+            }
+            ExplorationSchedule::Linear { start, end, visits } => {
+                out.push(1);
+                start.encode(out);
+                end.encode(out);
+                visits.encode(out);
+            }
+            ExplorationSchedule::Logarithmic { init, base } => {
+                out.push(2);
+                init.encode(out);
+                base.encode(out);
+            }
+        }
+    }
+}
+
+impl Decode for ExplorationSchedule {
+    fn decode(input: &mut &[u8]) -> Result<Self, DecodeError> {
+        match u8::decode(input)? {
+            0 => return Ok(ExplorationSchedule::Constant(f32::decode(input)?)),
+            1 => return Ok(ExplorationSchedule::Linear { start: f32::decode(input)?, end: f32::decode(input)?, visits: u64::decode(input)? }),
+            2 => return Ok(ExplorationSchedule::Logarithmic { init: f32::decode(input)?, base: f32::decode(input)? }),
+            tag => return Err(DecodeError(format!("invalid exploration schedule tag {}", tag))),
+        }
+    }
+}
+
+impl Encode for SearchBudget {
+    fn encode(&self, out: &mut Vec<u8>) {
+        self.max_nodes.encode(out);
+        self.max_memory_bytes.encode(out);
+    }
+}
+
+impl Decode for SearchBudget {
+    fn decode(input: &mut &[u8]) -> Result<Self, DecodeError> {
+        return Ok(SearchBudget { max_nodes: Option::decode(input)?, max_memory_bytes: Option::decode(input)? });
+    }
+}
+
+/// Generator states are encoded as a tag byte, followed by the words of the state.
+impl Encode for RngState {
+    fn encode(&self, out: &mut Vec<u8>) {
+        match self {
+            RngState::Xoshiro256PlusPlus(state) => {
+                out.push(0);
+                state.iter().for_each(|word| word.encode(out));
+            }
+            RngState::Xorshift128(state) => {
+                out.push(1);
+                state.iter().for_each(|word| word.encode(out));
+            }
+            RngState::Seed(seed) => {
+                out.push(2);
+                seed.encode(out);
+            }
+        }
+    }
+}
+
+impl Decode for RngState {
+    fn decode(input: &mut &[u8]) -> Result<Self, DecodeError> {
+        match u8::decode(input)? {
+            0 => return Ok(RngState::Xoshiro256PlusPlus([u64::decode(input)?, u64::decode(input)?, u64::decode(input)?, u64::decode(input)?])),
+            1 => return Ok(RngState::Xorshift128([u64::decode(input)?, u64::decode(input)?])),
+            2 => return Ok(RngState::Seed(u128::decode(input)?)),
+            tag => return Err(DecodeError(format!("invalid generator state tag {}", tag))),
+        }
+    }
+}
+
+
+/// Decodes a game state from its string encoding, for game states whose
+/// string encoding holds every part of the state.
+pub fn decode_from_str<Action, GameStateObj>(input: &mut &[u8]) -> Result<GameStateObj, DecodeError>
+where
+    GameStateObj: GameState<Action>,
+    GameStateObj::ParseError: fmt::Display
+{
+    let encoded = String::decode(input)?;
+    return GameStateObj::try_from_str(&encoded).map_err(|error| DecodeError(error.to_string()));
+}
+
+
+/// Appends `value` to `out` as a LEB128 varint, 7 bits per byte from the lowest,
+/// with the high bit of every byte but the last set.
+pub fn encode_varint(mut value: u64, out: &mut Vec<u8>) {
+    while value >= 0x80 {
+        out.push(value as u8 | 0x80);
+        value >>= 7;
+    }
+    out.push(value as u8);
+}
+
+/// Reads a varint written by `encode_varint` from the front of `input`.
+pub fn decode_varint(input: &mut &[u8]) -> Result<u64, DecodeError> {
+    let mut value = 0;
+    let mut shift = 0;
+    loop {
+        let byte = u8::decode(input)?;
+        if shift >= 64 {
+            return Err(DecodeError("varint longer than 64 bits".to_string()));
+        }
+        value |= ((byte & 0x7F) as u64) << shift;
+        if byte & 0x80 == 0 {
+            return Ok(value);
+        }
+        shift += 7;
+    }
+}
+
+/// Maps signed values to unsigned ones with small magnitudes staying small, as 0, -1, 1, -2...
+fn zigzag(value: i64) -> u64 {
+    return ((value << 1) ^ (value >> 63)) as u64;
+}
+
+/// Reverses `zigzag`.
+fn unzigzag(value: u64) -> i64 {
+    return (value >> 1) as i64 ^ -((value & 1) as i64);
+}
+
+
+/// Bit mask of the actions of `legal` which are in `unexpanded`, one bit per action from the lowest.
+///
+/// # Returns
+/// The mask, or None if `unexpanded` is not a subsequence of `legal`, in which case it can not be
+/// re-derived from the mask.
+fn unexpanded_mask<Action: PartialEq>(legal: &[Action], unexpanded: &[&Action]) -> Option<Vec<u8>> {
+    let mut mask = vec![0; legal.len().div_ceil(8)];
+    let mut remaining = unexpanded.iter().peekable();
+    for (i, action) in legal.iter().enumerate() {
+        if remaining.peek().is_some_and(|next| **next == action) {
+            mask[i / 8] |= 1 << (i % 8);
+            remaining.next();
+        }
+    }
+    return if remaining.peek().is_none() { Some(mask) } else { None };
+}
+
+
+/// Flags of a node in the compact tree format.
+const FLAG_SIDE_TO_MOVE: u8 = 1;
+const FLAG_CHANCE: u8 = 1 << 1;
+const FLAG_STORED: u8 = 1 << 2;
+const FLAG_COMPLETE: u8 = 1 << 3;
+/// Wins, draws and score are packed into a single varint, see `pack_outcomes`.
+const FLAG_PACKED: u8 = 1 << 4;
+/// A byte of extra flags follows, for nodes which differ from the common case.
+const FLAG_EXTRA: u8 = 1 << 5;
+
+/// The top two bits of the flags give how the unexpanded actions are written.
+const UNEXPANDED_SHIFT: u8 = 6;
+/// No unexpanded actions.
+const UNEXPANDED_NONE: u8 = 0;
+/// Every legal action is unexpanded.
+const UNEXPANDED_ALL: u8 = 1;
+/// A bit mask over the legal actions, in generation order, of the unexpanded ones.
+const UNEXPANDED_MASK: u8 = 2;
+/// A list of the unexpanded actions.
+const UNEXPANDED_LIST: u8 = 3;
+
+/// Extra flags of a node. Without them the probability is 1, the score is a number of halves
+/// away from the wins, and the expanded children are the nodes with the node as their parent,
+/// in index order.
+const EXTRA_PROBABILITY: u8 = 1;
+/// The score is written as its difference from the wins as a float.
+const EXTRA_SCORE_FLOAT: u8 = 1 << 1;
+/// The expanded children are written, as differences from the previous child starting from the node.
+const EXTRA_CHILDREN: u8 = 1 << 2;
+
+/// Simulations below which outcomes are packed.
+const PACKED_SIMS_LIMIT: u64 = 1 << 16;
+
+
+/// Packs the outcomes of a node with `sims` simulations into one value, which fits in a single
+/// varint byte for nodes with a few simulations, as most nodes of large trees have.
+///
+/// Two forms are packed: a score equal to the wins, with any number of draws scoring nothing,
+/// and a score a number of halves away from the wins without draws, such as from averaged
+/// rollouts. The value holds the wins and, respectively, the draws or the number of halves.
+/// Draws are worth `Searcher::draw_score`, so nodes with draws are only packed under a draw
+/// score of 0. Under the default of 0.5, or any other, their score is written as a float and
+/// they lose the compaction.
+///
+/// # Returns
+/// The packed value, or None if the outcomes can not be packed.
+fn pack_outcomes(stats: &NodeStats) -> Option<u64> {
+    let halves = (stats.score - stats.wins as f64) * 2.0;
+    if stats.sims >= PACKED_SIMS_LIMIT || stats.wins > stats.sims || stats.draws > stats.sims {
+        return None;
+    }
+    let (other, halved) = if halves == 0.0 {
+        (stats.draws, false)
+    }
+    else if stats.draws == 0 && halves.fract() == 0.0 && halves > 0.0 && halves <= stats.sims as f64 {
+        (halves as u64, true)
+    }
+    else {
+        return None;
+    };
+    let packed = other * (stats.sims + 1) + stats.wins;
+    return Some(packed << 1 | halved as u64);
+}
+
+/// Reverses `pack_outcomes` for a node with `sims` simulations.
+///
+/// # Returns
+/// The wins, draws and score, or None if `packed` is not valid.
+fn unpack_outcomes(sims: u64, packed: u64) -> Option<(u64, u64, f64)> {
+    let halved = packed & 1 == 1;
+    let wins = (packed >> 1) % sims.checked_add(1)?;
+    let other = (packed >> 1) / (sims + 1);
+    if other > sims {
+        return None;
+    }
+    if halved {
+        return Some((wins, 0, wins as f64 + other as f64 / 2.0));
+    }
+    return Some((wins, other, wins as f64));
+}
+
+
+/// A node as read from either tree format.
+struct NodeRecord<Action> {
+    parent: Option<usize>,
+    action: Option<Action>,
+    side_to_move: bool,
+    is_chance: bool,
+    probability: f32,
+    stats: NodeStats,
+    stored: bool,
+    complete: bool,
+
+    /// None if the children are the nodes with this node as their parent.
+    expanded: Option<Vec<usize>>,
+    unexpanded: Unexpanded<Action>,
+}
+
+
+/// Unexpanded actions of a node as read from either tree format.
+enum Unexpanded<Action> {
+    /// The actions, as written.
+    List(Vec<Action>),
+
+    /// Every legal action of the node.
+    All,
+
+    /// The legal actions of the node selected by a bit mask, one bit per action from the lowest.
+    Mask(Vec<u8>),
+}
+
+
+/// Saving and restoring of trees.
+///
+/// Trees are written in one of two formats, told apart by their magic bytes and sharing a header
+/// holding the format version, the configuration of the tree and its search settings, its random
+/// generator and its root game state:
+/// * `to_bytes` encodes every value of every node at its full width.
+/// * `to_compact_bytes` is built for trees of millions of nodes.
+///
+/// `from_bytes` reads both formats.
+impl<Action, GameStateObj, Data> MCTSTree<Action, GameStateObj, Data>
+where
+    Action: Encode + Decode + PartialEq,
+    GameStateObj: GameState<Action> + Clone + Encode + Decode
+{
+    /// Encodes the tree, with its statistics, configuration and random generator,
+    /// in the fixed width format.
+    ///
+    /// Only the root game state is written, the states of other nodes are re-derived from
+    /// their actions when the tree is restored. The rollout policy can not be encoded,
+    /// and restored trees use `RandomRollout` until another policy is set. User data of
+    /// the nodes is not written either, so restored nodes have none.
+    ///
+    /// The state of the random generator is written, see `SearchRng::state`, so searches
+    /// continued from this tree and the restored one make the same random choices.
+    ///
+    /// # Returns
+    /// The encoded tree, which `from_bytes` decodes.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut out = self.encode_header(TREE_MAGIC);
+        self.arena.len().encode(&mut out);
+        for node in 0..self.arena.len() {
+            let topology = &self.arena.topology[node];
+            let stats = &self.arena.stats[node];
+            self.arena.actions[node].encode(&mut out);
+            topology.parent.map(|parent| parent as usize).encode(&mut out);
+            topology.side_to_move.encode(&mut out);
+            topology.is_chance.encode(&mut out);
+            topology.probability.encode(&mut out);
+            stats.wins.encode(&mut out);
+            stats.draws.encode(&mut out);
+            stats.sims.encode(&mut out);
+            stats.score.encode(&mut out);
+            self.arena.state_slots[node].is_some().encode(&mut out);
+
+            // Children are written as the expanded node indices followed by the unexpanded actions.
+            topology.children.complete.encode(&mut out);
+            let expanded: Vec<usize> = self.arena.expanded(node as NodeIndex).map(|child| child as usize).collect();
+            expanded.encode(&mut out);
+            self.arena.unexpanded_count(node as NodeIndex).encode(&mut out);
+            for action in self.arena.unexpanded(node as NodeIndex) {
+                action.encode(&mut out);
+            }
+        }
+        return out;
+    }
+
+    /// Encodes the tree like `to_bytes`, in the compact format.
+    ///
+    /// Parents are written as varint differences from the node, and the expanded children are
+    /// left out, as they are the nodes with the node as their parent. Statistics are written as
+    /// varints, with the wins, draws and score packed together while they are small, see
+    /// `pack_outcomes`. Unexpanded actions are left out
+    /// whenever they are the legal actions of the node without its expanded ones, in
+    /// generation order, and re-derived when the tree is restored.
+    ///
+    /// Re-deriving unexpanded actions requires the game state of every node, which is
+    /// replayed from the nearest stored ancestor for trees that do not store every state.
+    ///
+    /// # Returns
+    /// The encoded tree, which `from_bytes` decodes.
+    pub fn to_compact_bytes(&self) -> Vec<u8> {
+        let mut out = self.encode_header(COMPACT_TREE_MAGIC);
+        encode_varint(self.arena.len() as u64, &mut out);
+
+        // Children are pushed after their parents, so listing the nodes by parent in index
+        // order gives the expanded children of every node, unless they were reordered.
+        let mut derived_children = vec![Vec::new(); self.arena.len()];
+        for node in 1..self.arena.len() {
+            if let Some(parent) = self.arena.topology[node].parent {
+                derived_children[parent as usize].push(node as NodeIndex);
+            }
+        }
+
+        for (node, derived) in derived_children.iter().enumerate() {
+            let topology = &self.arena.topology[node];
+            let stats = &self.arena.stats[node];
+            let unexpanded: Vec<&Action> = self.arena.unexpanded(node as NodeIndex).collect();
+            let legal = if unexpanded.is_empty() { Vec::new() } else { self.game_state_at(node as NodeIndex).generate_legal_actions() };
+            let mask = unexpanded_mask(&legal, &unexpanded);
+            let unexpanded_mode = if unexpanded.is_empty() {
+                UNEXPANDED_NONE
+            }
+            else if unexpanded.len() == legal.len() && mask.is_some() {
+                UNEXPANDED_ALL
+            }
+            else if mask.is_some() {
+                UNEXPANDED_MASK
+            }
+            else {
+                UNEXPANDED_LIST
+            };
+
+            // Rewards of wins, draws and losses are whole or half points, so the score is
+            // usually a small number of halves away from the wins.
+            let packed = pack_outcomes(stats);
+            let halves = (stats.score - stats.wins as f64) * 2.0;
+            let halves = if Float::fract(halves) == 0.0 && Float::abs(halves) < (1u64 << 53) as f64 { Some(halves as i64) } else { None };
+            let mut extra = 0;
+            if packed.is_none() && halves.is_none() {
+                extra |= EXTRA_SCORE_FLOAT;
+            }
+            if topology.probability != 1.0 {
+                extra |= EXTRA_PROBABILITY;
+            }
+            let explicit_children = !self.arena.expanded(node as NodeIndex).eq(derived.iter().copied());
+            if explicit_children {
+                extra |= EXTRA_CHILDREN;
+            }
+
+            let mut flags = unexpanded_mode << UNEXPANDED_SHIFT;
+            for (flag, set) in [
+                (FLAG_SIDE_TO_MOVE, topology.side_to_move),
+                (FLAG_CHANCE, topology.is_chance),
+                (FLAG_STORED, self.arena.state_slots[node].is_some()),
+                (FLAG_COMPLETE, topology.children.complete),
+                (FLAG_PACKED, packed.is_some()),
+                (FLAG_EXTRA, extra != 0),
+            ] {
+                if set {
+                    flags |= flag;
+                }
+            }
+            out.push(flags);
+            if extra != 0 {
+                out.push(extra);
+            }
+
+            // The root is the only node without a parent or an action.
+            if let Some(parent) = topology.parent {
+                encode_varint((node - parent as usize) as u64, &mut out);
+                self.arena.actions[node].as_ref().expect("no action").encode(&mut out);
+            }
+            if extra & EXTRA_PROBABILITY != 0 {
+                topology.probability.encode(&mut out);
+            }
+            encode_varint(stats.sims, &mut out);
+            match (packed, halves) {
+                (Some(packed), _) => encode_varint(packed, &mut out),
+                (None, Some(halves)) => {
+                    encode_varint(stats.wins, &mut out);
+                    encode_varint(stats.draws, &mut out);
+                    encode_varint(zigzag(halves), &mut out);
+                }
+                (None, None) => {
+                    encode_varint(stats.wins, &mut out);
+                    encode_varint(stats.draws, &mut out);
+                    (stats.score - stats.wins as f64).encode(&mut out);
+                }
+            }
+
+            if explicit_children {
+                encode_varint(self.arena.expanded_count(node as NodeIndex) as u64, &mut out);
+                let mut previous = node as i64;
+                for child in self.arena.expanded(node as NodeIndex) {
+                    encode_varint(zigzag(child as i64 - previous), &mut out);
+                    previous = child as i64;
+                }
+            }
+            match unexpanded_mode {
+                UNEXPANDED_MASK => {
+                    let mask = mask.expect("no mask");
+                    encode_varint(mask.len() as u64, &mut out);
+                    out.extend(mask);
+                }
+                UNEXPANDED_LIST => {
+                    encode_varint(unexpanded.len() as u64, &mut out);
+                    for action in unexpanded {
+                        action.encode(&mut out);
+                    }
+                }
+                _ => {}
+            }
+        }
+        return out;
+    }
+
+    /// Starts an encoding in the format of `magic` with the header shared by both formats.
+    fn encode_header(&self, magic: &[u8; 4]) -> Vec<u8> {
+        let mut out = Vec::new();
+        out.extend_from_slice(magic);
+        TREE_FORMAT_VERSION.encode(&mut out);
+
+        self.searcher.random_generator.state().encode(&mut out);
+
+        // Configuration of the tree.
+        self.average_child_count.encode(&mut out);
+        (self.state_storage == StateStorage::ActionsOnly).encode(&mut out);
+        (self.searcher.player_mode == PlayerMode::SinglePlayer).encode(&mut out);
+        self.searcher.margin_weight.encode(&mut out);
+        (self.searcher.selection_policy == SelectionPolicy::Puct).encode(&mut out);
+        self.searcher.rollout_depth_limit.encode(&mut out);
+        self.searcher.expansion_threshold.encode(&mut out);
+
+        self.arena.generation.encode(&mut out);
+        self.game_state_at(0).encode(&mut out);
+        self.encode_settings(&mut out);
+        return out;
+    }
+
+    /// Writes the search settings of the header.
+    fn encode_settings(&self, out: &mut Vec<u8>) {
+        let searcher = &self.searcher;
+        searcher.perspective.encode(out);
+        searcher.draw_score.encode(out);
+        searcher.discount.encode(out);
+        searcher.max_backup_weight.encode(out);
+        searcher.normalize_rewards.encode(out);
+        searcher.backup_policy.encode(out);
+        searcher.first_play_urgency.encode(out);
+        searcher.random_tie_breaking.encode(out);
+        searcher.rollout_move_cap.encode(out);
+        searcher.simulations_per_expansion.encode(out);
+        searcher.exploration_schedule.encode(out);
+        searcher.early_termination.encode(out);
+        self.reward_bounds.encode(out);
+        self.budget.encode(out);
+    }
+
+    /// Reads the search settings written by `encode_settings`.
+    fn decode_settings(&mut self, input: &mut &[u8]) -> Result<(), DecodeError> {
+        let searcher = &mut self.searcher;
+        searcher.perspective = Perspective::decode(input)?;
+        searcher.draw_score = f64::decode(input)?;
+        searcher.discount = f64::decode(input)?;
+        searcher.max_backup_weight = f32::decode(input)?;
+        searcher.normalize_rewards = bool::decode(input)?;
+        searcher.backup_policy = BackupPolicy::decode(input)?;
+        searcher.first_play_urgency = Option::decode(input)?;
+        searcher.random_tie_breaking = bool::decode(input)?;
+        searcher.rollout_move_cap = Option::decode(input)?;
+        searcher.simulations_per_expansion = u32::decode(input)?;
+        searcher.exploration_schedule = ExplorationSchedule::decode(input)?;
+        searcher.early_termination = bool::decode(input)?;
+        self.reward_bounds = Option::decode(input)?;
+        self.budget = SearchBudget::decode(input)?;
+        return Ok(());
+    }
+
+    /// Decodes a tree written by `to_bytes` or `to_compact_bytes`.
+    ///
+    /// Nodes which stored their game state when the tree was encoded store it again,
+    /// re-derived by applying their action to the state of their parent.
+    ///
+    /// # Returns
+    /// The tree, or a `DecodeError` if `bytes` is not a valid tree encoding.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, DecodeError> {
+        let mut input = bytes;
+        let compact = match take(&mut input, TREE_MAGIC.len())? {
+            magic if magic == TREE_MAGIC => false,
+            magic if magic == COMPACT_TREE_MAGIC => true,
+            _ => return Err(DecodeError("missing tree header".to_string())),
+        };
+        let version = u32::decode(&mut input)?;
+        if version != TREE_FORMAT_VERSION {
+            return Err(DecodeError(format!("unsupported tree format version {}", version)));
+        }
+        let rng_state = RngState::decode(&mut input)?;
+
+        let average_child_count = usize::decode(&mut input)?;
+        let state_storage = if bool::decode(&mut input)? { StateStorage::ActionsOnly } else { StateStorage::Full };
+        let player_mode = if bool::decode(&mut input)? { PlayerMode::SinglePlayer } else { PlayerMode::TwoPlayer };
+        let margin_weight = f64::decode(&mut input)?;
+        let selection_policy = if bool::decode(&mut input)? { SelectionPolicy::Puct } else { SelectionPolicy::Uct };
+        let rollout_depth_limit = Option::<u32>::decode(&mut input)?;
+        let expansion_threshold = u32::decode(&mut input)?;
+
+        let generation = u32::decode(&mut input)?;
+        let root_game_state = GameStateObj::decode(&mut input)?;
+
+        let mut tree = Self::from_state(root_game_state.clone(), TreeConfig { arena_capacity: 0, seed: None, average_child_count });
+        tree.decode_settings(&mut input)?;
+        let len = if compact { decode_varint(&mut input)? as usize } else { usize::decode(&mut input)? };
+        tree.searcher.random_generator = SearchRng::from_state(rng_state);
+        tree.state_storage = state_storage;
+        tree.searcher.player_mode = player_mode;
+        tree.searcher.margin_weight = margin_weight;
+        tree.searcher.selection_policy = selection_policy;
+        tree.searcher.rollout_depth_limit = rollout_depth_limit;
+        tree.searcher.expansion_threshold = expansion_threshold;
+        tree.arena = MCTSArena::with_capacity(len.min(input.len()), 0);
+        tree.arena.generation = generation;
+
+        if len == 0 {
+            return Err(DecodeError("tree without a root".to_string()));
+        }
+        let mut records = Vec::with_capacity(len.min(input.len()));
+        for node in 0..len {
+            let record = if compact { Self::decode_compact_node(&mut input, node)? } else { Self::decode_node(&mut input)? };
+
+            // Parents precede their children, so the state of the parent can always be derived.
+            if (node == 0) != record.parent.is_none() || record.parent.is_some_and(|parent| parent >= node) {
+                return Err(DecodeError(format!("node {} has an invalid parent", node)));
+            }
+            if record.expanded.as_ref().is_some_and(|expanded| expanded.iter().any(|child| *child <= node || *child >= len)) {
+                return Err(DecodeError(format!("node {} has an invalid child", node)));
+            }
+            if record.parent.is_some() && record.action.is_none() {
+                return Err(DecodeError(format!("node {} has no action", node)));
+            }
+            records.push(record);
+        }
+        let mut derived_children = vec![Vec::new(); len];
+        for (node, record) in records.iter().enumerate() {
+            if let Some(parent) = record.parent {
+                derived_children[parent].push(node);
+            }
+        }
+
+        let mut root_game_state = Some(root_game_state);
+        for (node, (record, derived)) in records.into_iter().zip(derived_children).enumerate() {
+            let needs_state = record.stored || matches!(record.unexpanded, Unexpanded::All | Unexpanded::Mask(_));
+            let game_state = match (record.parent, &record.action) {
+                (None, _) => root_game_state.take(),
+                (Some(parent), Some(action)) if needs_state => Some(tree.game_state_at(parent as NodeIndex).apply_action(action)),
+                _ => None,
+            };
+            let unexpanded = match record.unexpanded {
+                Unexpanded::List(unexpanded) => unexpanded,
+                Unexpanded::All => game_state.as_ref().expect("no state").generate_legal_actions(),
+                Unexpanded::Mask(mask) => {
+                    let legal = game_state.as_ref().expect("no state").generate_legal_actions();
+                    if mask.len() != legal.len().div_ceil(8) {
+                        return Err(DecodeError(format!("node {} has an invalid action mask", node)));
+                    }
+                    legal.into_iter().enumerate().filter(|(i, _)| mask[i / 8] & (1 << (i % 8)) != 0).map(|(_, action)| action).collect()
+                }
+            };
+
+            let expanded = record.expanded.unwrap_or(derived);
+            let children = tree.arena.allocate_children(expanded.into_iter().map(|child| child as NodeIndex), unexpanded);
+            let topology = NodeTopology {
+                parent: record.parent.map(|parent| parent as NodeIndex),
+                side_to_move: record.side_to_move,
+                is_chance: record.is_chance,
+                probability: record.probability,
+                children,
+            };
+            let game_state = if record.stored { game_state } else { None };
+            let pushed = tree.arena.push_parts(record.stats, topology, record.action, game_state, None);
+            tree.arena.topology[pushed as usize].children.complete = record.complete;
+        }
+        if !input.is_empty() {
+            return Err(DecodeError(format!("{} trailing bytes", input.len())));
+        }
+        return Ok(tree);
+    }
+
+    /// Reads a node of the fixed width format.
+    fn decode_node(input: &mut &[u8]) -> Result<NodeRecord<Action>, DecodeError> {
+        let action = Option::<Action>::decode(input)?;
+        let parent = Option::<usize>::decode(input)?;
+        let side_to_move = bool::decode(input)?;
+        let is_chance = bool::decode(input)?;
+        let probability = f32::decode(input)?;
+        let stats = NodeStats {
+            wins: u64::decode(input)?,
+            draws: u64::decode(input)?,
+            sims: u64::decode(input)?,
+            score: f64::decode(input)?,
+        };
+        let stored = bool::decode(input)?;
+        let complete = bool::decode(input)?;
+        let expanded = Vec::<usize>::decode(input)?;
+        let unexpanded_count = usize::decode(input)?;
+        let mut unexpanded = Vec::with_capacity(unexpanded_count.min(input.len()));
+        for _i in 0..unexpanded_count {
+            unexpanded.push(Action::decode(input)?);
+        }
+        return Ok(NodeRecord {
+            parent, action, side_to_move, is_chance, probability, stats, stored, complete,
+            expanded: Some(expanded),
+            unexpanded: Unexpanded::List(unexpanded),
+        });
+    }
+
+    /// Reads node `node` of the compact format.
+    fn decode_compact_node(input: &mut &[u8], node: usize) -> Result<NodeRecord<Action>, DecodeError> {
+        let flags = u8::decode(input)?;
+        let extra = if flags & FLAG_EXTRA != 0 { u8::decode(input)? } else { 0 };
+        let (parent, action) = if node == 0 {
+            (None, None)
+        }
+        else {
+            let distance = decode_varint(input)? as usize;
+            (Some(node.wrapping_sub(distance)), Some(Action::decode(input)?))
+        };
+        let probability = if extra & EXTRA_PROBABILITY != 0 { f32::decode(input)? } else { 1.0 };
+        let sims = decode_varint(input)?;
+        let (wins, draws, score) = if flags & FLAG_PACKED != 0 {
+            let packed = decode_varint(input)?;
+            unpack_outcomes(sims, packed).ok_or_else(|| DecodeError(format!("node {} has invalid outcomes", node)))?
+        }
+        else {
+            let wins = decode_varint(input)?;
+            let draws = decode_varint(input)?;
+            let score = if extra & EXTRA_SCORE_FLOAT != 0 {
+                wins as f64 + f64::decode(input)?
+            }
+            else {
+                wins as f64 + unzigzag(decode_varint(input)?) as f64 / 2.0
+            };
+            (wins, draws, score)
+        };
+
+        let expanded = if extra & EXTRA_CHILDREN != 0 {
+            let expanded_count = decode_varint(input)? as usize;
+            let mut expanded = Vec::with_capacity(expanded_count.min(input.len()));
+            let mut previous = node as i64;
+            for _i in 0..expanded_count {
+                previous = previous.wrapping_add(unzigzag(decode_varint(input)?));
+                expanded.push(previous.max(0) as usize);
+            }
+            Some(expanded)
+        }
+        else {
+            None
+        };
+        let unexpanded = match flags >> UNEXPANDED_SHIFT {
+            UNEXPANDED_NONE => Unexpanded::List(Vec::new()),
+            UNEXPANDED_ALL => Unexpanded::All,
+            UNEXPANDED_MASK => {
+                let mask_len = decode_varint(input)? as usize;
+                Unexpanded::Mask(take(input, mask_len)?.to_vec())
+            }
+            _ => {
+                let unexpanded_count = decode_varint(input)? as usize;
+                let mut unexpanded = Vec::with_capacity(unexpanded_count.min(input.len()));
+                for _i in 0..unexpanded_count {
+                    unexpanded.push(Action::decode(input)?);
+                }
+                Unexpanded::List(unexpanded)
+            }
+        };
+        return Ok(NodeRecord {
+            parent,
+            action,
+            side_to_move: flags & FLAG_SIDE_TO_MOVE != 0,
+            is_chance: flags & FLAG_CHANCE != 0,
+            probability,
+            stats: NodeStats { wins, draws, sims, score },
+            stored: flags & FLAG_STORED != 0,
+            complete: flags & FLAG_COMPLETE != 0,
+            expanded,
+            unexpanded,
+        });
+    }
+
+    /// Writes the tree encoded by `to_bytes` to `writer`, such as a file.
+    #[cfg(feature = "std")]
+    pub fn write_to<W: io::Write>(&self, writer: &mut W) -> io::Result<()> {
+        return writer.write_all(&self.to_bytes());
+    }
+
+    /// Reads a tree written by `write_to` from `reader`.
+    ///
+    /// # Returns
+    /// The tree, or an error of kind `InvalidData` if the tree can not be decoded.
+    #[cfg(feature = "std")]
+    pub fn read_from<R: io::Read>(reader: &mut R) -> io::Result<Self> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes)?;
+        return Ok(Self::from_bytes(&bytes)?);
+    }
+}
+
+
+/// Defines unit tests for the encoding of values and trees.
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[cfg(feature = "chess")]
+    use crate::chess_env::ChessState;
+    use crate::game2048_env::{Direction, Game2048Move, Game2048State};
+    use crate::go_env::{GoMove, GoState};
+    use crate::nim_env::NimState;
+    use crate::tictactoe_env::TicTacToeState;
+    #[cfg(feature = "chess")]
+    use chess::ChessMove;
+
+    /// Asserts that every node of `restored` matches the node of `tree`.
+    fn assert_same_tree<Action, GameStateObj>(restored: &MCTSTree<Action, GameStateObj>, tree: &MCTSTree<Action, GameStateObj>)
+    where
+        Action: PartialEq + fmt::Debug,
+        GameStateObj: GameState<Action> + Clone + PartialEq + fmt::Debug
+    {
+        assert_eq!(restored.arena.len(), tree.arena.len());
+        for node in 0..tree.arena.len() {
+            assert_eq!(restored.arena.stats[node], tree.arena.stats[node]);
+            assert_eq!(restored.arena.actions[node], tree.arena.actions[node]);
+            assert_eq!(restored.arena.topology[node].parent, tree.arena.topology[node].parent);
+            assert_eq!(restored.arena.topology[node].probability, tree.arena.topology[node].probability);
+            assert_eq!(restored.arena.topology[node].children.complete, tree.arena.topology[node].children.complete);
+            assert_eq!(restored.arena.stored_state(node as NodeIndex), tree.arena.stored_state(node as NodeIndex));
+            assert!(restored.arena.expanded(node as NodeIndex).eq(tree.arena.expanded(node as NodeIndex)));
+            assert!(restored.arena.unexpanded(node as NodeIndex).eq(tree.arena.unexpanded(node as NodeIndex)));
+        }
+    }
+
+    /// Test if varints and zigzag encoded values survive a round trip.
+    #[test]
+    fn test_varint() {
+        let mut out = Vec::new();
+        for value in [0, 1, 127, 128, 300, u32::MAX as u64, u64::MAX] {
+            out.clear();
+            encode_varint(value, &mut out);
+            assert_eq!(decode_varint(&mut &out[..]), Ok(value));
+        }
+        out.clear();
+        encode_varint(127, &mut out);
+        assert_eq!(out, [127]);
+        encode_varint(300, &mut out);
+        assert_eq!(out, [127, 0xAC, 0x02]);
+        assert!(decode_varint(&mut &[0x80][..]).is_err());
+        assert!(decode_varint(&mut &[0xFF; 11][..]).is_err());
+
+        for value in [0, -1, 1, -64, 64, i64::MIN, i64::MAX] {
+            assert_eq!(unzigzag(zigzag(value)), value);
+        }
+        assert_eq!(zigzag(-1), 1);
+        assert_eq!(zigzag(1), 2);
+    }
+
+    /// Test if values survive a round trip, and truncated input is rejected.
+    #[test]
+    fn test_values() {
+        let value: (Vec<Option<u16>>, (String, f64)) = (vec![Some(3), None, Some(65535)], ("nim".to_string(), -0.5));
+        let mut out = Vec::new();
+        value.encode(&mut out);
+        let mut input = &out[..];
+        assert_eq!(<(Vec<Option<u16>>, (String, f64))>::decode(&mut input), Ok(value));
+        assert!(input.is_empty());
+
+        let mut truncated = &out[..out.len() - 1];
+        assert!(<(Vec<Option<u16>>, (String, f64))>::decode(&mut truncated).is_err());
+        assert!(bool::decode(&mut &[2u8][..]).is_err());
+    }
+
+    /// Encodes `value` and decodes it back.
+    fn round_trip<T: Encode + Decode>(value: &T) -> T {
+        let mut out = Vec::new();
+        value.encode(&mut out);
+        let mut input = &out[..];
+        let decoded = T::decode(&mut input).unwrap();
+        assert!(input.is_empty());
+        return decoded;
+    }
+
+    /// Test if the actions and game states of the environments survive a round trip.
+    #[test]
+    fn test_environment_values() {
+        let go_state = GoState::from_str("5".to_string()).apply_action(&GoMove::Place(12)).apply_action(&GoMove::Pass);
+        let restored = round_trip(&go_state);
+        assert_eq!(restored.history, go_state.history);
+        assert_eq!(restored.to_string(), go_state.to_string());
+        assert_eq!(round_trip(&GoMove::Pass), GoMove::Pass);
+
+        let spawn = Game2048Move::Spawn { cell: 15, exponent: 2 };
+        assert_eq!(round_trip(&spawn), spawn);
+        assert_eq!(round_trip(&Game2048Move::Slide(Direction::Left)), Game2048Move::Slide(Direction::Left));
+    }
+
+    /// Test if chess moves and states survive a round trip.
+    #[test]
+    #[cfg(feature = "chess")]
+    fn test_chess_values() {
+        let chess_move = ChessMove::from_san(&chess::Board::default(), "e4").unwrap();
+        let chess_state = ChessState::from_str("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1".to_string()).apply_action(&chess_move);
+        let restored = round_trip(&chess_state);
+        assert_eq!(restored.board, chess_state.board);
+        assert_eq!(restored.last_move, Some(chess_move));
+        assert_eq!(restored.to_fen(), chess_state.to_fen());
+    }
+
+    /// Test if a restored tree matches the original, and continues the search identically.
+    #[test]
+    fn test_tree_round_trip() {
+        let mut tree = MCTSTree::<u8, TicTacToeState>::with_capacity(2000, Some(4), "X...O....".to_string(), 9);
+        tree.search(500);
+        let bytes = tree.to_bytes();
+        let mut restored = MCTSTree::<u8, TicTacToeState>::from_bytes(&bytes).unwrap();
+
+        assert_same_tree(&restored, &tree);
+
+        tree.search(500);
+        restored.search(500);
+        for node in 0..tree.arena.len() {
+            assert_eq!(restored.arena.stats[node], tree.arena.stats[node]);
+        }
+    }
+
+    /// Test if the search settings of a tree survive both formats, so the restored search continues identically.
+    #[test]
+    fn test_tree_settings() {
+        let mut tree = MCTSTree::<u8, TicTacToeState>::with_capacity(2000, Some(4), "X...O....".to_string(), 9);
+        tree.searcher.perspective = Perspective::Alternating;
+        tree.searcher.draw_score = 0.25;
+        tree.searcher.discount = 0.99;
+        tree.searcher.max_backup_weight = 0.3;
+        tree.searcher.normalize_rewards = true;
+        tree.searcher.backup_policy = BackupPolicy::Solver;
+        tree.searcher.first_play_urgency = Some(0.5);
+        tree.searcher.random_tie_breaking = false;
+        tree.searcher.rollout_move_cap = Some(20);
+        tree.searcher.simulations_per_expansion = 2;
+        tree.searcher.exploration_schedule = ExplorationSchedule::Linear { start: 2.0, end: 0.5, visits: 1000 };
+        tree.searcher.early_termination = true;
+        tree.budget = SearchBudget { max_nodes: Some(5000), max_memory_bytes: Some(1 << 24) };
+        tree.search(300);
+        assert!(tree.reward_bounds.is_some());
+
+        for compact in [false, true] {
+            let bytes = if compact { tree.to_compact_bytes() } else { tree.to_bytes() };
+            let mut restored = MCTSTree::<u8, TicTacToeState>::from_bytes(&bytes).unwrap();
+            let searcher = &restored.searcher;
+            assert_eq!(searcher.perspective, Perspective::Alternating);
+            assert_eq!((searcher.draw_score, searcher.discount, searcher.max_backup_weight), (0.25, 0.99, 0.3));
+            assert!(searcher.normalize_rewards && !searcher.random_tie_breaking && searcher.early_termination);
+            assert_eq!(searcher.backup_policy, BackupPolicy::Solver);
+            assert_eq!(searcher.first_play_urgency, Some(0.5));
+            assert_eq!(searcher.rollout_move_cap, Some(20));
+            assert_eq!(searcher.simulations_per_expansion, 2);
+            assert_eq!(searcher.exploration_schedule, tree.searcher.exploration_schedule);
+            assert_eq!(restored.reward_bounds, tree.reward_bounds);
+            assert_eq!(restored.budget, tree.budget);
+
+            tree.search(300);
+            restored.search(300);
+            assert_same_tree(&restored, &tree);
+        }
+    }
+
+    /// Test if trees with actions only storage and tuple actions are restored, and invalid bytes rejected.
+    #[test]
+    fn test_tree_actions_only() {
+        let mut tree = MCTSTree::<(usize, u32), NimState>::with_capacity(1000, Some(2), "2 3 4".to_string(), 9);
+        tree.state_storage = StateStorage::ActionsOnly;
+        tree.searcher.rollout_depth_limit = Some(5);
+        tree.search(300);
+        let bytes = tree.to_bytes();
+        let restored = MCTSTree::<(usize, u32), NimState>::from_bytes(&bytes).unwrap();
+        assert_eq!(restored.state_storage, StateStorage::ActionsOnly);
+        assert_eq!(restored.searcher.rollout_depth_limit, Some(5));
+        assert_eq!(restored.arena.states.len(), 1);
+        let leaf = restored.arena.len() as NodeIndex - 1;
+        assert_eq!(restored.game_state_at(leaf).into_owned(), tree.game_state_at(leaf).into_owned());
+
+        assert!(MCTSTree::<(usize, u32), NimState>::from_bytes(&bytes[..bytes.len() - 1]).is_err());
+        assert!(MCTSTree::<(usize, u32), NimState>::from_bytes(b"MCTX").is_err());
+    }
+
+    /// Test if trees are written to and read from I/O streams, and invalid streams rejected as invalid data.
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_tree_io() {
+        let mut tree = MCTSTree::<(usize, u32), NimState>::with_capacity(1000, Some(2), "2 3 4".to_string(), 9);
+        tree.search(300);
+        let mut bytes = Vec::new();
+        tree.write_to(&mut bytes).unwrap();
+        let restored = MCTSTree::<(usize, u32), NimState>::read_from(&mut &bytes[..]).unwrap();
+        assert_same_tree(&restored, &tree);
+
+        let error = MCTSTree::<(usize, u32), NimState>::read_from(&mut &b"MCTS"[..]).err().unwrap();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+    }
+
+    /// Test if the compact format restores the tree, continues the search identically,
+    /// and is more than 10 times smaller than the JSON export.
+    #[test]
+    fn test_tree_compact() {
+        let mut tree = MCTSTree::<u8, TicTacToeState>::with_capacity(4000, Some(5), ".........".to_string(), 9);
+        tree.search(2000);
+        let fixed_width_len = tree.to_bytes().len();
+        let bytes = tree.to_compact_bytes();
+        let mut restored = MCTSTree::<u8, TicTacToeState>::from_bytes(&bytes).unwrap();
+        assert_same_tree(&restored, &tree);
+
+        let json = tree.export_json(None, 0);
+        assert!(bytes.len() * 10 < json.len(), "{} bytes against {} bytes of JSON", bytes.len(), json.len());
+        assert!(bytes.len() * 4 < fixed_width_len);
+
+        tree.search(500);
+        restored.search(500);
+        for node in 0..tree.arena.len() {
+            assert_eq!(restored.arena.stats[node], tree.arena.stats[node]);
+        }
+        assert!(MCTSTree::<u8, TicTacToeState>::from_bytes(&bytes[..bytes.len() - 1]).is_err());
+
+        // The compact format is told apart by its magic bytes, and other versions are rejected.
+        assert_eq!(&bytes[..4], COMPACT_TREE_MAGIC);
+        let mut unknown_version = bytes.clone();
+        unknown_version[4] = 2;
+        assert_eq!(
+            MCTSTree::<u8, TicTacToeState>::from_bytes(&unknown_version).err(),
+            Some(DecodeError("unsupported tree format version 2".to_string()))
+        );
+    }
+
+    /// Test if counters beyond 32 bits survive both formats.
+    #[test]
+    fn test_tree_wide_counters() {
+        let mut tree = MCTSTree::<u8, TicTacToeState>::with_capacity(10, Some(7), ".........".to_string(), 9);
+        tree.arena.stats[0] = NodeStats { wins: 3 << 32, draws: 1 << 33, sims: 5 << 32, score: (3u64 << 32) as f64 };
+        for bytes in [tree.to_bytes(), tree.to_compact_bytes()] {
+            let restored = MCTSTree::<u8, TicTacToeState>::from_bytes(&bytes).unwrap();
+            assert_eq!(restored.arena.stats[0], tree.arena.stats[0]);
+        }
+    }
+
+    /// Test if the compact format restores trees with chance nodes, scores and actions only storage.
+    #[test]
+    fn test_tree_compact_chance() {
+        let mut tree = MCTSTree::<Game2048Move, Game2048State>::with_capacity(2000, Some(6), String::new(), 4);
+        tree.state_storage = StateStorage::ActionsOnly;
+        tree.searcher.player_mode = PlayerMode::SinglePlayer;
+        tree.searcher.rollout_depth_limit = Some(1);
+        tree.search(300);
+        assert!(tree.arena.topology.iter().any(|topology| topology.is_chance));
+
+        let bytes = tree.to_compact_bytes();
+        let restored = MCTSTree::<Game2048Move, Game2048State>::from_bytes(&bytes).unwrap();
+        assert_same_tree(&restored, &tree);
+        let leaf = restored.arena.len() as NodeIndex - 1;
+        assert_eq!(restored.game_state_at(leaf).into_owned(), tree.game_state_at(leaf).into_owned());
+    }
+}
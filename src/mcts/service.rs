@@ -0,0 +1,439 @@
+//! Search as a service: a position and a budget in, the best action and root statistics out,
+//! for every bundled environment.
+//!
+//! Requests and responses are independent of the transport. The `MCTSServer` binary serves
+//! them over TCP as lines, a request in the text format of `SearchRequest::parse` and a
+//! response as the JSON object of `SearchResponse::to_json`.
+
+#[cfg(feature = "chess")]
+use crate::chess_env::{ChessRolloutPolicy, ChessState};
+use crate::connect4_env::Connect4State;
+use crate::export::write_json_string;
+use crate::game2048_env::{Direction, Game2048Move, Game2048State};
+use crate::game_state_trait::GameState;
+use crate::go_env::{GoMove, GoRolloutPolicy, GoState};
+use crate::hex_env::HexState;
+use crate::mcts::{ExplorationSchedule, MCTSTree, PlayerMode};
+use crate::nim_env::NimState;
+use crate::tictactoe_env::TicTacToeState;
+use crate::time_manager::TimeControl;
+use std::fmt;
+use std::fmt::Write;
+use std::str::FromStr;
+use std::time::Duration;
+
+
+/// Nodes reserved for searches limited by time, which also caps the size of every search and the
+/// iterations a request may ask for.
+pub const TIMED_SEARCH_CAPACITY: usize = 1000000;
+
+
+/// A bundled environment the service can search. Chess is served with the `chess` feature.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Environment {
+    #[cfg(feature = "chess")]
+    Chess,
+    TicTacToe,
+    Connect4,
+    Hex,
+    Go,
+    Nim,
+    Game2048,
+}
+
+impl Environment {
+    /// Every environment, in the order of their names.
+    pub const ALL: &'static [Environment] = &[
+        #[cfg(feature = "chess")]
+        Environment::Chess,
+        Environment::TicTacToe,
+        Environment::Connect4,
+        Environment::Hex,
+        Environment::Go,
+        Environment::Nim,
+        Environment::Game2048,
+    ];
+
+    /// Name of the environment in requests.
+    pub fn name(&self) -> &'static str {
+        match self {
+            #[cfg(feature = "chess")]
+            Environment::Chess => return "chess",
+            Environment::TicTacToe => return "tictactoe",
+            Environment::Connect4 => return "connect4",
+            Environment::Hex => return "hex",
+            Environment::Go => return "go",
+            Environment::Nim => return "nim",
+            Environment::Game2048 => return "2048",
+        }
+    }
+}
+
+impl FromStr for Environment {
+    type Err = ServiceError;
+
+    fn from_str(name: &str) -> Result<Self, ServiceError> {
+        return Environment::ALL.iter().copied()
+            .find(|environment| environment.name() == name)
+            .ok_or_else(|| ServiceError::UnknownEnvironment(name.to_string()));
+    }
+}
+
+
+/// Limit of a requested search.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SearchLimit {
+    /// Number of iterations, the tree reserving a node for each. At most `TIMED_SEARCH_CAPACITY`.
+    Iterations(u64),
+
+    /// Time of the search, in a tree of at most `TIMED_SEARCH_CAPACITY` nodes.
+    Time(Duration),
+}
+
+
+/// A request to search a position.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SearchRequest {
+    pub environment: Environment,
+
+    /// Position in the string format of the environment's game state, such as a FEN for chess.
+    pub position: String,
+
+    pub limit: SearchLimit,
+
+    /// Seed of the random generator of the tree, so that searches are reproducible.
+    pub seed: u64,
+
+    /// Constant exploration factor of selection, None for the default schedule of the tree.
+    pub exploration: Option<f32>,
+
+    /// Depth of the tree exported with the response, see `MCTSTree::export_json`. None to not export it.
+    pub export_depth: Option<u32>,
+}
+
+impl SearchRequest {
+    /// Creates a request of `position` with the defaults of `parse`.
+    pub fn new(environment: Environment, position: &str) -> Self {
+        return SearchRequest {
+            environment,
+            position: position.to_string(),
+            limit: SearchLimit::Iterations(10000),
+            seed: 1,
+            exploration: None,
+            export_depth: None,
+        };
+    }
+
+    /// Parses a request from a line `<environment> [iterations=<n> | movetime=<ms>] [seed=<n>]
+    /// [exploration=<c>] [export=<depth>] <position>`, such as `tictactoe iterations=1000 X...O....`.
+    /// Options may come in any order, and the rest of the line is the position. Searches default
+    /// to 10000 iterations with seed 1, and may not ask for more than `TIMED_SEARCH_CAPACITY`.
+    pub fn parse(line: &str) -> Result<Self, ServiceError> {
+        let mut tokens = line.split_whitespace().peekable();
+        let environment: Environment = tokens.next().ok_or(ServiceError::EmptyRequest)?.parse()?;
+        let mut request = SearchRequest::new(environment, "");
+
+        while let Some((key, value)) = tokens.peek().and_then(|token| token.split_once('=')) {
+            let invalid = || ServiceError::InvalidOption(key.to_string());
+            if key == "exploration" {
+                let factor: f32 = value.parse().map_err(|_| invalid())?;
+                if !(factor >= 0.0 && factor.is_finite()) {
+                    return Err(invalid());
+                }
+                request.exploration = Some(factor);
+                tokens.next();
+                continue;
+            }
+            let number: u64 = value.parse().map_err(|_| invalid())?;
+            match key {
+                "iterations" if number > 0 && number <= TIMED_SEARCH_CAPACITY as u64 => request.limit = SearchLimit::Iterations(number),
+                "movetime" if number > 0 => request.limit = SearchLimit::Time(Duration::from_millis(number)),
+                "seed" => request.seed = number,
+                "export" => request.export_depth = Some(number.min(u32::MAX as u64) as u32),
+                _ => return Err(invalid()),
+            }
+            tokens.next();
+        }
+        request.position = tokens.collect::<Vec<_>>().join(" ");
+        return Ok(request);
+    }
+}
+
+
+/// Statistics of a child of the root in a `SearchResponse`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RootMove {
+    /// Action of the child, in the move notation of the environment.
+    pub action: String,
+
+    pub visits: u64,
+
+    /// Mean score of the child for the player choosing it, 0 if it was not visited.
+    pub value: f64,
+}
+
+
+/// Result of a search.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SearchResponse {
+    /// Most visited action at the root.
+    pub best_action: String,
+
+    /// Expanded children of the root, most visited first.
+    pub moves: Vec<RootMove>,
+
+    /// Simulations of the root.
+    pub root_visits: u64,
+
+    /// Iterations searched.
+    pub iterations: u64,
+
+    /// Nodes in the tree.
+    pub nodes: usize,
+
+    pub elapsed: Duration,
+
+    /// JSON export of the tree to the `export_depth` of the request, leaving out unvisited nodes.
+    pub tree: Option<String>,
+}
+
+impl SearchResponse {
+    /// Encodes the response as a single line JSON object, with the fields of the response and
+    /// the elapsed time as `elapsed_ms`. The exported tree is only included when there is one.
+    pub fn to_json(&self) -> String {
+        let mut out = String::from("{\"best_action\":");
+        write_json_string(&mut out, &self.best_action);
+        out.push_str(",\"moves\":[");
+        for (i, root_move) in self.moves.iter().enumerate() {
+            if i > 0 {
+                out.push(',');
+            }
+            out.push_str("{\"action\":");
+            write_json_string(&mut out, &root_move.action);
+            write!(out, ",\"visits\":{},\"value\":{}}}", root_move.visits, root_move.value).expect("write to string");
+        }
+        write!(
+            out,
+            "],\"root_visits\":{},\"iterations\":{},\"nodes\":{},\"elapsed_ms\":{}",
+            self.root_visits, self.iterations, self.nodes, self.elapsed.as_millis()
+        ).expect("write to string");
+        if let Some(tree) = &self.tree {
+            out.push_str(",\"tree\":");
+            out.push_str(tree);
+        }
+        out.push('}');
+        return out;
+    }
+}
+
+
+/// Reasons a request can not be served.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ServiceError {
+    EmptyRequest,
+    UnknownEnvironment(String),
+
+    /// An option is unknown or has an invalid value.
+    InvalidOption(String),
+
+    /// The position can not be parsed, with the parse error of the environment.
+    InvalidPosition(String),
+
+    /// The position has ended, so there is no action to search.
+    NoLegalActions,
+}
+
+impl fmt::Display for ServiceError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ServiceError::EmptyRequest => return write!(f, "empty request"),
+            ServiceError::UnknownEnvironment(name) => return write!(f, "unknown environment: {}", name),
+            ServiceError::InvalidOption(option) => return write!(f, "invalid option: {}", option),
+            ServiceError::InvalidPosition(error) => return write!(f, "invalid position: {}", error),
+            ServiceError::NoLegalActions => return write!(f, "no legal actions"),
+        }
+    }
+}
+
+impl std::error::Error for ServiceError {}
+
+impl ServiceError {
+    /// Encodes the error as a single line JSON object with an `error` message.
+    pub fn to_json(&self) -> String {
+        let mut out = String::from("{\"error\":");
+        write_json_string(&mut out, &self.to_string());
+        out.push('}');
+        return out;
+    }
+}
+
+
+/// Searches the position of `request`.
+pub fn search(request: &SearchRequest) -> Result<SearchResponse, ServiceError> {
+    match request.environment {
+        #[cfg(feature = "chess")]
+        Environment::Chess => return search_with(request, 30, |tree: &mut MCTSTree<_, ChessState>| {
+            tree.searcher.rollout_policy = Box::new(ChessRolloutPolicy::default());
+        }, |action| action.to_string()),
+        Environment::TicTacToe => return search_with(request, 9, |_: &mut MCTSTree<_, TicTacToeState>| {}, |action| action.to_string()),
+        Environment::Connect4 => return search_with(request, 7, |_: &mut MCTSTree<_, Connect4State>| {}, |action| action.to_string()),
+        Environment::Hex => return search_with(request, 49, |_: &mut MCTSTree<_, HexState>| {}, |action| action.to_string()),
+        Environment::Go => return search_with(request, 82, |tree: &mut MCTSTree<_, GoState>| {
+            tree.searcher.rollout_policy = Box::new(GoRolloutPolicy);
+        }, |action| match action {
+            GoMove::Place(point) => point.to_string(),
+            GoMove::Pass => "pass".to_string(),
+        }),
+        Environment::Nim => return search_with(request, 12, |_: &mut MCTSTree<_, NimState>| {}, |(pile, count)| format!("{}:{}", pile, count)),
+        Environment::Game2048 => return search_with(request, 4, |tree: &mut MCTSTree<_, Game2048State>| {
+            tree.searcher.player_mode = PlayerMode::SinglePlayer;
+        }, |action| match action {
+            Game2048Move::Slide(Direction::Up) => "up".to_string(),
+            Game2048Move::Slide(Direction::Right) => "right".to_string(),
+            Game2048Move::Slide(Direction::Down) => "down".to_string(),
+            Game2048Move::Slide(Direction::Left) => "left".to_string(),
+            Game2048Move::Spawn { cell, exponent } => format!("spawn {} {}", cell, 1 << exponent),
+        }),
+    }
+}
+
+/// Searches the position of `request` in a tree of `GameStateObj`.
+///
+/// # Arguments
+/// * `average_child_count` : Number of children expected for nodes in the tree.
+/// * `configure` : Sets the options of the environment on the new tree, such as its rollout policy.
+/// * `label` : Encodes an action in the move notation of the environment.
+fn search_with<Action, GameStateObj, C, L>(request: &SearchRequest, average_child_count: usize, configure: C, label: L) -> Result<SearchResponse, ServiceError>
+where
+    GameStateObj: GameState<Action> + Clone,
+    GameStateObj::ParseError: fmt::Display,
+    C: FnOnce(&mut MCTSTree<Action, GameStateObj>),
+    L: Fn(&Action) -> String
+{
+    let capacity = match request.limit {
+        SearchLimit::Iterations(iterations) => iterations.min(TIMED_SEARCH_CAPACITY as u64) as usize + 1,
+        SearchLimit::Time(_) => TIMED_SEARCH_CAPACITY,
+    };
+    let mut tree = MCTSTree::<Action, GameStateObj>::try_with_capacity(capacity, Some(request.seed), &request.position, average_child_count)
+        .map_err(|error| ServiceError::InvalidPosition(error.to_string()))?;
+    if !tree.arena.has_untried_actions(0) {
+        return Err(ServiceError::NoLegalActions);
+    }
+    configure(&mut tree);
+    if let Some(exploration) = request.exploration {
+        tree.searcher.exploration_schedule = ExplorationSchedule::Constant(exploration);
+    }
+
+    // Requests built without `parse` are held to the same limits.
+    tree.budget.max_nodes = Some(TIMED_SEARCH_CAPACITY);
+    let iterations = match request.limit {
+        SearchLimit::Iterations(iterations) => tree.search_with_hooks(iterations.min(TIMED_SEARCH_CAPACITY as u64), &mut ()),
+        SearchLimit::Time(time) => tree.go(TimeControl::fixed(time)),
+    };
+
+    let mut children: Vec<_> = tree.arena.expanded(0).collect();
+    children.sort_by_key(|child| std::cmp::Reverse(tree.arena.stats[*child as usize].sims));
+    let moves: Vec<RootMove> = children.iter().map(|child| {
+        let stats = &tree.arena.stats[*child as usize];
+        return RootMove {
+            action: label(tree.arena.actions[*child as usize].as_ref().expect("no action")),
+            visits: stats.sims,
+            value: if stats.sims == 0 { 0.0 } else { stats.score / stats.sims as f64 },
+        };
+    }).collect();
+
+    return Ok(SearchResponse {
+        best_action: moves.first().map(|root_move| root_move.action.clone()).ok_or(ServiceError::NoLegalActions)?,
+        moves,
+        root_visits: tree.arena.stats[0].sims,
+        iterations,
+        nodes: tree.arena.len(),
+        elapsed: tree.counters.search_time,
+        tree: request.export_depth.map(|depth| tree.export_json_with(Some(depth), 1, &label)),
+    });
+}
+
+
+/// Defines unit tests for the search service.
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Test if requests are parsed with their options and position.
+    #[test]
+    fn test_parse() {
+        let request = SearchRequest::parse("tictactoe iterations=500 seed=7 exploration=0.5 export=2 X...O....").unwrap();
+        assert_eq!(request, SearchRequest {
+            environment: Environment::TicTacToe,
+            position: "X...O....".to_string(),
+            limit: SearchLimit::Iterations(500),
+            seed: 7,
+            exploration: Some(0.5),
+            export_depth: Some(2),
+        });
+        #[cfg(feature = "chess")]
+        {
+            let request = SearchRequest::parse("chess movetime=250 rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1").unwrap();
+            assert_eq!(request.limit, SearchLimit::Time(Duration::from_millis(250)));
+            assert_eq!(request.position, "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1");
+        }
+
+        assert_eq!(SearchRequest::parse(""), Err(ServiceError::EmptyRequest));
+        assert_eq!(SearchRequest::parse("checkers 1"), Err(ServiceError::UnknownEnvironment("checkers".to_string())));
+        assert_eq!(SearchRequest::parse("nim depth=3 3 4 5"), Err(ServiceError::InvalidOption("depth".to_string())));
+        assert_eq!(SearchRequest::parse("nim iterations=0 3 4 5"), Err(ServiceError::InvalidOption("iterations".to_string())));
+        assert_eq!(SearchRequest::parse("nim iterations=1000001 3 4 5"), Err(ServiceError::InvalidOption("iterations".to_string())));
+        assert_eq!(SearchRequest::parse("nim iterations=18446744073709551615 3"), Err(ServiceError::InvalidOption("iterations".to_string())));
+        assert_eq!(SearchRequest::parse("nim exploration=-1 3 4 5"), Err(ServiceError::InvalidOption("exploration".to_string())));
+    }
+
+    /// Test if searches return the best action and the statistics of the root.
+    #[test]
+    fn test_search() {
+        let response = search(&SearchRequest::parse("tictactoe iterations=2000 XX.OO....").unwrap()).unwrap();
+        assert_eq!(response.best_action, "2");
+        assert_eq!((response.root_visits, response.iterations), (2000, 2000));
+        assert_eq!(response.moves.len(), 5);
+        assert!(response.moves.windows(2).all(|pair| pair[0].visits >= pair[1].visits));
+        assert!(response.to_json().starts_with("{\"best_action\":\"2\",\"moves\":[{\"action\":\"2\",\"visits\":"));
+
+        // Every environment is served from its starting position.
+        let positions = [
+            #[cfg(feature = "chess")]
+            (Environment::Chess, "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1".to_string()),
+            (Environment::Connect4, Connect4State::new().to_string()),
+            (Environment::Hex, HexState::new(5).to_string()),
+            (Environment::Go, GoState::default().to_string()),
+            (Environment::Nim, "3 4 5".to_string()),
+            (Environment::Game2048, Game2048State::new().to_string()),
+        ];
+        for (environment, position) in positions {
+            let request = SearchRequest { limit: SearchLimit::Iterations(50), ..SearchRequest::new(environment, &position) };
+            let response = search(&request).unwrap();
+            assert_eq!(response.root_visits, 50, "{}", environment.name());
+        }
+
+        // Requests built without `parse` are clamped to the capacity of the service.
+        let request = SearchRequest { limit: SearchLimit::Iterations(u64::MAX), ..SearchRequest::new(Environment::Nim, "1") };
+        assert_eq!(search(&request).unwrap().iterations, TIMED_SEARCH_CAPACITY as u64);
+
+        // Trees are exported on request.
+        let response = search(&SearchRequest::parse("tictactoe iterations=200 export=1 exploration=0 XX.OO....").unwrap()).unwrap();
+        let tree = response.tree.as_ref().unwrap();
+        assert!(tree.starts_with("{\"action\":null,\"sims\":200"));
+        assert!(response.to_json().ends_with(&format!(",\"tree\":{}}}", tree)));
+
+        // Time limited searches stop at their time.
+        let response = search(&SearchRequest::parse("connect4 movetime=20").unwrap()).unwrap();
+        assert!(response.iterations > 0);
+    }
+
+    /// Test if invalid and finished positions are reported.
+    #[test]
+    fn test_search_errors() {
+        let invalid = search(&SearchRequest::parse("tictactoe XX").unwrap());
+        assert!(matches!(invalid, Err(ServiceError::InvalidPosition(_))));
+        assert_eq!(search(&SearchRequest::parse("tictactoe XXXOO....").unwrap()), Err(ServiceError::NoLegalActions));
+        assert_eq!(ServiceError::NoLegalActions.to_json(), "{\"error\":\"no legal actions\"}");
+    }
+}
@@ -0,0 +1,327 @@
+//! Statistics of searches, for the observability of engine integrations.
+
+use crate::arena::NodeIndex;
+use crate::game_state_trait::GameState;
+use crate::mcts::MCTSTree;
+use crate::float::Float;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::fmt;
+use core::time::Duration;
+
+
+/// Counters of the work done by the search, kept by the tree as it is searched.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SearchCounters {
+    /// Number of iterations searched, counted by backpropagated results.
+    pub iterations: u64,
+
+    /// Number of rollouts of every length, indexed by the number of moves played.
+    pub rollout_lengths: Vec<u64>,
+
+    /// Time spent in `MCTSTree::search`.
+    pub search_time: Duration,
+
+    /// Time spent in every phase of the iterations of `MCTSTree::search`.
+    /// Only recorded with the `trace` feature.
+    pub phase_times: PhaseTimes,
+}
+
+
+/// Cumulative time spent in every phase of the search driver.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct PhaseTimes {
+    /// Time spent selecting leaves.
+    pub select: Duration,
+
+    /// Time spent expanding leaves.
+    pub expand: Duration,
+
+    /// Time spent in rollouts.
+    pub simulate: Duration,
+
+    /// Time spent backpropagating results.
+    pub backpropagate: Duration,
+}
+
+impl PhaseTimes {
+    /// Time spent in all four phases.
+    pub fn total(&self) -> Duration {
+        return self.select + self.expand + self.simulate + self.backpropagate;
+    }
+
+    /// Shares of the total time spent in selection, expansion, simulation and backpropagation,
+    /// in that order. All 0 if no time was spent.
+    pub fn shares(&self) -> [f64; 4] {
+        let total = self.total().as_secs_f64();
+        if total == 0.0 {
+            return [0.0; 4];
+        }
+        return [self.select, self.expand, self.simulate, self.backpropagate].map(|time| time.as_secs_f64() / total);
+    }
+}
+
+impl fmt::Display for PhaseTimes {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let [select, expand, simulate, backpropagate] = self.shares().map(|share| 100.0 * share);
+        return write!(
+            f,
+            "select {:.1}% expand {:.1}% simulate {:.1}% backpropagate {:.1}%",
+            select, expand, simulate, backpropagate
+        );
+    }
+}
+
+
+impl SearchCounters {
+    /// Records a rollout which played `moves` moves.
+    pub fn record_rollout(&mut self, moves: u32) {
+        let moves = moves as usize;
+        if self.rollout_lengths.len() <= moves {
+            self.rollout_lengths.resize(moves + 1, 0);
+        }
+        self.rollout_lengths[moves] += 1;
+    }
+}
+
+
+/// Snapshot of the statistics of a search, see `MCTSTree::stats`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SearchStatistics {
+    /// Number of iterations searched.
+    pub iterations: u64,
+
+    /// Number of nodes allocated in the arena.
+    pub nodes: usize,
+
+    /// Depth of the deepest node, the root being at depth 0.
+    pub max_depth: u32,
+
+    /// Average depth of the leaves of the tree, the nodes without expanded children.
+    pub average_depth: f64,
+
+    /// Number of rollouts played.
+    pub rollouts: u64,
+
+    /// Average number of moves played per rollout.
+    pub average_rollout_length: f64,
+
+    /// Number of rollouts of every length, indexed by the number of moves played.
+    pub rollout_lengths: Vec<u64>,
+
+    /// Time spent in `MCTSTree::search`.
+    pub search_time: Duration,
+
+    /// Time spent in every phase of the search, such as rollouts dominating chess searches
+    /// with the cost of move generation. Only recorded with the `trace` feature, 0 otherwise.
+    pub phase_times: PhaseTimes,
+
+    /// Iterations searched per second of `search_time`, which engines report as nodes per second.
+    /// 0 if the tree was not searched with `MCTSTree::search`.
+    pub nodes_per_second: f64,
+
+    /// Bytes reserved by the arena, see `MCTSArena::memory_usage`.
+    pub memory_bytes: usize,
+}
+
+impl fmt::Display for SearchStatistics {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        return write!(
+            f,
+            "iterations {} nodes {} depth {}/{:.1} rollout {:.1} time {}ms nps {:.0} memory {}KiB",
+            self.iterations,
+            self.nodes,
+            self.max_depth,
+            self.average_depth,
+            self.average_rollout_length,
+            self.search_time.as_millis(),
+            self.nodes_per_second,
+            self.memory_bytes / 1024
+        );
+    }
+}
+
+
+/// Shape of the tree, for tuning exploration constants, see `MCTSTree::shape`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TreeShape<Action> {
+    /// Number of nodes at every depth, indexed by depth, the root being at depth 0.
+    pub depth_histogram: Vec<usize>,
+
+    /// Average number of expanded children of the nodes with expanded children.
+    pub branching_factor: f64,
+
+    /// Branching factor of a uniform tree with as many nodes and the same depth,
+    /// `nodes^(1/depth)`. 0 for a tree of only its root.
+    pub effective_branching_factor: f64,
+
+    /// Actions leading from the root to the deepest node, the first allocated if several are deepest.
+    pub deepest_line: Vec<Action>,
+}
+
+
+/// Statistics of the tree.
+impl<Action, GameStateObj, Data> MCTSTree<Action, GameStateObj, Data>
+where
+    GameStateObj: GameState<Action> + Clone
+{
+    /// Gathers the statistics of the search.
+    ///
+    /// Depths are computed by a pass over the arena, so the cost grows with the size of the tree.
+    pub fn stats(&self) -> SearchStatistics {
+        // Parents precede their children, so the depth of every parent is known before its children.
+        let mut depths = vec![0u32; self.arena.len()];
+        let mut max_depth = 0;
+        let mut leaf_depths = 0u64;
+        let mut leaves = 0u64;
+        for node in 0..self.arena.len() {
+            if let Some(parent) = self.arena.topology[node].parent {
+                depths[node] = depths[parent as usize] + 1;
+            }
+            max_depth = max_depth.max(depths[node]);
+            if self.arena.topology[node].children.expanded == 0 {
+                leaf_depths += depths[node] as u64;
+                leaves += 1;
+            }
+        }
+
+        let counters = &self.counters;
+        let rollouts: u64 = counters.rollout_lengths.iter().sum();
+        let rollout_moves: u64 = counters.rollout_lengths.iter().enumerate().map(|(moves, count)| moves as u64 * count).sum();
+        let seconds = counters.search_time.as_secs_f64();
+        return SearchStatistics {
+            iterations: counters.iterations,
+            nodes: self.arena.len(),
+            max_depth,
+            average_depth: if leaves == 0 { 0.0 } else { leaf_depths as f64 / leaves as f64 },
+            rollouts,
+            average_rollout_length: if rollouts == 0 { 0.0 } else { rollout_moves as f64 / rollouts as f64 },
+            rollout_lengths: counters.rollout_lengths.clone(),
+            search_time: counters.search_time,
+            phase_times: counters.phase_times,
+            nodes_per_second: if seconds == 0.0 { 0.0 } else { counters.iterations as f64 / seconds },
+            memory_bytes: self.arena.memory_usage(),
+        };
+    }
+
+    /// Computes the depth distribution, branching factors and deepest line of the tree.
+    ///
+    /// Like `stats`, this is a pass over the arena, so the cost grows with the size of the tree.
+    pub fn shape(&self) -> TreeShape<Action>
+    where
+        Action: Clone
+    {
+        let mut depths = vec![0u32; self.arena.len()];
+        let mut depth_histogram = vec![0usize; 1];
+        let mut deepest: NodeIndex = 0;
+        let mut parents = 0u64;
+        let mut expanded = 0u64;
+        for node in 0..self.arena.len() {
+            if let Some(parent) = self.arena.topology[node].parent {
+                depths[node] = depths[parent as usize] + 1;
+            }
+            let depth = depths[node] as usize;
+            if depth_histogram.len() <= depth {
+                depth_histogram.resize(depth + 1, 0);
+            }
+            depth_histogram[depth] += 1;
+            if depths[node] > depths[deepest as usize] {
+                deepest = node as NodeIndex;
+            }
+
+            let children = self.arena.expanded_count(node as NodeIndex);
+            if children > 0 {
+                parents += 1;
+                expanded += children as u64;
+            }
+        }
+
+        let max_depth = depth_histogram.len() - 1;
+        let deepest_line = self.trace_path_at(deepest).into_iter()
+            .map(|node| self.arena.actions[node as usize].clone().expect("no action"))
+            .collect();
+        return TreeShape {
+            depth_histogram,
+            branching_factor: if parents == 0 { 0.0 } else { expanded as f64 / parents as f64 },
+            effective_branching_factor: if max_depth == 0 { 0.0 } else { Float::powf(self.arena.len() as f64, 1.0 / max_depth as f64) },
+            deepest_line,
+        };
+    }
+}
+
+
+/// Defines unit tests for search statistics.
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tictactoe_env::TicTacToeState;
+
+    /// Test if the statistics count the work of the search.
+    #[test]
+    fn test_stats() {
+        let mut tree = MCTSTree::<u8, TicTacToeState>::with_capacity(1000, Some(1), ".........".to_string(), 9);
+        let empty = tree.stats();
+        assert_eq!((empty.iterations, empty.nodes, empty.max_depth, empty.rollouts), (0, 1, 0, 0));
+        assert_eq!(empty.nodes_per_second, 0.0);
+        assert!(empty.memory_bytes > 0);
+
+        tree.search(500);
+        let stats = tree.stats();
+        assert_eq!(stats.iterations, 500);
+        assert_eq!(stats.nodes, tree.arena.len());
+        assert_eq!(stats.rollouts, 500);
+        assert!(stats.max_depth >= 2 && stats.max_depth <= 9);
+        assert!(stats.average_depth >= 1.0 && stats.average_depth <= stats.max_depth as f64);
+
+        // Rollouts of Tic-Tac-Toe end within 9 moves, and the first expands a single move.
+        assert!(stats.rollout_lengths.len() <= 9);
+        assert!(stats.average_rollout_length > 0.0 && stats.average_rollout_length < 8.0);
+        assert!(stats.to_string().starts_with("iterations 500 nodes "));
+        assert!(stats.phase_times.to_string().starts_with("select "));
+        assert_eq!(empty.phase_times.shares(), [0.0; 4]);
+
+        // Every phase was timed, within the time of the search. Times read 0 without a clock,
+        // and phases are only timed with the trace feature.
+        #[cfg(feature = "std")]
+        {
+            assert!(stats.search_time > Duration::ZERO);
+            assert!(stats.nodes_per_second > 0.0);
+        }
+        #[cfg(not(feature = "trace"))]
+        assert_eq!(stats.phase_times, PhaseTimes::default());
+        #[cfg(feature = "trace")]
+        {
+            let phases = stats.phase_times;
+            assert!(phases.select > Duration::ZERO && phases.expand > Duration::ZERO);
+            assert!(phases.simulate > Duration::ZERO && phases.backpropagate > Duration::ZERO);
+            assert!(phases.total() <= stats.search_time);
+            assert!((phases.shares().iter().sum::<f64>() - 1.0).abs() < 1e-9);
+        }
+    }
+
+    /// Test if the shape of the tree agrees with its nodes and statistics.
+    #[test]
+    fn test_shape() {
+        let mut tree = MCTSTree::<u8, TicTacToeState>::with_capacity(1000, Some(1), ".........".to_string(), 9);
+        let empty = tree.shape();
+        assert_eq!(empty.depth_histogram, vec![1]);
+        assert_eq!((empty.branching_factor, empty.effective_branching_factor), (0.0, 0.0));
+        assert!(empty.deepest_line.is_empty());
+
+        tree.search(500);
+        let shape = tree.shape();
+        let stats = tree.stats();
+        assert_eq!(shape.depth_histogram.iter().sum::<usize>(), tree.arena.len());
+        assert_eq!(shape.depth_histogram.len() as u32, stats.max_depth + 1);
+        assert_eq!(shape.depth_histogram[..2], [1, 9]);
+        assert_eq!(shape.deepest_line.len() as u32, stats.max_depth);
+
+        // The deepest line is a sequence of distinct squares.
+        let mut squares = shape.deepest_line.clone();
+        squares.sort();
+        squares.dedup();
+        assert_eq!(squares.len(), shape.deepest_line.len());
+        assert!(shape.branching_factor > 1.0 && shape.branching_factor <= 9.0);
+        assert!(shape.effective_branching_factor > 1.0);
+    }
+}
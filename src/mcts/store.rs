@@ -0,0 +1,262 @@
+//! A persistent store of node statistics keyed by state hash, which accumulates the statistics
+//! of searches across sessions, such as for the precomputation of opening books.
+//!
+//! The store is a single file holding the statistics of every flushed state, read into memory
+//! when it is opened and rewritten when it is saved, so it needs no database.
+
+use crate::arena::{NodeIndex, NodeStats};
+use crate::checkpoint::write_atomically;
+use crate::game_state_trait::GameState;
+use crate::mcts::{MCTSTree, StateStorage};
+use crate::serialize::{Decode, DecodeError, Encode};
+use std::collections::{HashMap, VecDeque};
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+
+/// Header written at the start of every store file.
+pub const STORE_MAGIC: &[u8; 4] = b"MCTB";
+
+/// Version of the store file format.
+pub const STORE_FORMAT_VERSION: u32 = 1;
+
+
+/// Node statistics keyed by the `GameState::state_hash` of their states, backed by a file.
+///
+/// Trees are warm started from the store before they are searched, which expands every
+/// stored state reachable from the root along with its statistics, and flushed to the store
+/// afterwards. Flushed statistics replace the stored ones, so statistics carried over by the
+/// warm start accumulate across sessions.
+pub struct TreeStore {
+    /// File the store is saved to.
+    pub path: PathBuf,
+
+    /// Statistics of every stored state.
+    pub entries: HashMap<u64, NodeStats>,
+}
+
+
+impl TreeStore {
+    /// Opens the store saved at `path`, or an empty store if no file exists.
+    ///
+    /// # Returns
+    /// The store, or an error if the file can not be read or is not a valid store.
+    pub fn open(path: impl Into<PathBuf>) -> io::Result<Self> {
+        let path = path.into();
+        let bytes = match fs::read(&path) {
+            Ok(bytes) => bytes,
+            Err(error) if error.kind() == io::ErrorKind::NotFound => return Ok(TreeStore { path, entries: HashMap::new() }),
+            Err(error) => return Err(error),
+        };
+        let entries = Self::decode_entries(&bytes)?;
+        return Ok(TreeStore { path, entries });
+    }
+
+    /// Decodes the entries of a store file.
+    fn decode_entries(bytes: &[u8]) -> Result<HashMap<u64, NodeStats>, DecodeError> {
+        let mut input = bytes;
+        if input.len() < STORE_MAGIC.len() || &input[..STORE_MAGIC.len()] != STORE_MAGIC {
+            return Err(DecodeError("missing store header".to_string()));
+        }
+        input = &input[STORE_MAGIC.len()..];
+        let version = u32::decode(&mut input)?;
+        if version != STORE_FORMAT_VERSION {
+            return Err(DecodeError(format!("unsupported store format version {}", version)));
+        }
+        let len = usize::decode(&mut input)?;
+        let mut entries = HashMap::with_capacity(len.min(input.len()));
+        for _i in 0..len {
+            let hash = u64::decode(&mut input)?;
+            let stats = NodeStats {
+                wins: u64::decode(&mut input)?,
+                draws: u64::decode(&mut input)?,
+                sims: u64::decode(&mut input)?,
+                score: f64::decode(&mut input)?,
+            };
+            entries.insert(hash, stats);
+        }
+        if !input.is_empty() {
+            return Err(DecodeError(format!("{} trailing bytes", input.len())));
+        }
+        return Ok(entries);
+    }
+
+    /// Saves the store to its file.
+    ///
+    /// The store is written to a temporary file next to it, which is synced to disk and then
+    /// renamed over it, so a crash while saving never leaves a truncated store behind.
+    pub fn save(&self) -> io::Result<()> {
+        let mut bytes = Vec::new();
+        bytes.extend_from_slice(STORE_MAGIC);
+        STORE_FORMAT_VERSION.encode(&mut bytes);
+        self.entries.len().encode(&mut bytes);
+
+        // Entries are written in hash order, so equal stores are saved to equal files.
+        let mut hashes: Vec<&u64> = self.entries.keys().collect();
+        hashes.sort();
+        for hash in hashes {
+            let stats = &self.entries[hash];
+            hash.encode(&mut bytes);
+            stats.wins.encode(&mut bytes);
+            stats.draws.encode(&mut bytes);
+            stats.sims.encode(&mut bytes);
+            stats.score.encode(&mut bytes);
+        }
+
+        return write_atomically(&self.path, &bytes);
+    }
+
+    /// Flushes the statistics of the nodes of `tree` to the store, replacing the stored ones.
+    ///
+    /// States reached by several nodes of the tree keep the statistics of the most simulated one.
+    /// Nodes whose states have no state hash are skipped.
+    ///
+    /// # Arguments
+    /// * `min_sims` : Nodes with fewer simulations are skipped, which keeps rarely visited leaves out of the store.
+    ///
+    /// # Returns
+    /// The number of states flushed.
+    pub fn flush<Action, GameStateObj, Data>(&mut self, tree: &MCTSTree<Action, GameStateObj, Data>, min_sims: u64) -> usize
+    where
+        GameStateObj: GameState<Action> + Clone
+    {
+        let mut flushed: HashMap<u64, NodeStats> = HashMap::new();
+        for node in 0..tree.arena.len() {
+            let stats = tree.arena.stats[node];
+            if stats.sims == 0 || stats.sims < min_sims {
+                continue;
+            }
+            if let Some(hash) = tree.game_state_at(node as NodeIndex).state_hash() {
+                let entry = flushed.entry(hash).or_insert(stats);
+                if stats.sims > entry.sims {
+                    *entry = stats;
+                }
+            }
+        }
+        let count = flushed.len();
+        self.entries.extend(flushed);
+        return count;
+    }
+
+    /// Warm starts `tree` from the store.
+    ///
+    /// Starting from the root, every unexpanded action leading to a stored state is expanded,
+    /// with the stored statistics, and so on below it. Unvisited nodes already in the tree
+    /// take the stored statistics of their states.
+    ///
+    /// Chance nodes and lazily generated nodes are not expanded, the search expands them.
+    ///
+    /// # Returns
+    /// The number of nodes which took stored statistics.
+    pub fn warm_start<Action, GameStateObj, Data>(&self, tree: &mut MCTSTree<Action, GameStateObj, Data>) -> usize
+    where
+        Action: Clone + PartialEq,
+        GameStateObj: GameState<Action> + Clone
+    {
+        let mut count = 0;
+        let mut queue: VecDeque<NodeIndex> = VecDeque::from([0]);
+        while let Some(node) = queue.pop_front() {
+            let game_state = tree.game_state_at(node).into_owned();
+            if tree.arena.stats[node as usize].sims == 0 {
+                if let Some(stats) = game_state.state_hash().and_then(|hash| self.entries.get(&hash)) {
+                    tree.arena.stats[node as usize] = *stats;
+                    count += 1;
+                }
+            }
+            queue.extend(tree.arena.expanded(node));
+            if tree.arena.topology[node as usize].is_chance || GameStateObj::LAZY_ACTIONS {
+                continue;
+            }
+
+            let unexpanded: Vec<Action> = if tree.arena.topology[node as usize].children.complete {
+                tree.arena.unexpanded(node).cloned().collect()
+            }
+            else {
+                game_state.generate_legal_actions()
+            };
+            for action in unexpanded {
+                let child_game_state = game_state.apply_action(&action);
+                let stats = match child_game_state.state_hash().and_then(|hash| self.entries.get(&hash)) {
+                    Some(stats) => *stats,
+                    None => continue,
+                };
+                let child = tree.arena.len() as NodeIndex;
+                tree.attach_child(node, &action, child);
+                let store_state = tree.state_storage == StateStorage::Full;
+                tree.push_node(child_game_state, Some(action), Some(node), store_state);
+                tree.arena.stats[child as usize] = stats;
+                count += 1;
+                queue.push_back(child);
+            }
+        }
+        return count;
+    }
+}
+
+
+/// Defines unit tests for the tree store.
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tictactoe_env::TicTacToeState;
+
+    /// Test if statistics accumulate across sessions through the store file.
+    #[test]
+    fn test_sessions() {
+        let path = std::env::temp_dir().join(format!("mcts_store_{}", std::process::id()));
+        let mut store = TreeStore::open(&path).unwrap();
+        assert!(store.entries.is_empty());
+
+        let mut tree = MCTSTree::<u8, TicTacToeState>::with_capacity(1000, Some(1), "X...O....".to_string(), 9);
+        tree.search(300);
+        assert!(store.flush(&tree, 2) > 1);
+        store.save().unwrap();
+
+        // The next session resumes with the statistics of the first.
+        let store = TreeStore::open(&path).unwrap();
+        let mut resumed = MCTSTree::<u8, TicTacToeState>::with_capacity(1000, Some(2), "X...O....".to_string(), 9);
+        let warm_started = store.warm_start(&mut resumed);
+        assert!(warm_started >= store.entries.len());
+        assert_eq!(resumed.arena.stats[0], tree.arena.stats[0]);
+        for child in resumed.arena.expanded(0) {
+            let action = resumed.arena.actions[child as usize];
+            let original = tree.arena.expanded(0).find(|original| tree.arena.actions[*original as usize] == action).unwrap();
+            assert_eq!(resumed.arena.stats[child as usize], tree.arena.stats[original as usize]);
+        }
+        assert_eq!(resumed.arena.expanded_count(0) + resumed.arena.unexpanded_count(0), 7);
+
+        resumed.search(100);
+        let mut store = store;
+        store.flush(&resumed, 2);
+        assert_eq!(store.entries[&resumed.game_state_at(0).state_hash().unwrap()].sims, 400);
+        fs::remove_file(&path).unwrap();
+    }
+
+    /// Test if invalid store files are rejected.
+    #[test]
+    fn test_open_invalid() {
+        let path = std::env::temp_dir().join(format!("mcts_store_invalid_{}", std::process::id()));
+        fs::write(&path, b"MCTS").unwrap();
+        assert_eq!(TreeStore::open(&path).err().unwrap().kind(), io::ErrorKind::InvalidData);
+        fs::remove_file(&path).unwrap();
+    }
+
+    /// Test if stores are read with 64 bit counters, and stores of other versions are rejected.
+    #[test]
+    fn test_open_version() {
+        let mut bytes = STORE_MAGIC.to_vec();
+        1u32.encode(&mut bytes);
+        1usize.encode(&mut bytes);
+        42u64.encode(&mut bytes);
+        for counter in [3u64 << 32, 2, 7 << 32] {
+            counter.encode(&mut bytes);
+        }
+        4.0f64.encode(&mut bytes);
+        let entries = TreeStore::decode_entries(&bytes).unwrap();
+        assert_eq!(entries[&42], NodeStats { wins: 3 << 32, draws: 2, sims: 7 << 32, score: 4.0 });
+
+        bytes[STORE_MAGIC.len()] = 2;
+        assert_eq!(TreeStore::decode_entries(&bytes), Err(DecodeError("unsupported store format version 2".to_string())));
+    }
+}
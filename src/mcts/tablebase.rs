@@ -0,0 +1,74 @@
+use chess::{Board, CastleRights, Color};
+use std::sync::OnceLock;
+
+
+/// Win/draw/loss outcome of an endgame position with perfect play,
+/// from the perspective of the side to move.
+///
+/// Cursed wins and blessed losses are wins and losses which can not be forced
+/// within the fifty move rule, so they are scored as draws.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Wdl {
+    Loss,
+    BlessedLoss,
+    Draw,
+    CursedWin,
+    Win,
+}
+
+
+/// Source of endgame tablebase results, such as Syzygy WDL tables.
+///
+/// This crate does not decode tablebase files itself. A prober wraps a tablebase
+/// library or service and is registered with `set_tablebase`, after which chess game states
+/// with few enough pieces end immediately with the exact result, instead of continuing
+/// random play.
+pub trait TablebaseProber: Send + Sync {
+    /// Largest number of pieces, including kings, the tables cover. 6 for the full Syzygy set.
+    fn max_pieces(&self) -> u32;
+
+    /// Probes the win/draw/loss outcome of `board`.
+    ///
+    /// # Invariants
+    /// `board` has at most `max_pieces` pieces and no castling rights.
+    ///
+    /// # Returns
+    /// The outcome for the side to move, or None if the position is not covered by the tables.
+    fn probe_wdl(&self, board: &Board) -> Option<Wdl>;
+}
+
+
+/// Prober registered for the process.
+static TABLEBASE: OnceLock<Box<dyn TablebaseProber>> = OnceLock::new();
+
+
+/// Registers the tablebase prober used by chess game states.
+///
+/// The prober can only be registered once per process, as game states probe it
+/// on every rollout ply without synchronization.
+///
+/// # Returns
+/// The prober back as an error if one has already been registered.
+pub fn set_tablebase(prober: Box<dyn TablebaseProber>) -> Result<(), Box<dyn TablebaseProber>> {
+    return TABLEBASE.set(prober);
+}
+
+
+/// Probes the registered tablebase for `board`.
+///
+/// # Returns
+/// The outcome for the side to move, or None without a registered prober,
+/// with too many pieces on the board, with castling rights left or for positions
+/// the tables do not cover.
+pub fn probe(board: &Board) -> Option<Wdl> {
+    let prober = TABLEBASE.get()?;
+    if board.combined().popcnt() > prober.max_pieces() {
+        return None;
+    }
+    let no_castling = board.castle_rights(Color::White) == CastleRights::NoRights
+        && board.castle_rights(Color::Black) == CastleRights::NoRights;
+    if !no_castling {
+        return None;
+    }
+    return prober.probe_wdl(board);
+}
@@ -0,0 +1,285 @@
+//! Stopping rules of searches, besides their number of iterations.
+//!
+//! Searches terminate early when their best move can no longer change: the most visited child
+//! is the move played, and every iteration adds a single visit, so a runner-up trailing by more
+//! visits than there are iterations left can never overtake it. Searches also stop once the tree
+//! exhausts its `SearchBudget` of nodes or memory, and with the `KlStopping` hooks once the
+//! visit distribution of the root has converged.
+
+use crate::arena::{NodeId, NodeIndex};
+use crate::game_state_trait::GameState;
+use crate::hooks::{SearchControl, SearchHooks};
+use crate::mcts::MCTSTree;
+use crate::float::Float;
+use alloc::vec::Vec;
+
+
+/// Limits on the size of a tree, for memory constrained environments and fair engine matches.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SearchBudget {
+    /// Number of nodes in the arena at which searches stop. None for no limit.
+    pub max_nodes: Option<usize>,
+
+    /// Bytes reserved by the arena, see `MCTSArena::memory_usage`, at which searches stop.
+    /// Reservations grow in steps as the arena fills, so the limit can be exceeded by the last step.
+    /// None for no limit.
+    pub max_memory_bytes: Option<usize>,
+}
+
+
+/// Stopping rules.
+impl<Action, GameStateObj, Data> MCTSTree<Action, GameStateObj, Data>
+where
+    GameStateObj: GameState<Action> + Clone
+{
+    /// The most visited expanded child of `node` with its visits, and the visits of the runner-up.
+    /// Ties go to the first expanded child.
+    ///
+    /// # Returns
+    /// None if `node` has no expanded children. The runner-up has 0 visits if there is none.
+    ///
+    /// # Panics
+    /// If `node` is stale.
+    pub fn best_and_runner_up(&self, node: NodeId) -> Option<(NodeId, u64, u64)> {
+        let best = self.best_and_runner_up_at(self.index_of(node));
+        return best.map(|(child, sims, runner_up)| (self.arena.handle(child), sims, runner_up));
+    }
+
+    /// `best_and_runner_up` by arena index.
+    pub(crate) fn best_and_runner_up_at(&self, node: NodeIndex) -> Option<(NodeIndex, u64, u64)> {
+        let mut best: Option<(NodeIndex, u64)> = None;
+        let mut runner_up = 0;
+        for child in self.arena.expanded(node) {
+            let sims = self.arena.stats[child as usize].sims;
+            match best {
+                Some((_, best_sims)) if sims <= best_sims => runner_up = runner_up.max(sims),
+                _ => {
+                    runner_up = best.map_or(0, |(_, best_sims)| best_sims);
+                    best = Some((child, sims));
+                },
+            }
+        }
+        return best.map(|(child, sims)| (child, sims, runner_up));
+    }
+
+    /// Checks whether the most visited child of `root` is certain to remain the most visited
+    /// after `remaining` more iterations searched from `root`.
+    ///
+    /// Children which have not been expanded yet count as runners-up without visits. A root
+    /// with a single legal action is always decided.
+    ///
+    /// # Panics
+    /// If `root` is stale.
+    pub fn best_move_decided(&self, root: NodeId, remaining: u64) -> bool {
+        return self.best_move_decided_at(self.index_of(root), remaining);
+    }
+
+    /// `best_move_decided` by arena index.
+    pub(crate) fn best_move_decided_at(&self, root: NodeIndex, remaining: u64) -> bool {
+        let Some((_, best, runner_up)) = self.best_and_runner_up_at(root) else {
+            return false;
+        };
+        if best == 0 {
+            return false;
+        }
+        if self.arena.expanded_count(root) == 1 && !self.arena.has_untried_actions(root) {
+            return true;
+        }
+
+        // The runner-up wins ties by being expanded first in the worst case, so it must stay strictly behind.
+        return best - runner_up > remaining;
+    }
+
+    /// Checks whether the tree has reached a limit of its `budget`.
+    pub fn budget_exhausted(&self) -> bool {
+        return self.budget.max_nodes.is_some_and(|max_nodes| self.arena.len() >= max_nodes)
+            || self.budget.max_memory_bytes.is_some_and(|max_memory_bytes| self.arena.memory_usage() >= max_memory_bytes);
+    }
+}
+
+
+/// Stops a search once the visit distribution of the root stops changing, spending less time on obvious moves.
+///
+/// Every `interval` iterations, the KL divergence of the current visit distribution of the root's
+/// children from the one at the previous check is measured, and scaled to a divergence per 1000
+/// simulations of the root. The search stops once it falls below `threshold`. Children visited
+/// for the first time since the previous check make the divergence infinite, so the search goes on.
+#[derive(Debug, Clone)]
+pub struct KlStopping {
+    /// Number of iterations between measurements.
+    pub interval: u64,
+
+    /// Divergence per 1000 simulations of the root below which the search stops.
+    pub threshold: f64,
+
+    /// Visits of the children of the root at the previous check, in expansion order.
+    previous: Vec<u64>,
+}
+
+
+impl KlStopping {
+    /// Creates a stopping rule measuring the divergence every `interval` iterations.
+    ///
+    /// # Panics
+    /// If `interval` is 0.
+    pub fn new(interval: u64, threshold: f64) -> Self {
+        assert!(interval > 0, "interval must be positive");
+        return KlStopping { interval, threshold, previous: Vec::new() };
+    }
+
+    /// KL divergence of the current visit distribution of the children of the root from the
+    /// one at the previous check, per 1000 simulations added since, and records the current one.
+    ///
+    /// # Returns
+    /// None at the first check, or if no child has been visited since the previous check.
+    /// Infinity if a child was visited for the first time since the previous check.
+    pub fn divergence<Action, GameStateObj, Data>(&mut self, tree: &MCTSTree<Action, GameStateObj, Data>) -> Option<f64>
+    where
+        GameStateObj: GameState<Action> + Clone
+    {
+        let current: Vec<u64> = tree.arena.expanded(0).map(|child| tree.arena.stats[child as usize].sims).collect();
+        let previous = core::mem::replace(&mut self.previous, current);
+        let previous_total: u64 = previous.iter().sum();
+        let current_total: u64 = self.previous.iter().sum();
+        if previous_total == 0 || current_total == previous_total {
+            return None;
+        }
+
+        // Children expanded since the previous check had no visits then.
+        let mut divergence = 0.0;
+        for (i, sims) in self.previous.iter().enumerate() {
+            if *sims == 0 {
+                continue;
+            }
+            let old = previous.get(i).copied().unwrap_or(0);
+            if old == 0 {
+                return Some(f64::INFINITY);
+            }
+            let p = *sims as f64 / current_total as f64;
+            let q = old as f64 / previous_total as f64;
+            divergence += p * Float::ln(p / q);
+        }
+        return Some(divergence * 1000.0 / (current_total - previous_total) as f64);
+    }
+}
+
+impl<Action, GameStateObj, Data> SearchHooks<Action, GameStateObj, Data> for KlStopping
+where
+    GameStateObj: GameState<Action> + Clone
+{
+    fn on_iteration(&mut self, tree: &MCTSTree<Action, GameStateObj, Data>, iteration: u64) -> SearchControl {
+        if !iteration.is_multiple_of(self.interval) {
+            return SearchControl::Continue;
+        }
+        return match self.divergence(tree) {
+            Some(divergence) if divergence < self.threshold => SearchControl::Stop,
+            _ => SearchControl::Continue,
+        };
+    }
+
+    fn tracks_best_move(&self) -> bool {
+        return false;
+    }
+}
+
+
+/// Defines unit tests for stopping rules.
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game_state_trait::GameResult;
+    use crate::tictactoe_env::TicTacToeState;
+
+    /// Test if the best move is decided once the runner-up cannot catch up.
+    #[test]
+    fn test_best_move_decided() {
+        let mut tree = MCTSTree::<u8, TicTacToeState>::with_capacity(10, Some(1), "X...O....".to_string(), 9);
+        assert!(!tree.best_move_decided_at(0, 0));
+        let first = tree.expand_at(0);
+        let second = tree.expand_at(0);
+        for _i in 0..5 {
+            tree.backpropagate_at(first, GameResult::Draw);
+        }
+        tree.backpropagate_at(second, GameResult::Draw);
+        assert_eq!(tree.best_and_runner_up_at(0), Some((first, 5, 1)));
+        assert!(tree.best_move_decided_at(0, 3));
+        assert!(!tree.best_move_decided_at(0, 4));
+
+        // A single legal action is decided regardless of the budget.
+        let mut tree = MCTSTree::<u8, TicTacToeState>::with_capacity(10, Some(1), "XOXOXOOX.".to_string(), 9);
+        let only = tree.expand_at(0);
+        tree.backpropagate_at(only, GameResult::Draw);
+        assert!(tree.best_move_decided_at(0, u64::MAX));
+    }
+
+    /// Test if searches with early termination stop before their iteration budget, with the same best move.
+    #[test]
+    fn test_early_termination() {
+        let mut tree = MCTSTree::<u8, TicTacToeState>::with_capacity(20000, Some(1), "X...O....".to_string(), 9);
+        let mut full = MCTSTree::<u8, TicTacToeState>::with_capacity(20000, Some(1), "X...O....".to_string(), 9);
+        tree.searcher.early_termination = true;
+        let searched = tree.search_with_hooks(10000, &mut ());
+        full.search(10000);
+        assert!(searched < 10000);
+        assert!(tree.best_move_decided_at(0, 10000 - searched));
+        let best = tree.best_and_runner_up_at(0).unwrap().0;
+        let full_best = full.best_and_runner_up_at(0).unwrap().0;
+        assert_eq!(tree.arena.actions[best as usize], full.arena.actions[full_best as usize]);
+    }
+
+    /// Test if the divergence of the root's visit distribution is measured between checks.
+    #[test]
+    fn test_kl_divergence() {
+        let mut tree = MCTSTree::<u8, TicTacToeState>::with_capacity(10, Some(1), "X...O....".to_string(), 9);
+        let mut rule = KlStopping::new(1, 0.0);
+        let first = tree.expand_at(0);
+        let second = tree.expand_at(0);
+        assert_eq!(rule.divergence(&tree), None);
+        tree.backpropagate_at(first, GameResult::Draw);
+        assert_eq!(rule.divergence(&tree), None);
+        tree.backpropagate_at(second, GameResult::Draw);
+        assert_eq!(rule.divergence(&tree), Some(f64::INFINITY));
+
+        // Keeping the distribution at 1/2 diverges by nothing, moving it to 3/4 by KL(3/4, 1/4 || 1/2, 1/2).
+        tree.backpropagate_at(first, GameResult::Draw);
+        tree.backpropagate_at(second, GameResult::Draw);
+        assert!(rule.divergence(&tree).unwrap().abs() < 1e-12);
+        for _i in 0..4 {
+            tree.backpropagate_at(first, GameResult::Draw);
+        }
+        let expected = (0.75 * f64::ln(1.5) + 0.25 * f64::ln(0.5)) * 1000.0 / 4.0;
+        assert!((rule.divergence(&tree).unwrap() - expected).abs() < 1e-9);
+        assert_eq!(rule.divergence(&tree), None);
+    }
+
+    /// Test if searches stop once their root distribution converges, sooner for looser thresholds.
+    #[test]
+    fn test_kl_stopping() {
+        let mut searched = Vec::new();
+        for threshold in [0.02, 0.001] {
+            let mut tree = MCTSTree::<u8, TicTacToeState>::with_capacity(60000, Some(1), ".........".to_string(), 9);
+            searched.push(tree.search_with_hooks(50000, &mut KlStopping::new(500, threshold)));
+        }
+        assert!(searched[0] < searched[1]);
+        assert!(searched[0] < 50000 && searched[0].is_multiple_of(500));
+    }
+
+    /// Test if searches stop once the tree reaches its node or memory budget.
+    #[test]
+    fn test_budget() {
+        let mut tree = MCTSTree::<u8, TicTacToeState>::with_capacity(100, Some(1), ".........".to_string(), 9);
+        tree.budget.max_nodes = Some(50);
+        assert_eq!(tree.search_with_hooks(1000, &mut ()), 49);
+        assert_eq!(tree.arena.len(), 50);
+        assert!(tree.budget_exhausted());
+        assert_eq!(tree.search_with_hooks(1000, &mut ()), 0);
+
+        // Arrays reserved for 100 nodes are outgrown before 1000 nodes are allocated.
+        let mut tree = MCTSTree::<u8, TicTacToeState>::with_capacity(100, Some(1), ".........".to_string(), 9);
+        let reserved = tree.arena.memory_usage();
+        tree.budget.max_memory_bytes = Some(reserved + 1);
+        let searched = tree.search_with_hooks(1000, &mut ());
+        assert!((99..1000).contains(&searched));
+        assert!(tree.arena.memory_usage() > reserved);
+    }
+}
@@ -0,0 +1,386 @@
+use crate::game_state_trait::{GameResult, GameState};
+use crate::serialize::{decode_from_str, Decode, DecodeError, Encode};
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::fmt;
+
+
+/// Lines of three cells which win the game, as indexes into the board.
+const LINES: [[usize; 3]; 8] = [
+    [0, 1, 2], [3, 4, 5], [6, 7, 8],
+    [0, 3, 6], [1, 4, 7], [2, 5, 8],
+    [0, 4, 8], [2, 4, 6],
+];
+
+
+/// Error produced when a string is not a valid Tic-Tac-Toe position.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TicTacToeParseError(pub String);
+
+impl fmt::Display for TicTacToeParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        return write!(f, "invalid tic-tac-toe position: {}", self.0);
+    }
+}
+
+impl core::error::Error for TicTacToeParseError {}
+
+
+/// Holds the state of a Tic-Tac-Toe board.
+///
+/// Cells are indexed from 0 to 8, row by row from the top left corner, and actions are
+/// the index of the cell to mark. X moves first and is the first player.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct TicTacToeState {
+    /// Marks of every cell, with true for X and false for O.
+    pub cells: [Option<bool>; 9],
+
+    /// True if X is the side to move.
+    pub x_to_move: bool,
+}
+
+
+impl TicTacToeState {
+    /// Creates the empty starting board.
+    pub fn new() -> Self {
+        return TicTacToeState { cells: [None; 9], x_to_move: true };
+    }
+
+    /// Determines the player who has completed a line, if any.
+    ///
+    /// # Returns
+    /// True if X has won, false if O has won, or None if neither has a line.
+    pub fn winner(&self) -> Option<bool> {
+        for line in LINES {
+            let first = self.cells[line[0]];
+            if first.is_some() && first == self.cells[line[1]] && first == self.cells[line[2]] {
+                return first;
+            }
+        }
+        return None;
+    }
+
+    /// Reverts `action`, which must be the last action applied with `apply_action_mut`.
+    pub fn undo_action(&mut self, action: &u8) {
+        self.cells[*action as usize] = None;
+        self.x_to_move = !self.x_to_move;
+    }
+}
+
+impl Default for TicTacToeState {
+    fn default() -> Self {
+        return TicTacToeState::new();
+    }
+}
+
+
+/// Encodes the board as 9 characters, row by row, with `X`, `O` and `.` for empty cells,
+/// which is the format parsed by `GameState::from_str`.
+impl fmt::Display for TicTacToeState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for cell in self.cells {
+            let mark = match cell {
+                Some(true) => 'X',
+                Some(false) => 'O',
+                None => '.',
+            };
+            write!(f, "{}", mark)?;
+        }
+        return Ok(());
+    }
+}
+
+
+/// Allows the MCTS engine to build TicTacToeState trees.
+impl GameState<u8> for TicTacToeState {
+    type ParseError = TicTacToeParseError;
+
+    fn from_str(game_state: String) -> Self {
+        return Self::try_from_str(&game_state).expect("invalid tic-tac-toe position");
+    }
+
+    fn try_from_str(game_state: &str) -> Result<Self, TicTacToeParseError> {
+        // Whitespace is ignored so boards can be written as three rows.
+        let marks: Vec<char> = game_state.chars().filter(|mark| !mark.is_whitespace()).collect();
+        if marks.len() != 9 {
+            return Err(TicTacToeParseError(game_state.to_string()));
+        }
+
+        let mut state = TicTacToeState::new();
+        for (cell, mark) in marks.iter().enumerate() {
+            state.cells[cell] = match mark.to_ascii_uppercase() {
+                'X' => Some(true),
+                'O' => Some(false),
+                '.' | '-' | '_' => None,
+                _ => return Err(TicTacToeParseError(game_state.to_string())),
+            };
+        }
+
+        // X always moves first, so the side to move follows from the number of marks.
+        let x_count = state.cells.iter().filter(|cell| **cell == Some(true)).count();
+        let o_count = state.cells.iter().filter(|cell| **cell == Some(false)).count();
+        if x_count != o_count && x_count != o_count + 1 {
+            return Err(TicTacToeParseError(game_state.to_string()));
+        }
+        state.x_to_move = x_count == o_count;
+        return Ok(state);
+    }
+
+    fn apply_action(&self, action: &u8) -> Self {
+        let mut new_state = *self;
+        new_state.apply_action_mut(action);
+        return new_state;
+    }
+
+    fn apply_action_mut(&mut self, action: &u8) {
+        self.cells[*action as usize] = Some(self.x_to_move);
+        self.x_to_move = !self.x_to_move;
+    }
+
+    fn status_with_moves_left(&self) -> bool {
+        return self.winner().is_none();
+    }
+
+    fn result(&self) -> GameResult {
+        match self.winner() {
+            Some(true) => return GameResult::FirstPlayerWin,
+            Some(false) => return GameResult::SecondPlayerWin,
+            None => return GameResult::Draw,
+        }
+    }
+
+    fn generate_legal_actions(&self) -> Vec<u8> {
+        let mut actions = Vec::with_capacity(9);
+        self.legal_actions_into(&mut actions);
+        return actions;
+    }
+
+    fn legal_actions_into(&self, actions: &mut Vec<u8>) {
+        actions.clear();
+        // A completed line ends the game, even with empty cells left.
+        if self.winner().is_some() {
+            return;
+        }
+        actions.extend((0..9).filter(|cell| self.cells[*cell as usize].is_none()));
+    }
+
+    fn state_hash(&self) -> Option<u64> {
+        // Every cell is encoded in base 3, which also determines the side to move.
+        let hash = self.cells.iter().fold(0, |hash, cell| {
+            return hash * 3 + match cell { None => 0, Some(true) => 1, Some(false) => 2 };
+        });
+        return Some(hash);
+    }
+
+    fn action_code(&self, action: &u8) -> Option<u64> {
+        // The same cell is coded apart for either side.
+        return Some(*action as u64 * 2 + self.x_to_move as u64);
+    }
+
+    fn rollout_move_cap(&self) -> Option<u32> {
+        // Games end within 9 moves.
+        return None;
+    }
+
+    fn side_to_move(&self) -> bool {
+        return self.x_to_move;
+    }
+}
+
+
+/// Encodes the state through its string encoding, which holds every part of it.
+impl Encode for TicTacToeState {
+    fn encode(&self, out: &mut Vec<u8>) {
+        self.to_string().encode(out);
+    }
+}
+
+impl Decode for TicTacToeState {
+    fn decode(input: &mut &[u8]) -> Result<Self, DecodeError> {
+        return decode_from_str(input);
+    }
+}
+
+
+/// Encodes the board as two 3x3 planes, the marks of the side to move and of its opponent.
+/// Actions are the index of their cell.
+#[cfg(feature = "neural")]
+impl crate::neural::TensorEncoding<u8> for TicTacToeState {
+    const INPUT_SHAPE: &'static [usize] = &[2, 3, 3];
+    const POLICY_SIZE: usize = 9;
+
+    fn encode(&self, input: &mut [f32]) {
+        for (cell, mark) in self.cells.iter().enumerate() {
+            if let Some(x) = mark {
+                let plane = if *x == self.x_to_move { 0 } else { 1 };
+                input[plane * 9 + cell] = 1.0;
+            }
+        }
+    }
+
+    fn action_index(&self, action: &u8) -> usize {
+        return *action as usize;
+    }
+}
+
+
+/// Defines unit tests for the GameState implementation of TicTacToeState,
+/// checking the rules exhaustively and the engine against perfect play.
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mcts::MCTSTree;
+    use std::collections::HashMap;
+
+    /// Counts the games reachable from `state` by their outcome, as first player wins,
+    /// second player wins and draws, while recording every distinct position reached.
+    fn count_games(state: &mut TicTacToeState, positions: &mut HashMap<u64, TicTacToeState>) -> [u64; 3] {
+        positions.insert(state.state_hash().unwrap(), *state);
+        let actions = state.generate_legal_actions();
+        if actions.len() == 0 || !state.status_with_moves_left() {
+            match state.result() {
+                GameResult::FirstPlayerWin => return [1, 0, 0],
+                GameResult::SecondPlayerWin => return [0, 1, 0],
+                _ => return [0, 0, 1],
+            }
+        }
+        let mut counts = [0; 3];
+        for action in actions {
+            state.apply_action_mut(&action);
+            let child_counts = count_games(state, positions);
+            state.undo_action(&action);
+            for i in 0..3 {
+                counts[i] += child_counts[i];
+            }
+        }
+        return counts;
+    }
+
+    /// Solves `state` with negamax, memoized by state hash.
+    ///
+    /// # Returns
+    /// 1 if the side to move wins with perfect play, 0 for a draw and -1 for a loss.
+    fn solve(state: &TicTacToeState, memo: &mut HashMap<u64, i32>) -> i32 {
+        let hash = state.state_hash().unwrap();
+        if let Some(value) = memo.get(&hash) {
+            return *value;
+        }
+        let actions = state.generate_legal_actions();
+        let value = if actions.len() == 0 {
+            match state.result().winner() {
+                Some(winner) if winner == state.side_to_move() => 1,
+                Some(_) => -1,
+                None => 0,
+            }
+        }
+        else {
+            actions.iter().map(|action| -solve(&state.apply_action(action), memo)).max().unwrap()
+        };
+        memo.insert(hash, value);
+        return value;
+    }
+
+    /// Chooses the move of the engine after `iterations` iterations of search from `state`.
+    fn engine_move(state: &TicTacToeState, iterations: u32, seed: u64) -> u8 {
+        let mut tree = MCTSTree::<u8, TicTacToeState>::with_capacity(iterations as usize + 1, Some(seed), state.to_string(), 9);
+        tree.search(iterations as u64);
+        let best = tree.get_max_uct_child_at(0, Some(0.0)).expect("no legal moves");
+        return tree.arena.actions[best as usize].expect("no action");
+    }
+
+    /// Test if the game tree matches the known number of games and positions.
+    #[test]
+    fn test_game_tree_counts() {
+        let mut positions = HashMap::new();
+        let counts = count_games(&mut TicTacToeState::new(), &mut positions);
+        assert_eq!(counts, [131184, 77904, 46080]);
+        assert_eq!(positions.len(), 5478);
+
+        // Every reachable position survives a round trip through its string encoding.
+        for state in positions.values() {
+            assert_eq!(TicTacToeState::from_str(state.to_string()), *state);
+        }
+    }
+
+    /// Test if positions are parsed with the side to move, and invalid ones are rejected.
+    #[test]
+    fn test_try_from_str() {
+        let state = TicTacToeState::try_from_str("X.. .O. ..X").unwrap();
+        assert_eq!(state.cells[0], Some(true));
+        assert_eq!(state.cells[4], Some(false));
+        assert!(!state.side_to_move());
+        assert_eq!(state.to_string(), "X...O...X");
+
+        assert!(TicTacToeState::try_from_str("X........").is_ok());
+        assert!(TicTacToeState::try_from_str("XX.......").is_err());
+        assert!(TicTacToeState::try_from_str("O........").is_err());
+        assert!(TicTacToeState::try_from_str("X..").is_err());
+        assert!(TicTacToeState::try_from_str("X.......Z").is_err());
+    }
+
+    /// Test if completed lines end the game with the right winner, and full boards are drawn.
+    #[test]
+    fn test_result() {
+        let x_wins = TicTacToeState::from_str("XXXOO....".to_string());
+        assert!(x_wins.generate_legal_actions().is_empty());
+        assert_eq!(x_wins.result(), GameResult::FirstPlayerWin);
+
+        let o_wins = TicTacToeState::from_str("XXOXO.O..".to_string());
+        assert!(!o_wins.status_with_moves_left());
+        assert_eq!(o_wins.result(), GameResult::SecondPlayerWin);
+
+        let draw = TicTacToeState::from_str("XOXXOOOXX".to_string());
+        assert!(draw.generate_legal_actions().is_empty());
+        assert_eq!(draw.result(), GameResult::Draw);
+    }
+
+    /// Test if the engine finds immediate wins and blocks immediate losses.
+    #[test]
+    fn test_tactics() {
+        // X completes the top row rather than blocking.
+        let win = TicTacToeState::from_str("XX.OO....".to_string());
+        assert_eq!(engine_move(&win, 2000, 1), 2);
+
+        // O has to block the top row.
+        let block = TicTacToeState::from_str("XX..O....".to_string());
+        assert_eq!(engine_move(&block, 2000, 1), 2);
+    }
+
+    /// Test if the engine never loses against a perfect opponent, playing either side from every
+    /// opening move, which checks selection, expansion, simulation and backpropagation end to end.
+    #[test]
+    fn test_never_loses() {
+        let mut memo = HashMap::new();
+        assert_eq!(solve(&TicTacToeState::new(), &mut memo), 0);
+
+        for engine_is_x in [true, false] {
+            for opening in 0..9 {
+                let mut state = TicTacToeState::new();
+                if !engine_is_x {
+                    state.apply_action_mut(&opening);
+                }
+                let mut seed = opening as u64;
+                while state.generate_legal_actions().len() > 0 {
+                    let action = if state.side_to_move() == engine_is_x {
+                        seed += 1;
+                        engine_move(&state, 5000, seed)
+                    }
+                    else {
+                        // The opponent plays the first move which keeps its best outcome.
+                        let actions = state.generate_legal_actions();
+                        let best = actions.iter().map(|action| -solve(&state.apply_action(action), &mut memo)).max().unwrap();
+                        *actions.iter().find(|action| -solve(&state.apply_action(action), &mut memo) == best).unwrap()
+                    };
+
+                    // Every engine move keeps at least a draw.
+                    let before = solve(&state, &mut memo);
+                    state.apply_action_mut(&action);
+                    if state.side_to_move() != engine_is_x {
+                        assert!(-solve(&state, &mut memo) >= before.min(0), "engine blundered into {}", state);
+                    }
+                }
+                assert!(state.result().winner() != Some(!engine_is_x), "engine lost {}", state);
+            }
+        }
+    }
+}
@@ -0,0 +1,200 @@
+//! Time management of searches, with a soft limit which is extended while the best move is
+//! unstable and a hard limit which is never exceeded.
+
+use crate::arena::NodeIndex;
+use crate::game_state_trait::GameState;
+use crate::hooks::{SearchControl, SearchHooks};
+use crate::mcts::MCTSTree;
+use crate::clock::Instant;
+use std::time::Duration;
+
+
+/// Visits of the runner-up, relative to the best move, above which the best move is unstable.
+const RUNNER_UP_RATIO: f64 = 0.9;
+
+
+/// Time limits of a search.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TimeControl {
+    /// Time after which the search stops, as soon as the best move is stable.
+    pub soft: Duration,
+
+    /// Time after which the search stops regardless of the best move.
+    pub hard: Duration,
+}
+
+impl TimeControl {
+    /// Time control stopping at exactly `time`, without extensions.
+    pub fn fixed(time: Duration) -> Self {
+        return TimeControl { soft: time, hard: time };
+    }
+}
+
+
+/// Decides when a search should stop under a `TimeControl`.
+///
+/// Past the soft limit, the best move is considered unstable if it changed during the last
+/// quarter of the soft limit, or if the runner-up has nearly as many visits, in which case the
+/// search is extended until it stabilizes or the hard limit is reached.
+///
+/// With `Searcher::early_termination`, the search also stops as soon as the best move cannot
+/// change within the iterations expected to fit before the hard limit.
+#[derive(Debug, Clone)]
+pub struct TimeManager {
+    /// Limits of the search.
+    pub control: TimeControl,
+
+    /// Start of the search.
+    start: Instant,
+
+    /// Most visited child of the root at the last check.
+    best: Option<NodeIndex>,
+
+    /// Time the best move last changed.
+    last_change: Instant,
+
+    /// Time of the first check and visits of the root then, to measure the rate of the search.
+    first_check: Option<(Instant, u64)>,
+}
+
+
+impl TimeManager {
+    /// Creates a manager for `control`, starting the clock of the search.
+    pub fn new(control: TimeControl) -> Self {
+        let start = Instant::now();
+        return TimeManager { control, start, best: None, last_change: start, first_check: None };
+    }
+
+    /// Time since the search started.
+    pub fn elapsed(&self) -> Duration {
+        return self.start.elapsed();
+    }
+
+    /// Checks whether the search of `tree` should stop, tracking changes of the best move.
+    ///
+    /// Changes of the best move are only seen when checked, so this should be called regularly,
+    /// such as every few iterations.
+    pub fn should_stop<Action, GameStateObj, Data>(&mut self, tree: &MCTSTree<Action, GameStateObj, Data>) -> bool
+    where
+        GameStateObj: GameState<Action> + Clone
+    {
+        let now = Instant::now();
+        let elapsed = now - self.start;
+        if elapsed >= self.control.hard {
+            return true;
+        }
+
+        let best = tree.best_and_runner_up_at(0);
+        let best_child = best.filter(|(_, sims, _)| *sims > 0).map(|(child, _, _)| child);
+        if best_child != self.best {
+            self.best = best_child;
+            self.last_change = now;
+        }
+
+        // Estimate the iterations left before the hard limit from the rate of the search since the first check.
+        let visits = tree.arena.stats[0].sims;
+        let (first_time, first_visits) = *self.first_check.get_or_insert((now, visits));
+        if tree.searcher.early_termination && now > first_time {
+            let rate = (visits - first_visits) as f64 / (now - first_time).as_secs_f64();
+            let remaining = rate * (self.control.hard - elapsed).as_secs_f64();
+            if tree.best_move_decided_at(0, remaining.ceil() as u64) {
+                return true;
+            }
+        }
+        if elapsed < self.control.soft {
+            return false;
+        }
+
+        let recently_changed = now - self.last_change < self.control.soft / 4;
+        let contested = best.is_some_and(|(_, best_sims, runner_up)| runner_up as f64 >= RUNNER_UP_RATIO * best_sims as f64);
+        return !recently_changed && !contested;
+    }
+}
+
+impl<Action, GameStateObj, Data> SearchHooks<Action, GameStateObj, Data> for TimeManager
+where
+    GameStateObj: GameState<Action> + Clone
+{
+    fn on_iteration(&mut self, tree: &MCTSTree<Action, GameStateObj, Data>, _iteration: u64) -> SearchControl {
+        return if self.should_stop(tree) { SearchControl::Stop } else { SearchControl::Continue };
+    }
+
+    fn tracks_best_move(&self) -> bool {
+        return false;
+    }
+}
+
+
+/// Time managed search driver.
+impl<Action, GameStateObj, Data> MCTSTree<Action, GameStateObj, Data>
+where
+    GameStateObj: GameState<Action> + Clone
+{
+    /// Searches from the root under `control`, until the soft limit once the best move is
+    /// stable, and never past the hard limit, see `TimeManager`.
+    ///
+    /// # Returns
+    /// The number of iterations searched.
+    pub fn go(&mut self, control: TimeControl) -> u64 {
+        return self.search_with_hooks(u64::MAX, &mut TimeManager::new(control));
+    }
+}
+
+
+/// Defines unit tests for time management.
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game_state_trait::GameResult;
+    use crate::tictactoe_env::TicTacToeState;
+
+    /// Test if searches stop between the soft and hard limits.
+    #[test]
+    fn test_go() {
+        let mut tree = MCTSTree::<u8, TicTacToeState>::with_capacity(100000, Some(1), ".........".to_string(), 9);
+        let control = TimeControl { soft: Duration::from_millis(20), hard: Duration::from_millis(200) };
+        let start = Instant::now();
+        let searched = tree.go(control);
+        let elapsed = start.elapsed();
+        assert!(searched > 0);
+        assert!(elapsed >= control.soft);
+        assert!(elapsed < control.hard + Duration::from_millis(100));
+        assert_eq!(tree.counters.iterations, searched);
+    }
+
+    /// Test if unstable best moves extend the search past the soft limit.
+    #[test]
+    fn test_extension() {
+        let mut tree = MCTSTree::<u8, TicTacToeState>::with_capacity(10, Some(1), ".........".to_string(), 9);
+        let control = TimeControl { soft: Duration::from_millis(10), hard: Duration::from_millis(40) };
+        let mut manager = TimeManager::new(control);
+
+        // Two children with equal visits contest the best move.
+        for _i in 0..2 {
+            let child = tree.expand_at(0);
+            tree.backpropagate_at(child, GameResult::Draw);
+        }
+        assert!(!manager.should_stop(&tree));
+        std::thread::sleep(control.soft);
+        assert!(!manager.should_stop(&tree));
+
+        // Once one child leads, the best move is stable.
+        let leader = tree.arena.expanded(0).next().unwrap();
+        for _i in 0..10 {
+            tree.backpropagate_at(leader, GameResult::Draw);
+        }
+        assert!(manager.should_stop(&tree));
+
+        // Fixed time controls stop at their limit regardless of the best move.
+        let mut fixed = TimeManager::new(TimeControl::fixed(control.soft));
+        std::thread::sleep(control.soft);
+        assert!(fixed.should_stop(&tree));
+
+        // With early termination, a lead which cannot be caught before the hard limit stops the search.
+        tree.searcher.early_termination = true;
+        let mut early = TimeManager::new(TimeControl { soft: Duration::from_secs(60), hard: Duration::from_secs(120) });
+        assert!(!early.should_stop(&tree));
+        std::thread::sleep(Duration::from_millis(10));
+        assert!(early.should_stop(&tree));
+    }
+}
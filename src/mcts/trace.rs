@@ -0,0 +1,174 @@
+//! Instrumentation of the search phases, compiled in with the `trace` feature.
+//!
+//! Selection, expansion, simulation and backpropagation each open a span, which reports its
+//! phase, its node and the time spent in it to the current `Subscriber` once it closes.
+//! Simulations also report the number of moves played as a counter. Without the feature,
+//! the instrumentation is compiled out of the search entirely.
+//!
+//! The model follows the `tracing` crate, without depending on it: subscribers are set for
+//! the whole process with `set_global_subscriber`, or for a scope of the current thread with
+//! `with_subscriber`, and a subscriber forwarding to `tracing` can be written on top of this one.
+
+use crate::arena::NodeIndex;
+use std::cell::RefCell;
+use std::sync::{Arc, OnceLock};
+use crate::clock::Instant;
+use std::time::Duration;
+
+
+/// Phase of a search iteration.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Phase {
+    Select,
+    Expand,
+    Simulate,
+    Backpropagate,
+}
+
+impl Phase {
+    /// Name of the phase, as the name of its method on `MCTSTree`.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Phase::Select => return "select",
+            Phase::Expand => return "expand",
+            Phase::Simulate => return "simulate",
+            Phase::Backpropagate => return "backpropagate",
+        }
+    }
+}
+
+
+/// Receives the spans and counters of the search.
+///
+/// Subscribers are called from every thread searching, so they must be cheap and thread safe.
+pub trait Subscriber: Send + Sync {
+    /// Called when a span of `phase` opens, at `node`.
+    fn on_enter(&self, _phase: Phase, _node: NodeIndex) {}
+
+    /// Called when a span of `phase` at `node` closes, after `elapsed` time.
+    fn on_exit(&self, phase: Phase, node: NodeIndex, elapsed: Duration);
+
+    /// Called with the value of a counter, such as `rollout_moves`.
+    fn on_counter(&self, _name: &'static str, _value: u64) {}
+}
+
+
+/// Subscriber of the whole process.
+static GLOBAL_SUBSCRIBER: OnceLock<Arc<dyn Subscriber>> = OnceLock::new();
+
+thread_local! {
+    /// Subscriber of the current thread, which takes precedence over the global one.
+    static THREAD_SUBSCRIBER: RefCell<Option<Arc<dyn Subscriber>>> = const { RefCell::new(None) };
+}
+
+
+/// Sets the subscriber of the whole process. It can only be set once.
+///
+/// # Returns
+/// The subscriber back if one was already set.
+pub fn set_global_subscriber(subscriber: Arc<dyn Subscriber>) -> Result<(), Arc<dyn Subscriber>> {
+    return GLOBAL_SUBSCRIBER.set(subscriber);
+}
+
+/// Runs `scope` with `subscriber` receiving the spans of the current thread.
+pub fn with_subscriber<T>(subscriber: Arc<dyn Subscriber>, scope: impl FnOnce() -> T) -> T {
+    let previous = THREAD_SUBSCRIBER.with(|current| current.replace(Some(subscriber)));
+    let result = scope();
+    THREAD_SUBSCRIBER.with(|current| current.replace(previous));
+    return result;
+}
+
+/// The subscriber of the current thread, or the global one.
+fn current_subscriber() -> Option<Arc<dyn Subscriber>> {
+    let thread_subscriber = THREAD_SUBSCRIBER.with(|current| current.borrow().clone());
+    return thread_subscriber.or_else(|| GLOBAL_SUBSCRIBER.get().cloned());
+}
+
+
+/// An open span, which reports to the subscriber it was opened with once dropped.
+pub struct Span {
+    /// None if no subscriber was set when the span opened.
+    open: Option<(Arc<dyn Subscriber>, Phase, NodeIndex, Instant)>,
+}
+
+/// Opens a span of `phase` at `node`.
+pub fn span(phase: Phase, node: NodeIndex) -> Span {
+    let open = current_subscriber().map(|subscriber| {
+        subscriber.on_enter(phase, node);
+        return (subscriber, phase, node, Instant::now());
+    });
+    return Span { open };
+}
+
+impl Drop for Span {
+    fn drop(&mut self) {
+        if let Some((subscriber, phase, node, start)) = self.open.take() {
+            subscriber.on_exit(phase, node, start.elapsed());
+        }
+    }
+}
+
+/// Reports the value of counter `name` to the current subscriber.
+pub fn counter(name: &'static str, value: u64) {
+    if let Some(subscriber) = current_subscriber() {
+        subscriber.on_counter(name, value);
+    }
+}
+
+
+/// Defines unit tests for the instrumentation of the search.
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mcts::MCTSTree;
+    use crate::tictactoe_env::TicTacToeState;
+    use std::sync::Mutex;
+
+    /// Counts the spans of every phase, and sums the rollout moves.
+    #[derive(Default)]
+    struct CountingSubscriber {
+        entered: Mutex<Vec<Phase>>,
+        exited: Mutex<Vec<Phase>>,
+        rollout_moves: Mutex<u64>,
+    }
+
+    impl Subscriber for CountingSubscriber {
+        fn on_enter(&self, phase: Phase, _node: NodeIndex) {
+            self.entered.lock().unwrap().push(phase);
+        }
+
+        fn on_exit(&self, phase: Phase, _node: NodeIndex, _elapsed: Duration) {
+            self.exited.lock().unwrap().push(phase);
+        }
+
+        fn on_counter(&self, name: &'static str, value: u64) {
+            assert_eq!(name, "rollout_moves");
+            *self.rollout_moves.lock().unwrap() += value;
+        }
+    }
+
+    /// Test if every phase of every iteration reports a span to the subscriber of the thread.
+    #[test]
+    fn test_spans() {
+        let subscriber = Arc::new(CountingSubscriber::default());
+        let mut tree = MCTSTree::<u8, TicTacToeState>::with_capacity(1000, Some(1), ".........".to_string(), 9);
+        with_subscriber(subscriber.clone(), || tree.search(100));
+
+        let entered = subscriber.entered.lock().unwrap();
+        assert_eq!(entered.len(), 400);
+        assert_eq!(&entered[..4], &[Phase::Select, Phase::Expand, Phase::Simulate, Phase::Backpropagate]);
+        drop(entered);
+        let exited = subscriber.exited.lock().unwrap();
+        assert_eq!(exited.iter().filter(|phase| **phase == Phase::Simulate).count(), 100);
+        drop(exited);
+
+        let stats = tree.stats();
+        let moves: u64 = stats.rollout_lengths.iter().enumerate().map(|(moves, count)| moves as u64 * count).sum();
+        assert_eq!(*subscriber.rollout_moves.lock().unwrap(), moves);
+
+        // Spans outside of the scope are not reported.
+        tree.search(10);
+        assert_eq!(subscriber.entered.lock().unwrap().len(), 400);
+        assert_eq!(Phase::Backpropagate.name(), "backpropagate");
+    }
+}
@@ -0,0 +1,63 @@
+//! Search server, exposing the engine as a microservice for every bundled environment.
+//!
+//! Clients connect over TCP and send one request per line, in the format of
+//! `mcts::service::SearchRequest::parse`, such as `chess movetime=500 <fen>`. Every request is
+//! answered with one line of JSON, the best action and root statistics of
+//! `SearchResponse::to_json`, or an `error` message. Connections are served on their own threads
+//! and may send any number of requests. The service is transport independent, and the protocol
+//! is plain lines until a gRPC transport such as tonic is available to the build.
+//!
+//! Run as `MCTSServer [address]`, listening on `127.0.0.1:50051` by default.
+
+// Explicit returns are the style of the codebase.
+#![allow(clippy::needless_return)]
+
+#[cfg(not(target_arch = "wasm32"))]
+#[global_allocator]
+static GLOBAL: mimalloc::MiMalloc = mimalloc::MiMalloc;
+
+use mcts::service::{search, SearchRequest};
+use std::io::{BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream};
+use std::thread;
+
+
+/// Address listened on without an address argument.
+const DEFAULT_ADDRESS: &str = "127.0.0.1:50051";
+
+
+/// Answers every request line of `stream` until the client disconnects.
+fn serve(stream: TcpStream) -> std::io::Result<()> {
+    let mut writer = stream.try_clone()?;
+    for line in BufReader::new(stream).lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let response = match SearchRequest::parse(&line).and_then(|request| search(&request)) {
+            Ok(response) => response.to_json(),
+            Err(error) => error.to_json(),
+        };
+        writeln!(writer, "{}", response)?;
+    }
+    return Ok(());
+}
+
+fn main() {
+    let address = std::env::args().nth(1).unwrap_or_else(|| DEFAULT_ADDRESS.to_string());
+    let listener = TcpListener::bind(&address).unwrap_or_else(|error| panic!("can not listen on {}: {}", address, error));
+    println!("Listening on {}", address);
+
+    for stream in listener.incoming() {
+        match stream {
+            Ok(stream) => {
+                thread::spawn(move || {
+                    if let Err(error) = serve(stream) {
+                        eprintln!("connection failed: {}", error);
+                    }
+                });
+            },
+            Err(error) => eprintln!("connection failed: {}", error),
+        }
+    }
+}
@@ -0,0 +1,254 @@
+// Explicit returns are the style of the codebase.
+#![allow(clippy::needless_return)]
+
+#[cfg(not(target_arch = "wasm32"))]
+#[global_allocator]
+static GLOBAL: mimalloc::MiMalloc = mimalloc::MiMalloc;
+
+use chess::ChessMove;
+use mcts::game_state_trait::GameState;
+use mcts::mcts::{MCTSTree, NodeId, TreeConfig};
+use mcts::chess_env::{ChessRolloutPolicy, ChessState};
+use mcts::time_manager::{TimeControl, TimeManager};
+use std::io::stdin;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+
+/// Fen of the standard starting position.
+const STARTING_FEN: &str = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+
+/// Number of iterations between checks of the stop flag and the search limits.
+const CHECK_INTERVAL: u64 = 256;
+
+/// Time between `info` reports while searching.
+const INFO_INTERVAL: Duration = Duration::from_secs(1);
+
+
+/// Limits of a single `go` command. A search without limits runs until `stop`.
+#[derive(Debug, Default)]
+struct SearchLimits {
+    time: Option<TimeControl>,
+    nodes: Option<u64>,
+}
+
+
+/// Parses a `position` command into the game state it describes.
+///
+/// Moves are applied in order from the given start position, in UCI long algebraic
+/// notation such as `e2e4` or `e7e8q`, with Chess960 castling given as the king capturing its own rook.
+/// Parsing stops at the first invalid or illegal move.
+///
+/// # Returns
+/// The resulting game state, or None if the start position is invalid.
+fn parse_position(tokens: &[&str]) -> Option<ChessState> {
+    let moves_index = tokens.iter().position(|token| *token == "moves").unwrap_or(tokens.len());
+    let mut game_state = match tokens.first() {
+        Some(&"startpos") => ChessState::try_from_str(STARTING_FEN).ok()?,
+        Some(&"fen") => ChessState::try_from_str(&tokens[1..moves_index].join(" ")).ok()?,
+        _ => return None,
+    };
+
+    for token in tokens.iter().skip(moves_index + 1) {
+        match ChessMove::from_str(token) {
+            Ok(action) if game_state.generate_legal_actions().contains(&action) => game_state.apply_action_mut(&action),
+            _ => break,
+        }
+    }
+    return Some(game_state);
+}
+
+
+/// Parses the arguments of a `go` command.
+///
+/// `movetime` and `nodes` are honoured directly. With a clock, a fixed fraction of the
+/// remaining time plus the increment of the side to move is the soft limit, which unstable
+/// searches may extend up to three times over. `infinite` and
+/// unknown arguments leave the search unlimited.
+fn parse_go(tokens: &[&str], white_to_move: bool) -> SearchLimits {
+    let mut limits = SearchLimits::default();
+    let (mut time_left, mut increment) = (None, 0);
+    let mut i = 0;
+    while i < tokens.len() {
+        let value = tokens.get(i + 1).and_then(|value| value.parse::<u64>().ok());
+        match (tokens[i], value) {
+            ("movetime", Some(value)) => limits.time = Some(TimeControl::fixed(Duration::from_millis(value))),
+            ("nodes", Some(value)) => limits.nodes = Some(value),
+            ("wtime", Some(value)) if white_to_move => time_left = Some(value),
+            ("btime", Some(value)) if !white_to_move => time_left = Some(value),
+            ("winc", Some(value)) if white_to_move => increment = value,
+            ("binc", Some(value)) if !white_to_move => increment = value,
+            _ => {
+                i += 1;
+                continue;
+            },
+        }
+        i += 2;
+    }
+
+    // Plan for roughly 30 more moves, while keeping a margin on the clock.
+    if limits.time.is_none() {
+        if let Some(time_left) = time_left {
+            let reserve = time_left.saturating_sub(50);
+            let budget = (time_left / 30 + increment).min(reserve).max(1);
+            limits.time = Some(TimeControl {
+                soft: Duration::from_millis(budget),
+                hard: Duration::from_millis((3 * budget).min(reserve).max(budget)),
+            });
+        }
+    }
+    return limits;
+}
+
+
+/// Follows the children with the highest win rate from the root.
+///
+/// # Returns
+/// The most promising line of play, which is empty if the root has no expanded children.
+fn principal_variation(tree: &MCTSTree<ChessMove, ChessState>) -> Vec<NodeId> {
+    let mut line = Vec::new();
+    let mut node = tree.root();
+    while let Some(child) = tree.get_max_uct_child(node, Some(0.0)) {
+        node = child;
+        line.push(node);
+    }
+    return line;
+}
+
+
+/// Converts the win rate of the side to move into a centipawn score,
+/// using the inverse of the logistic curve of `ChessState::evaluate`.
+fn centipawns(win_rate: f64) -> i64 {
+    let win_rate = win_rate.clamp(0.001, 0.999);
+    return (-400.0 * f64::log10(1.0 / win_rate - 1.0)) as i64;
+}
+
+
+/// Prints an `info` line for the current state of the search.
+fn print_info(tree: &MCTSTree<ChessMove, ChessState>, iterations: u64, elapsed: Duration) {
+    let line = principal_variation(tree);
+    let millis = elapsed.as_millis().max(1);
+    let mut info = format!(
+        "info nodes {} nps {} time {}",
+        iterations,
+        iterations as u128 * 1000 / millis,
+        millis
+    );
+    if let Some(&best) = line.first() {
+        let stats = tree.node_stats(best).expect("stale node");
+        info += &format!(" depth {} score cp {}", line.len(), centipawns(stats.score / stats.sims as f64));
+        let moves: Vec<String> = line.iter()
+            .map(|node| tree.action(*node).expect("stale node").expect("no action").to_string())
+            .collect();
+        info += &format!(" pv {}", moves.join(" "));
+    }
+    println!("{}", info);
+}
+
+
+/// Runs the search until a limit is reached or `stop` is set, then prints `bestmove`.
+fn search(game_state: ChessState, limits: SearchLimits, stop: Arc<AtomicBool>) {
+    let capacity = limits.nodes.map_or(1_000_000, |nodes| nodes as usize + 1);
+    let mut tree = MCTSTree::<ChessMove, ChessState>::from_state(
+        game_state.clone(),
+        TreeConfig { arena_capacity: capacity, seed: None, average_child_count: 30 }
+    );
+    tree.searcher.rollout_policy = Box::new(ChessRolloutPolicy::default());
+    tree.searcher.early_termination = true;
+    let start = Instant::now();
+    let mut time_manager = limits.time.map(TimeManager::new);
+    let mut last_info = start;
+    let mut iterations: u64 = 0;
+    loop {
+        let select = tree.select(tree.root(), None);
+        let expand = tree.expand(select);
+        let simulate = tree.simulate(expand);
+        tree.backpropagate(expand, simulate);
+        iterations += 1;
+
+        if limits.nodes.is_some_and(|nodes| iterations >= nodes) {
+            break;
+        }
+        if iterations.is_multiple_of(CHECK_INTERVAL) {
+            if stop.load(Ordering::Relaxed) || time_manager.as_mut().is_some_and(|manager| manager.should_stop(&tree)) {
+                break;
+            }
+            if last_info.elapsed() >= INFO_INTERVAL {
+                print_info(&tree, iterations, start.elapsed());
+                last_info = Instant::now();
+            }
+        }
+    }
+    print_info(&tree, iterations, start.elapsed());
+
+    // Fall back to any legal move if the root was never expanded, such as in terminal positions.
+    let best_move = match principal_variation(&tree).first() {
+        Some(&best) => tree.action(best).expect("stale node").copied(),
+        None => game_state.generate_legal_actions().first().copied(),
+    };
+    match best_move {
+        Some(best_move) => println!("bestmove {}", best_move),
+        None => println!("bestmove 0000"),
+    }
+}
+
+
+/// Stops a running search and waits for it to print its best move.
+fn stop_search(search_thread: &mut Option<JoinHandle<()>>, stop: &Arc<AtomicBool>) {
+    if let Some(handle) = search_thread.take() {
+        stop.store(true, Ordering::Relaxed);
+        let _ = handle.join();
+    }
+}
+
+
+/// Provides a UCI engine for use in chess GUIs and engine matches.
+/// Reads commands from stdin and writes responses to stdout.
+pub fn main() {
+    let mut game_state = ChessState::try_from_str(STARTING_FEN).expect("invalid starting fen");
+    let mut search_thread: Option<JoinHandle<()>> = None;
+    let mut stop = Arc::new(AtomicBool::new(false));
+
+    for line in stdin().lines() {
+        let line = match line {
+            Ok(line) => line,
+            Err(_) => break,
+        };
+        let tokens: Vec<&str> = line.split_whitespace().collect();
+        match tokens.first() {
+            Some(&"uci") => {
+                println!("id name mcts");
+                println!("id author aklimk");
+                println!("option name UCI_Chess960 type check default false");
+                println!("uciok");
+            },
+            Some(&"isready") => println!("readyok"),
+            Some(&"ucinewgame") => {
+                stop_search(&mut search_thread, &stop);
+                game_state = ChessState::try_from_str(STARTING_FEN).expect("invalid starting fen");
+            },
+            Some(&"position") => {
+                stop_search(&mut search_thread, &stop);
+                match parse_position(&tokens[1..]) {
+                    Some(position) => game_state = position,
+                    None => println!("info string invalid position"),
+                }
+            },
+            Some(&"go") => {
+                stop_search(&mut search_thread, &stop);
+                let limits = parse_go(&tokens[1..], game_state.board.side_to_move() == chess::Color::White);
+                stop = Arc::new(AtomicBool::new(false));
+                let (search_state, search_stop) = (game_state.clone(), stop.clone());
+                search_thread = Some(std::thread::spawn(move || search(search_state, limits, search_stop)));
+            },
+            Some(&"stop") => stop_search(&mut search_thread, &stop),
+            Some(&"quit") => break,
+            _ => {},
+        }
+    }
+    stop_search(&mut search_thread, &stop);
+}
+
@@ -1,3 +1,6 @@
+// Explicit returns are the style of the codebase.
+#![allow(clippy::needless_return)]
+
 #[global_allocator]
 static GLOBAL: mimalloc::MiMalloc = mimalloc::MiMalloc;
 
@@ -47,6 +50,7 @@ fn action_to_string(game_state: ChessState, action: &ChessMove) -> String {
 /// module. The safety of this parsing from abitrary code execution
 /// is not garunteed. </div>
 #[test]
+#[allow(clippy::needless_range_loop)]
 fn test_mcts() {
     let runs = 500000;
 
@@ -88,21 +92,22 @@ fn test_mcts() {
         
         // Perform `runs` amount of MCTS iterations.
         for _j in 0..runs {
-            let select = tree.select(0, None);
+            let select = tree.select(tree.root(), None);
             let expand = tree.expand(select);
             let simulate = tree.simulate(expand);
             tree.backpropagate(expand, simulate);
         }
 
-        // Optimal path generated by the MCTS, in terms of tree indexes. 
+        // Optimal path generated by the MCTS, in terms of node handles. 
         // Ignores exploration by setting exploration factor to 0.
-        let path_raw = tree.trace_path( tree.select(0, Some(0.0))); 
+        let best_leaf = tree.select(tree.root(), Some(0.0));
+        let path_raw = tree.trace_path(best_leaf); 
 
         // Path mapped to UCI chess moves.
         let path: Vec<String> = path_raw.into_iter().map(
-            |index| action_to_string(
-                tree.arena[index].game_state.clone(), // Get chess position of the node.
-                &tree.arena[index].game_state.last_move
+            |node| action_to_string(
+                tree.game_state(node).into_owned(), // Get chess position of the node.
+                tree.action(node).expect("Stale node.")
                     .expect("No last move.") // Get the move that created the node's position.
             )
         ).collect();